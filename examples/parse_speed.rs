@@ -0,0 +1,42 @@
+//! Compares `Idf30::parse` with `Idf30::parse_fast` on a generated placement heavy board, run
+//! with `cargo run --release --features fast-parser --example parse_speed [placements]`.
+
+use idf::idf30::Idf30;
+use std::env;
+use std::fmt::Write;
+use std::time::Instant;
+
+fn main() {
+    let count: usize = env::args()
+        .nth(1)
+        .map(|n| n.parse().expect("Number of placements"))
+        .unwrap_or(100_000);
+
+    let mut file = String::from(
+        ".HEADER\nBOARD_FILE 3.0 parse_speed 2023/10/18.14:05:44 1\nboard.brd MM\n.END_HEADER\n\
+         .BOARD_OUTLINE ECAD\n1.6\n0 0.0 0.0 0.0\n0 100.0 0.0 0.0\n0 100.0 100.0 0.0\n\
+         0 0.0 0.0 0.0\n.END_BOARD_OUTLINE\n.PLACEMENT\n",
+    );
+    for i in 0..count {
+        let x = (i % 1000) as f32 * 0.1;
+        let y = (i / 1000) as f32 * 0.1;
+        writeln!(file, "RES_0402 \"PN-{}\" R{i}", i % 50).unwrap();
+        writeln!(file, "{x:.4} {y:.4} 0.0 90.0 TOP PLACED").unwrap();
+    }
+    file.push_str(".END_PLACEMENT\n");
+
+    let start = Instant::now();
+    let pest = Idf30::parse(&file).unwrap();
+    let pest_time = start.elapsed();
+    let start = Instant::now();
+    let fast = Idf30::parse_fast(&file).unwrap();
+    let fast_time = start.elapsed();
+    assert_eq!(pest, fast);
+
+    println!(
+        "{count} placements, {} MB\nparse:      {pest_time:?}\nparse_fast: {fast_time:?}\n\
+         speedup:    {:.1}x",
+        file.len() / 1_000_000,
+        pest_time.as_secs_f64() / fast_time.as_secs_f64()
+    );
+}
@@ -1,10 +1,13 @@
 use either::Either;
+use idf::assembly::IdfAssembly;
 use idf::idf30::{FileType, Idf30};
+use idf::rename::RenameField;
+use std::collections::HashMap;
 use std::env;
 use std::fs::read_to_string;
 
 fn main() {
-    let mut args = env::args().into_iter().skip(1);
+    let mut args = env::args().skip(1);
     let idf_path = args.next().expect("IDF file path");
     let ldf_path = args.next().expect("LDF file path");
 
@@ -22,27 +25,38 @@ fn main() {
             board_name,
             idf_file.placement.len()
         );
-
-        let mut removed = 0;
-        idf_file.placement.retain(|c| {
-            if c.designator.is_test_point() {
-                removed += 1;
-                false
-            } else {
-                true
-            }
-        });
-        for component in &mut idf_file.placement {
-            component.part_number = component.package_name.clone();
-        }
-        println!("Removed: {removed}");
     }
+    let mut removed = 0;
+    idf_file.placement.retain(|c| {
+        if c.designator.is_test_point() {
+            removed += 1;
+            false
+        } else {
+            true
+        }
+    });
+    println!("Removed: {removed}");
 
-    if let FileType::LibraryFile { components } = &mut ldf_file.header.ty {
+    // Every part is renamed after its package, in the board and the library together
+    let mut mapping = HashMap::new();
+    for c in &idf_file.placement {
+        mapping
+            .entry(c.part_number.to_string())
+            .or_insert_with(|| c.package_name.to_string());
+    }
+    if let FileType::LibraryFile { components } = &ldf_file.header.ty {
         println!("Components defs: {}", components.len());
-        for def in components.iter_mut() {
-            def.part_number = def.geometry_name.clone();
+        for def in components {
+            mapping
+                .entry(def.part_number.to_string())
+                .or_insert_with(|| def.geometry_name.to_string());
         }
+    }
+    let mut assembly = IdfAssembly::new(idf_file, ldf_file).expect("Board and library file");
+    let report = assembly.rename(RenameField::PartNumber, &mapping);
+    println!("Renamed: {}", report.renamed);
+
+    if let FileType::LibraryFile { components } = &mut assembly.library.header.ty {
         let mut seen = vec![];
         components.retain(|c| {
             let name = format!("{}", c.geometry_name);
@@ -56,6 +70,6 @@ fn main() {
         println!("After removing duplicates: {}", seen.len());
     }
 
-    std::fs::write("./out.bdf", idf_file.to_string()).expect("Write file failed");
-    std::fs::write("./out.ldf", ldf_file.to_string()).expect("Write file failed");
+    std::fs::write("./out.bdf", assembly.board.to_string()).expect("Write file failed");
+    std::fs::write("./out.ldf", assembly.library.to_string()).expect("Write file failed");
 }
@@ -0,0 +1,137 @@
+use crate::idf30::{
+    ComponentDefinition, ComponentPlacement, FileType, Idf30, IdfSection, IdfValue, Loop, Point,
+};
+
+impl<'a> Idf30<'a> {
+    /// Compares coordinates within `tolerance` and strings by content, so that files written with
+    /// different precision or quoting compare equal. Header source, date and board file version
+    /// are provenance and are not compared.
+    pub fn approx_eq(&self, other: &Idf30, tolerance: f32) -> bool {
+        let ty_eq = match (&self.header.ty, &other.header.ty) {
+            (
+                FileType::BoardFile { board_name, units },
+                FileType::BoardFile {
+                    board_name: other_name,
+                    units: other_units,
+                },
+            )
+            | (
+                FileType::PanelFile { board_name, units },
+                FileType::PanelFile {
+                    board_name: other_name,
+                    units: other_units,
+                },
+            ) => **board_name == **other_name && units == other_units,
+            (
+                FileType::LibraryFile { components },
+                FileType::LibraryFile {
+                    components: other_components,
+                },
+            ) => {
+                components.len() == other_components.len()
+                    && components
+                        .iter()
+                        .zip(other_components)
+                        .all(|(a, b)| a.approx_eq(b, tolerance))
+            }
+            _ => false,
+        };
+        ty_eq
+            && self.placement.len() == other.placement.len()
+            && self
+                .placement
+                .iter()
+                .zip(&other.placement)
+                .all(|(a, b)| a.approx_eq(b, tolerance))
+            && self.other_sections.len() == other.other_sections.len()
+            && self
+                .other_sections
+                .iter()
+                .zip(&other.other_sections)
+                .all(|(a, b)| a.approx_eq(b, tolerance))
+    }
+}
+
+impl<'a> ComponentPlacement<'a> {
+    pub fn approx_eq(&self, other: &ComponentPlacement, tolerance: f32) -> bool {
+        *self.package_name == *other.package_name
+            && *self.part_number == *other.part_number
+            && self.designator == other.designator
+            && (self.x - other.x).abs() <= tolerance
+            && (self.y - other.y).abs() <= tolerance
+            && (self.z - other.z).abs() <= tolerance
+            && rotation_difference(self.rotation, other.rotation) <= tolerance
+            && self.board_side == other.board_side
+            && self.placement_status == other.placement_status
+    }
+}
+
+/// Difference between two rotations in degrees across the 0/360 wraparound, so that 359.999
+/// and 0 differ by 0.001.
+fn rotation_difference(a: f32, b: f32) -> f32 {
+    let d = (a - b).rem_euclid(360.0);
+    d.min(360.0 - d)
+}
+
+impl<'a> ComponentDefinition<'a> {
+    pub fn approx_eq(&self, other: &ComponentDefinition, tolerance: f32) -> bool {
+        *self.geometry_name == *other.geometry_name
+            && *self.part_number == *other.part_number
+            && self.units == other.units
+            && (self.height - other.height).abs() <= tolerance
+            && self.loops.len() == other.loops.len()
+            && self
+                .loops
+                .iter()
+                .zip(&other.loops)
+                .all(|(a, b)| a.approx_eq(b, tolerance))
+    }
+}
+
+impl Loop {
+    pub fn approx_eq(&self, other: &Loop, tolerance: f32) -> bool {
+        self.index == other.index
+            && self.points.len() == other.points.len()
+            && self
+                .points
+                .iter()
+                .zip(&other.points)
+                .all(|(a, b)| a.approx_eq(b, tolerance))
+    }
+}
+
+impl Point {
+    pub fn approx_eq(&self, other: &Point, tolerance: f32) -> bool {
+        (self.x - other.x).abs() <= tolerance
+            && (self.y - other.y).abs() <= tolerance
+            && (self.angle - other.angle).abs() <= tolerance
+    }
+}
+
+impl<'a> IdfSection<'a> {
+    /// Outlines, keepouts, holes and any other section kept as raw records are compared value by
+    /// value, integers and floats numerically.
+    pub fn approx_eq(&self, other: &IdfSection, tolerance: f32) -> bool {
+        self.name() == other.name()
+            && self.owner() == other.owner()
+            && self.args().len() == other.args().len()
+            && self.args().iter().zip(other.args()).all(|(a, b)| a == b)
+            && self.records().len() == other.records().len()
+            && self.records().iter().zip(other.records()).all(|(a, b)| {
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.approx_eq(b, tolerance))
+            })
+    }
+}
+
+impl<'a> IdfValue<'a> {
+    pub fn approx_eq(&self, other: &IdfValue, tolerance: f32) -> bool {
+        match (self, other) {
+            (IdfValue::String(a), IdfValue::String(b)) => **a == **b,
+            (IdfValue::String(_), _) | (_, IdfValue::String(_)) => false,
+            (a, b) => a
+                .as_f64()
+                .zip(b.as_f64())
+                .is_some_and(|(a, b)| (a - b).abs() <= tolerance as f64),
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use crate::geometry::{
+    definition_loops, polylines_contain, section_loops, tessellate, BoundingBox, BoundingBox3d,
+};
+use crate::idf30::{
+    BoardSide, ComponentDefinition, ComponentPlacement, Error, FileType, Idf30, Unit,
+};
+
+/// Closed polylines in board coordinates.
+pub type Outline = Vec<Vec<(f64, f64)>>;
+
+/// Board or panel file together with the library file its placements refer to.
+#[derive(Clone, Debug)]
+pub struct IdfAssembly<'a> {
+    pub board: Idf30<'a>,
+    pub library: Idf30<'a>,
+}
+
+/// Placement with its library definition, if the library has one.
+#[derive(Clone, Copy, Debug)]
+pub struct ResolvedComponent<'s, 'a> {
+    pub placement: &'s ComponentPlacement<'a>,
+    pub definition: Option<&'s ComponentDefinition<'a>>,
+    /// Units of the board the placement belongs to
+    pub units: &'s Unit,
+}
+
+impl<'s, 'a> ResolvedComponent<'s, 'a> {
+    /// Height of the component top above the board surface, in board units.
+    pub fn height(&self) -> Option<f32> {
+        let definition = self.definition?;
+        let height = definition
+            .units
+            .convert(definition.height as f64, self.units);
+        Some(self.placement.z + height as f32)
+    }
+
+    /// Lowest and highest Z of the component, in board units. The bottom surface of the board
+    /// is at zero and the top surface at `thickness`; top side components extend up from the
+    /// top surface and bottom side components down from the bottom one, both raised by the
+    /// placement offset.
+    pub fn z_span(&self, thickness: f32) -> Option<(f32, f32)> {
+        let top = self.height()?;
+        Some(match self.placement.board_side {
+            BoardSide::Top => (thickness + self.placement.z, thickness + top),
+            BoardSide::Bottom => (-top, -self.placement.z),
+        })
+    }
+
+    /// Library outline in board coordinates and units, arcs are tessellated.
+    pub fn outline(&self) -> Outline {
+        let Some(definition) = self.definition else {
+            return vec![];
+        };
+        let transform = self.placement.placement_transform();
+        definition_loops(definition)
+            .iter()
+            .map(|l| {
+                tessellate(l)
+                    .into_iter()
+                    .map(|(x, y)| {
+                        transform.apply(
+                            definition.units.convert(x, self.units),
+                            definition.units.convert(y, self.units),
+                        )
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Library outline, or the reference point if the component has no definition.
+    pub fn footprint(&self) -> Outline {
+        let outline = self.outline();
+        if outline.is_empty() {
+            vec![vec![(self.placement.x as f64, self.placement.y as f64)]]
+        } else {
+            outline
+        }
+    }
+
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        BoundingBox::from_points(self.outline().into_iter().flatten())
+    }
+}
+
+/// Grid of the tallest component height over each cell, zero where there is none.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeightMap {
+    /// Lower left corner of the first cell
+    pub origin: (f64, f64),
+    pub cell_size: f64,
+    pub cols: usize,
+    pub rows: usize,
+    /// Row by row, starting at the bottom
+    pub heights: Vec<f32>,
+}
+
+impl HeightMap {
+    pub fn get(&self, col: usize, row: usize) -> Option<f32> {
+        if col >= self.cols {
+            return None;
+        }
+        self.heights.get(row * self.cols + col).copied()
+    }
+}
+
+impl<'a> IdfAssembly<'a> {
+    pub fn new(board: Idf30<'a>, library: Idf30<'a>) -> Result<IdfAssembly<'a>, Error> {
+        if matches!(board.header.ty, FileType::LibraryFile { .. })
+            || !matches!(library.header.ty, FileType::LibraryFile { .. })
+        {
+            return Err(Error::WrongFileType);
+        }
+        Ok(IdfAssembly { board, library })
+    }
+
+    pub fn components(&self) -> impl Iterator<Item = ResolvedComponent<'_, 'a>> {
+        let units = self.board.units().unwrap_or(&Unit::SImm);
+        self.board
+            .placement
+            .iter()
+            .map(move |placement| ResolvedComponent {
+                placement,
+                definition: self
+                    .library
+                    .definition(&placement.package_name, &placement.part_number),
+                units,
+            })
+    }
+
+    /// Placements without a library definition.
+    pub fn unresolved(&self) -> Vec<&ComponentPlacement<'a>> {
+        self.components()
+            .filter(|c| c.definition.is_none())
+            .map(|c| c.placement)
+            .collect()
+    }
+
+    /// Tallest component on a side and its height in board units.
+    pub fn max_height(&self, side: BoardSide) -> Option<(&ComponentPlacement<'a>, f32)> {
+        self.components()
+            .filter(|c| c.placement.board_side == side)
+            .filter_map(|c| Some((c.placement, c.height()?)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    /// Extents of the board and the components on both sides, in board units. Z runs from the
+    /// bottom of the lowest bottom side component to the top of the tallest top side one, see
+    /// `ResolvedComponent::z_span`. Components without a definition are left out, as is the
+    /// thickness of a malformed outline.
+    pub fn bounding_box_3d(&self) -> Option<BoundingBox3d> {
+        let thickness = self.board.board_thickness().ok().flatten().unwrap_or(0.0);
+        let mut xy = self
+            .board
+            .section("BOARD_OUTLINE")
+            .or_else(|| self.board.section("PANEL_OUTLINE"))
+            .and_then(|s| s.bounding_box());
+        let (mut min_z, mut max_z) = (0.0f32, thickness);
+        for c in self.components() {
+            let (Some(bbox), Some((low, high))) = (c.bounding_box(), c.z_span(thickness)) else {
+                continue;
+            };
+            xy = Some(xy.map_or(bbox, |xy| xy.union(&bbox)));
+            min_z = min_z.min(low);
+            max_z = max_z.max(high);
+        }
+        let xy = xy?;
+        Some(BoundingBox3d {
+            min_x: xy.min_x,
+            min_y: xy.min_y,
+            min_z: min_z as f64,
+            max_x: xy.max_x,
+            max_y: xy.max_y,
+            max_z: max_z as f64,
+        })
+    }
+
+    /// Samples component heights on a side at the centers of `cell_size` cells covering the
+    /// board outline, or the components themselves if there is no outline.
+    pub fn height_map(&self, side: BoardSide, cell_size: f64) -> Option<HeightMap> {
+        let components: Vec<(Outline, f32)> = self
+            .components()
+            .filter(|c| c.placement.board_side == side)
+            .filter_map(|c| Some((c.outline(), c.height()?)))
+            .collect();
+        let extents = match self
+            .board
+            .section("BOARD_OUTLINE")
+            .or_else(|| self.board.section("PANEL_OUTLINE"))
+        {
+            Some(outline) => {
+                BoundingBox::from_points(section_loops(outline).iter().flat_map(|l| tessellate(l)))
+            }
+            None => BoundingBox::from_points(
+                components
+                    .iter()
+                    .flat_map(|(o, _)| o.iter().flatten().copied()),
+            ),
+        }?;
+        if cell_size <= 0.0 {
+            return None;
+        }
+        let cols = (extents.width() / cell_size).ceil().max(1.0) as usize;
+        let rows = (extents.height() / cell_size).ceil().max(1.0) as usize;
+        let mut map = HeightMap {
+            origin: (extents.min_x, extents.min_y),
+            cell_size,
+            cols,
+            rows,
+            heights: vec![0.0; cols * rows],
+        };
+        for (outline, height) in &components {
+            let Some(bbox) = BoundingBox::from_points(outline.iter().flatten().copied()) else {
+                continue;
+            };
+            let cell = |v: f64, origin: f64, n: usize| {
+                (((v - origin) / cell_size).floor().max(0.0) as usize).min(n - 1)
+            };
+            for row in cell(bbox.min_y, extents.min_y, rows)..=cell(bbox.max_y, extents.min_y, rows)
+            {
+                for col in
+                    cell(bbox.min_x, extents.min_x, cols)..=cell(bbox.max_x, extents.min_x, cols)
+                {
+                    let x = extents.min_x + (col as f64 + 0.5) * cell_size;
+                    let y = extents.min_y + (row as f64 + 0.5) * cell_size;
+                    let h = &mut map.heights[row * cols + col];
+                    if *height > *h && polylines_contain(outline, x, y) {
+                        *h = *height;
+                    }
+                }
+            }
+        }
+        Some(map)
+    }
+}
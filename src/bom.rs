@@ -0,0 +1,105 @@
+use crate::designators::split_designator;
+use crate::formats::centroid::csv_field;
+use crate::idf30::{Idf30, PlacementStatus, ReferenceDesignator};
+use either::Either;
+use std::collections::BTreeMap;
+
+/// One line of a bill of materials: all placements sharing a part number and package.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BomLine {
+    pub part_number: String,
+    pub package: String,
+    pub quantity: usize,
+    /// Reference designators in natural order, NOREFDES placements count towards the quantity
+    /// but are not listed
+    pub designators: Vec<String>,
+}
+
+impl BomLine {
+    /// Designators with runs of consecutive numbers collapsed, e.g. "C1-C4, C7, R2A".
+    pub fn designator_ranges(&self) -> String {
+        let mut ranges: Vec<String> = vec![];
+        // Prefix, first and last number of the run being built
+        let mut run: Option<(&str, u64, u64)> = None;
+        let flush = |run: Option<(&str, u64, u64)>, ranges: &mut Vec<String>| match run {
+            Some((prefix, first, last)) if first == last => ranges.push(format!("{prefix}{first}")),
+            Some((prefix, first, last)) if first + 1 == last => {
+                ranges.push(format!("{prefix}{first}"));
+                ranges.push(format!("{prefix}{last}"));
+            }
+            Some((prefix, first, last)) => ranges.push(format!("{prefix}{first}-{prefix}{last}")),
+            None => {}
+        };
+        for d in &self.designators {
+            let (prefix, number, suffix) = split_designator(d);
+            // Leading zeros or suffixes would not survive the collapsed form
+            let number = match number.parse::<u64>() {
+                Ok(n) if suffix.is_empty() && !number.starts_with('0') => n,
+                _ => {
+                    flush(run.take(), &mut ranges);
+                    ranges.push(d.clone());
+                    continue;
+                }
+            };
+            match &mut run {
+                Some((p, _, last)) if *p == prefix && *last + 1 == number => *last = number,
+                _ => {
+                    flush(run.take(), &mut ranges);
+                    run = Some((prefix, number, number));
+                }
+            }
+        }
+        flush(run, &mut ranges);
+        ranges.join(", ")
+    }
+}
+
+impl<'a> Idf30<'a> {
+    /// Groups the placements by part number and package, sorted by part number. Unplaced
+    /// components are left out.
+    pub fn bom(&self) -> Vec<BomLine> {
+        let mut lines: BTreeMap<(&str, &str), BomLine> = BTreeMap::new();
+        for c in &self.placement {
+            if c.placement_status == PlacementStatus::Unplaced {
+                continue;
+            }
+            let line = lines
+                .entry((&c.part_number, &c.package_name))
+                .or_insert_with(|| BomLine {
+                    part_number: c.part_number.to_string(),
+                    package: c.package_name.to_string(),
+                    quantity: 0,
+                    designators: vec![],
+                });
+            line.quantity += 1;
+            if let ReferenceDesignator::Any(_) = c.designator {
+                line.designators.push(c.designator.to_string());
+            }
+        }
+        lines
+            .into_values()
+            .map(|mut line| {
+                line.designators.sort_by(|a, b| {
+                    ReferenceDesignator::Any(Either::Left(a.as_str()))
+                        .cmp(&ReferenceDesignator::Any(Either::Left(b.as_str())))
+                });
+                line
+            })
+            .collect()
+    }
+
+    /// Bill of materials as CSV with PartNumber, Package, Quantity and RefDes columns.
+    pub fn to_bom_csv(&self) -> String {
+        let mut s = String::from("PartNumber,Package,Quantity,RefDes\n");
+        for line in self.bom() {
+            s.push_str(&format!(
+                "{},{},{},{}\n",
+                csv_field(&line.part_number),
+                csv_field(&line.package),
+                line.quantity,
+                csv_field(&line.designator_ranges()),
+            ));
+        }
+        s
+    }
+}
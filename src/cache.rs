@@ -0,0 +1,370 @@
+//! Binary cache of parsed models, so tools run repeatedly over large unchanged inputs can skip
+//! parsing them.
+//!
+//! The encoding is little endian with length prefixed strings and lists. It is only meant to be
+//! read back by the same crate version: caches of other versions are rejected as stale. Parse
+//! warnings are not kept.
+
+use crate::canonical::fnv1a;
+use crate::idf30::{
+    BoardSide, ComponentDefinition, ComponentPlacement, Error, FileType, Header, Idf30, IdfSection,
+    IdfValue, Loop, Owner, PlacementStatus, Point, ReferenceDesignator, SectionRef, Unit,
+};
+use either::Either;
+
+const MAGIC: &[u8; 4] = b"IDFC";
+/// Bumped with every change to the encoding
+const FORMAT_VERSION: u8 = 2;
+const MALFORMED: Error = Error::Malformed("Malformed cache");
+
+impl<'a> Idf30<'a> {
+    /// Encodes the model together with the hash of `source`, the text it was parsed from.
+    pub fn to_cache(&self, source: &str) -> Vec<u8> {
+        let mut w = Writer(Vec::with_capacity(source.len() / 2));
+        w.0.extend_from_slice(MAGIC);
+        w.u8(FORMAT_VERSION);
+        w.str(env!("CARGO_PKG_VERSION"));
+        w.u64(fnv1a(source.as_bytes()));
+        w.header(&self.header);
+        w.list(&self.placement, Writer::placement);
+        w.list(&self.other_sections, Writer::section);
+        w.list(&self.section_order, |w, r| match r {
+            SectionRef::Other(i) => {
+                w.u8(0);
+                w.u32(*i as u32);
+            }
+            SectionRef::Placement => w.u8(1),
+            SectionRef::Definition(i) => {
+                w.u8(2);
+                w.u32(*i as u32);
+            }
+        });
+        w.0
+    }
+
+    /// Decodes a cache written by `to_cache`. Returns `None` if it is stale: written for
+    /// another `source` or by another version of this crate.
+    pub fn from_cache(cache: &[u8], source: &str) -> Result<Option<Idf30<'static>>, Error> {
+        let mut r = Reader(cache);
+        if r.take(4)? != MAGIC {
+            return Err(MALFORMED);
+        }
+        if r.u8()? != FORMAT_VERSION
+            || r.str()? != env!("CARGO_PKG_VERSION")
+            || r.u64()? != fnv1a(source.as_bytes())
+        {
+            return Ok(None);
+        }
+        let header = r.header()?;
+        let placement = r.list(Reader::placement)?;
+        let other_sections = r.list(Reader::section)?;
+        let section_order = r.list(|r| match r.u8()? {
+            0 => Ok(SectionRef::Other(r.u32()? as usize)),
+            1 => Ok(SectionRef::Placement),
+            2 => Ok(SectionRef::Definition(r.u32()? as usize)),
+            _ => Err(MALFORMED),
+        })?;
+        if !r.0.is_empty() {
+            return Err(MALFORMED);
+        }
+        Ok(Some(Idf30 {
+            header,
+            placement,
+            other_sections,
+            section_order,
+            warnings: vec![],
+        }))
+    }
+}
+
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn u8(&mut self, x: u8) {
+        self.0.push(x);
+    }
+
+    fn u32(&mut self, x: u32) {
+        self.0.extend_from_slice(&x.to_le_bytes());
+    }
+
+    fn u64(&mut self, x: u64) {
+        self.0.extend_from_slice(&x.to_le_bytes());
+    }
+
+    fn f32(&mut self, x: f32) {
+        self.0.extend_from_slice(&x.to_le_bytes());
+    }
+
+    fn str(&mut self, s: &str) {
+        self.u32(s.len() as u32);
+        self.0.extend_from_slice(s.as_bytes());
+    }
+
+    fn list<T>(&mut self, items: &[T], mut item: impl FnMut(&mut Self, &T)) {
+        self.u32(items.len() as u32);
+        for i in items {
+            item(self, i);
+        }
+    }
+
+    fn unit(&mut self, unit: &Unit) {
+        self.u8(match unit {
+            Unit::SImm => 0,
+            Unit::Mils => 1,
+        });
+    }
+
+    fn header(&mut self, header: &Header) {
+        match &header.ty {
+            FileType::BoardFile { board_name, units } => {
+                self.u8(0);
+                self.str(board_name);
+                self.unit(units);
+            }
+            FileType::PanelFile { board_name, units } => {
+                self.u8(1);
+                self.str(board_name);
+                self.unit(units);
+            }
+            FileType::LibraryFile { components } => {
+                self.u8(2);
+                self.list(components, Writer::definition);
+            }
+        }
+        self.str(&header.source);
+        self.str(&header.date);
+        self.u32(header.board_file_version);
+    }
+
+    fn placement(&mut self, c: &ComponentPlacement) {
+        self.str(&c.package_name);
+        self.str(&c.part_number);
+        match &c.designator {
+            ReferenceDesignator::Any(d) => {
+                self.u8(0);
+                self.str(d);
+            }
+            ReferenceDesignator::NoRefDes => self.u8(1),
+            ReferenceDesignator::Board => self.u8(2),
+        }
+        for x in [c.x, c.y, c.z, c.rotation] {
+            self.f32(x);
+        }
+        self.u8(match c.board_side {
+            BoardSide::Top => 0,
+            BoardSide::Bottom => 1,
+        });
+        self.u8(match c.placement_status {
+            PlacementStatus::Placed => 0,
+            PlacementStatus::Unplaced => 1,
+            PlacementStatus::MCad => 2,
+            PlacementStatus::ECad => 3,
+        });
+    }
+
+    fn definition(&mut self, d: &ComponentDefinition) {
+        self.str(&d.geometry_name);
+        self.str(&d.part_number);
+        self.unit(&d.units);
+        self.f32(d.height);
+        self.list(&d.loops, |w, l| {
+            w.u32(l.index);
+            w.list(&l.points, |w, p| {
+                w.f32(p.x);
+                w.f32(p.y);
+                w.f32(p.angle);
+            });
+        });
+    }
+
+    fn section(&mut self, section: &IdfSection) {
+        self.str(section.name());
+        self.u8(match section.owner() {
+            None => 0,
+            Some(Owner::ECad) => 1,
+            Some(Owner::MCad) => 2,
+            Some(Owner::Unowned) => 3,
+        });
+        self.list(section.args(), |w, a| w.str(a));
+        self.list(section.records(), |w, record| {
+            w.list(record, |w, value| match value {
+                IdfValue::Integer(x) => {
+                    w.u8(0);
+                    w.0.extend_from_slice(&x.to_le_bytes());
+                }
+                IdfValue::Float(x) => {
+                    w.u8(1);
+                    w.0.extend_from_slice(&x.to_le_bytes());
+                }
+                IdfValue::String(s) => {
+                    w.u8(2);
+                    w.str(s);
+                }
+            })
+        });
+    }
+}
+
+struct Reader<'c>(&'c [u8]);
+
+impl<'c> Reader<'c> {
+    fn take(&mut self, n: usize) -> Result<&'c [u8], Error> {
+        if self.0.len() < n {
+            return Err(MALFORMED);
+        }
+        let (taken, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn bytes<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        Ok(self.take(N)?.try_into().expect("length checked"))
+    }
+
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        Ok(u32::from_le_bytes(self.bytes()?))
+    }
+
+    fn u64(&mut self) -> Result<u64, Error> {
+        Ok(u64::from_le_bytes(self.bytes()?))
+    }
+
+    fn f32(&mut self) -> Result<f32, Error> {
+        Ok(f32::from_le_bytes(self.bytes()?))
+    }
+
+    fn str(&mut self) -> Result<&'c str, Error> {
+        let len = self.u32()? as usize;
+        std::str::from_utf8(self.take(len)?).map_err(|_| MALFORMED)
+    }
+
+    fn string(&mut self) -> Result<Either<&'static str, String>, Error> {
+        Ok(Either::Right(self.str()?.to_string()))
+    }
+
+    fn list<T>(
+        &mut self,
+        mut item: impl FnMut(&mut Self) -> Result<T, Error>,
+    ) -> Result<Vec<T>, Error> {
+        let len = self.u32()? as usize;
+        // Every item takes at least a byte, which bounds allocations for corrupt lengths
+        if len > self.0.len() {
+            return Err(MALFORMED);
+        }
+        (0..len).map(|_| item(self)).collect()
+    }
+
+    fn unit(&mut self) -> Result<Unit, Error> {
+        match self.u8()? {
+            0 => Ok(Unit::SImm),
+            1 => Ok(Unit::Mils),
+            _ => Err(MALFORMED),
+        }
+    }
+
+    fn header(&mut self) -> Result<Header<'static>, Error> {
+        let ty = match self.u8()? {
+            0 => FileType::BoardFile {
+                board_name: self.string()?,
+                units: self.unit()?,
+            },
+            1 => FileType::PanelFile {
+                board_name: self.string()?,
+                units: self.unit()?,
+            },
+            2 => FileType::LibraryFile {
+                components: self.list(Reader::definition)?,
+            },
+            _ => return Err(MALFORMED),
+        };
+        Ok(Header {
+            ty,
+            source: self.string()?,
+            date: self.string()?,
+            board_file_version: self.u32()?,
+        })
+    }
+
+    fn placement(&mut self) -> Result<ComponentPlacement<'static>, Error> {
+        let package_name = self.string()?;
+        let part_number = self.string()?;
+        let designator = match self.u8()? {
+            0 => ReferenceDesignator::Any(self.string()?),
+            1 => ReferenceDesignator::NoRefDes,
+            2 => ReferenceDesignator::Board,
+            _ => return Err(MALFORMED),
+        };
+        Ok(ComponentPlacement {
+            package_name,
+            part_number,
+            designator,
+            x: self.f32()?,
+            y: self.f32()?,
+            z: self.f32()?,
+            rotation: self.f32()?,
+            board_side: match self.u8()? {
+                0 => BoardSide::Top,
+                1 => BoardSide::Bottom,
+                _ => return Err(MALFORMED),
+            },
+            placement_status: match self.u8()? {
+                0 => PlacementStatus::Placed,
+                1 => PlacementStatus::Unplaced,
+                2 => PlacementStatus::MCad,
+                3 => PlacementStatus::ECad,
+                _ => return Err(MALFORMED),
+            },
+        })
+    }
+
+    fn definition(&mut self) -> Result<ComponentDefinition<'static>, Error> {
+        Ok(ComponentDefinition {
+            geometry_name: self.string()?,
+            part_number: self.string()?,
+            units: self.unit()?,
+            height: self.f32()?,
+            loops: self.list(|r| {
+                Ok(Loop {
+                    index: r.u32()?,
+                    points: r.list(|r| {
+                        Ok(Point {
+                            x: r.f32()?,
+                            y: r.f32()?,
+                            angle: r.f32()?,
+                        })
+                    })?,
+                })
+            })?,
+        })
+    }
+
+    fn section(&mut self) -> Result<IdfSection<'static>, Error> {
+        let name = self.string()?;
+        let owner = match self.u8()? {
+            0 => None,
+            1 => Some(Owner::ECad),
+            2 => Some(Owner::MCad),
+            3 => Some(Owner::Unowned),
+            _ => return Err(MALFORMED),
+        };
+        let args = self.list(Reader::string)?;
+        let records = self.list(|r| {
+            r.list(|r| match r.u8()? {
+                0 => Ok(IdfValue::Integer(i64::from_le_bytes(r.bytes()?))),
+                1 => Ok(IdfValue::Float(f64::from_le_bytes(r.bytes()?))),
+                2 => Ok(IdfValue::String(r.string()?)),
+                _ => Err(MALFORMED),
+            })
+        })?;
+        let mut section = IdfSection::new(name, args, records);
+        if let Some(owner) = owner {
+            section.set_owner(owner);
+        }
+        Ok(section)
+    }
+}
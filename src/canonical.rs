@@ -0,0 +1,138 @@
+//! Deterministic form of a model, so that text diffs between revisions only show real changes,
+//! and fingerprints of it.
+
+use crate::idf30::{FileType, Idf30, IdfSection, IdfValue};
+use crate::transform::{is_loop_point, normalize_angle, LOOP_SECTIONS};
+use crate::write::SPEC_ORDER;
+use either::Either;
+use std::cmp::Ordering;
+
+impl<'a> Idf30<'a> {
+    /// Puts the model in a canonical form:
+    /// * sections in specification order, unknown ones after them in their original order
+    /// * placements by reference designator in natural order (R2 before R10), then by package,
+    ///   part and position, library definitions by geometry and part name
+    /// * drilled holes by position, then diameter
+    /// * rotations in [0, 360), coordinates rounded to four decimals, three for rotations,
+    ///   integer coordinates written as floats and no negative zeros
+    pub fn canonicalize(&mut self) {
+        let rank = |s: &IdfSection| {
+            SPEC_ORDER
+                .iter()
+                .position(|o| *o == s.name())
+                .unwrap_or(SPEC_ORDER.len())
+        };
+        self.other_sections.sort_by_key(rank);
+        self.section_order.clear();
+        for section in &mut self.other_sections {
+            canonicalize_section(section);
+        }
+
+        for c in &mut self.placement {
+            c.x = round(c.x, 4);
+            c.y = round(c.y, 4);
+            c.z = round(c.z, 4);
+            c.rotation = normalize_angle(round(c.rotation, 3) as f64) as f32;
+        }
+        self.placement.sort_by(|a, b| {
+            a.designator
+                .cmp(&b.designator)
+                .then_with(|| (*a.package_name).cmp(&*b.package_name))
+                .then_with(|| (*a.part_number).cmp(&*b.part_number))
+                .then_with(|| a.x.total_cmp(&b.x))
+                .then_with(|| a.y.total_cmp(&b.y))
+        });
+
+        if let FileType::LibraryFile { components } = &mut self.header.ty {
+            for d in components.iter_mut() {
+                d.height = round(d.height, 4);
+                for p in d.loops.iter_mut().flat_map(|l| l.points.iter_mut()) {
+                    p.x = round(p.x, 4);
+                    p.y = round(p.y, 4);
+                    p.angle = round(p.angle, 4);
+                }
+            }
+            components.sort_by(|a, b| {
+                (*a.geometry_name)
+                    .cmp(&*b.geometry_name)
+                    .then_with(|| (*a.part_number).cmp(&*b.part_number))
+            });
+        }
+    }
+
+    /// Hash of the canonical form, leaving out the header source, date and board file version.
+    /// Exports that only differ in formatting, order or provenance have the same fingerprint.
+    ///
+    /// The hash is FNV-1a and stays the same across crate and compiler versions, so it can be
+    /// stored and compared later.
+    pub fn fingerprint(&self) -> u64 {
+        let mut canonical = self.clone();
+        canonical.canonicalize();
+        canonical.header.source = Either::Left("");
+        canonical.header.date = Either::Left("");
+        canonical.header.board_file_version = 0;
+        fnv1a(canonical.to_string().as_bytes())
+    }
+}
+
+/// 64-bit FNV-1a hash of `bytes`.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+fn canonicalize_section(section: &mut IdfSection) {
+    let name = section.name().to_string();
+    // Fields holding coordinates or dimensions, by section
+    let numbers = match name.as_str() {
+        "DRILLED_HOLES" => 0..3,
+        "NOTES" => 0..4,
+        _ => 0..0,
+    };
+    let loops = LOOP_SECTIONS.contains(&name.as_str());
+    let records = section.records_mut();
+    for record in records.iter_mut() {
+        let numbers = match loops && is_loop_point(record) {
+            true => 1..4,
+            false => numbers.clone(),
+        };
+        for (i, value) in record.iter_mut().enumerate() {
+            match value {
+                IdfValue::Integer(x) if numbers.contains(&i) => {
+                    *value = IdfValue::Float(*x as f64);
+                }
+                IdfValue::Float(x) => *value = IdfValue::Float(round_f64(*x, 4)),
+                _ => {}
+            }
+        }
+    }
+    if name == "DRILLED_HOLES" {
+        let key = |r: &Vec<IdfValue>, i: usize| r.get(i).and_then(|v| v.as_f64());
+        records.sort_by(|a, b| {
+            [1, 2, 0]
+                .iter()
+                .map(|&i| match (key(a, i), key(b, i)) {
+                    (Some(x), Some(y)) => x.total_cmp(&y),
+                    (x, y) => x.is_some().cmp(&y.is_some()),
+                })
+                .find(|o| o.is_ne())
+                .unwrap_or(Ordering::Equal)
+        });
+    }
+}
+
+fn round(x: f32, decimals: i32) -> f32 {
+    round_f64(x as f64, decimals) as f32
+}
+
+/// Rounds to `decimals`, turning negative zero into zero.
+fn round_f64(x: f64, decimals: i32) -> f64 {
+    let scale = 10f64.powi(decimals);
+    let x = (x * scale).round() / scale;
+    if x == 0.0 {
+        0.0
+    } else {
+        x
+    }
+}
@@ -0,0 +1,219 @@
+//! Design checks on resolved assemblies: component collisions, keepout violations and
+//! placements outside of the board.
+
+use crate::assembly::{IdfAssembly, ResolvedComponent};
+use crate::geometry::{
+    edges_distance, loop_contains, number, polylines_contain, polylines_distance, section_loops,
+    tessellate, BoundingBox,
+};
+use crate::idf30::{BoardSide, IdfValue, Unit};
+
+/// Two components on the same side closer than the requested clearance.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Collision {
+    pub first: String,
+    pub second: String,
+    pub side: BoardSide,
+    /// Distance between the outlines in board units, zero if they overlap
+    pub distance: f64,
+}
+
+impl<'a> IdfAssembly<'a> {
+    /// Reports pairs of components on the same side whose library outlines overlap or are less
+    /// than `clearance` apart. Components without a definition are skipped.
+    pub fn collisions(&self, clearance: f64) -> Vec<Collision> {
+        self.close_pairs(clearance, |_, _| true)
+    }
+
+    /// Like `collisions`, limited to pairs with one component from `first` and the other one
+    /// from `second`, given by designator. An empty list selects every component.
+    /// `min_clearance_mm` is in millimeters whatever the board units, reported distances are
+    /// in board units.
+    pub fn clearance_report(
+        &self,
+        first: &[&str],
+        second: &[&str],
+        min_clearance_mm: f64,
+    ) -> Vec<Collision> {
+        let units = self.board.units().unwrap_or(&Unit::SImm);
+        let clearance = Unit::SImm.convert(min_clearance_mm, units);
+        let selected = |group: &[&str], c: &ResolvedComponent| {
+            group.is_empty() || group.contains(&c.placement.designator.to_string().as_str())
+        };
+        self.close_pairs(clearance, |a, b| {
+            (selected(first, a) && selected(second, b))
+                || (selected(first, b) && selected(second, a))
+        })
+    }
+
+    fn close_pairs(
+        &self,
+        clearance: f64,
+        selected: impl Fn(&ResolvedComponent, &ResolvedComponent) -> bool,
+    ) -> Vec<Collision> {
+        let components: Vec<_> = self
+            .components()
+            .filter_map(|c| {
+                let outline = c.outline();
+                let bbox = BoundingBox::from_points(outline.iter().flatten().copied())?;
+                Some((c, outline, bbox))
+            })
+            .collect();
+        let mut collisions = vec![];
+        for (i, (a, outline_a, bbox_a)) in components.iter().enumerate() {
+            for (b, outline_b, bbox_b) in &components[i + 1..] {
+                if a.placement.board_side != b.placement.board_side
+                    || bbox_a.min_x - clearance > bbox_b.max_x
+                    || bbox_b.min_x - clearance > bbox_a.max_x
+                    || bbox_a.min_y - clearance > bbox_b.max_y
+                    || bbox_b.min_y - clearance > bbox_a.max_y
+                    || !selected(a, b)
+                {
+                    continue;
+                }
+                let distance = polylines_distance(outline_a, outline_b);
+                if distance == 0.0 || distance < clearance {
+                    collisions.push(Collision {
+                        first: a.placement.designator.to_string(),
+                        second: b.placement.designator.to_string(),
+                        side: a.placement.board_side.clone(),
+                        distance,
+                    });
+                }
+            }
+        }
+        collisions
+    }
+}
+
+/// Component within a `.PLACE_KEEPOUT` region, or closer to it than the requested clearance.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeepoutViolation {
+    pub designator: String,
+    /// Index of the keepout among the `.PLACE_KEEPOUT` sections
+    pub keepout: usize,
+    /// Distance between component and keepout outline in board units, zero if they overlap
+    pub distance: f64,
+    /// Height of the component, if its definition is known
+    pub height: Option<f32>,
+    /// Height limit of the keepout, zero if no component may be placed
+    pub max_height: f32,
+}
+
+impl<'a> IdfAssembly<'a> {
+    /// Checks placements against the place keepouts on their side.
+    ///
+    /// Components without a definition are checked by their reference point. With
+    /// `check_heights`, components lower than the keepout's height limit are allowed, otherwise
+    /// any component in the region is reported.
+    pub fn keepout_violations(&self, clearance: f64, check_heights: bool) -> Vec<KeepoutViolation> {
+        let keepouts: Vec<_> = self
+            .board
+            .other_sections
+            .iter()
+            .filter(|s| s.name() == "PLACE_KEEPOUT")
+            .map(|s| {
+                let (sides, max_height) = match s.records().first().map(|r| r.as_slice()) {
+                    Some([IdfValue::String(side), height, ..]) => {
+                        (side.trim_matches('"').to_string(), number(height) as f32)
+                    }
+                    _ => ("BOTH".to_string(), 0.0),
+                };
+                let outline: Vec<Vec<(f64, f64)>> =
+                    section_loops(s).iter().map(|l| tessellate(l)).collect();
+                (sides, max_height, outline)
+            })
+            .collect();
+        let mut violations = vec![];
+        for c in self.components() {
+            let outline = c.footprint();
+            let height = c.height();
+            for (index, (sides, max_height, keepout)) in keepouts.iter().enumerate() {
+                let side = match c.placement.board_side {
+                    BoardSide::Top => "TOP",
+                    BoardSide::Bottom => "BOTTOM",
+                };
+                if sides != "BOTH" && sides != side {
+                    continue;
+                }
+                if check_heights && *max_height > 0.0 && height.is_some_and(|h| h <= *max_height) {
+                    continue;
+                }
+                let distance = polylines_distance(keepout, &outline);
+                if distance == 0.0 || distance < clearance {
+                    violations.push(KeepoutViolation {
+                        designator: c.placement.designator.to_string(),
+                        keepout: index,
+                        distance,
+                        height,
+                        max_height: *max_height,
+                    });
+                }
+            }
+        }
+        violations
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OffBoardReason {
+    /// Reference point outside of the board outline
+    Outside,
+    /// Reference point inside a cutout
+    InCutout,
+    /// Component outline crosses the board edge or a cutout edge
+    CrossesEdge,
+    /// Component outline covers a whole cutout
+    CoversCutout,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OffBoard {
+    pub designator: String,
+    pub reason: OffBoardReason,
+}
+
+impl<'a> IdfAssembly<'a> {
+    /// Reports components that are not fully on the board: by reference point, and by library
+    /// outline where a definition exists. Empty if the board has no outline.
+    pub fn off_board_components(&self) -> Vec<OffBoard> {
+        let Some(outline) = self
+            .board
+            .section("BOARD_OUTLINE")
+            .or_else(|| self.board.section("PANEL_OUTLINE"))
+        else {
+            return vec![];
+        };
+        let raw_loops = section_loops(outline);
+        let loops: Vec<Vec<(f64, f64)>> = raw_loops.iter().map(|l| tessellate(l)).collect();
+        let Some((board, cutouts)) = raw_loops.split_first() else {
+            return vec![];
+        };
+        let mut off_board = vec![];
+        for c in self.components() {
+            let (x, y) = (c.placement.x as f64, c.placement.y as f64);
+            let outline = c.outline();
+            let reason = if !loop_contains(board, x, y) {
+                Some(OffBoardReason::Outside)
+            } else if cutouts.iter().any(|l| loop_contains(l, x, y)) {
+                Some(OffBoardReason::InCutout)
+            } else if !outline.is_empty() && edges_distance(&loops, &outline) == 0.0 {
+                Some(OffBoardReason::CrossesEdge)
+            } else if cutouts.iter().any(|l| {
+                l.first()
+                    .is_some_and(|p| polylines_contain(&outline, p[0], p[1]))
+            }) {
+                Some(OffBoardReason::CoversCutout)
+            } else {
+                None
+            };
+            if let Some(reason) = reason {
+                off_board.push(OffBoard {
+                    designator: c.placement.designator.to_string(),
+                    reason,
+                });
+            }
+        }
+        off_board
+    }
+}
@@ -0,0 +1,153 @@
+use crate::idf30::{ComponentPlacement, Idf30, ReferenceDesignator};
+use std::sync::LazyLock;
+
+/// Designator and package name patterns identifying one kind of component.
+///
+/// Patterns are matched case insensitive against the whole name. `*` matches any run of
+/// characters, `?` any single character and `#` a single digit, so `TP*` is a prefix and
+/// `*FID*` a substring match.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Rule {
+    pub designators: Vec<String>,
+    pub packages: Vec<String>,
+}
+
+impl Rule {
+    pub fn new(designators: &[&str], packages: &[&str]) -> Self {
+        Rule {
+            designators: designators.iter().map(|p| p.to_string()).collect(),
+            packages: packages.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+
+    pub fn matches_designator(&self, designator: &ReferenceDesignator) -> bool {
+        match designator {
+            ReferenceDesignator::Any(d) => self.designators.iter().any(|p| glob(p, d)),
+            _ => false,
+        }
+    }
+
+    pub fn matches_package(&self, package: &str) -> bool {
+        self.packages.iter().any(|p| glob(p, package))
+    }
+
+    /// True if either the designator or the package name of `placement` matches.
+    pub fn matches(&self, placement: &ComponentPlacement) -> bool {
+        self.matches_designator(&placement.designator)
+            || self.matches_package(&placement.package_name)
+    }
+}
+
+/// Tells test points, fiducials and mounting holes apart from regular components.
+///
+/// The default rules follow common naming: `TP` test points, `FID` fiducials and `MH` or
+/// `H<digit>` mounting holes, with packages named after fiducials, mounting holes or test
+/// points matching as well. Fiducials are often placed as NOREFDES, so the package rules
+/// matter for them.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Classifier {
+    pub test_point: Rule,
+    pub fiducial: Rule,
+    pub mounting_hole: Rule,
+}
+
+impl Default for Classifier {
+    fn default() -> Self {
+        Classifier {
+            test_point: Rule::new(&["TP*"], &["TP_*", "TESTPOINT*", "TEST_POINT*"]),
+            fiducial: Rule::new(&["FID*", "FD#*"], &["*FID*", "*FIDUCIAL*"]),
+            mounting_hole: Rule::new(
+                &["MH*", "H#*"],
+                &["*MTG*", "*MOUNTING*", "*MOUNT_HOLE*", "MH_*", "HOLE*"],
+            ),
+        }
+    }
+}
+
+impl Classifier {
+    pub fn is_test_point(&self, placement: &ComponentPlacement) -> bool {
+        self.test_point.matches(placement)
+    }
+
+    pub fn is_fiducial(&self, placement: &ComponentPlacement) -> bool {
+        self.fiducial.matches(placement)
+    }
+
+    pub fn is_mounting_hole(&self, placement: &ComponentPlacement) -> bool {
+        self.mounting_hole.matches(placement)
+    }
+
+    pub fn test_points<'s, 'a>(
+        &'s self,
+        idf: &'s Idf30<'a>,
+    ) -> impl Iterator<Item = &'s ComponentPlacement<'a>> {
+        idf.placement.iter().filter(|p| self.is_test_point(p))
+    }
+
+    pub fn fiducials<'s, 'a>(
+        &'s self,
+        idf: &'s Idf30<'a>,
+    ) -> impl Iterator<Item = &'s ComponentPlacement<'a>> {
+        idf.placement.iter().filter(|p| self.is_fiducial(p))
+    }
+
+    pub fn mounting_holes<'s, 'a>(
+        &'s self,
+        idf: &'s Idf30<'a>,
+    ) -> impl Iterator<Item = &'s ComponentPlacement<'a>> {
+        idf.placement.iter().filter(|p| self.is_mounting_hole(p))
+    }
+}
+
+/// Default rules, built once for the shortcuts below and `ReferenceDesignator::is_test_point`.
+pub(crate) static DEFAULT: LazyLock<Classifier> = LazyLock::new(Classifier::default);
+
+impl<'a> Idf30<'a> {
+    /// Test point placements according to the default `Classifier`.
+    pub fn test_points(&self) -> impl Iterator<Item = &ComponentPlacement<'a>> {
+        DEFAULT.test_points(self)
+    }
+
+    /// Fiducial placements according to the default `Classifier`.
+    pub fn fiducials(&self) -> impl Iterator<Item = &ComponentPlacement<'a>> {
+        DEFAULT.fiducials(self)
+    }
+}
+
+/// Case insensitive match of `name` against a pattern with `*`, `?` and `#` wildcards.
+pub(crate) fn glob(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().map(|c| c.to_ascii_uppercase()).collect();
+    let name: Vec<char> = name.chars().map(|c| c.to_ascii_uppercase()).collect();
+    let (mut p, mut n) = (0, 0);
+    // Position after the last `*` and the name position it was tried at
+    let mut backtrack = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p + 1, n));
+                p += 1;
+            }
+            Some('?') => {
+                p += 1;
+                n += 1;
+            }
+            Some('#') if name[n].is_ascii_digit() => {
+                p += 1;
+                n += 1;
+            }
+            Some(&c) if c != '#' && c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((bp, bn)) => {
+                    p = bp;
+                    n = bn + 1;
+                    backtrack = Some((bp, bn + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
@@ -0,0 +1,129 @@
+//! Header dates, written as `YYYY/MM/DD.HH:MM:SS`.
+
+use crate::idf30::{Error, Header};
+use either::Either;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct HeaderDate {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl HeaderDate {
+    /// Current time in UTC.
+    pub fn now() -> HeaderDate {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        HeaderDate::from_unix(seconds)
+    }
+
+    /// Date and time of seconds since 1970-01-01 00:00:00 UTC.
+    pub fn from_unix(seconds: u64) -> HeaderDate {
+        let (days, time) = (seconds / 86400, seconds % 86400);
+        // Civil from days, counting eras of 400 years starting at 0000-03-01
+        let z = days as i64 + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z.rem_euclid(146097);
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u8;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+        let year = (yoe + era * 400 + i64::from(month <= 2)) as u16;
+        HeaderDate {
+            year,
+            month,
+            day,
+            hour: (time / 3600) as u8,
+            minute: (time % 3600 / 60) as u8,
+            second: (time % 60) as u8,
+        }
+    }
+}
+
+impl FromStr for HeaderDate {
+    type Err = Error;
+
+    /// Accepts `/` or `-` between date fields, `.`, `T`, `-` or `_` before the time, fields
+    /// without leading zeros, and a time without seconds or no time at all.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let malformed = || Error::Malformed("Expected date as YYYY/MM/DD.HH:MM:SS");
+        let s = s.trim_matches('"');
+        // Year, month and day, each followed by its separator if there is one
+        let mut date = vec![];
+        let mut rest = s;
+        while date.len() < 3 {
+            let digits = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            if digits == 0 {
+                return Err(malformed());
+            }
+            date.push(&rest[..digits]);
+            rest = &rest[digits..];
+            if date.len() < 3 {
+                rest = rest.strip_prefix(['/', '-']).ok_or_else(malformed)?;
+            }
+        }
+        let time: Vec<&str> = match rest.chars().next() {
+            None => vec![],
+            Some('.' | 'T' | '-' | '_') => rest[1..].split(':').collect(),
+            Some(_) => return Err(malformed()),
+        };
+        if time.len() > 3 || time.len() == 1 {
+            return Err(malformed());
+        }
+        let field = |v: Option<&&str>, max: u32| -> Result<u32, Error> {
+            match v {
+                None => Ok(0),
+                Some(v) => v.parse().ok().filter(|n| *n <= max).ok_or_else(malformed),
+            }
+        };
+        let date = HeaderDate {
+            year: field(date.first(), 9999)? as u16,
+            month: field(date.get(1), 12)? as u8,
+            day: field(date.get(2), 31)? as u8,
+            hour: field(time.first(), 23)? as u8,
+            minute: field(time.get(1), 59)? as u8,
+            second: field(time.get(2), 60)? as u8,
+        };
+        if date.month == 0 || date.day == 0 {
+            return Err(malformed());
+        }
+        Ok(date)
+    }
+}
+
+impl Display for HeaderDate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:04}/{:02}/{:02}.{:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+impl<'a> Header<'a> {
+    /// Parsed header date, `None` if it is not in a recognized format.
+    pub fn parsed_date(&self) -> Option<HeaderDate> {
+        self.date.parse().ok()
+    }
+
+    pub fn set_date(&mut self, date: HeaderDate) {
+        self.date = Either::Right(date.to_string());
+    }
+
+    /// Sets the date to the current time.
+    pub fn touch(&mut self) {
+        self.set_date(HeaderDate::now());
+    }
+}
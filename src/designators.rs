@@ -0,0 +1,275 @@
+use crate::idf30::{Error, Idf30, ReferenceDesignator};
+use either::Either;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+/// What to do with placements that share a reference designator.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DuplicatePolicy {
+    /// Fail with `Error::DuplicateDesignator` on the first duplicate
+    Error,
+    /// Keep the first placement and remove the later ones
+    KeepFirst,
+    /// Rename the later placements to `<refdes>_2`, `<refdes>_3` and so on
+    AutoSuffix,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DuplicateDesignator {
+    pub designator: String,
+    /// Indices into `Idf30::placement` before resolution, the first one is the original
+    pub indices: Vec<usize>,
+    /// New names of the duplicates, in order, when resolved with `DuplicatePolicy::AutoSuffix`
+    pub renamed: Vec<String>,
+}
+
+impl<'a> Idf30<'a> {
+    /// Reference designators used by more than one placement. NOREFDES and BOARD are expected
+    /// to repeat and are not reported.
+    pub fn duplicate_designators(&self) -> Vec<DuplicateDesignator> {
+        let mut seen: HashMap<&str, usize> = HashMap::new();
+        let mut duplicates: Vec<DuplicateDesignator> = vec![];
+        for (i, c) in self.placement.iter().enumerate() {
+            let ReferenceDesignator::Any(d) = &c.designator else {
+                continue;
+            };
+            match seen.get(&**d) {
+                Some(&at) => duplicates[at].indices.push(i),
+                None => {
+                    seen.insert(d, duplicates.len());
+                    duplicates.push(DuplicateDesignator {
+                        designator: d.to_string(),
+                        indices: vec![i],
+                        renamed: vec![],
+                    });
+                }
+            }
+        }
+        duplicates.retain(|d| d.indices.len() > 1);
+        duplicates
+    }
+
+    /// Applies `policy` to all duplicate reference designators and returns what was found.
+    pub fn resolve_duplicate_designators(
+        &mut self,
+        policy: DuplicatePolicy,
+    ) -> Result<Vec<DuplicateDesignator>, Error> {
+        let mut duplicates = self.duplicate_designators();
+        match policy {
+            DuplicatePolicy::Error => {
+                if let Some(d) = duplicates.first() {
+                    return Err(Error::DuplicateDesignator(d.designator.clone()));
+                }
+            }
+            DuplicatePolicy::KeepFirst => {
+                let remove: HashSet<usize> = duplicates
+                    .iter()
+                    .flat_map(|d| d.indices.iter().skip(1).copied())
+                    .collect();
+                let mut i = 0;
+                self.placement.retain(|_| {
+                    i += 1;
+                    !remove.contains(&(i - 1))
+                });
+            }
+            DuplicatePolicy::AutoSuffix => {
+                let mut taken: HashSet<String> = self
+                    .placement
+                    .iter()
+                    .map(|c| c.designator.to_string())
+                    .collect();
+                for d in &mut duplicates {
+                    let mut n = 2;
+                    for &i in &d.indices[1..] {
+                        let mut name = format!("{}_{}", d.designator, n);
+                        while taken.contains(&name) {
+                            n += 1;
+                            name = format!("{}_{}", d.designator, n);
+                        }
+                        n += 1;
+                        taken.insert(name.clone());
+                        self.placement[i].designator =
+                            ReferenceDesignator::Any(Either::Right(name.clone()));
+                        d.renamed.push(name);
+                    }
+                }
+            }
+        }
+        Ok(duplicates)
+    }
+}
+
+impl<'a> ReferenceDesignator<'a> {
+    /// Alphabetic part in front of the number, e.g. "R" in "R15".
+    pub fn prefix(&self) -> Option<&str> {
+        match self {
+            ReferenceDesignator::Any(d) => Some(split_designator(d).0),
+            _ => None,
+        }
+    }
+
+    /// Number following the prefix, e.g. 15 in "R15" or "R15A".
+    pub fn number(&self) -> Option<u32> {
+        match self {
+            ReferenceDesignator::Any(d) => split_designator(d).1.parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+/// Splits "R15A" into ("R", "15", "A").
+pub(crate) fn split_designator(d: &str) -> (&str, &str, &str) {
+    let digits_at = d.find(|c: char| c.is_ascii_digit()).unwrap_or(d.len());
+    let (prefix, rest) = d.split_at(digits_at);
+    let digits_end = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    let (number, suffix) = rest.split_at(digits_end);
+    (prefix, number, suffix)
+}
+
+impl<'a> PartialEq for ReferenceDesignator<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<'a> Eq for ReferenceDesignator<'a> {}
+
+/// Consistent with `Eq`, borrowed and owned designators with the same text hash the same.
+impl<'a> Hash for ReferenceDesignator<'a> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        rank(self).hash(state);
+        if let ReferenceDesignator::Any(d) = self {
+            (**d).hash(state);
+        }
+    }
+}
+
+impl<'a> PartialOrd for ReferenceDesignator<'a> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Natural ordering: by prefix, then numerically (R2 before R10), then by the remainder.
+/// Designated components come before NOREFDES, which come before BOARD.
+impl<'a> Ord for ReferenceDesignator<'a> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (ReferenceDesignator::Any(a), ReferenceDesignator::Any(b)) => {
+                let (a_prefix, a_number, a_suffix) = split_designator(a);
+                let (b_prefix, b_number, b_suffix) = split_designator(b);
+                a_prefix
+                    .cmp(b_prefix)
+                    .then_with(|| {
+                        let a_number = a_number.trim_start_matches('0');
+                        let b_number = b_number.trim_start_matches('0');
+                        a_number
+                            .len()
+                            .cmp(&b_number.len())
+                            .then_with(|| a_number.cmp(b_number))
+                    })
+                    .then_with(|| a_suffix.cmp(b_suffix))
+                    .then_with(|| (**a).cmp(&**b))
+            }
+            (a, b) => rank(a).cmp(&rank(b)),
+        }
+    }
+}
+
+fn rank(d: &ReferenceDesignator) -> u8 {
+    match d {
+        ReferenceDesignator::Any(_) => 0,
+        ReferenceDesignator::NoRefDes => 1,
+        ReferenceDesignator::Board => 2,
+    }
+}
+
+/// Order in which `Idf30::renumber_designators` hands out new numbers within each prefix.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RenumberScheme {
+    /// Keep the current natural order of the designators
+    Sequential,
+    /// Rows from top to bottom, left to right within a row. Rows are bands `row_height` high
+    /// counted down from the topmost placement, so two components close to a band edge can
+    /// sit in different rows.
+    Geographic { row_height: f32 },
+}
+
+impl<'a> Idf30<'a> {
+    /// Renumbers designated placements per prefix starting from 1, e.g. R7, R3, R12 become R1, R2,
+    /// R3, and returns the old to new name mapping so netlists can be updated. Suffixes are kept
+    /// and designators sharing a number keep sharing one, e.g. U4A and U4B become U1A and U1B.
+    /// The prefix is kept as written, so J-4 becomes J-1 and is numbered apart from J4.
+    /// Designators starting with a digit or without one, such as LOGO, are left as they are.
+    ///
+    /// Duplicate designators should be resolved first, as they map to a single entry.
+    pub fn renumber_designators(
+        &mut self,
+        scheme: RenumberScheme,
+    ) -> Result<HashMap<String, String>, Error> {
+        if let RenumberScheme::Geographic { row_height } = scheme {
+            if row_height.is_nan() || row_height <= 0.0 {
+                return Err(Error::Malformed("Row height has to be positive"));
+            }
+        }
+        // Placements of every number, e.g. U4A and U4B, by prefix
+        let mut groups: HashMap<String, HashMap<String, Vec<usize>>> = HashMap::new();
+        for (i, c) in self.placement.iter().enumerate() {
+            let ReferenceDesignator::Any(d) = &c.designator else {
+                continue;
+            };
+            let (prefix, number, _) = split_designator(d);
+            if prefix.is_empty() || number.is_empty() {
+                continue;
+            }
+            groups
+                .entry(prefix.to_string())
+                .or_default()
+                .entry(number.to_string())
+                .or_default()
+                .push(i);
+        }
+        let top = self
+            .placement
+            .iter()
+            .map(|c| c.y)
+            .fold(f32::NEG_INFINITY, f32::max);
+        let mut mapping = HashMap::new();
+        for (prefix, numbers) in groups {
+            // Every number is ordered by its first placement in the scheme's order
+            let mut numbered: Vec<Vec<usize>> = numbers.into_values().collect();
+            for indices in &mut numbered {
+                indices.sort_by(|&a, &b| self.compare(scheme, top, a, b));
+            }
+            numbered.sort_by(|a, b| self.compare(scheme, top, a[0], b[0]));
+            for (n, indices) in numbered.into_iter().enumerate() {
+                for i in indices {
+                    let old = self.placement[i].designator.to_string();
+                    let (_, _, suffix) = split_designator(&old);
+                    let new = format!("{}{}{}", prefix, n + 1, suffix);
+                    self.placement[i].designator =
+                        ReferenceDesignator::Any(Either::Right(new.clone()));
+                    mapping.insert(old, new);
+                }
+            }
+        }
+        Ok(mapping)
+    }
+
+    /// Order of two placements under `scheme`, `top` being the highest Y of the board.
+    fn compare(&self, scheme: RenumberScheme, top: f32, a: usize, b: usize) -> Ordering {
+        let (a, b) = (&self.placement[a], &self.placement[b]);
+        match scheme {
+            RenumberScheme::Sequential => a.designator.cmp(&b.designator),
+            RenumberScheme::Geographic { row_height } => {
+                let row = |y: f32| ((top - y) / row_height).floor();
+                row(a.y)
+                    .total_cmp(&row(b.y))
+                    .then_with(|| a.x.total_cmp(&b.x))
+            }
+        }
+    }
+}
@@ -0,0 +1,246 @@
+//! Manufacturability summary combining the design checks and outline validation, written as
+//! CSV or JSON for review tools.
+
+use crate::assembly::IdfAssembly;
+use crate::formats::centroid::csv_field;
+use crate::geometry::{edges_distance, section_loops, tessellate};
+use crate::idf30::{BoardSide, Error};
+use std::fmt::{Display, Formatter};
+
+/// Limits checked by `IdfAssembly::dfm_report`, in board units. Unset limits are not checked.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DfmRules {
+    pub max_height_top: Option<f32>,
+    pub max_height_bottom: Option<f32>,
+    pub min_hole_diameter: Option<f32>,
+    /// Smallest distance of component outlines and holes to the board edge and cutouts
+    pub min_edge_clearance: Option<f64>,
+    /// Clearance to place keepouts, keepout height limits are taken into account
+    pub keepout_clearance: f64,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DfmCategory {
+    Height,
+    HoleDiameter,
+    EdgeClearance,
+    Keepout,
+    /// Loops that are not closed, cross themselves or run the wrong way
+    Outline,
+    /// Records that could not be read and were left out of the other checks
+    Malformed,
+}
+
+impl Display for DfmCategory {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DfmCategory::Height => write!(f, "height"),
+            DfmCategory::HoleDiameter => write!(f, "hole_diameter"),
+            DfmCategory::EdgeClearance => write!(f, "edge_clearance"),
+            DfmCategory::Keepout => write!(f, "keepout"),
+            DfmCategory::Outline => write!(f, "outline"),
+            DfmCategory::Malformed => write!(f, "malformed"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DfmFinding {
+    pub category: DfmCategory,
+    /// Designator, hole position or outline the finding is about
+    pub subject: String,
+    /// Measured value and the limit it breaks, for numeric checks
+    pub value: Option<f64>,
+    pub limit: Option<f64>,
+    pub message: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DfmReport {
+    pub findings: Vec<DfmFinding>,
+}
+
+impl DfmReport {
+    pub fn is_empty(&self) -> bool {
+        self.findings.is_empty()
+    }
+
+    /// One finding per row with Category, Subject, Value, Limit and Message columns.
+    pub fn to_csv(&self) -> String {
+        let mut s = String::from("Category,Subject,Value,Limit,Message\n");
+        let number = |v: Option<f64>| v.map(|v| format!("{v:.4}")).unwrap_or_default();
+        for f in &self.findings {
+            s.push_str(&format!(
+                "{},{},{},{},{}\n",
+                f.category,
+                csv_field(&f.subject),
+                number(f.value),
+                number(f.limit),
+                csv_field(&f.message),
+            ));
+        }
+        s
+    }
+
+    /// Array of finding objects with the same fields as the CSV, missing numbers are null.
+    pub fn to_json(&self) -> String {
+        let number = |v: Option<f64>| match v {
+            Some(v) if v.is_finite() => format!("{v:.4}"),
+            _ => "null".to_string(),
+        };
+        let findings: Vec<String> = self
+            .findings
+            .iter()
+            .map(|f| {
+                format!(
+                    "  {{\"category\": \"{}\", \"subject\": {}, \"value\": {}, \"limit\": {}, \"message\": {}}}",
+                    f.category,
+                    json_string(&f.subject),
+                    number(f.value),
+                    number(f.limit),
+                    json_string(&f.message),
+                )
+            })
+            .collect();
+        if findings.is_empty() {
+            return "[]\n".to_string();
+        }
+        format!("[\n{}\n]\n", findings.join(",\n"))
+    }
+}
+
+impl<'a> IdfAssembly<'a> {
+    /// Checks component heights, hole diameters, edge clearances, place keepouts and the board
+    /// and library outlines against `rules`.
+    pub fn dfm_report(&self, rules: &DfmRules) -> Result<DfmReport, Error> {
+        let mut findings = vec![];
+        let mut push = |category, subject: String, value, limit, message: String| {
+            findings.push(DfmFinding {
+                category,
+                subject,
+                value,
+                limit,
+                message,
+            })
+        };
+
+        for c in self.components() {
+            let limit = match c.placement.board_side {
+                BoardSide::Top => rules.max_height_top,
+                BoardSide::Bottom => rules.max_height_bottom,
+            };
+            if let (Some(height), Some(limit)) = (c.height(), limit) {
+                if height > limit {
+                    push(
+                        DfmCategory::Height,
+                        c.placement.designator.to_string(),
+                        Some(height as f64),
+                        Some(limit as f64),
+                        format!("Component is {height} high, {limit} allowed"),
+                    );
+                }
+            }
+        }
+
+        let (holes, errors) = self.board.drilled_holes_lossy();
+        for e in errors {
+            let (subject, message) = match e {
+                Error::At { location, source } => (location.to_string(), source.to_string()),
+                e => (".DRILLED_HOLES".to_string(), e.to_string()),
+            };
+            push(DfmCategory::Malformed, subject, None, None, message);
+        }
+        if let Some(min) = rules.min_hole_diameter {
+            for h in holes.iter().filter(|h| h.diameter < min) {
+                push(
+                    DfmCategory::HoleDiameter,
+                    hole_name(h.x, h.y),
+                    Some(h.diameter as f64),
+                    Some(min as f64),
+                    format!("Hole diameter {} below {min}", h.diameter),
+                );
+            }
+        }
+
+        let outline = self
+            .board
+            .section("BOARD_OUTLINE")
+            .or_else(|| self.board.section("PANEL_OUTLINE"));
+        if let (Some(outline), Some(min)) = (outline, rules.min_edge_clearance) {
+            let edges: Vec<Vec<(f64, f64)>> = section_loops(outline)
+                .iter()
+                .map(|l| tessellate(l))
+                .collect();
+            for c in self.components() {
+                let distance = edges_distance(&edges, &c.footprint());
+                if distance < min {
+                    push(
+                        DfmCategory::EdgeClearance,
+                        c.placement.designator.to_string(),
+                        Some(distance),
+                        Some(min),
+                        format!("Component is {distance:.4} from the board edge"),
+                    );
+                }
+            }
+            for h in &holes {
+                let point = vec![vec![(h.x as f64, h.y as f64)]];
+                let distance = (edges_distance(&edges, &point) - h.diameter as f64 / 2.0).max(0.0);
+                if distance < min {
+                    push(
+                        DfmCategory::EdgeClearance,
+                        hole_name(h.x, h.y),
+                        Some(distance),
+                        Some(min),
+                        format!("Hole is {distance:.4} from the board edge"),
+                    );
+                }
+            }
+        }
+
+        for v in self.keepout_violations(rules.keepout_clearance, true) {
+            push(
+                DfmCategory::Keepout,
+                v.designator,
+                Some(v.distance),
+                Some(rules.keepout_clearance),
+                format!("Component is in or near place keepout {}", v.keepout),
+            );
+        }
+
+        for issue in self
+            .board
+            .loop_issues(false)
+            .into_iter()
+            .chain(self.library.loop_issues(false))
+        {
+            push(
+                DfmCategory::Outline,
+                issue.source.to_string(),
+                None,
+                None,
+                issue.to_string(),
+            );
+        }
+        Ok(DfmReport { findings })
+    }
+}
+
+fn hole_name(x: f32, y: f32) -> String {
+    format!("hole at {x:.4} {y:.4}")
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -0,0 +1,242 @@
+//! Parsing of files from tools that deviate from the IDF 3.0 specification. Their quirks are
+//! rewritten into standard IDF text, which is then parsed as usual.
+
+use crate::idf30::{escape_string, field_len, Error, Idf30};
+use std::borrow::Cow;
+
+/// Tool whose deviations from the specification are accepted.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Dialect {
+    /// Only files following the specification
+    #[default]
+    Standard,
+    /// Altium Designer: multi-word source, board name, part number and geometry fields without
+    /// quotes, and placement statuses such as `Placed` or `Locked`
+    Altium,
+    /// Cadence Allegro: extra columns after the owner of drilled holes and `ANY` as owner
+    Allegro,
+    /// Mentor Xpedition: both placement records of a component on one line and property
+    /// records other than `PROP` in ELECTRICAL sections, which are rewritten as `PROP`. The
+    /// model keeps no properties, each one is reported as `ParseWarning::DroppedProperty`.
+    Xpedition,
+    /// Eagle and Fusion ULP exports: header without board file version and coordinates
+    /// without decimals
+    Eagle,
+}
+
+impl<'a> Idf30<'a> {
+    /// Parses a file written by a tool with the quirks of `dialect`. The model owns its strings
+    /// as the text is rewritten before parsing.
+    pub fn parse_dialect(file: &str, dialect: Dialect) -> Result<Idf30<'static>, Error> {
+        Ok(Idf30::parse(&dialect.normalize(file))?.into_owned())
+    }
+}
+
+impl Dialect {
+    /// `file` with the quirks of this dialect rewritten into standard IDF, unchanged for
+    /// `Standard`.
+    pub fn normalize<'f>(&self, file: &'f str) -> Cow<'f, str> {
+        if *self == Dialect::Standard {
+            return Cow::Borrowed(file);
+        }
+        let mut out = String::with_capacity(file.len());
+        let mut section: Option<&str> = None;
+        // Records of the current section written so far
+        let mut record = 0;
+        for line in file.lines() {
+            let trimmed = line.trim();
+            if let Some(keyword) = trimmed.strip_prefix('.') {
+                let fixed = match section {
+                    None => {
+                        section = Some(keyword.split_whitespace().next().unwrap_or_default());
+                        self.section_start(&fields(trimmed))
+                    }
+                    Some(_) => {
+                        section = None;
+                        None
+                    }
+                };
+                record = 0;
+                out.push_str(fixed.as_deref().unwrap_or(line));
+                out.push('\n');
+                continue;
+            }
+            let Some(name) = section.filter(|_| !trimmed.is_empty() && !trimmed.starts_with('#'))
+            else {
+                out.push_str(line);
+                out.push('\n');
+                continue;
+            };
+            let fields = fields(trimmed);
+            let fixed = match self {
+                Dialect::Standard => None,
+                Dialect::Altium => altium_record(name, record, &fields),
+                Dialect::Allegro => allegro_record(name, &fields),
+                Dialect::Xpedition => xpedition_record(name, record, &fields),
+                Dialect::Eagle => eagle_record(name, record, &fields),
+            };
+            match fixed {
+                Some(records) => {
+                    for r in records {
+                        out.push_str(&r.join(" "));
+                        out.push('\n');
+                        record += 1;
+                    }
+                }
+                None => {
+                    out.push_str(line);
+                    out.push('\n');
+                    record += 1;
+                }
+            }
+        }
+        Cow::Owned(out)
+    }
+
+    /// Replacement for the first line of a section, `None` to keep it.
+    fn section_start(&self, fields: &[&str]) -> Option<String> {
+        match self {
+            Dialect::Allegro if fields.contains(&"ANY") => Some(
+                fields
+                    .iter()
+                    .map(|f| allegro_owner(f))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            ),
+            _ => None,
+        }
+    }
+}
+
+/// Whitespace separated fields, quoted strings kept whole with their quotes.
+fn fields(line: &str) -> Vec<&str> {
+    let mut fields = vec![];
+    let mut rest = line;
+    while let Some(start) = rest.find(|c: char| !c.is_whitespace()) {
+        let end = start + field_len(&rest[start..]);
+        fields.push(&rest[start..end]);
+        rest = &rest[end..];
+    }
+    fields
+}
+
+/// Unquoted words forming one string field, quoted as needed.
+fn joined(words: &[&str]) -> String {
+    escape_string(&words.join(" ")).into_owned()
+}
+
+/// Records replacing `fields`, the `index`-th record of `section`, `None` to keep it as is.
+type Fixed = Option<Vec<Vec<String>>>;
+
+fn altium_record(section: &str, index: usize, fields: &[&str]) -> Fixed {
+    let n = fields.len();
+    let owned = |f: &[&str]| f.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+    let record = match (section, index) {
+        // Type, version, source, date and board file version
+        ("HEADER", 0) if n > 5 => [
+            owned(&fields[..2]),
+            vec![joined(&fields[2..n - 2])],
+            owned(&fields[n - 2..]),
+        ]
+        .concat(),
+        ("HEADER", 1) if n > 2 => vec![joined(&fields[..n - 1]), fields[n - 1].to_string()],
+        ("PLACEMENT", i) if i % 2 == 0 && n > 3 => vec![
+            fields[0].to_string(),
+            joined(&fields[1..n - 1]),
+            fields[n - 1].to_string(),
+        ],
+        ("PLACEMENT", i) if i % 2 != 0 && n == 6 => {
+            let mut record = owned(fields);
+            record[5] = altium_status(fields[5])?.to_string();
+            record
+        }
+        ("ELECTRICAL", 0) if n > 4 => [
+            vec![fields[0].to_string(), joined(&fields[1..n - 2])],
+            owned(&fields[n - 2..]),
+        ]
+        .concat(),
+        _ => return None,
+    };
+    Some(vec![record])
+}
+
+/// Standard placement status for the ones Altium writes, case insensitive. Locked components
+/// are owned by the ECAD side.
+fn altium_status(status: &str) -> Option<&'static str> {
+    Some(match status.to_ascii_uppercase().as_str() {
+        "PLACED" => "PLACED",
+        "UNPLACED" | "NONE" | "FREE" => "UNPLACED",
+        "ECAD" | "LOCKED" | "FIXED" => "ECAD",
+        "MCAD" => "MCAD",
+        _ => return None,
+    })
+}
+
+/// Drilled holes have seven fields, the owner last.
+fn allegro_record(section: &str, fields: &[&str]) -> Fixed {
+    if section != "DRILLED_HOLES" || fields.len() < 7 {
+        return None;
+    }
+    if fields.len() == 7 && fields[6] != "ANY" {
+        return None;
+    }
+    let mut record: Vec<String> = fields[..6].iter().map(|f| f.to_string()).collect();
+    record.push(allegro_owner(fields[6]).to_string());
+    Some(vec![record])
+}
+
+/// `ANY` lets either side change the data, which is what UNOWNED means.
+fn allegro_owner(owner: &str) -> &str {
+    if owner == "ANY" {
+        "UNOWNED"
+    } else {
+        owner
+    }
+}
+
+fn xpedition_record(section: &str, index: usize, fields: &[&str]) -> Fixed {
+    let n = fields.len();
+    match section {
+        // Names followed by position, rotation, side and status
+        "PLACEMENT" if index % 2 == 0 && n >= 9 && matches!(fields[n - 2], "TOP" | "BOTTOM") => {
+            let owned = |f: &[&str]| f.iter().map(|s| s.to_string()).collect();
+            Some(vec![owned(&fields[..n - 6]), owned(&fields[n - 6..])])
+        }
+        // Properties such as PART_PROP, with the name and value of a PROP record
+        "ELECTRICAL" if index > 0 && fields[0] != "PROP" && fields[0].parse::<u32>().is_err() => {
+            let mut record = vec!["PROP".to_string()];
+            record.extend(fields[1..].iter().map(|f| f.to_string()));
+            Some(vec![record])
+        }
+        _ => None,
+    }
+}
+
+fn eagle_record(section: &str, index: usize, fields: &[&str]) -> Fixed {
+    let n = fields.len();
+    let floats = |range: std::ops::Range<usize>| {
+        let record: Vec<String> = fields
+            .iter()
+            .enumerate()
+            .map(|(i, f)| match f.parse::<i64>() {
+                Ok(_) if range.contains(&i) => format!("{f}.0"),
+                _ => f.to_string(),
+            })
+            .collect();
+        Some(vec![record])
+    };
+    match (section, index) {
+        // Version 1 when missing
+        ("HEADER", 0) if n == 4 => {
+            let mut record: Vec<String> = fields.iter().map(|f| f.to_string()).collect();
+            record.push("1".to_string());
+            Some(vec![record])
+        }
+        // Position, z and rotation
+        ("PLACEMENT", i) if i % 2 != 0 => floats(0..4),
+        // Height, then loop points
+        ("ELECTRICAL", 0) => floats(n.saturating_sub(1)..n),
+        ("ELECTRICAL", _) if fields[0] != "PROP" => floats(1..4),
+        _ => None,
+    }
+}
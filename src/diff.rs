@@ -0,0 +1,321 @@
+//! Differences between revisions of a board and three-way merging of ECAD and MCAD changes.
+
+use crate::idf30::{
+    ComponentPlacement, Error, FileType, Idf30, IdfSection, IdfValue, Owner, SectionRef,
+};
+use std::collections::{HashMap, HashSet};
+
+/// Changes needed to turn one revision of a board into another.
+#[derive(Clone, Debug, Default)]
+pub struct BoardDiff<'a> {
+    pub placements: Vec<ItemChange<ComponentPlacement<'a>>>,
+    pub sections: Vec<ItemChange<IdfSection<'a>>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ItemChange<T> {
+    /// Reference designator of a placement or section name, numbered with its index (e.g.
+    /// `ROUTE_KEEPOUT[1]`) if the board has more than one
+    pub key: String,
+    pub change: Change<T>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Change<T> {
+    Added(T),
+    Removed(T),
+    Changed { before: T, after: T },
+}
+
+impl<'a> BoardDiff<'a> {
+    pub fn is_empty(&self) -> bool {
+        self.placements.is_empty() && self.sections.is_empty()
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ConflictKind {
+    /// Both sides changed the item in different ways
+    BothChanged,
+    /// The side that changed the item does not own it, the change was dropped
+    NotOwner(Owner),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MergeConflict {
+    /// Key of the item as in `ItemChange`
+    pub item: String,
+    pub kind: ConflictKind,
+}
+
+#[derive(Clone, Debug)]
+pub struct Merge<'a> {
+    pub merged: Idf30<'a>,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+impl<'a> Idf30<'a> {
+    /// Placements are matched by reference designator and sections by name and order,
+    /// coordinates within `tolerance` are considered unchanged.
+    pub fn diff(&self, other: &Idf30<'a>, tolerance: f32) -> BoardDiff<'a> {
+        BoardDiff {
+            placements: diff_items(&self.placement, &other.placement, placement_keys, |a, b| {
+                a.approx_eq(b, tolerance)
+            }),
+            sections: diff_items(
+                &self.other_sections,
+                &other.other_sections,
+                section_keys,
+                |a, b| a.approx_eq(b, tolerance),
+            ),
+        }
+    }
+
+    /// Three-way merge of an ECAD and an MCAD revision of `base`.
+    ///
+    /// An item changed by one side only takes that change if the side may modify it: placements
+    /// with ECAD or MCAD status, sections and drilled holes with an ECAD or MCAD owner belong to
+    /// that system, everything else may be changed by both. Holes are merged one by one, matched
+    /// by position and diameter. Items changed by both sides in different ways
+    /// are conflicts and keep the owner's version, or the base version if there is no owner.
+    /// The header is taken from `base`.
+    pub fn merge(
+        base: &Idf30<'a>,
+        ecad: &Idf30<'a>,
+        mcad: &Idf30<'a>,
+        tolerance: f32,
+    ) -> Result<Merge<'a>, Error> {
+        for board in [base, ecad, mcad] {
+            if matches!(board.header.ty, FileType::LibraryFile { .. }) {
+                return Err(Error::WrongFileType);
+            }
+        }
+        let mut conflicts = vec![];
+        let placement = merge_items(
+            [&base.placement, &ecad.placement, &mcad.placement],
+            placement_keys,
+            |a, b| a.approx_eq(b, tolerance),
+            |p| p.placement_status.owner(),
+            &mut conflicts,
+        );
+        let [base_sections, ecad_sections, mcad_sections] = [base, ecad, mcad].map(|board| {
+            board
+                .other_sections
+                .iter()
+                .filter(|s| s.name() != "DRILLED_HOLES")
+                .cloned()
+                .collect::<Vec<_>>()
+        });
+        let mut other_sections = merge_items(
+            [&base_sections, &ecad_sections, &mcad_sections],
+            section_keys,
+            |a, b| a.approx_eq(b, tolerance),
+            |s| s.owner().filter(|o| *o != Owner::Unowned),
+            &mut conflicts,
+        );
+
+        // Holes are merged one by one, each with the owner of its record
+        let holes = [base, ecad, mcad].map(|board| {
+            board
+                .other_sections
+                .iter()
+                .filter(|s| s.name() == "DRILLED_HOLES")
+                .flat_map(|s| s.records().iter().cloned())
+                .collect::<Vec<_>>()
+        });
+        let hole_section = [base, ecad, mcad]
+            .iter()
+            .find_map(|board| board.section("DRILLED_HOLES"));
+        if let Some(section) = hole_section {
+            let mut section = section.clone();
+            *section.records_mut() = merge_items(
+                holes.each_ref().map(Vec::as_slice),
+                hole_keys,
+                |a, b| {
+                    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.approx_eq(b, tolerance))
+                },
+                |r| {
+                    r.get(6)
+                        .and_then(IdfValue::as_str)
+                        .and_then(|o| o.parse().ok())
+                        .filter(|o| *o != Owner::Unowned)
+                },
+                &mut conflicts,
+            );
+            other_sections.push(section);
+        }
+
+        // Sections in base order, additions as they were merged
+        let merged_keys = section_keys(&other_sections);
+        let base_keys = section_keys(&base.other_sections);
+        let section_order = base
+            .section_order
+            .iter()
+            .filter_map(|r| match *r {
+                SectionRef::Other(i) => {
+                    let key = base_keys.get(i)?;
+                    merged_keys
+                        .iter()
+                        .position(|k| k == key)
+                        .map(SectionRef::Other)
+                }
+                r => Some(r),
+            })
+            .collect();
+        Ok(Merge {
+            merged: Idf30 {
+                header: base.header.clone(),
+                placement,
+                other_sections,
+                section_order,
+                warnings: vec![],
+            },
+            conflicts,
+        })
+    }
+}
+
+/// Holes keyed by position and diameter, e.g. `DRILLED_HOLES(65, 60.5, 1.397)`.
+fn hole_keys(records: &[Vec<IdfValue>]) -> Vec<String> {
+    keys(records.iter().map(|r| {
+        let value = |i: usize| r.get(i).and_then(IdfValue::as_f64).unwrap_or(f64::NAN);
+        format!("DRILLED_HOLES({}, {}, {})", value(1), value(2), value(0))
+    }))
+}
+
+pub(crate) fn placement_keys(placements: &[ComponentPlacement]) -> Vec<String> {
+    keys(placements.iter().map(|p| p.designator.to_string()))
+}
+
+pub(crate) fn section_keys(sections: &[IdfSection]) -> Vec<String> {
+    keys(sections.iter().map(|s| s.name().to_string()))
+}
+
+/// Numbers repeated names in order, e.g. `NOREFDES[0]`, `NOREFDES[1]`, and leaves unique ones
+/// as they are.
+fn keys(names: impl Iterator<Item = String>) -> Vec<String> {
+    let names: Vec<String> = names.collect();
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for name in &names {
+        *counts.entry(name).or_default() += 1;
+    }
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    names
+        .iter()
+        .map(|name| {
+            if counts[name.as_str()] == 1 {
+                name.clone()
+            } else {
+                let nth = seen.entry(name).or_default();
+                *nth += 1;
+                format!("{name}[{}]", *nth - 1)
+            }
+        })
+        .collect()
+}
+
+fn diff_items<T: Clone>(
+    before: &[T],
+    after: &[T],
+    keys: impl Fn(&[T]) -> Vec<String>,
+    same: impl Fn(&T, &T) -> bool,
+) -> Vec<ItemChange<T>> {
+    let (before_keys, after_keys) = (keys(before), keys(after));
+    let after_index: HashMap<&str, usize> = after_keys
+        .iter()
+        .enumerate()
+        .map(|(i, k)| (k.as_str(), i))
+        .collect();
+    let before_index: HashSet<&str> = before_keys.iter().map(String::as_str).collect();
+    let mut changes = vec![];
+    for (key, item) in before_keys.iter().zip(before) {
+        let change = match after_index.get(key.as_str()).copied() {
+            Some(i) if !same(item, &after[i]) => Change::Changed {
+                before: item.clone(),
+                after: after[i].clone(),
+            },
+            Some(_) => continue,
+            None => Change::Removed(item.clone()),
+        };
+        changes.push(ItemChange {
+            key: key.clone(),
+            change,
+        });
+    }
+    for (key, item) in after_keys.iter().zip(after) {
+        if !before_index.contains(key.as_str()) {
+            changes.push(ItemChange {
+                key: key.clone(),
+                change: Change::Added(item.clone()),
+            });
+        }
+    }
+    changes
+}
+
+fn merge_items<T: Clone>(
+    [base, ecad, mcad]: [&[T]; 3],
+    keys: impl Fn(&[T]) -> Vec<String>,
+    same: impl Fn(&T, &T) -> bool,
+    owner: impl Fn(&T) -> Option<Owner>,
+    conflicts: &mut Vec<MergeConflict>,
+) -> Vec<T> {
+    let revisions = [base, ecad, mcad].map(|items| {
+        keys(items)
+            .into_iter()
+            .zip(items.iter())
+            .collect::<Vec<(String, &T)>>()
+    });
+    let index = revisions.each_ref().map(|revision| {
+        revision
+            .iter()
+            .map(|(k, item)| (k.as_str(), *item))
+            .collect::<HashMap<&str, &T>>()
+    });
+    // Base order first, then additions of ECAD and MCAD
+    let mut seen = HashSet::new();
+    let mut all_keys: Vec<&String> = vec![];
+    for (key, _) in revisions.iter().flatten() {
+        if seen.insert(key) {
+            all_keys.push(key);
+        }
+    }
+    let find = |revision: usize, key: &String| index[revision].get(key.as_str()).copied();
+    let same = |a: Option<&T>, b: Option<&T>| match (a, b) {
+        (Some(a), Some(b)) => same(a, b),
+        (None, None) => true,
+        _ => false,
+    };
+    let mut merged = vec![];
+    for key in all_keys {
+        let (b, e, m) = (find(0, key), find(1, key), find(2, key));
+        let item_owner = b.or(e).or(m).and_then(&owner);
+        let may_change = |side: Owner| !matches!(item_owner, Some(o) if o != side);
+        let mut conflict = |kind| {
+            conflicts.push(MergeConflict {
+                item: key.clone(),
+                kind,
+            })
+        };
+        let result = match (!same(b, e), !same(b, m)) {
+            (false, false) => b,
+            (true, false) if may_change(Owner::ECad) => e,
+            (false, true) if may_change(Owner::MCad) => m,
+            (true, false) | (false, true) => {
+                conflict(ConflictKind::NotOwner(item_owner.unwrap_or(Owner::Unowned)));
+                b
+            }
+            (true, true) if same(e, m) => e,
+            (true, true) => {
+                conflict(ConflictKind::BothChanged);
+                match item_owner {
+                    Some(Owner::ECad) => e,
+                    Some(Owner::MCad) => m,
+                    _ => b,
+                }
+            }
+        };
+        merged.extend(result.cloned());
+    }
+    merged
+}
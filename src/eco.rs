@@ -0,0 +1,139 @@
+//! Engineering change order reports from a diff, for sending placement changes to the
+//! mechanical team.
+
+use crate::diff::{BoardDiff, Change};
+use crate::idf30::{ComponentPlacement, Unit};
+
+/// Distances below this are not reported as moves.
+const EPSILON: f32 = 5e-5;
+
+impl<'a> BoardDiff<'a> {
+    /// One line per changed item, e.g. `R15 moved 2.54 mm +X, rotated 90°`. Distances are in
+    /// `units`, the units of the boards compared.
+    pub fn to_eco_text(&self, units: &Unit) -> String {
+        self.eco_entries(units)
+            .iter()
+            .map(|(key, description)| format!("{key} {description}\n"))
+            .collect()
+    }
+
+    /// Placement and section changes as two Markdown tables, sections without changes are left
+    /// out.
+    pub fn to_eco_markdown(&self, units: &Unit) -> String {
+        let mut s = String::from("# Engineering change order\n");
+        if self.is_empty() {
+            s.push_str("\nNo changes.\n");
+        }
+        let entries = self.eco_entries(units);
+        let (placements, sections) = entries.split_at(self.placements.len());
+        for (title, column, entries) in [
+            ("Components", "Designator", placements),
+            ("Sections", "Section", sections),
+        ] {
+            if entries.is_empty() {
+                continue;
+            }
+            s.push_str(&format!(
+                "\n## {title}\n\n| {column} | Change |\n|---|---|\n"
+            ));
+            for (key, description) in entries {
+                s.push_str(&format!(
+                    "| {} | {} |\n",
+                    key.replace('|', "\\|"),
+                    description.replace('|', "\\|")
+                ));
+            }
+        }
+        s
+    }
+
+    /// Key and description of every change, placements first.
+    fn eco_entries(&self, units: &Unit) -> Vec<(String, String)> {
+        let units = match units {
+            Unit::SImm => "mm",
+            Unit::Mils => "mil",
+        };
+        let placements = self.placements.iter().map(|c| {
+            let description = match &c.change {
+                Change::Added(p) => format!(
+                    "added at ({:.2}, {:.2}) {units}, {}° on {}, package {}",
+                    p.x,
+                    p.y,
+                    trimmed(p.rotation),
+                    p.board_side,
+                    &*p.package_name
+                ),
+                Change::Removed(_) => "removed".to_string(),
+                Change::Changed { before, after } => placement_changes(before, after, units),
+            };
+            (c.key.clone(), description)
+        });
+        let sections = self.sections.iter().map(|c| {
+            let description = match &c.change {
+                Change::Added(_) => "added",
+                Change::Removed(_) => "removed",
+                Change::Changed { .. } => "changed",
+            };
+            (c.key.clone(), description.to_string())
+        });
+        placements.chain(sections).collect()
+    }
+}
+
+fn placement_changes(
+    before: &ComponentPlacement,
+    after: &ComponentPlacement,
+    units: &str,
+) -> String {
+    let mut changes = vec![];
+    let moves: Vec<String> = [("X", after.x - before.x), ("Y", after.y - before.y)]
+        .iter()
+        .filter(|(_, d)| d.abs() >= EPSILON)
+        .map(|(axis, d)| {
+            let sign = if *d > 0.0 { '+' } else { '-' };
+            format!("{:.2} {units} {sign}{axis}", d.abs())
+        })
+        .collect();
+    if !moves.is_empty() {
+        changes.push(format!("moved {}", moves.join(", ")));
+    }
+    let dz = after.z - before.z;
+    if dz.abs() >= EPSILON {
+        changes.push(format!("z offset {:+.2} {units}", dz));
+    }
+    if before.board_side != after.board_side {
+        changes.push(format!("flipped to {}", after.board_side));
+    }
+    // Signed rotation in (-180, 180]
+    let mut rotation = (after.rotation - before.rotation).rem_euclid(360.0);
+    if rotation > 180.0 {
+        rotation -= 360.0;
+    }
+    if rotation.abs() >= EPSILON && (360.0 - rotation.abs()) >= EPSILON {
+        changes.push(format!("rotated {}°", trimmed(rotation)));
+    }
+    for (what, a, b) in [
+        ("package", &*before.package_name, &*after.package_name),
+        ("part", &*before.part_number, &*after.part_number),
+    ] {
+        if a != b {
+            changes.push(format!("{what} {a} → {b}"));
+        }
+    }
+    if before.placement_status != after.placement_status {
+        changes.push(format!(
+            "status {} → {}",
+            before.placement_status, after.placement_status
+        ));
+    }
+    if changes.is_empty() {
+        return "changed".to_string();
+    }
+    changes.join(", ")
+}
+
+/// Angle with up to three decimals and no trailing zeros.
+fn trimmed(angle: f32) -> String {
+    let s = format!("{angle:.3}");
+    s.trim_end_matches('0').trim_end_matches('.').to_string()
+}
@@ -0,0 +1,179 @@
+//! Placement edits with undo and redo, for interactive placement tools.
+
+use crate::diff::BoardDiff;
+use crate::idf30::{ComponentPlacement, Error, Idf30, ReferenceDesignator};
+use crate::transform::normalize_angle;
+
+/// Board being edited, with the log of edits made since it was opened.
+#[derive(Clone, Debug)]
+pub struct BoardEditor<'a> {
+    board: Idf30<'a>,
+    original: Idf30<'a>,
+    done: Vec<EditCommand<'a>>,
+    undone: Vec<EditCommand<'a>>,
+}
+
+/// One edit: the placement at `index` before and after it, `None` before adding or after
+/// removing a component.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EditCommand<'a> {
+    pub index: usize,
+    pub before: Option<ComponentPlacement<'a>>,
+    pub after: Option<ComponentPlacement<'a>>,
+}
+
+impl<'a> BoardEditor<'a> {
+    pub fn new(board: Idf30<'a>) -> Self {
+        BoardEditor {
+            original: board.clone(),
+            board,
+            done: vec![],
+            undone: vec![],
+        }
+    }
+
+    pub fn board(&self) -> &Idf30<'a> {
+        &self.board
+    }
+
+    pub fn into_board(self) -> Idf30<'a> {
+        self.board
+    }
+
+    /// Edits that `undo` would revert, oldest first.
+    pub fn history(&self) -> &[EditCommand<'a>] {
+        &self.done
+    }
+
+    pub fn move_by(&mut self, designator: &str, dx: f32, dy: f32) -> Result<(), Error> {
+        self.modify(designator, |c| {
+            c.x += dx;
+            c.y += dy;
+        })
+    }
+
+    /// Rotates counterclockwise by `angle` degrees around the component origin.
+    pub fn rotate(&mut self, designator: &str, angle: f32) -> Result<(), Error> {
+        self.modify(designator, |c| {
+            c.rotation = normalize_angle((c.rotation + angle) as f64) as f32
+        })
+    }
+
+    /// Moves the component to the other side, mirrored about the vertical axis through its
+    /// origin.
+    pub fn flip(&mut self, designator: &str) -> Result<(), Error> {
+        self.modify(designator, |c| c.flip_about(c.x as f64))
+    }
+
+    /// Adds a component at the end of the placement. Designators other than NOREFDES and BOARD
+    /// have to be unique.
+    pub fn add(&mut self, component: ComponentPlacement<'a>) -> Result<(), Error> {
+        if let ReferenceDesignator::Any(d) = &component.designator {
+            if self.find(d).is_ok() {
+                return Err(Error::DuplicateDesignator(d.to_string()));
+            }
+        }
+        self.apply(EditCommand {
+            index: self.board.placement.len(),
+            before: None,
+            after: Some(component),
+        });
+        Ok(())
+    }
+
+    pub fn remove(&mut self, designator: &str) -> Result<ComponentPlacement<'a>, Error> {
+        let index = self.find(designator)?;
+        let component = self.board.placement[index].clone();
+        self.apply(EditCommand {
+            index,
+            before: Some(component.clone()),
+            after: None,
+        });
+        Ok(component)
+    }
+
+    /// Reverts the last edit, `false` if there is none.
+    pub fn undo(&mut self) -> bool {
+        let Some(command) = self.done.pop() else {
+            return false;
+        };
+        self.replace(
+            command.index,
+            command.after.is_some(),
+            command.before.clone(),
+        );
+        self.undone.push(command);
+        true
+    }
+
+    /// Applies the last undone edit again, `false` if there is none.
+    pub fn redo(&mut self) -> bool {
+        let Some(command) = self.undone.pop() else {
+            return false;
+        };
+        self.replace(
+            command.index,
+            command.before.is_some(),
+            command.after.clone(),
+        );
+        self.done.push(command);
+        true
+    }
+
+    /// Changes from the board as it was opened, coordinates within `tolerance` are considered
+    /// unchanged.
+    pub fn changes(&self, tolerance: f32) -> BoardDiff<'a> {
+        self.original.diff(&self.board, tolerance)
+    }
+
+    /// Index of the first component with this designator.
+    fn find(&self, designator: &str) -> Result<usize, Error> {
+        let designator = ReferenceDesignator::new(designator);
+        self.board
+            .placement
+            .iter()
+            .position(|c| c.designator == designator)
+            .ok_or_else(|| Error::UnknownDesignator(designator.to_string()))
+    }
+
+    fn modify(
+        &mut self,
+        designator: &str,
+        edit: impl FnOnce(&mut ComponentPlacement<'a>),
+    ) -> Result<(), Error> {
+        let index = self.find(designator)?;
+        let before = self.board.placement[index].clone();
+        let mut after = before.clone();
+        edit(&mut after);
+        self.apply(EditCommand {
+            index,
+            before: Some(before),
+            after: Some(after),
+        });
+        Ok(())
+    }
+
+    /// Makes a new edit, which clears the edits that could be redone.
+    fn apply(&mut self, command: EditCommand<'a>) {
+        self.replace(
+            command.index,
+            command.before.is_some(),
+            command.after.clone(),
+        );
+        self.done.push(command);
+        self.undone.clear();
+    }
+
+    /// Puts `component` at `index`, replacing the component there if `occupied`.
+    fn replace(&mut self, index: usize, occupied: bool, component: Option<ComponentPlacement<'a>>) {
+        let placement = &mut self.board.placement;
+        match (occupied, component) {
+            (true, Some(c)) => placement[index] = c,
+            (true, None) => {
+                placement.remove(index);
+            }
+            (false, Some(c)) => placement.insert(index, c),
+            (false, None) => {}
+        }
+    }
+}
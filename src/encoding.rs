@@ -0,0 +1,85 @@
+//! Parsing from byte buffers, such as uploads, in the encodings exporters write.
+
+use crate::idf30::{Error, Idf30};
+use std::borrow::Cow;
+
+/// Text encoding of a file, see `detect_encoding`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Encoding {
+    /// With or without a byte order mark
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    /// Single byte text that is not UTF-8, as written by older Windows exporters
+    Windows1252,
+}
+
+/// Encoding of `bytes`, told by a byte order mark or by the zero high byte of the first
+/// character, which is ASCII in an IDF file. Text that is not UTF-8 is taken for Windows-1252
+/// only if no part of it is UTF-8 beyond ASCII, so that UTF-8 with a few broken bytes is
+/// reported as invalid by `decode` rather than misread.
+pub fn detect_encoding(bytes: &[u8]) -> Encoding {
+    match bytes {
+        [0xef, 0xbb, 0xbf, ..] => Encoding::Utf8,
+        [0xff, 0xfe, ..] => Encoding::Utf16Le,
+        [0xfe, 0xff, ..] => Encoding::Utf16Be,
+        [a, 0, ..] if *a != 0 => Encoding::Utf16Le,
+        [0, b, ..] if *b != 0 => Encoding::Utf16Be,
+        _ if std::str::from_utf8(bytes).is_ok() => Encoding::Utf8,
+        _ if bytes.utf8_chunks().any(|chunk| !chunk.valid().is_ascii()) => Encoding::Utf8,
+        _ => Encoding::Windows1252,
+    }
+}
+
+/// Text of `bytes` in the detected encoding, without a byte order mark. UTF-8 is borrowed.
+pub fn decode(bytes: &[u8]) -> Result<Cow<'_, str>, Error> {
+    match detect_encoding(bytes) {
+        Encoding::Utf8 => {
+            let bytes = bytes.strip_prefix(&[0xef, 0xbb, 0xbf]).unwrap_or(bytes);
+            std::str::from_utf8(bytes)
+                .map(Cow::Borrowed)
+                .map_err(|_| Error::Malformed("Invalid UTF-8"))
+        }
+        Encoding::Utf16Le => utf16(bytes, u16::from_le_bytes, [0xff, 0xfe]),
+        Encoding::Utf16Be => utf16(bytes, u16::from_be_bytes, [0xfe, 0xff]),
+        Encoding::Windows1252 => Ok(Cow::Owned(bytes.iter().map(|&b| windows1252(b)).collect())),
+    }
+}
+
+fn utf16(bytes: &[u8], unit: fn([u8; 2]) -> u16, bom: [u8; 2]) -> Result<Cow<'_, str>, Error> {
+    let bytes = bytes.strip_prefix(&bom).unwrap_or(bytes);
+    if bytes.len() % 2 != 0 {
+        return Err(Error::Malformed("Odd number of bytes in UTF-16 text"));
+    }
+    let units = bytes.chunks_exact(2).map(|c| unit([c[0], c[1]]));
+    char::decode_utf16(units)
+        .collect::<Result<String, _>>()
+        .map(Cow::Owned)
+        .map_err(|_| Error::Malformed("Unpaired surrogate in UTF-16 text"))
+}
+
+/// Windows-1252 byte as a character, bytes the code page leaves undefined as the control
+/// characters of the same value.
+fn windows1252(b: u8) -> char {
+    const HIGH: [char; 32] = [
+        '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8d}', 'Ž',
+        '\u{8f}', '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9d}',
+        'ž', 'Ÿ',
+    ];
+    match b {
+        0x80..=0x9f => HIGH[(b - 0x80) as usize],
+        _ => b as char,
+    }
+}
+
+impl<'a> Idf30<'a> {
+    /// Parses a file from bytes in any encoding `detect_encoding` tells apart, e.g. a shared
+    /// upload buffer. UTF-8 input is parsed in place, other encodings are decoded first and
+    /// the model owns its strings.
+    pub fn parse_bytes<B: AsRef<[u8]> + ?Sized>(bytes: &'a B) -> Result<Idf30<'a>, Error> {
+        match decode(bytes.as_ref())? {
+            Cow::Borrowed(text) => Idf30::parse(text),
+            Cow::Owned(text) => Ok(Idf30::parse(&text)?.into_owned()),
+        }
+    }
+}
@@ -0,0 +1,318 @@
+//! Outlines of standard packages built from their body sizes, to fill in library definitions
+//! that are missing from incomplete `.emp` files, or a whole library when only the board exists.
+
+use crate::idf30::{ComponentDefinition, Error, FileType, Header, Idf30, Loop, Point, Unit};
+use std::collections::HashMap;
+
+/// Two-terminal chip sizes by imperial code.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ChipSize {
+    C0201,
+    C0402,
+    C0603,
+    C0805,
+    C1206,
+    C1210,
+    C2512,
+}
+
+impl ChipSize {
+    /// Length, width and typical height in millimeters.
+    pub fn dimensions(&self) -> (f32, f32, f32) {
+        match self {
+            ChipSize::C0201 => (0.6, 0.3, 0.3),
+            ChipSize::C0402 => (1.0, 0.5, 0.35),
+            ChipSize::C0603 => (1.6, 0.8, 0.45),
+            ChipSize::C0805 => (2.0, 1.25, 0.6),
+            ChipSize::C1206 => (3.2, 1.6, 0.6),
+            ChipSize::C1210 => (3.2, 2.5, 0.6),
+            ChipSize::C2512 => (6.4, 3.2, 0.6),
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<ChipSize> {
+        match code {
+            "0201" => Some(ChipSize::C0201),
+            "0402" => Some(ChipSize::C0402),
+            "0603" => Some(ChipSize::C0603),
+            "0805" => Some(ChipSize::C0805),
+            "1206" => Some(ChipSize::C1206),
+            "1210" => Some(ChipSize::C1210),
+            "2512" => Some(ChipSize::C2512),
+            _ => None,
+        }
+    }
+}
+
+/// Standard package shapes, all dimensions in millimeters.
+///
+/// Outlines are centered on the origin in the IPC-7351 zero orientation: chips lie along X,
+/// gull wing packages have their lead rows on the left and right. Outlines with leads cover
+/// the lead span.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PackageOutline {
+    Chip(ChipSize),
+    Soic {
+        /// Body length along the lead rows
+        body_length: f32,
+        /// Tip to tip distance of the two lead rows
+        lead_span: f32,
+        height: f32,
+    },
+    Qfp {
+        /// Tip to tip lead span along X and Y
+        lead_span: (f32, f32),
+        height: f32,
+    },
+    Qfn {
+        body: (f32, f32),
+        height: f32,
+    },
+    Bga {
+        body: (f32, f32),
+        height: f32,
+    },
+    /// Plain box, for packages without a standard shape
+    Rectangle {
+        size: (f32, f32),
+        height: f32,
+    },
+}
+
+impl PackageOutline {
+    /// Guesses the shape from common package names such as "R0603", "SOIC-8", "QFN-32_5x5" or
+    /// "BGA256_17x17". Gull wing and leadless packages need a `<X>x<Y>` body size in the name,
+    /// SOIC defaults to the narrow 3.9 mm body with a length from the pin count.
+    pub fn from_name(name: &str) -> Option<PackageOutline> {
+        let upper = name.to_ascii_uppercase();
+        let size = body_size(&upper);
+        let family = upper.trim_start_matches(|c: char| !c.is_ascii_alphabetic());
+        if let Some(chip) = chip_code(&upper) {
+            return Some(PackageOutline::Chip(chip));
+        }
+        if family.starts_with("SOIC") || family.starts_with("SO-") {
+            let pins = pin_count(family).unwrap_or(8);
+            // Names give the body as width x length, leads add about 1 mm on each side
+            let (body_length, lead_span) = match size {
+                Some((width, length)) => (length, width + 2.1),
+                None => (pins.div_ceil(2) as f32 * 1.27, 6.0),
+            };
+            return Some(PackageOutline::Soic {
+                body_length,
+                lead_span,
+                height: 1.75,
+            });
+        }
+        let size = size?;
+        if family.starts_with("TQFP") || family.starts_with("LQFP") || family.starts_with("QFP") {
+            // Body size in the name, leads add 1 mm on each side
+            return Some(PackageOutline::Qfp {
+                lead_span: (size.0 + 2.0, size.1 + 2.0),
+                height: if family.starts_with("TQFP") { 1.2 } else { 1.6 },
+            });
+        }
+        if family.starts_with("QFN") || family.starts_with("DFN") {
+            return Some(PackageOutline::Qfn {
+                body: size,
+                height: 0.9,
+            });
+        }
+        if family.starts_with("BGA") || family.starts_with("FBGA") {
+            return Some(PackageOutline::Bga {
+                body: size,
+                height: 1.5,
+            });
+        }
+        None
+    }
+
+    /// Outline size along X and Y and height, in millimeters.
+    pub fn extent(&self) -> (f32, f32, f32) {
+        match *self {
+            PackageOutline::Chip(chip) => chip.dimensions(),
+            PackageOutline::Soic {
+                body_length,
+                lead_span,
+                height,
+            } => (lead_span, body_length, height),
+            PackageOutline::Qfp { lead_span, height } => (lead_span.0, lead_span.1, height),
+            PackageOutline::Qfn { body, height }
+            | PackageOutline::Bga { body, height }
+            | PackageOutline::Rectangle { size: body, height } => (body.0, body.1, height),
+        }
+    }
+
+    /// Library definition with a single counterclockwise rectangle, converted to `units`.
+    pub fn definition(
+        &self,
+        geometry_name: impl Into<String>,
+        part_number: impl Into<String>,
+        units: Unit,
+    ) -> ComponentDefinition<'static> {
+        let (x, y, height) = self.extent();
+        let convert = |v: f32| Unit::SImm.convert(v as f64, &units) as f32;
+        let (hx, hy) = (convert(x / 2.0), convert(y / 2.0));
+        let points = [(-hx, -hy), (hx, -hy), (hx, hy), (-hx, hy), (-hx, -hy)]
+            .into_iter()
+            .map(|(x, y)| Point { x, y, angle: 0.0 })
+            .collect();
+        let mut definition = ComponentDefinition::new(
+            geometry_name.into(),
+            part_number.into(),
+            units.clone(),
+            convert(height),
+        );
+        definition.loops.push(Loop { index: 0, points });
+        definition
+    }
+}
+
+impl<'a> Idf30<'a> {
+    /// Adds definitions for the packages of `board` that this library does not define, using
+    /// `outline` to pick a shape by package name. Returns the number of definitions added,
+    /// packages for which `outline` returns nothing stay missing.
+    pub fn fill_missing_definitions(
+        &mut self,
+        board: &Idf30,
+        units: Unit,
+        outline: impl Fn(&str) -> Option<PackageOutline>,
+    ) -> usize {
+        if !matches!(self.header.ty, FileType::LibraryFile { .. }) {
+            return 0;
+        }
+        let mut added = vec![];
+        for c in &board.placement {
+            if self.definition(&c.package_name, &c.part_number).is_some()
+                || added
+                    .iter()
+                    .any(|d: &ComponentDefinition| *d.geometry_name == *c.package_name)
+            {
+                continue;
+            }
+            if let Some(shape) = outline(&c.package_name) {
+                added.push(shape.definition(
+                    c.package_name.to_string(),
+                    c.part_number.to_string(),
+                    units.clone(),
+                ));
+            }
+        }
+        let count = added.len();
+        if let FileType::LibraryFile { components } = &mut self.header.ty {
+            components.extend(added);
+        }
+        count
+    }
+}
+
+/// Body size of a package for `Idf30::synthesize_library`, in board units.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PackageSize {
+    pub x: f32,
+    pub y: f32,
+    pub height: f32,
+}
+
+/// Library built by `Idf30::synthesize_library`.
+#[derive(Clone, Debug)]
+pub struct SynthesizedLibrary {
+    pub library: Idf30<'static>,
+    /// Packages placed on the board that are not in the size table, in order of first use
+    pub missing: Vec<String>,
+}
+
+impl<'a> Idf30<'a> {
+    /// Creates a library with a rectangular stub definition for every package of this board
+    /// found in `sizes`, keyed by package name. Definitions use the board units and take the
+    /// part number of the first placement of the package.
+    ///
+    /// Packages not in the table are reported as missing, `fill_missing_definitions` can
+    /// guess some of them.
+    pub fn synthesize_library(
+        &self,
+        sizes: &HashMap<String, PackageSize>,
+    ) -> Result<SynthesizedLibrary, Error> {
+        let units = self.units().ok_or(Error::WrongFileType)?.clone();
+        let mut components: Vec<ComponentDefinition<'static>> = vec![];
+        let mut missing: Vec<String> = vec![];
+        for c in &self.placement {
+            let name = &*c.package_name;
+            if components.iter().any(|d| *d.geometry_name == *name)
+                || missing.iter().any(|m| m == name)
+            {
+                continue;
+            }
+            match sizes.get(name) {
+                Some(size) => {
+                    let mm = |v: f32| units.convert(v as f64, &Unit::SImm) as f32;
+                    let outline = PackageOutline::Rectangle {
+                        size: (mm(size.x), mm(size.y)),
+                        height: mm(size.height),
+                    };
+                    components.push(outline.definition(
+                        name,
+                        c.part_number.to_string(),
+                        units.clone(),
+                    ));
+                }
+                None => missing.push(name.to_string()),
+            }
+        }
+        let mut header = Header::new_library();
+        header.ty = FileType::LibraryFile { components };
+        Ok(SynthesizedLibrary {
+            library: Idf30 {
+                header,
+                placement: vec![],
+                other_sections: vec![],
+                section_order: vec![],
+                warnings: vec![],
+            },
+            missing,
+        })
+    }
+}
+
+/// Imperial chip code in names like "0603", "R0402" or "CAP_0805".
+fn chip_code(name: &str) -> Option<ChipSize> {
+    let prefix_len = name.find(|c: char| c.is_ascii_digit())?;
+    let prefix = name[..prefix_len].trim_end_matches(['_', '-']);
+    if !matches!(
+        prefix,
+        "" | "R" | "C" | "L" | "D" | "RES" | "CAP" | "IND" | "LED"
+    ) {
+        return None;
+    }
+    let code = name.get(prefix_len..prefix_len + 4)?;
+    let rest = &name[prefix_len + 4..];
+    if rest.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    ChipSize::from_code(code)
+}
+
+/// Pin count following the family name, "SOIC-8" or "SOIC14".
+fn pin_count(family: &str) -> Option<u32> {
+    let digits = family.trim_start_matches(|c: char| !c.is_ascii_digit());
+    let end = digits
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(digits.len());
+    digits[..end].parse().ok()
+}
+
+/// Body size written as `<X>x<Y>` anywhere in the name, e.g. "QFN-32_5X5MM".
+fn body_size(name: &str) -> Option<(f32, f32)> {
+    let number = |s: &str| -> Option<f32> {
+        let end = s
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(s.len());
+        s[..end].parse().ok()
+    };
+    name.match_indices('X').find_map(|(at, _)| {
+        let before = &name[..at];
+        let start = before
+            .rfind(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .map_or(0, |i| i + 1);
+        Some((number(&before[start..])?, number(&name[at + 1..])?))
+    })
+}
@@ -0,0 +1,239 @@
+//! Pick-and-place (centroid) CSV with RefDes, X, Y, Rotation, Side, Package and PartNumber
+//! columns, as expected by most assembly houses, and refreshing placements from one.
+
+use crate::idf30::{BoardSide, ComponentPlacement, Error, Idf30, Unit};
+use std::collections::HashMap;
+
+/// Units and origin of the exported coordinates.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CentroidOptions {
+    /// Output units, units of the board file if not set
+    pub units: Option<Unit>,
+    /// Coordinates of the assembly origin in board units, subtracted from every placement
+    pub origin: (f32, f32),
+}
+
+impl<'a> Idf30<'a> {
+    pub fn to_centroid_csv(&self, options: &CentroidOptions) -> String {
+        let board_units = self.units().cloned().unwrap_or(Unit::SImm);
+        let units = options.units.clone().unwrap_or(board_units.clone());
+        let mut s = String::from("RefDes,X,Y,Rotation,Side,Package,PartNumber\n");
+        for c in &self.placement {
+            let x = board_units.convert((c.x - options.origin.0) as f64, &units);
+            let y = board_units.convert((c.y - options.origin.1) as f64, &units);
+            s.push_str(&format!(
+                "{},{:.4},{:.4},{:.3},{},{},{}\n",
+                csv_field(&c.designator.to_string()),
+                x,
+                y,
+                c.rotation,
+                c.board_side,
+                csv_field(&c.package_name),
+                csv_field(&c.part_number),
+            ));
+        }
+        s
+    }
+}
+
+/// Outcome of `Idf30::update_from_centroid_csv`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CentroidImport {
+    /// Placements whose position, rotation or side were refreshed
+    pub updated: usize,
+    /// Placements created for rows that did not match any existing placement
+    pub added: usize,
+    /// Designators of rows that did not match any placement and were not added
+    pub unmatched: Vec<String>,
+}
+
+impl<'a> Idf30<'a> {
+    /// Refreshes placements from a centroid CSV, matching rows by reference designator.
+    ///
+    /// The first line must name the columns, common spellings such as "Designator", "Mid X" or
+    /// "Layer" are recognized. Coordinates are taken to be in `options.units` relative to
+    /// `options.origin`, as written by `to_centroid_csv`, unless followed by "mm", "mil" or
+    /// "in", which are converted to board units. Other unit letters are an error. Rows without a
+    /// matching placement are added as new placements when `add_unmatched` is set, otherwise
+    /// they are reported.
+    pub fn update_from_centroid_csv(
+        &mut self,
+        csv: &str,
+        options: &CentroidOptions,
+        add_unmatched: bool,
+    ) -> Result<CentroidImport, Error> {
+        let board_units = self.units().cloned().unwrap_or(Unit::SImm);
+        let units = options.units.clone().unwrap_or(board_units.clone());
+        let mut lines = csv
+            .lines()
+            .enumerate()
+            .filter(|(_, l)| !l.trim().is_empty());
+        let (_, header) = lines
+            .next()
+            .ok_or(Error::MalformedCsv(1, "Missing header"))?;
+        let columns = Columns::new(&split_csv_line(header))?;
+
+        let index: HashMap<String, usize> = self
+            .placement
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (c.designator.to_string(), i))
+            .collect();
+        let mut report = CentroidImport::default();
+        for (n, line) in lines {
+            let line_number = n + 1;
+            let fields = split_csv_line(line);
+            let field = |i: Option<usize>| i.and_then(|i| fields.get(i)).map(|f| f.trim());
+            let refdes = field(Some(columns.refdes))
+                .filter(|r| !r.is_empty())
+                .ok_or(Error::MalformedCsv(
+                    line_number,
+                    "Missing reference designator",
+                ))?;
+            // Value and the unit letters following it
+            let number = |i: Option<usize>, what: &'static str| -> Result<(f64, &str), Error> {
+                let value = field(i).ok_or(Error::MalformedCsv(line_number, what))?;
+                let number = value.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+                let suffix = &value[number.len()..];
+                number
+                    .trim()
+                    .parse::<f64>()
+                    .map(|v| (v, suffix))
+                    .map_err(|_| Error::MalformedCsv(line_number, what))
+            };
+            let coordinate = |i: usize, what: &'static str| -> Result<f32, Error> {
+                let (value, suffix) = number(Some(i), what)?;
+                let value = match suffix.to_ascii_lowercase().as_str() {
+                    "" => units.convert(value, &board_units),
+                    "mm" => Unit::SImm.convert(value, &board_units),
+                    "mil" | "mils" | "thou" => Unit::Mils.convert(value, &board_units),
+                    "in" | "inch" => Unit::Mils.convert(value * 1000.0, &board_units),
+                    _ => return Err(Error::MalformedCsv(line_number, "Unknown unit")),
+                };
+                Ok(value as f32)
+            };
+            let x = coordinate(columns.x, "Invalid X")? + options.origin.0;
+            let y = coordinate(columns.y, "Invalid Y")? + options.origin.1;
+            let rotation = match columns.rotation {
+                Some(_) => match number(columns.rotation, "Invalid rotation")? {
+                    (rotation, "" | "deg") => Some(rotation as f32),
+                    _ => return Err(Error::MalformedCsv(line_number, "Unknown unit")),
+                },
+                None => None,
+            };
+            let side = match field(columns.side) {
+                Some(side) => Some(parse_side(side).ok_or(Error::MalformedCsv(
+                    line_number,
+                    "Expected top or bottom side",
+                ))?),
+                None => None,
+            };
+
+            if let Some(&i) = index.get(refdes) {
+                let c = &mut self.placement[i];
+                c.x = x;
+                c.y = y;
+                if let Some(rotation) = rotation {
+                    c.rotation = rotation;
+                }
+                if let Some(side) = side {
+                    c.board_side = side;
+                }
+                report.updated += 1;
+            } else if add_unmatched {
+                let owned = |i: Option<usize>| field(i).unwrap_or("").to_string();
+                self.placement.push(
+                    ComponentPlacement::new(
+                        owned(columns.package),
+                        owned(columns.part_number),
+                        refdes.to_string(),
+                    )
+                    .placed_at(
+                        x,
+                        y,
+                        rotation.unwrap_or(0.0),
+                        side.unwrap_or(BoardSide::Top),
+                    ),
+                );
+                report.added += 1;
+            } else {
+                report.unmatched.push(refdes.to_string());
+            }
+        }
+        Ok(report)
+    }
+}
+
+/// Column positions found in the CSV header line.
+struct Columns {
+    refdes: usize,
+    x: usize,
+    y: usize,
+    rotation: Option<usize>,
+    side: Option<usize>,
+    package: Option<usize>,
+    part_number: Option<usize>,
+}
+
+impl Columns {
+    fn new(header: &[String]) -> Result<Columns, Error> {
+        let find = |names: &[&str]| {
+            header.iter().position(|h| {
+                let h: String = h
+                    .to_ascii_lowercase()
+                    .chars()
+                    .filter(|c| c.is_ascii_alphanumeric())
+                    .collect();
+                names.contains(&h.as_str())
+            })
+        };
+        Ok(Columns {
+            refdes: find(&["refdes", "designator", "ref", "reference"])
+                .ok_or(Error::MalformedCsv(1, "Missing RefDes column"))?,
+            x: find(&["x", "midx", "centerx", "posx", "refx"])
+                .ok_or(Error::MalformedCsv(1, "Missing X column"))?,
+            y: find(&["y", "midy", "centery", "posy", "refy"])
+                .ok_or(Error::MalformedCsv(1, "Missing Y column"))?,
+            rotation: find(&["rotation", "rot", "angle"]),
+            side: find(&["side", "layer", "tb"]),
+            package: find(&["package", "footprint"]),
+            part_number: find(&["partnumber", "part", "pn"]),
+        })
+    }
+}
+
+fn parse_side(side: &str) -> Option<BoardSide> {
+    match side.to_ascii_lowercase().as_str() {
+        "top" | "t" | "toplayer" => Some(BoardSide::Top),
+        "bottom" | "bot" | "b" | "bottomlayer" => Some(BoardSide::Bottom),
+        _ => None,
+    }
+}
+
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+pub(crate) fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
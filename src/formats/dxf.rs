@@ -0,0 +1,156 @@
+//! Board outlines and keepouts from the ENTITIES of an ASCII DXF drawing.
+
+use super::{chain_segments, import_loops};
+use crate::idf30::{Error, Idf30, Unit};
+
+impl<'a> Idf30<'a> {
+    /// Replaces the loops of the outline or keepout section `section` (e.g. `BOARD_OUTLINE` or
+    /// `ROUTE_KEEPOUT`) with the closed contours of a DXF drawing in `units`.
+    ///
+    /// Closed LWPOLYLINE and POLYLINE entities and CIRCLEs are taken as is, LINEs and ARCs are
+    /// chained into loops. The largest contour becomes the outline, the others cutouts.
+    pub fn import_dxf_outline(
+        &mut self,
+        dxf: &str,
+        section: &str,
+        units: Unit,
+    ) -> Result<(), Error> {
+        import_loops(self, section, dxf_loops(dxf)?, &units)
+    }
+}
+
+/// Contours of the drawing as loops of `[x, y, angle]` in drawing units.
+pub fn dxf_loops(dxf: &str) -> Result<Vec<Vec<[f64; 3]>>, Error> {
+    let mut loops = vec![];
+    let mut segments = vec![];
+    // Vertices and closed flag of the POLYLINE being read
+    let mut polyline: Option<(Vec<[f64; 3]>, bool)> = None;
+    for entity in entities(dxf)? {
+        let real = |code: i32| -> Result<f64, Error> {
+            entity
+                .iter()
+                .find(|(c, _)| *c == code)
+                .map_or(Ok(0.0), |(_, v)| {
+                    v.parse()
+                        .map_err(|_| Error::MalformedOutline("Expected a number in DXF entity"))
+                })
+        };
+        let flags = real(70)? as i64;
+        match entity[0].1 {
+            "LWPOLYLINE" => {
+                // Every vertex starts with its x, bulges follow the vertex they belong to
+                let mut vertices: Vec<[f64; 3]> = vec![];
+                for (code, value) in &entity[1..] {
+                    let value: f64 = match code {
+                        10 | 20 | 42 => value.parse().map_err(|_| {
+                            Error::MalformedOutline("Expected a number in DXF entity")
+                        })?,
+                        _ => continue,
+                    };
+                    match code {
+                        10 => vertices.push([value, 0.0, 0.0]),
+                        20 => {
+                            if let Some(v) = vertices.last_mut() {
+                                v[1] = value
+                            }
+                        }
+                        _ => {
+                            if let Some(v) = vertices.last_mut() {
+                                v[2] = value
+                            }
+                        }
+                    }
+                }
+                push_polyline(&mut loops, &mut segments, &vertices, flags & 1 != 0);
+            }
+            "POLYLINE" => polyline = Some((vec![], flags & 1 != 0)),
+            "VERTEX" => {
+                if let Some((vertices, _)) = polyline.as_mut() {
+                    vertices.push([real(10)?, real(20)?, real(42)?]);
+                }
+            }
+            "SEQEND" => {
+                if let Some((vertices, closed)) = polyline.take() {
+                    push_polyline(&mut loops, &mut segments, &vertices, closed);
+                }
+            }
+            "LINE" => segments.push(([real(10)?, real(20)?], [real(11)?, real(21)?], 0.0)),
+            "ARC" => {
+                let (cx, cy, r) = (real(10)?, real(20)?, real(40)?);
+                let (start, end) = (real(50)?, real(51)?);
+                let sweep = (end - start).rem_euclid(360.0);
+                let at = |a: f64| [cx + r * a.to_radians().cos(), cy + r * a.to_radians().sin()];
+                segments.push((at(start), at(end), sweep));
+            }
+            "CIRCLE" => {
+                let (cx, cy, r) = (real(10)?, real(20)?, real(40)?);
+                loops.push(vec![[cx, cy, 0.0], [cx + r, cy, 360.0]]);
+            }
+            _ => {}
+        }
+    }
+    loops.extend(chain_segments(segments)?);
+    Ok(loops)
+}
+
+/// Adds a closed polyline as a loop, open ones are left to be chained with other segments.
+fn push_polyline(
+    loops: &mut Vec<Vec<[f64; 3]>>,
+    segments: &mut Vec<([f64; 2], [f64; 2], f64)>,
+    vertices: &[[f64; 3]],
+    closed: bool,
+) {
+    let n = vertices.len();
+    let count = if closed { n } else { n.saturating_sub(1) };
+    // A bulge is the tangent of a quarter of the included angle, positive counterclockwise
+    let edges = (0..count).map(|i| {
+        let (from, to) = (vertices[i], vertices[(i + 1) % n]);
+        (
+            [from[0], from[1]],
+            [to[0], to[1]],
+            4.0 * from[2].atan().to_degrees(),
+        )
+    });
+    if closed && n > 1 {
+        let mut points = vec![[vertices[0][0], vertices[0][1], 0.0]];
+        points.extend(edges.map(|(_, to, angle)| [to[0], to[1], angle]));
+        loops.push(points);
+    } else {
+        segments.extend(edges);
+    }
+}
+
+/// Group code and value pairs of each entity in the ENTITIES section, starting with the
+/// `0 <TYPE>` pair.
+fn entities(dxf: &str) -> Result<Vec<Vec<(i32, &str)>>, Error> {
+    let mut lines = dxf.lines();
+    let mut entities: Vec<Vec<(i32, &str)>> = vec![];
+    let mut in_entities = false;
+    let mut section_start = false;
+    while let Some(code) = lines.next() {
+        let code: i32 = code
+            .trim()
+            .parse()
+            .map_err(|_| Error::MalformedOutline("Expected a DXF group code"))?;
+        let value = lines
+            .next()
+            .ok_or(Error::MalformedOutline("DXF group code without a value"))?
+            .trim();
+        match (code, value) {
+            (0, "SECTION") => section_start = true,
+            (2, name) if section_start => {
+                in_entities = name == "ENTITIES";
+                section_start = false;
+            }
+            (0, "ENDSEC") => in_entities = false,
+            (0, kind) if in_entities => entities.push(vec![(0, kind)]),
+            pair if in_entities => {
+                if let Some(e) = entities.last_mut() {
+                    e.push(pair);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(entities)
+}
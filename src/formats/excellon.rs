@@ -0,0 +1,243 @@
+//! Excellon drill files generated from `.DRILLED_HOLES`, with plated and non-plated holes split
+//! into separate files, and merging drill files back into `.DRILLED_HOLES`.
+
+use crate::holes::{DrilledHole, HoleType, Plating};
+use crate::idf30::{Error, Idf30, Owner, ReferenceDesignator, Unit};
+use either::Either;
+use std::collections::HashMap;
+
+/// Tool diameters closer than this (in file units) share a tool.
+const TOOL_TOLERANCE: f32 = 1e-4;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ExcellonFiles {
+    pub plated: String,
+    pub non_plated: String,
+}
+
+impl<'a> Idf30<'a> {
+    /// Writes the drilled holes in metric or inch Excellon, following the board units, with one
+    /// tool per distinct diameter in ascending order.
+    pub fn to_excellon(&self) -> Result<ExcellonFiles, Error> {
+        let units = self.units().ok_or(Error::WrongFileType)?;
+        let holes = self.drilled_holes()?;
+        let file = |plating: Plating| {
+            let holes: Vec<&DrilledHole> = holes.iter().filter(|h| h.plating == plating).collect();
+            excellon(&holes, units, plating)
+        };
+        Ok(ExcellonFiles {
+            plated: file(Plating::Plated),
+            non_plated: file(Plating::NonPlated),
+        })
+    }
+}
+
+fn excellon(holes: &[&DrilledHole], units: &Unit, plating: Plating) -> String {
+    // Excellon has no mils, THOU boards are written in inches
+    let (format, scale) = match units {
+        Unit::SImm => ("METRIC,LZ", 1.0),
+        Unit::Mils => ("INCH,LZ", 0.001),
+    };
+    let mut tools: Vec<f32> = vec![];
+    for h in holes {
+        if !tools
+            .iter()
+            .any(|t| (t - h.diameter).abs() < TOOL_TOLERANCE)
+        {
+            tools.push(h.diameter);
+        }
+    }
+    tools.sort_by(f32::total_cmp);
+
+    let mut s = String::from("M48\n");
+    s.push_str(&format!(
+        "; generated by {} {}\n",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION")
+    ));
+    s.push_str(match plating {
+        Plating::Plated => "; TYPE=PLATED\n",
+        Plating::NonPlated => "; TYPE=NON_PLATED\n",
+    });
+    s.push_str(&format!("{format}\n"));
+    for (i, t) in tools.iter().enumerate() {
+        s.push_str(&format!("T{}C{:.4}\n", i + 1, *t as f64 * scale));
+    }
+    s.push_str("%\nG90\nG05\n");
+    for (i, t) in tools.iter().enumerate() {
+        s.push_str(&format!("T{}\n", i + 1));
+        for h in holes
+            .iter()
+            .filter(|h| (h.diameter - t).abs() < TOOL_TOLERANCE)
+        {
+            s.push_str(&format!(
+                "X{:.4}Y{:.4}\n",
+                h.x as f64 * scale,
+                h.y as f64 * scale
+            ));
+        }
+    }
+    s.push_str("M30\n");
+    s
+}
+
+/// Outcome of `Idf30::merge_excellon`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ExcellonImport {
+    pub added: usize,
+    /// Holes of the drill file that matched an existing hole and were skipped
+    pub duplicates: usize,
+}
+
+impl<'a> Idf30<'a> {
+    /// Adds the holes of an Excellon drill file to `.DRILLED_HOLES`.
+    ///
+    /// Holes within `tolerance` (in board units) of an existing hole of the same diameter are
+    /// skipped. New holes are associated with the BOARD and unowned. `plating` applies unless
+    /// the file carries a `; TYPE=PLATED` or `; TYPE=NON_PLATED` comment. Both decimal and
+    /// zero-suppressed coordinates are accepted, slots are ignored.
+    pub fn merge_excellon(
+        &mut self,
+        drill: &str,
+        plating: Plating,
+        tolerance: f32,
+    ) -> Result<ExcellonImport, Error> {
+        let units = self.units().ok_or(Error::WrongFileType)?.clone();
+        let mut holes = self.drilled_holes()?;
+        let mut report = ExcellonImport::default();
+        for (diameter, x, y, plating) in parse_excellon(drill, plating)? {
+            let (diameter, x, y) = (
+                Unit::SImm.convert(diameter, &units) as f32,
+                Unit::SImm.convert(x, &units) as f32,
+                Unit::SImm.convert(y, &units) as f32,
+            );
+            let duplicate = holes.iter().any(|h| {
+                (h.diameter - diameter).abs() <= tolerance
+                    && (h.x - x).abs() <= tolerance
+                    && (h.y - y).abs() <= tolerance
+            });
+            if duplicate {
+                report.duplicates += 1;
+                continue;
+            }
+            holes.push(DrilledHole {
+                diameter,
+                x,
+                y,
+                plating,
+                association: ReferenceDesignator::Board,
+                hole_type: HoleType::Other(Either::Left("OTHER")),
+                owner: Owner::Unowned,
+            });
+            report.added += 1;
+        }
+        self.set_drilled_holes(&holes);
+        Ok(report)
+    }
+}
+
+/// Holes of a drill file as `(diameter, x, y, plating)` in millimeters.
+fn parse_excellon(
+    drill: &str,
+    mut plating: Plating,
+) -> Result<Vec<(f64, f64, f64, Plating)>, Error> {
+    let mut scale = 25.4;
+    // Digits after the implied decimal point and whether leading zeros are kept
+    let mut decimals = 4;
+    let mut leading_zeros = true;
+    let mut tools: HashMap<u32, f64> = HashMap::new();
+    let mut tool = None;
+    let mut in_header = false;
+    let (mut x, mut y) = (0.0, 0.0);
+    let mut holes = vec![];
+    for (n, line) in drill.lines().enumerate() {
+        let line_number = n + 1;
+        let line = line.trim();
+        if let Some(comment) = line.strip_prefix(';') {
+            match comment.trim() {
+                "TYPE=PLATED" => plating = Plating::Plated,
+                "TYPE=NON_PLATED" => plating = Plating::NonPlated,
+                _ => {}
+            }
+            continue;
+        }
+        if line == "M48" {
+            in_header = true;
+            continue;
+        }
+        if line == "%" || line == "M95" {
+            in_header = false;
+            continue;
+        }
+        if line.starts_with("METRIC") || line == "M71" {
+            scale = 1.0;
+            decimals = 3;
+            leading_zeros = !line.contains("TZ");
+            continue;
+        }
+        if line.starts_with("INCH") || line == "M72" {
+            scale = 25.4;
+            decimals = 4;
+            leading_zeros = !line.contains("TZ");
+            continue;
+        }
+        if let Some(def) = line.strip_prefix('T') {
+            let digits_end = def.find(|c: char| !c.is_ascii_digit()).unwrap_or(def.len());
+            let number: u32 = def[..digits_end]
+                .parse()
+                .map_err(|_| Error::MalformedDrill(line_number, "Expected tool number"))?;
+            if let Some(at) = def.find('C') {
+                let end = def[at + 1..]
+                    .find(|c: char| c.is_ascii_alphabetic())
+                    .map_or(def.len(), |e| at + 1 + e);
+                let diameter: f64 = def[at + 1..end]
+                    .parse()
+                    .map_err(|_| Error::MalformedDrill(line_number, "Expected tool diameter"))?;
+                tools.insert(number, diameter * scale);
+            }
+            if !in_header {
+                tool = Some(number);
+            }
+            continue;
+        }
+        if line.starts_with('X') || line.starts_with('Y') {
+            if line.contains("G85") {
+                continue;
+            }
+            let coordinate = |axis: char| -> Result<Option<f64>, Error> {
+                let Some(at) = line.find(axis) else {
+                    return Ok(None);
+                };
+                let rest = &line[at + 1..];
+                let end = rest
+                    .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-' || c == '+'))
+                    .unwrap_or(rest.len());
+                let text = &rest[..end];
+                let value = if text.contains('.') {
+                    text.parse::<f64>()
+                } else if leading_zeros {
+                    let digits = text.trim_start_matches(['-', '+']).len();
+                    let int_digits = if scale == 1.0 { 3 } else { 2 };
+                    text.parse::<f64>()
+                        .map(|v| v / 10f64.powi(digits as i32 - int_digits))
+                } else {
+                    text.parse::<f64>().map(|v| v / 10f64.powi(decimals))
+                };
+                value
+                    .map(|v| Some(v * scale))
+                    .map_err(|_| Error::MalformedDrill(line_number, "Expected coordinate"))
+            };
+            if let Some(v) = coordinate('X')? {
+                x = v;
+            }
+            if let Some(v) = coordinate('Y')? {
+                y = v;
+            }
+            let diameter = tool
+                .and_then(|t| tools.get(&t))
+                .ok_or(Error::MalformedDrill(line_number, "Hole without a tool"))?;
+            holes.push((*diameter, x, y, plating));
+        }
+    }
+    Ok(holes)
+}
@@ -0,0 +1,112 @@
+//! Gerber X3 component layers: one file per board side with a flash at every component
+//! reference point carrying the component attributes, plus the body outline when a library is
+//! available.
+
+use crate::geometry::{definition_loops, tessellate};
+use crate::idf30::{BoardSide, Error, Idf30, Unit};
+
+/// Top and bottom component layer of a board.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ComponentLayers {
+    pub top: String,
+    pub bottom: String,
+}
+
+impl<'a> Idf30<'a> {
+    /// Writes the Gerber X3 component layers of the placed components.
+    ///
+    /// `copper_layers` is the number of copper layers of the board, the bottom component layer
+    /// is attached to the last one. Component heights and body outlines are emitted for
+    /// placements that have a definition in `library`.
+    pub fn to_gerber_x3(
+        &self,
+        library: Option<&Idf30>,
+        copper_layers: u32,
+    ) -> Result<ComponentLayers, Error> {
+        let units = self.units().ok_or(Error::WrongFileType)?;
+        Ok(ComponentLayers {
+            top: self.component_layer(library, units, BoardSide::Top, 1),
+            bottom: self.component_layer(library, units, BoardSide::Bottom, copper_layers.max(2)),
+        })
+    }
+
+    fn component_layer(
+        &self,
+        library: Option<&Idf30>,
+        units: &Unit,
+        side: BoardSide,
+        layer: u32,
+    ) -> String {
+        let side_name = match side {
+            BoardSide::Top => "Top",
+            BoardSide::Bottom => "Bot",
+        };
+        let mut s = format!(
+            "%TF.GenerationSoftware,idf,{},{}*%\n\
+             %TF.FileFunction,Component,L{layer},{side_name}*%\n\
+             %TF.FilePolarity,Positive*%\n\
+             %FSLAX46Y46*%\n\
+             %MOMM*%\n\
+             %LPD*%\n\
+             %TA.AperFunction,ComponentMain*%\n\
+             %ADD10C,0.300*%\n\
+             %TA.AperFunction,ComponentOutline,Body*%\n\
+             %ADD11C,0.100*%\n\
+             %TD*%\n",
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION"),
+        );
+        let coord = |v: f64, from: &Unit| (from.convert(v, &Unit::SImm) * 1e6).round() as i64;
+        for c in self.placement.iter().filter(|c| c.board_side == side) {
+            let definition = library.and_then(|l| l.definition(&c.package_name, &c.part_number));
+            s.push_str(&format!(
+                "%TO.C,{}*%\n",
+                attribute(&c.designator.to_string())
+            ));
+            s.push_str(&format!("%TO.CRot,{:.3}*%\n", c.rotation));
+            s.push_str(&format!("%TO.CFtp,{}*%\n", attribute(&c.package_name)));
+            if !c.part_number.is_empty() {
+                s.push_str(&format!("%TO.CMPN,{}*%\n", attribute(&c.part_number)));
+            }
+            if let Some(definition) = definition {
+                let height = definition
+                    .units
+                    .convert(definition.height as f64, &Unit::SImm);
+                s.push_str(&format!("%TO.CHgt,{height:.4}*%\n"));
+            }
+            s.push_str(&format!(
+                "D10*\nX{}Y{}D03*\n",
+                coord(c.x as f64, units),
+                coord(c.y as f64, units)
+            ));
+            if let Some(definition) = definition {
+                let transform = c.placement_transform();
+                s.push_str("D11*\n");
+                for outline in definition_loops(definition) {
+                    for (i, (x, y)) in tessellate(&outline).into_iter().enumerate() {
+                        // Library points are in definition units, placement in board units
+                        let x = definition.units.convert(x, units);
+                        let y = definition.units.convert(y, units);
+                        let (x, y) = transform.apply(x, y);
+                        let op = if i == 0 { "D02" } else { "D01" };
+                        s.push_str(&format!("X{}Y{}{op}*\n", coord(x, units), coord(y, units)));
+                    }
+                }
+            }
+            s.push_str("%TD*%\n");
+        }
+        s.push_str("M02*\n");
+        s
+    }
+}
+
+/// Escapes characters that cannot appear in a Gerber attribute value.
+fn attribute(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            ',' | '*' | '%' | '\\' => format!("\\u{:04X}", c as u32),
+            c => c.to_string(),
+        })
+        .collect()
+}
@@ -0,0 +1,128 @@
+//! IDF 2.0 writer for legacy MCAD importers.
+//!
+//! 2.0 has no ownership, mounting offsets, ECAD placement status or layer and height
+//! attributes on keepouts, and knows only outline, keepout, hole, note and placement sections.
+
+use crate::idf30::{
+    escape_header_string, escape_string, FileType, Idf30, IdfSection, IdfValue, Owner,
+    PlacementStatus,
+};
+use crate::transform::is_loop_point;
+use either::Either;
+
+/// Information lost when writing IDF 2.0.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Idf20Warning {
+    /// Section type that does not exist in IDF 2.0
+    DroppedSection(String),
+    /// Placement with a non-zero mounting offset, written without it
+    DroppedOffset(String),
+    /// Placement with ECAD status, written as PLACED
+    ChangedStatus(String),
+    /// Section with an ECAD or MCAD owner, or drilled holes with owners, by section name
+    DroppedOwner(String),
+    /// Keepout with layer, side or height records, written with its outline only
+    DroppedKeepoutAttributes(String),
+}
+
+impl<'a> Idf30<'a> {
+    pub fn to_idf20(&self) -> (String, Vec<Idf20Warning>) {
+        let mut warnings = vec![];
+        let header = &self.header;
+        let mut s = format!(
+            ".HEADER\n{} 2.0 {} {} {}\n",
+            header.ty,
+            escape_string(&header.source),
+            escape_header_string(&header.date),
+            header.board_file_version
+        );
+        if let FileType::BoardFile { board_name, units }
+        | FileType::PanelFile { board_name, units } = &header.ty
+        {
+            s.push_str(&format!("{} {units}\n", escape_string(board_name)));
+        }
+        s.push_str(".END_HEADER\n");
+
+        for section in &self.other_sections {
+            let records: Vec<Vec<IdfValue>> = match section.name() {
+                "BOARD_OUTLINE" | "PANEL_OUTLINE" | "NOTES" => section.records().to_vec(),
+                // Layers, side and height records are 3.0 additions
+                "ROUTE_KEEPOUT" | "PLACE_KEEPOUT" => {
+                    let records: Vec<Vec<IdfValue>> = section
+                        .records()
+                        .iter()
+                        .filter(|r| is_loop_point(r))
+                        .cloned()
+                        .collect();
+                    if records.len() < section.records().len() {
+                        warnings.push(Idf20Warning::DroppedKeepoutAttributes(
+                            section.name().to_string(),
+                        ));
+                    }
+                    records
+                }
+                // No owner column
+                "DRILLED_HOLES" => {
+                    let owned = section.records().iter().any(|r| {
+                        r.get(6)
+                            .and_then(|o| o.as_str())
+                            .is_some_and(|o| o != "UNOWNED")
+                    });
+                    if owned {
+                        warnings.push(Idf20Warning::DroppedOwner(section.name().to_string()));
+                    }
+                    section
+                        .records()
+                        .iter()
+                        .map(|r| r.iter().take(6).cloned().collect())
+                        .collect()
+                }
+                name => {
+                    warnings.push(Idf20Warning::DroppedSection(name.to_string()));
+                    continue;
+                }
+            };
+            if section.owner().is_some_and(|o| o != Owner::Unowned) {
+                warnings.push(Idf20Warning::DroppedOwner(section.name().to_string()));
+            }
+            let name = Either::Right(section.name().to_string());
+            s.push_str(&IdfSection::new(name, vec![], records).to_string());
+        }
+
+        match &header.ty {
+            FileType::BoardFile { .. } | FileType::PanelFile { .. } => {
+                s.push_str(".PLACEMENT\n");
+                for c in &self.placement {
+                    if c.z != 0.0 {
+                        warnings.push(Idf20Warning::DroppedOffset(c.designator.to_string()));
+                    }
+                    let status = match c.placement_status {
+                        PlacementStatus::ECad => {
+                            warnings.push(Idf20Warning::ChangedStatus(c.designator.to_string()));
+                            PlacementStatus::Placed
+                        }
+                        ref status => status.clone(),
+                    };
+                    s.push_str(&format!(
+                        "{} {} {}\n  {:.4} {:.4} {:.3} {} {}\n",
+                        escape_string(&c.package_name),
+                        escape_string(&c.part_number),
+                        c.designator,
+                        c.x,
+                        c.y,
+                        c.rotation,
+                        c.board_side,
+                        status
+                    ));
+                }
+                s.push_str(".END_PLACEMENT\n");
+            }
+            FileType::LibraryFile { components } => {
+                for def in components {
+                    s.push_str(&def.to_string());
+                }
+            }
+        }
+        (s, warnings)
+    }
+}
@@ -0,0 +1,356 @@
+//! ProSTEP EDMD (IDX) baseline and change messages with board outline, keepouts, holes and
+//! component instances. Arcs are written as polylines, lengths in millimeters.
+
+use super::xml_escape;
+use crate::diff::{placement_keys, section_keys, BoardDiff, Change};
+use crate::geometry::{definition_loops, number, section_loops, tessellate};
+use crate::holes::{DrilledHole, Plating};
+use crate::idf30::{BoardSide, ComponentPlacement, Error, Idf30, IdfSection, IdfValue, Unit};
+
+impl<'a> Idf30<'a> {
+    /// Baseline message describing the whole board. Component bodies are included for
+    /// placements that have a definition in `library`.
+    pub fn to_idx_baseline(&self, library: Option<&Idf30>) -> Result<String, Error> {
+        let units = self.units().ok_or(Error::WrongFileType)?;
+        let mut idx = IdxWriter::new(units, library);
+        for (key, section) in section_keys(&self.other_sections)
+            .iter()
+            .zip(&self.other_sections)
+        {
+            idx.section(key, section)?;
+        }
+        for (key, placement) in placement_keys(&self.placement).iter().zip(&self.placement) {
+            idx.component(key, placement);
+        }
+        Ok(idx.finish(self.board_name().unwrap_or_default(), None))
+    }
+}
+
+impl<'a> BoardDiff<'a> {
+    /// Change message with the added and changed items of the new revision, and the changes
+    /// referring to them. Item ids match those of `Idf30::to_idx_baseline`.
+    pub fn to_idx_changes(
+        &self,
+        board_name: &str,
+        units: &Unit,
+        library: Option<&Idf30>,
+    ) -> Result<String, Error> {
+        let mut idx = IdxWriter::new(units, library);
+        let mut changes = String::new();
+        for c in &self.sections {
+            let (before, after) = match &c.change {
+                Change::Added(after) => (None, Some(after)),
+                Change::Removed(before) => (Some(before), None),
+                Change::Changed { before, after } => (Some(before), Some(after)),
+            };
+            let mut ids = vec![];
+            for section in before.iter().chain(after.iter()) {
+                for id in section_item_ids(&c.key, section)? {
+                    if !ids.contains(&id) {
+                        ids.push(id);
+                    }
+                }
+            }
+            if let Some(after) = after {
+                idx.section(&c.key, after)?;
+            }
+            for id in ids {
+                changes.push_str(&change(&id, &c.change));
+            }
+        }
+        for c in &self.placements {
+            if let Change::Added(after) | Change::Changed { after, .. } = &c.change {
+                idx.component(&c.key, after);
+            }
+            changes.push_str(&change(&item_id("CMP", &c.key), &c.change));
+        }
+        Ok(idx.finish(board_name, Some(changes)))
+    }
+}
+
+fn change<T>(id: &str, change: &Change<T>) -> String {
+    let element = match change {
+        Change::Added(_) => "NewItem",
+        Change::Removed(_) => "DeletedItem",
+        Change::Changed { .. } => "Item",
+    };
+    format!(
+        "      <computational:Change>\n        <computational:{element}>{id}</computational:{element}>\n      </computational:Change>\n"
+    )
+}
+
+/// Ids of the items a section is written as, holes are grouped by plating.
+fn section_item_ids(key: &str, section: &IdfSection) -> Result<Vec<String>, Error> {
+    if section.name() != "DRILLED_HOLES" {
+        return Ok(vec![item_id("SEC", key)]);
+    }
+    let mut ids = vec![];
+    for record in section.records() {
+        let id = item_id(
+            "SEC",
+            &format!("{key}_{}", DrilledHole::from_record(record)?.plating),
+        );
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    Ok(ids)
+}
+
+fn item_id(prefix: &str, key: &str) -> String {
+    let key: String = key
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("{prefix}_{key}")
+}
+
+struct IdxWriter<'l, 'u> {
+    body: String,
+    /// Packages already written as items
+    packages: Vec<String>,
+    next_id: usize,
+    units: &'u Unit,
+    library: Option<&'l Idf30<'l>>,
+}
+
+impl<'l, 'u> IdxWriter<'l, 'u> {
+    fn new(units: &'u Unit, library: Option<&'l Idf30<'l>>) -> Self {
+        IdxWriter {
+            body: String::new(),
+            packages: vec![],
+            next_id: 1,
+            units,
+            library,
+        }
+    }
+
+    fn id(&mut self, prefix: &str) -> String {
+        self.next_id += 1;
+        format!("{prefix}{}", self.next_id - 1)
+    }
+
+    /// Writes the geometry of closed polylines extruded from `lower` to `upper`, returns the
+    /// id of one shape element per loop. All loops but the first are cutouts.
+    fn shape(
+        &mut self,
+        loops: &[Vec<(f64, f64)>],
+        lower: f64,
+        upper: f64,
+        units: &Unit,
+    ) -> Vec<String> {
+        let mm = |v: f64| units.convert(v, &Unit::SImm);
+        let mut shapes = vec![];
+        for (i, polyline) in loops.iter().enumerate() {
+            let points: Vec<String> = polyline
+                .iter()
+                .map(|&(x, y)| {
+                    let id = self.id("PT");
+                    self.body.push_str(&format!(
+                        "    <foundation:CartesianPoint id=\"{id}\"><d2:X>{:.4}</d2:X><d2:Y>{:.4}</d2:Y></foundation:CartesianPoint>\n",
+                        mm(x),
+                        mm(y)
+                    ));
+                    id
+                })
+                .collect();
+            let line = self.id("PL");
+            self.body
+                .push_str(&format!("    <foundation:PolyLine id=\"{line}\">\n"));
+            for p in points {
+                self.body
+                    .push_str(&format!("      <d2:Point>{p}</d2:Point>\n"));
+            }
+            self.body.push_str("    </foundation:PolyLine>\n");
+            let curves = self.id("CS");
+            self.body.push_str(&format!(
+                "    <foundation:CurveSet2d id=\"{curves}\">\n\
+                 \x20     <pdm:ShapeDescriptionType>GeometricModel</pdm:ShapeDescriptionType>\n\
+                 \x20     <d2:LowerBound><property:Value>{:.4}</property:Value></d2:LowerBound>\n\
+                 \x20     <d2:UpperBound><property:Value>{:.4}</property:Value></d2:UpperBound>\n\
+                 \x20     <d2:DetailedGeometricModelElement>{line}</d2:DetailedGeometricModelElement>\n\
+                 \x20   </foundation:CurveSet2d>\n",
+                mm(lower),
+                mm(upper)
+            ));
+            let shape = self.id("SH");
+            self.body.push_str(&format!(
+                "    <foundation:ShapeElement id=\"{shape}\">\n\
+                 \x20     <pdm:ShapeElementType>FeatureShapeElement</pdm:ShapeElementType>\n\
+                 \x20     <pdm:Inverted>{}</pdm:Inverted>\n\
+                 \x20     <pdm:DefiningShape>{curves}</pdm:DefiningShape>\n\
+                 \x20   </foundation:ShapeElement>\n",
+                i > 0
+            ));
+            shapes.push(shape);
+        }
+        shapes
+    }
+
+    fn item(&mut self, id: &str, name: &str, geometry_type: &str, shapes: &[String]) {
+        self.body.push_str(&format!(
+            "    <foundation:Item id=\"{id}\" geometryType=\"{geometry_type}\">\n\
+             \x20     <foundation:Name>{}</foundation:Name>\n\
+             \x20     <pdm:ItemType>single</pdm:ItemType>\n",
+            xml_escape(name)
+        ));
+        for shape in shapes {
+            self.body
+                .push_str(&format!("      <pdm:Shape>{shape}</pdm:Shape>\n"));
+        }
+        self.body.push_str("    </foundation:Item>\n");
+    }
+
+    fn section(&mut self, key: &str, section: &IdfSection) -> Result<(), Error> {
+        let units = self.units;
+        if section.name() == "DRILLED_HOLES" {
+            let holes = section
+                .records()
+                .iter()
+                .map(|r| DrilledHole::from_record(r))
+                .collect::<Result<Vec<_>, _>>()?;
+            for plating in [Plating::Plated, Plating::NonPlated] {
+                let mut shapes = vec![];
+                for hole in holes.iter().filter(|h| h.plating == plating) {
+                    let r = hole.diameter as f64 / 2.0;
+                    let circle = tessellate(&[
+                        [hole.x as f64, hole.y as f64, 0.0],
+                        [hole.x as f64 + r, hole.y as f64, 360.0],
+                    ]);
+                    shapes.extend(self.shape(&[circle], 0.0, 0.0, units));
+                }
+                if shapes.is_empty() {
+                    continue;
+                }
+                let geometry_type = match plating {
+                    Plating::Plated => "HOLE_PLATED",
+                    Plating::NonPlated => "HOLE_NON_PLATED",
+                };
+                let id = item_id("SEC", &format!("{key}_{plating}"));
+                self.item(&id, section.name(), geometry_type, &shapes);
+            }
+            return Ok(());
+        }
+        let geometry_type = match section.name() {
+            "BOARD_OUTLINE" | "PANEL_OUTLINE" => "BOARD_OUTLINE",
+            "ROUTE_KEEPOUT" => "KEEPOUT_AREA_ROUTE",
+            "VIA_KEEPOUT" => "KEEPOUT_AREA_VIA",
+            "PLACE_KEEPOUT" => "KEEPOUT_AREA_COMPONENT",
+            "ROUTE_OUTLINE" => "KEEPIN_AREA_ROUTE",
+            "PLACE_OUTLINE" => "KEEPIN_AREA_COMPONENT",
+            "PLACE_REGION" => "PLACEMENT_GROUP_AREA",
+            "OTHER_OUTLINE" => "OTHER_OUTLINE",
+            // Notes and unknown sections have no IDX equivalent
+            _ => return Ok(()),
+        };
+        // Board thickness and place keepout height
+        let upper = match section.records().first().map(|r| r.as_slice()) {
+            Some([IdfValue::Float(_) | IdfValue::Integer(_)]) => number(&section.records()[0][0]),
+            Some([IdfValue::String(_), height]) => number(height),
+            _ => 0.0,
+        };
+        let loops: Vec<Vec<(f64, f64)>> = section_loops(section)
+            .iter()
+            .map(|l| tessellate(l))
+            .collect();
+        let shapes = self.shape(&loops, 0.0, upper, units);
+        self.item(&item_id("SEC", key), section.name(), geometry_type, &shapes);
+        Ok(())
+    }
+
+    fn component(&mut self, key: &str, placement: &ComponentPlacement) {
+        let definition = self
+            .library
+            .and_then(|l| l.definition(&placement.package_name, &placement.part_number));
+        let package_id = item_id(
+            "PKG",
+            &format!("{}_{}", placement.package_name, placement.part_number),
+        );
+        if !self.packages.contains(&package_id) {
+            self.packages.push(package_id.clone());
+            let mut shapes = vec![];
+            if let Some(definition) = definition {
+                let loops: Vec<Vec<(f64, f64)>> = definition_loops(definition)
+                    .iter()
+                    .map(|l| tessellate(l))
+                    .collect();
+                shapes = self.shape(&loops, 0.0, definition.height as f64, &definition.units);
+            }
+            self.item(&package_id, &placement.package_name, "COMPONENT", &shapes);
+        }
+
+        let mm = |v: f32| self.units.convert(v as f64, &Unit::SImm);
+        let (sin, cos) = (placement.rotation as f64).to_radians().sin_cos();
+        let (side, mirror) = match placement.board_side {
+            BoardSide::Top => ("TOP", 1.0),
+            BoardSide::Bottom => ("BOTTOM", -1.0),
+        };
+        self.body.push_str(&format!(
+            "    <foundation:Item id=\"{}\" geometryType=\"COMPONENT\">\n\
+             \x20     <pdm:ItemType>assembly</pdm:ItemType>\n\
+             \x20     <pdm:ItemInstance id=\"{}\">\n\
+             \x20       <pdm:Item>{package_id}</pdm:Item>\n\
+             \x20       <foundation:InstanceName><foundation:ObjectName>{}</foundation:ObjectName></foundation:InstanceName>\n\
+             \x20       <pdm:Transformation>\n\
+             \x20         <pdm:TransformationType>d2</pdm:TransformationType>\n\
+             \x20         <pdm:xx>{:.6}</pdm:xx><pdm:xy>{:.6}</pdm:xy>\n\
+             \x20         <pdm:yx>{:.6}</pdm:yx><pdm:yy>{:.6}</pdm:yy>\n\
+             \x20         <pdm:tx><property:Value>{:.4}</property:Value></pdm:tx>\n\
+             \x20         <pdm:ty><property:Value>{:.4}</property:Value></pdm:ty>\n\
+             \x20         <pdm:zOffset><property:Value>{:.4}</property:Value></pdm:zOffset>\n\
+             \x20       </pdm:Transformation>\n\
+             \x20     </pdm:ItemInstance>\n\
+             \x20     <pdm:AssembleToName>{side}</pdm:AssembleToName>\n\
+             \x20   </foundation:Item>\n",
+            item_id("CMP", key),
+            item_id("INST", key),
+            xml_escape(&placement.designator.to_string()),
+            cos * mirror,
+            -sin,
+            sin * mirror,
+            cos,
+            mm(placement.x),
+            mm(placement.y),
+            mm(placement.z),
+        ));
+    }
+
+    fn finish(self, board_name: &str, changes: Option<String>) -> String {
+        let mut s = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        s.push_str(
+            "<foundation:EDMDDataSet xmlns:foundation=\"http://www.prostep.org/EDMD/3.0/foundation\" \
+             xmlns:pdm=\"http://www.prostep.org/EDMD/3.0/pdm\" \
+             xmlns:d2=\"http://www.prostep.org/EDMD/3.0/geometry/d2\" \
+             xmlns:property=\"http://www.prostep.org/EDMD/3.0/property\" \
+             xmlns:computational=\"http://www.prostep.org/EDMD/3.0/computational\" \
+             xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\">\n",
+        );
+        s.push_str(&format!(
+            "  <foundation:Header xsi:type=\"foundation:EDMDHeader\">\n\
+             \x20   <foundation:CreatorSystem>{} {}</foundation:CreatorSystem>\n\
+             \x20   <foundation:Description>{}</foundation:Description>\n\
+             \x20   <foundation:GlobalUnitLength>UNIT_MM</foundation:GlobalUnitLength>\n\
+             \x20 </foundation:Header>\n",
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION"),
+            xml_escape(board_name),
+        ));
+        s.push_str("  <foundation:Body xsi:type=\"foundation:EDMDDataSetBody\">\n");
+        s.push_str(&self.body);
+        s.push_str("  </foundation:Body>\n");
+        match changes {
+            None => s.push_str(
+                "  <foundation:ProcessInstruction xsi:type=\"computational:EDMDProcessInstructionSendInformation\"/>\n",
+            ),
+            Some(changes) => {
+                s.push_str("  <foundation:ProcessInstruction xsi:type=\"computational:EDMDProcessInstructionSendChanges\">\n");
+                s.push_str("    <computational:Changes>\n");
+                s.push_str(&changes);
+                s.push_str("    </computational:Changes>\n");
+                s.push_str("  </foundation:ProcessInstruction>\n");
+            }
+        }
+        s.push_str("</foundation:EDMDDataSet>\n");
+        s
+    }
+}
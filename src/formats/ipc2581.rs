@@ -0,0 +1,150 @@
+//! Partial IPC-2581 (revision B) export: board profile with cutouts, drilled hole table and
+//! component placements, in millimeters.
+
+use super::xml_escape;
+use crate::geometry::{arc_center, number, section_loops};
+use crate::idf30::{BoardSide, Error, Idf30, IdfValue, Unit};
+
+impl<'a> Idf30<'a> {
+    pub fn to_ipc2581(&self) -> Result<String, Error> {
+        let units = self.units().ok_or(Error::WrongFileType)?;
+        let mm = |v: f64| units.convert(v, &Unit::SImm);
+        let step = xml_escape(self.board_name().unwrap_or_default());
+        let mut s = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        s.push_str("<IPC-2581 revision=\"B\" xmlns=\"http://webstds.ipc.org/2581\">\n");
+        s.push_str("  <Content roleRef=\"Owner\">\n");
+        s.push_str("    <FunctionMode mode=\"ASSEMBLY\"/>\n");
+        s.push_str(&format!("    <StepRef name=\"{step}\"/>\n"));
+        for layer in ["TOP", "BOTTOM", "DRILL"] {
+            s.push_str(&format!("    <LayerRef name=\"{layer}\"/>\n"));
+        }
+        s.push_str("  </Content>\n");
+        s.push_str(&format!("  <Ecad name=\"{step}\">\n"));
+        s.push_str("    <CadHeader units=\"MILLIMETER\"/>\n");
+        s.push_str("    <CadData>\n");
+        s.push_str(
+            "      <Layer name=\"TOP\" layerFunction=\"ASSEMBLY\" side=\"TOP\" polarity=\"POSITIVE\"/>\n\
+             \x20     <Layer name=\"BOTTOM\" layerFunction=\"ASSEMBLY\" side=\"BOTTOM\" polarity=\"POSITIVE\"/>\n\
+             \x20     <Layer name=\"DRILL\" layerFunction=\"DRILL\" side=\"ALL\" polarity=\"POSITIVE\"/>\n",
+        );
+        s.push_str(&format!("      <Step name=\"{step}\">\n"));
+
+        if let Some(outline) = self
+            .section("BOARD_OUTLINE")
+            .or_else(|| self.section("PANEL_OUTLINE"))
+        {
+            s.push_str("        <Profile>\n");
+            for (i, points) in section_loops(outline).iter().enumerate() {
+                let tag = if i == 0 { "Polygon" } else { "Cutout" };
+                s.push_str(&format!("          <{tag}>\n"));
+                polygon(&mut s, points, &mm);
+                s.push_str(&format!("          </{tag}>\n"));
+            }
+            s.push_str("        </Profile>\n");
+        }
+
+        for c in &self.placement {
+            let layer = match c.board_side {
+                BoardSide::Top => "TOP",
+                BoardSide::Bottom => "BOTTOM",
+            };
+            s.push_str(&format!(
+                "        <Component refDes=\"{}\" packageRef=\"{}\" part=\"{}\" layerRef=\"{layer}\">\n",
+                xml_escape(&c.designator.to_string()),
+                xml_escape(&c.package_name),
+                xml_escape(&c.part_number),
+            ));
+            let mirror = if c.board_side == BoardSide::Bottom {
+                " mirror=\"true\""
+            } else {
+                ""
+            };
+            s.push_str(&format!(
+                "          <Xform rotation=\"{:.3}\"{mirror}/>\n",
+                c.rotation
+            ));
+            s.push_str(&format!(
+                "          <Location x=\"{:.4}\" y=\"{:.4}\"/>\n",
+                mm(c.x as f64),
+                mm(c.y as f64)
+            ));
+            s.push_str("        </Component>\n");
+        }
+
+        if let Some(holes) = self.section("DRILLED_HOLES") {
+            s.push_str("        <LayerFeature layerRef=\"DRILL\">\n");
+            s.push_str("          <Set>\n");
+            for (i, hole) in holes.records().iter().filter(|r| r.len() >= 4).enumerate() {
+                let plating = match &hole[3] {
+                    IdfValue::String(p) if &**p == "NPTH" => "NONPLATED",
+                    _ => "PLATED",
+                };
+                s.push_str(&format!(
+                    "            <Hole name=\"H{}\" diameter=\"{:.4}\" platingStatus=\"{plating}\" \
+                     plusTol=\"0\" minusTol=\"0\" x=\"{:.4}\" y=\"{:.4}\"/>\n",
+                    i + 1,
+                    mm(number(&hole[0])),
+                    mm(number(&hole[1])),
+                    mm(number(&hole[2])),
+                ));
+            }
+            s.push_str("          </Set>\n");
+            s.push_str("        </LayerFeature>\n");
+        }
+
+        s.push_str("      </Step>\n");
+        s.push_str("    </CadData>\n");
+        s.push_str("  </Ecad>\n");
+        s.push_str("</IPC-2581>\n");
+        Ok(s)
+    }
+}
+
+fn polygon(s: &mut String, points: &[[f64; 3]], mm: &dyn Fn(f64) -> f64) {
+    let indent = "            ";
+    let Some(first) = points.first() else {
+        return;
+    };
+    if let Some(circle) = points.get(1).filter(|p| p[2].abs() >= 360.0) {
+        // Full circle around the first point
+        s.push_str(&format!(
+            "{indent}<PolyBegin x=\"{:.4}\" y=\"{:.4}\"/>\n",
+            mm(circle[0]),
+            mm(circle[1])
+        ));
+        s.push_str(&format!(
+            "{indent}<PolyStepCurve x=\"{:.4}\" y=\"{:.4}\" centerX=\"{:.4}\" centerY=\"{:.4}\" clockwise=\"{}\"/>\n",
+            mm(circle[0]),
+            mm(circle[1]),
+            mm(first[0]),
+            mm(first[1]),
+            circle[2] < 0.0
+        ));
+        return;
+    }
+    s.push_str(&format!(
+        "{indent}<PolyBegin x=\"{:.4}\" y=\"{:.4}\"/>\n",
+        mm(first[0]),
+        mm(first[1])
+    ));
+    for pair in points.windows(2) {
+        let (from, to) = (pair[0], pair[1]);
+        if to[2] == 0.0 {
+            s.push_str(&format!(
+                "{indent}<PolyStepSegment x=\"{:.4}\" y=\"{:.4}\"/>\n",
+                mm(to[0]),
+                mm(to[1])
+            ));
+        } else {
+            let (cx, cy) = arc_center(from, to);
+            s.push_str(&format!(
+                "{indent}<PolyStepCurve x=\"{:.4}\" y=\"{:.4}\" centerX=\"{:.4}\" centerY=\"{:.4}\" clockwise=\"{}\"/>\n",
+                mm(to[0]),
+                mm(to[1]),
+                mm(cx),
+                mm(cy),
+                to[2] < 0.0
+            ));
+        }
+    }
+}
@@ -0,0 +1,114 @@
+//! Conversions between IDF data and other file formats.
+
+use crate::idf30::{Error, Idf30, IdfSection, IdfValue, Owner, Unit};
+use either::Either;
+
+pub mod centroid;
+pub mod dxf;
+pub mod excellon;
+pub mod gerber_x3;
+pub mod idf20;
+pub mod idx;
+pub mod ipc2581;
+pub mod odb;
+pub mod svg;
+
+pub(crate) fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Points closer than this (in source units) are joined when chaining contour segments.
+pub(crate) const JOIN_TOLERANCE: f64 = 1e-4;
+
+/// Converts imported loops to board units and stores them in the outline or keepout section
+/// `name`. A missing keepout section is added, owned by MCAD and covering all layers, both sides
+/// or any height.
+pub(crate) fn import_loops<'a>(
+    idf: &mut Idf30<'a>,
+    name: &str,
+    mut loops: Vec<Vec<[f64; 3]>>,
+    units: &Unit,
+) -> Result<(), Error> {
+    if loops.is_empty() {
+        return Err(Error::MalformedOutline("No closed contour found"));
+    }
+    let board_units = idf.units().ok_or(Error::WrongFileType)?.clone();
+    for p in loops.iter_mut().flatten() {
+        p[0] = units.convert(p[0], &board_units);
+        p[1] = units.convert(p[1], &board_units);
+    }
+    // The largest loop is the outline, the rest are cutouts
+    let area = |l: &Vec<[f64; 3]>| crate::geometry::signed_area(l).abs();
+    let outer = (0..loops.len())
+        .max_by(|&a, &b| area(&loops[a]).total_cmp(&area(&loops[b])))
+        .unwrap_or(0);
+    loops.swap(0, outer);
+
+    let index = match idf.other_sections.iter().position(|s| s.name() == name) {
+        Some(i) => i,
+        None => {
+            let first_record = match name {
+                "ROUTE_KEEPOUT" | "ROUTE_OUTLINE" => {
+                    vec![IdfValue::String(Either::Left("ALL"))]
+                }
+                "PLACE_OUTLINE" | "PLACE_KEEPOUT" => {
+                    vec![IdfValue::String(Either::Left("BOTH")), IdfValue::Float(0.0)]
+                }
+                "VIA_KEEPOUT" => vec![],
+                "BOARD_OUTLINE" => return Err(Error::MissingSection("BOARD_OUTLINE")),
+                "PANEL_OUTLINE" => return Err(Error::MissingSection("PANEL_OUTLINE")),
+                _ => return Err(Error::MalformedOutline("Unsupported outline section")),
+            };
+            let records = if first_record.is_empty() {
+                vec![]
+            } else {
+                vec![first_record]
+            };
+            idf.other_sections.push(IdfSection::new(
+                Either::Right(name.to_string()),
+                vec![Either::Right(Owner::MCad.to_string())],
+                records,
+            ));
+            idf.other_sections.len() - 1
+        }
+    };
+    idf.other_sections[index].set_loops(&loops);
+    Ok(())
+}
+
+/// Joins open segments `[start, end]` with their arc angles into closed loops, reversing
+/// segments where needed.
+pub(crate) fn chain_segments(
+    mut segments: Vec<([f64; 2], [f64; 2], f64)>,
+) -> Result<Vec<Vec<[f64; 3]>>, Error> {
+    let close = |a: [f64; 2], b: [f64; 2]| (a[0] - b[0]).hypot(a[1] - b[1]) <= JOIN_TOLERANCE;
+    let mut loops = vec![];
+    while let Some((start, end, angle)) = segments.pop() {
+        let mut points = vec![[start[0], start[1], 0.0], [end[0], end[1], angle]];
+        let mut tip = end;
+        while !close(tip, start) {
+            let next = segments
+                .iter()
+                .position(|s| close(s.0, tip) || close(s.1, tip))
+                .ok_or(Error::MalformedOutline("Contour is not closed"))?;
+            let (a, b, angle) = segments.swap_remove(next);
+            let (to, angle) = if close(a, tip) {
+                (b, angle)
+            } else {
+                (a, -angle)
+            };
+            points.push([to[0], to[1], angle]);
+            tip = to;
+        }
+        if let Some(last) = points.last_mut() {
+            last[0] = start[0];
+            last[1] = start[1];
+        }
+        loops.push(points);
+    }
+    Ok(loops)
+}
@@ -0,0 +1,197 @@
+//! Minimal ODB++ job: matrix, board profile, component layers and the EDA package list the
+//! components refer to.
+
+use crate::geometry::{arc_center, definition_loops, section_loops, BoundingBox};
+use crate::idf30::{BoardSide, ComponentDefinition, Error, Idf30, Unit};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+const STEP: &str = "pcb";
+
+/// Files of an ODB++ job, keyed by path relative to the job directory.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct OdbJob {
+    pub files: BTreeMap<String, String>,
+}
+
+impl OdbJob {
+    /// Writes all files below `dir`, creating directories as needed.
+    pub fn write(&self, dir: &Path) -> Result<(), Error> {
+        for (path, contents) in &self.files {
+            let path = dir.join(path);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(path, contents)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Idf30<'a> {
+    /// Generates the `profile` and component layers of a single step job in millimeters.
+    /// Package outlines are taken from `library` where a definition exists, other packages get
+    /// an empty outline.
+    pub fn to_odb(&self, library: Option<&Idf30>) -> Result<OdbJob, Error> {
+        let units = self.units().ok_or(Error::WrongFileType)?;
+        let mm = |v: f64| units.convert(v, &Unit::SImm);
+        let mut job = OdbJob::default();
+
+        job.files.insert(
+            "misc/info".to_string(),
+            format!(
+                "JOB_NAME={}\nODB_VERSION_MAJOR=7\nODB_VERSION_MINOR=0\nODB_SOURCE={} {}\nUNITS=MM\n",
+                self.board_name().unwrap_or_default(),
+                env!("CARGO_PKG_NAME"),
+                env!("CARGO_PKG_VERSION"),
+            ),
+        );
+        let mut matrix = format!("STEP {{\n   COL=1\n   NAME={}\n}}\n\n", STEP.to_uppercase());
+        for (row, name) in ["comp_+_top", "comp_+_bot"].iter().enumerate() {
+            matrix.push_str(&format!(
+                "LAYER {{\n   ROW={}\n   CONTEXT=BOARD\n   TYPE=COMPONENT\n   NAME={}\n   POLARITY=POSITIVE\n}}\n\n",
+                row + 1,
+                name.to_uppercase()
+            ));
+        }
+        job.files.insert("matrix/matrix".to_string(), matrix);
+
+        let mut profile = String::from("UNITS=MM\n#\nS P 0\n");
+        if let Some(outline) = self
+            .section("BOARD_OUTLINE")
+            .or_else(|| self.section("PANEL_OUTLINE"))
+        {
+            for (i, points) in section_loops(outline).iter().enumerate() {
+                contour(&mut profile, points, i == 0, &mm);
+            }
+        }
+        profile.push_str("SE\n");
+        job.files.insert(format!("steps/{STEP}/profile"), profile);
+
+        // Packages in order of first use, components refer to them by index
+        let mut packages: Vec<(String, Option<&ComponentDefinition>)> = vec![];
+        let mut layers = [String::from("UNITS=MM\n#\n"), String::from("UNITS=MM\n#\n")];
+        for c in &self.placement {
+            let definition = library.and_then(|l| l.definition(&c.package_name, &c.part_number));
+            let package = match packages
+                .iter()
+                .position(|(name, _)| *name == *c.package_name)
+            {
+                Some(i) => i,
+                None => {
+                    packages.push((c.package_name.to_string(), definition));
+                    packages.len() - 1
+                }
+            };
+            let (layer, mirror) = match c.board_side {
+                BoardSide::Top => (&mut layers[0], 'N'),
+                BoardSide::Bottom => (&mut layers[1], 'M'),
+            };
+            // ODB++ rotations are clockwise
+            let rotation = (360.0 - c.rotation).rem_euclid(360.0);
+            layer.push_str(&format!(
+                "CMP {package} {:.4} {:.4} {rotation:.3} {mirror} {} {};\n",
+                mm(c.x as f64),
+                mm(c.y as f64),
+                odb_name(&c.designator.to_string()),
+                odb_name(&c.part_number),
+            ));
+        }
+        let [top, bottom] = layers;
+        job.files
+            .insert(format!("steps/{STEP}/layers/comp_+_top/components"), top);
+        job.files
+            .insert(format!("steps/{STEP}/layers/comp_+_bot/components"), bottom);
+
+        let mut eda = String::from("HDR idf\nUNITS=MM\n#\n");
+        for (name, definition) in &packages {
+            let loops = definition.map(definition_loops).unwrap_or_default();
+            let def_mm = |v: f64| {
+                definition
+                    .map(|d| d.units.convert(v, &Unit::SImm))
+                    .unwrap_or(v)
+            };
+            let bbox = BoundingBox::from_points(
+                loops.iter().flatten().map(|p| (def_mm(p[0]), def_mm(p[1]))),
+            )
+            .unwrap_or(BoundingBox {
+                min_x: 0.0,
+                min_y: 0.0,
+                max_x: 0.0,
+                max_y: 0.0,
+            });
+            eda.push_str(&format!(
+                "PKG {} 0 {:.4} {:.4} {:.4} {:.4};\n",
+                odb_name(name),
+                bbox.min_x,
+                bbox.min_y,
+                bbox.max_x,
+                bbox.max_y
+            ));
+            if !loops.is_empty() {
+                eda.push_str("CT\n");
+                for (i, points) in loops.iter().enumerate() {
+                    contour(&mut eda, points, i == 0, &def_mm);
+                }
+                eda.push_str("CE\n");
+            }
+        }
+        job.files.insert(format!("steps/{STEP}/eda/data"), eda);
+        Ok(job)
+    }
+}
+
+/// Writes one `OB .. OE` contour, islands for outer loops and holes for cutouts.
+fn contour(s: &mut String, points: &[[f64; 3]], island: bool, mm: &dyn Fn(f64) -> f64) {
+    let Some(first) = points.first() else {
+        return;
+    };
+    let kind = if island { 'I' } else { 'H' };
+    if let Some(circle) = points.get(1).filter(|p| p[2].abs() >= 360.0) {
+        s.push_str(&format!(
+            "OB {:.4} {:.4} {kind}\n",
+            mm(circle[0]),
+            mm(circle[1])
+        ));
+        s.push_str(&format!(
+            "OC {:.4} {:.4} {:.4} {:.4} {}\n",
+            mm(circle[0]),
+            mm(circle[1]),
+            mm(first[0]),
+            mm(first[1]),
+            if circle[2] < 0.0 { 'Y' } else { 'N' }
+        ));
+    } else {
+        s.push_str(&format!(
+            "OB {:.4} {:.4} {kind}\n",
+            mm(first[0]),
+            mm(first[1])
+        ));
+        for pair in points.windows(2) {
+            let (from, to) = (pair[0], pair[1]);
+            if to[2] == 0.0 {
+                s.push_str(&format!("OS {:.4} {:.4}\n", mm(to[0]), mm(to[1])));
+            } else {
+                let (cx, cy) = arc_center(from, to);
+                s.push_str(&format!(
+                    "OC {:.4} {:.4} {:.4} {:.4} {}\n",
+                    mm(to[0]),
+                    mm(to[1]),
+                    mm(cx),
+                    mm(cy),
+                    if to[2] < 0.0 { 'Y' } else { 'N' }
+                ));
+            }
+        }
+    }
+    s.push_str("OE\n");
+}
+
+/// ODB++ names cannot contain whitespace or be empty.
+fn odb_name(name: &str) -> String {
+    if name.is_empty() {
+        "-".to_string()
+    } else {
+        name.replace(|c: char| c.is_whitespace() || c == ';', "_")
+    }
+}
@@ -0,0 +1,186 @@
+//! Board outlines and keepouts from SVG path data.
+
+use super::{chain_segments, import_loops, JOIN_TOLERANCE};
+use crate::idf30::{Error, Idf30, Unit};
+
+impl<'a> Idf30<'a> {
+    /// Replaces the loops of the outline or keepout section `section` with the subpaths of the
+    /// SVG path data `d` (the `d` attribute of a `<path>`) in `units`.
+    ///
+    /// Only lines and circular arcs are supported. The SVG y axis points down, so y is negated.
+    pub fn import_svg_path(&mut self, d: &str, section: &str, units: Unit) -> Result<(), Error> {
+        import_loops(self, section, svg_path_loops(d)?, &units)
+    }
+}
+
+/// Subpaths of the path data as loops of `[x, y, angle]`, with y pointing up.
+pub fn svg_path_loops(d: &str) -> Result<Vec<Vec<[f64; 3]>>, Error> {
+    let mut tokens = Tokens { s: d.trim_start() };
+    let mut loops = vec![];
+    let mut segments = vec![];
+    let mut subpath: Vec<[f64; 3]> = vec![];
+    let (mut x, mut y) = (0.0, 0.0);
+    let mut command = None;
+    let finish = |subpath: &mut Vec<[f64; 3]>, segments: &mut Vec<([f64; 2], [f64; 2], f64)>| {
+        for pair in subpath.windows(2) {
+            segments.push((
+                [pair[0][0], pair[0][1]],
+                [pair[1][0], pair[1][1]],
+                pair[1][2],
+            ));
+        }
+        subpath.clear();
+    };
+    while !tokens.s.is_empty() {
+        if let Some(c) = tokens.command() {
+            command = Some(c);
+        } else if command.is_none() {
+            return Err(Error::MalformedOutline(
+                "SVG path data must start with a command",
+            ));
+        }
+        let Some(c) = command else { break };
+        let relative = c.is_ascii_lowercase();
+        let (ox, oy) = if relative { (x, y) } else { (0.0, 0.0) };
+        match c.to_ascii_uppercase() {
+            'M' => {
+                finish(&mut subpath, &mut segments);
+                x = ox + tokens.number()?;
+                y = oy + tokens.number()?;
+                subpath.push([x, y, 0.0]);
+                // Coordinates following a moveto are implicit linetos
+                command = Some(if relative { 'l' } else { 'L' });
+            }
+            'L' => {
+                x = ox + tokens.number()?;
+                y = oy + tokens.number()?;
+                subpath.push([x, y, 0.0]);
+            }
+            'H' => {
+                x = ox + tokens.number()?;
+                subpath.push([x, y, 0.0]);
+            }
+            'V' => {
+                y = oy + tokens.number()?;
+                subpath.push([x, y, 0.0]);
+            }
+            'A' => {
+                let (rx, ry) = (tokens.number()?, tokens.number()?);
+                let _rotation = tokens.number()?;
+                let (large_arc, sweep) = (tokens.flag()?, tokens.flag()?);
+                let (to_x, to_y) = (ox + tokens.number()?, oy + tokens.number()?);
+                if (rx.abs() - ry.abs()).abs() > JOIN_TOLERANCE {
+                    return Err(Error::MalformedOutline("Elliptical arcs are not supported"));
+                }
+                let chord = (to_x - x).hypot(to_y - y);
+                let half = (chord / (2.0 * rx.abs())).min(1.0).asin().to_degrees();
+                let mut angle = if large_arc {
+                    360.0 - 2.0 * half
+                } else {
+                    2.0 * half
+                };
+                // A positive sweep runs clockwise once y points up
+                if sweep {
+                    angle = -angle;
+                }
+                x = to_x;
+                y = to_y;
+                subpath.push([x, y, if rx == 0.0 { 0.0 } else { angle }]);
+            }
+            'Z' => {
+                if let Some(&first) = subpath.first() {
+                    let last = subpath[subpath.len() - 1];
+                    if (last[0] - first[0]).hypot(last[1] - first[1]) > JOIN_TOLERANCE {
+                        subpath.push([first[0], first[1], 0.0]);
+                    }
+                    x = first[0];
+                    y = first[1];
+                    if subpath.len() > 2 {
+                        loops.push(std::mem::take(&mut subpath));
+                    }
+                    subpath.push([x, y, 0.0]);
+                }
+                command = None;
+            }
+            _ => {
+                return Err(Error::MalformedOutline(
+                    "Only lines and arcs are supported in SVG paths",
+                ))
+            }
+        }
+    }
+    finish(&mut subpath, &mut segments);
+    let mut loops = [loops, chain_segments(segments)?].concat();
+    for p in loops.iter_mut().flatten() {
+        p[1] = -p[1];
+    }
+    Ok(loops)
+}
+
+struct Tokens<'s> {
+    s: &'s str,
+}
+
+impl<'s> Tokens<'s> {
+    fn skip_separators(&mut self) {
+        self.s = self
+            .s
+            .trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+    }
+
+    fn command(&mut self) -> Option<char> {
+        self.skip_separators();
+        let c = self
+            .s
+            .chars()
+            .next()
+            .filter(|c| c.is_ascii_alphabetic() && *c != 'e' && *c != 'E')?;
+        self.s = &self.s[1..];
+        Some(c)
+    }
+
+    fn number(&mut self) -> Result<f64, Error> {
+        self.skip_separators();
+        let bytes = self.s.as_bytes();
+        let mut end = 0;
+        let mut seen_dot = false;
+        if end < bytes.len() && (bytes[end] == b'-' || bytes[end] == b'+') {
+            end += 1;
+        }
+        while end < bytes.len() {
+            match bytes[end] {
+                b'0'..=b'9' => {}
+                b'.' if !seen_dot => seen_dot = true,
+                b'e' | b'E' => {
+                    end += 1;
+                    if end < bytes.len() && (bytes[end] == b'-' || bytes[end] == b'+') {
+                        end += 1;
+                    }
+                    continue;
+                }
+                _ => break,
+            }
+            end += 1;
+        }
+        let value = self.s[..end]
+            .parse()
+            .map_err(|_| Error::MalformedOutline("Expected a number in SVG path data"))?;
+        self.s = &self.s[end..];
+        Ok(value)
+    }
+
+    fn flag(&mut self) -> Result<bool, Error> {
+        self.skip_separators();
+        let flag = match self.s.chars().next() {
+            Some('0') => false,
+            Some('1') => true,
+            _ => {
+                return Err(Error::MalformedOutline(
+                    "Expected an arc flag in SVG path data",
+                ))
+            }
+        };
+        self.s = &self.s[1..];
+        Ok(flag)
+    }
+}
@@ -0,0 +1,462 @@
+use crate::idf30::{push_loop_point, ComponentDefinition, IdfSection, IdfValue, Loop, Point};
+use crate::transform::{is_loop_point, Transform};
+
+/// Maximum angle covered by a single straight segment when arcs are tessellated, in degrees.
+const ARC_STEP: f64 = 5.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingBox {
+    pub min_x: f64,
+    pub min_y: f64,
+    pub max_x: f64,
+    pub max_y: f64,
+}
+
+impl BoundingBox {
+    pub fn from_points(points: impl IntoIterator<Item = (f64, f64)>) -> Option<BoundingBox> {
+        let mut points = points.into_iter();
+        let (x, y) = points.next()?;
+        let mut bbox = BoundingBox {
+            min_x: x,
+            min_y: y,
+            max_x: x,
+            max_y: y,
+        };
+        for (x, y) in points {
+            bbox.include(x, y);
+        }
+        Some(bbox)
+    }
+
+    pub fn include(&mut self, x: f64, y: f64) {
+        self.min_x = self.min_x.min(x);
+        self.min_y = self.min_y.min(y);
+        self.max_x = self.max_x.max(x);
+        self.max_y = self.max_y.max(y);
+    }
+
+    pub fn union(&self, other: &BoundingBox) -> BoundingBox {
+        BoundingBox {
+            min_x: self.min_x.min(other.min_x),
+            min_y: self.min_y.min(other.min_y),
+            max_x: self.max_x.max(other.max_x),
+            max_y: self.max_y.max(other.max_y),
+        }
+    }
+
+    pub fn width(&self) -> f64 {
+        self.max_x - self.min_x
+    }
+
+    pub fn height(&self) -> f64 {
+        self.max_y - self.min_y
+    }
+}
+
+/// Extents along X, Y and Z.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingBox3d {
+    pub min_x: f64,
+    pub min_y: f64,
+    pub min_z: f64,
+    pub max_x: f64,
+    pub max_y: f64,
+    pub max_z: f64,
+}
+
+impl BoundingBox3d {
+    /// Size along X, Y and Z.
+    pub fn size(&self) -> (f64, f64, f64) {
+        (
+            self.max_x - self.min_x,
+            self.max_y - self.min_y,
+            self.max_z - self.min_z,
+        )
+    }
+}
+
+impl<'a> IdfSection<'a> {
+    /// Extents of all loops in the section, with arcs taken into account.
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        self.bounding_box_transformed(&Transform::identity())
+    }
+
+    pub(crate) fn bounding_box_transformed(&self, transform: &Transform) -> Option<BoundingBox> {
+        BoundingBox::from_points(
+            section_loops(self)
+                .iter()
+                .flat_map(|l| tessellate(l))
+                .map(|(x, y)| transform.apply(x, y)),
+        )
+    }
+}
+
+impl<'a> IdfSection<'a> {
+    /// Replaces the loop points of the section, keeping the other records in front of them.
+    /// The first loop is written counterclockwise with label 0, the following ones are cutouts
+    /// and written clockwise with labels 1, 2 and so on.
+    pub fn set_loops(&mut self, loops: &[Vec<[f64; 3]>]) {
+        let records = self.records_mut();
+        records.retain(|r| !is_loop_point(r));
+        for (label, points) in loops.iter().enumerate() {
+            let counterclockwise = signed_area(points) >= 0.0;
+            let points = if counterclockwise == (label == 0) {
+                points.clone()
+            } else {
+                reversed(points)
+            };
+            for p in points {
+                records.push(vec![
+                    IdfValue::Integer(label as i64),
+                    IdfValue::Float(p[0]),
+                    IdfValue::Float(p[1]),
+                    IdfValue::Float(p[2]),
+                ]);
+            }
+        }
+    }
+}
+
+impl<'a> IdfSection<'a> {
+    /// Loops of an outline, keepout or region section, in file order.
+    pub fn loops(&self) -> Vec<Loop> {
+        let mut loops = vec![];
+        for record in self.records().iter().filter(|r| is_loop_point(r)) {
+            let point = Point {
+                x: number(&record[1]) as f32,
+                y: number(&record[2]) as f32,
+                angle: number(&record[3]) as f32,
+            };
+            push_loop_point(&mut loops, number(&record[0]) as u32, point);
+        }
+        loops
+    }
+}
+
+impl Loop {
+    /// Center and radius if the loop is a full circle: a center point followed by a point on
+    /// the circle with a 360 degree angle.
+    pub fn as_circle(&self) -> Option<((f32, f32), f32)> {
+        let ((x, y), r) = circle(&self.raw_points())?;
+        Some(((x as f32, y as f32), r as f32))
+    }
+
+    /// Enclosed area, arcs and circles included.
+    pub fn area(&self) -> f64 {
+        signed_area(&self.raw_points()).abs()
+    }
+
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        if let Some(((x, y), r)) = circle(&self.raw_points()) {
+            return BoundingBox::from_points([(x - r, y - r), (x + r, y + r)]);
+        }
+        BoundingBox::from_points(self.polyline())
+    }
+
+    /// Points of the loop with arcs and circles replaced by short straight segments.
+    pub fn polyline(&self) -> Vec<(f64, f64)> {
+        tessellate(&self.raw_points())
+    }
+
+    /// Whether the point lies inside the loop, with exact arcs and circles.
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        loop_contains(&self.raw_points(), x, y)
+    }
+
+    /// True if the loop runs counterclockwise, as outer loops should.
+    pub fn is_counterclockwise(&self) -> bool {
+        signed_area(&self.raw_points()) >= 0.0
+    }
+
+    /// Reverses the direction of the loop, arcs keep their shape.
+    pub fn reverse(&mut self) {
+        self.points = reversed(&self.raw_points())
+            .into_iter()
+            .map(|p| Point {
+                x: p[0] as f32,
+                y: p[1] as f32,
+                angle: p[2] as f32,
+            })
+            .collect();
+    }
+
+    fn raw_points(&self) -> Vec<[f64; 3]> {
+        self.points
+            .iter()
+            .map(|p| [p.x as f64, p.y as f64, p.angle as f64])
+            .collect()
+    }
+}
+
+/// Loop points of the section as `[x, y, angle]`, grouped by consecutive loop label.
+pub(crate) fn section_loops(section: &IdfSection) -> Vec<Vec<[f64; 3]>> {
+    let mut loops: Vec<Vec<[f64; 3]>> = vec![];
+    let mut label = None;
+    for record in section.records().iter().filter(|r| is_loop_point(r)) {
+        let point = [number(&record[1]), number(&record[2]), number(&record[3])];
+        if label.as_ref() != Some(&record[0]) {
+            label = Some(record[0].clone());
+            loops.push(vec![]);
+        }
+        if let Some(l) = loops.last_mut() {
+            l.push(point);
+        }
+    }
+    loops
+}
+
+/// Outline loops of a library definition as `[x, y, angle]` points.
+pub(crate) fn definition_loops(definition: &ComponentDefinition) -> Vec<Vec<[f64; 3]>> {
+    definition
+        .loops
+        .iter()
+        .map(|l| {
+            l.points
+                .iter()
+                .map(|p| [p.x as f64, p.y as f64, p.angle as f64])
+                .collect()
+        })
+        .collect()
+}
+
+/// Converts a loop into a polyline, replacing arcs and circles with short straight segments.
+pub(crate) fn tessellate(points: &[[f64; 3]]) -> Vec<(f64, f64)> {
+    let mut out = vec![];
+    let mut prev: Option<[f64; 3]> = None;
+    for &p in points {
+        match prev {
+            Some(prev) if p[2].abs() >= 360.0 => {
+                let radius = (p[0] - prev[0]).hypot(p[1] - prev[1]);
+                out.pop();
+                let steps = (360.0 / ARC_STEP) as usize;
+                for i in 0..=steps {
+                    let a = (i as f64 * ARC_STEP * p[2].signum()).to_radians();
+                    out.push((prev[0] + radius * a.cos(), prev[1] + radius * a.sin()));
+                }
+            }
+            Some(prev) if p[2] != 0.0 => {
+                out.extend(arc(prev, p).into_iter().skip(1));
+            }
+            _ => out.push((p[0], p[1])),
+        }
+        prev = Some(p);
+    }
+    out
+}
+
+/// Center of the arc from `from` to `to` sweeping `to[2]` degrees, counterclockwise if positive.
+pub(crate) fn arc_center(from: [f64; 3], to: [f64; 3]) -> (f64, f64) {
+    let (dx, dy) = (to[0] - from[0], to[1] - from[1]);
+    let chord = dx.hypot(dy);
+    let half = (to[2] / 2.0).to_radians();
+    let offset = chord / (2.0 * half.tan());
+    let (mx, my) = ((from[0] + to[0]) / 2.0, (from[1] + to[1]) / 2.0);
+    (mx - dy / chord * offset, my + dx / chord * offset)
+}
+
+fn arc(from: [f64; 3], to: [f64; 3]) -> Vec<(f64, f64)> {
+    let (cx, cy) = arc_center(from, to);
+    let radius = (from[0] - cx).hypot(from[1] - cy);
+    let start = (from[1] - cy).atan2(from[0] - cx);
+    let steps = (to[2].abs() / ARC_STEP).ceil().max(1.0) as usize;
+    let mut points: Vec<(f64, f64)> = (0..steps)
+        .map(|i| {
+            let a = start + (to[2] * i as f64 / steps as f64).to_radians();
+            (cx + radius * a.cos(), cy + radius * a.sin())
+        })
+        .collect();
+    points.push((to[0], to[1]));
+    points
+}
+
+/// Even-odd test of a point against closed polylines, so points inside cutouts are outside.
+pub(crate) fn polylines_contain(polylines: &[Vec<(f64, f64)>], x: f64, y: f64) -> bool {
+    let mut inside = false;
+    for polyline in polylines {
+        for (a, b) in polyline.iter().zip(polyline.iter().cycle().skip(1)) {
+            if (a.1 > y) != (b.1 > y) && x < a.0 + (y - a.1) / (b.1 - a.1) * (b.0 - a.0) {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+/// Even-odd test of a point against a loop. The polygon through the loop points is tested
+/// first, then every arc flips the result for points between its chord and the arc.
+pub(crate) fn loop_contains(points: &[[f64; 3]], x: f64, y: f64) -> bool {
+    if let Some(((cx, cy), r)) = circle(points) {
+        return (x - cx).hypot(y - cy) <= r;
+    }
+    let polygon: Vec<(f64, f64)> = points.iter().map(|p| (p[0], p[1])).collect();
+    let mut inside = polylines_contain(std::slice::from_ref(&polygon), x, y);
+    for pair in points.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        if b[2] == 0.0 {
+            continue;
+        }
+        let (cx, cy) = arc_center(a, b);
+        let radius = (a[0] - cx).hypot(a[1] - cy);
+        // Counterclockwise arcs bulge to the right of the chord
+        let side = (b[0] - a[0]) * (y - a[1]) - (b[1] - a[1]) * (x - a[0]);
+        let bulge_side = if b[2] > 0.0 { side < 0.0 } else { side > 0.0 };
+        if bulge_side && (x - cx).hypot(y - cy) < radius {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Smallest distance between two sets of closed polylines, zero if they cross or one lies
+/// inside the other.
+pub(crate) fn polylines_distance(a: &[Vec<(f64, f64)>], b: &[Vec<(f64, f64)>]) -> f64 {
+    let inside = |outer: &[Vec<(f64, f64)>], inner: &[Vec<(f64, f64)>]| {
+        inner
+            .iter()
+            .flatten()
+            .next()
+            .is_some_and(|p| polylines_contain(outer, p.0, p.1))
+    };
+    if inside(a, b) || inside(b, a) {
+        return 0.0;
+    }
+    edges_distance(a, b)
+}
+
+/// True if the insides of two sets of closed polylines share an area, unlike outlines that
+/// only touch along an edge or at a corner. Points a small step away from every corner, edge
+/// midpoint and edge crossing are tested against both sets.
+pub(crate) fn polylines_overlap(a: &[Vec<(f64, f64)>], b: &[Vec<(f64, f64)>]) -> bool {
+    let extent = a
+        .iter()
+        .chain(b)
+        .flatten()
+        .fold(1.0f64, |m, p| m.max(p.0.abs()).max(p.1.abs()));
+    let step = extent * 1e-6;
+    let edges = |polylines: &[Vec<(f64, f64)>]| -> Vec<((f64, f64), (f64, f64))> {
+        polylines
+            .iter()
+            .flat_map(|l| l.iter().copied().zip(l.iter().copied().cycle().skip(1)))
+            .filter(|(p, q)| p != q)
+            .collect()
+    };
+    let (a_edges, b_edges) = (edges(a), edges(b));
+    let mut candidates: Vec<(f64, f64)> = vec![];
+    for &(p, q) in a_edges.iter().chain(&b_edges) {
+        let length = (q.0 - p.0).hypot(q.1 - p.1);
+        let normal = ((p.1 - q.1) / length * step, (q.0 - p.0) / length * step);
+        let mid = ((p.0 + q.0) / 2.0, (p.1 + q.1) / 2.0);
+        candidates.push((mid.0 + normal.0, mid.1 + normal.1));
+        candidates.push((mid.0 - normal.0, mid.1 - normal.1));
+        candidates.push(p);
+    }
+    for &(p, q) in &a_edges {
+        for &(r, s) in &b_edges {
+            let d = (q.0 - p.0) * (s.1 - r.1) - (q.1 - p.1) * (s.0 - r.0);
+            if d == 0.0 || segment_distance(p, q, r, s) > 0.0 {
+                continue;
+            }
+            let t = ((r.0 - p.0) * (s.1 - r.1) - (r.1 - p.1) * (s.0 - r.0)) / d;
+            candidates.push((p.0 + t * (q.0 - p.0), p.1 + t * (q.1 - p.1)));
+        }
+    }
+    candidates.iter().any(|&(x, y)| {
+        // A smaller step than the one off the edges, so no point lands back on one
+        [(1.0, 1.0), (1.0, -1.0), (-1.0, 1.0), (-1.0, -1.0)]
+            .iter()
+            .map(|(dx, dy)| (x + dx * step * 0.3, y + dy * step * 0.3))
+            .any(|(x, y)| polylines_contain(a, x, y) && polylines_contain(b, x, y))
+    })
+}
+
+/// Smallest distance between the edges of two sets of closed polylines, zero if they cross.
+pub(crate) fn edges_distance(a: &[Vec<(f64, f64)>], b: &[Vec<(f64, f64)>]) -> f64 {
+    let edges = |polylines: &[Vec<(f64, f64)>]| -> Vec<((f64, f64), (f64, f64))> {
+        polylines
+            .iter()
+            .flat_map(|l| l.iter().copied().zip(l.iter().copied().cycle().skip(1)))
+            .collect()
+    };
+    let (a, b) = (edges(a), edges(b));
+    let mut distance = f64::INFINITY;
+    for &(p, q) in &a {
+        for &(r, s) in &b {
+            distance = distance.min(segment_distance(p, q, r, s));
+        }
+    }
+    distance
+}
+
+/// Smallest distance between segments `pq` and `rs`.
+pub(crate) fn segment_distance(p: (f64, f64), q: (f64, f64), r: (f64, f64), s: (f64, f64)) -> f64 {
+    let cross = |o: (f64, f64), a: (f64, f64), b: (f64, f64)| {
+        (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+    };
+    let (d1, d2) = (cross(r, s, p), cross(r, s, q));
+    let (d3, d4) = (cross(p, q, r), cross(p, q, s));
+    if ((d1 > 0.0) != (d2 > 0.0)) && ((d3 > 0.0) != (d4 > 0.0)) {
+        return 0.0;
+    }
+    point_segment_distance(p, r, s)
+        .min(point_segment_distance(q, r, s))
+        .min(point_segment_distance(r, p, q))
+        .min(point_segment_distance(s, p, q))
+}
+
+pub(crate) fn point_segment_distance(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length = dx * dx + dy * dy;
+    let t = if length == 0.0 {
+        0.0
+    } else {
+        (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / length).clamp(0.0, 1.0)
+    };
+    (p.0 - a.0 - t * dx).hypot(p.1 - a.1 - t * dy)
+}
+
+/// Center and radius of a loop that is a full circle.
+pub(crate) fn circle(points: &[[f64; 3]]) -> Option<((f64, f64), f64)> {
+    match points {
+        [center, edge] if edge[2].abs() >= 360.0 => Some((
+            (center[0], center[1]),
+            (edge[0] - center[0]).hypot(edge[1] - center[1]),
+        )),
+        _ => None,
+    }
+}
+
+/// Area enclosed by a loop, positive when it runs counterclockwise.
+pub(crate) fn signed_area(points: &[[f64; 3]]) -> f64 {
+    if let Some((_, radius)) = circle(points) {
+        return std::f64::consts::PI * radius * radius * points[1][2].signum();
+    }
+    let polyline = tessellate(points);
+    polyline
+        .iter()
+        .zip(polyline.iter().cycle().skip(1))
+        .map(|(a, b)| a.0 * b.1 - b.0 * a.1)
+        .sum::<f64>()
+        / 2.0
+}
+
+/// The same loop traversed in the opposite direction, arcs keep their shape.
+pub(crate) fn reversed(points: &[[f64; 3]]) -> Vec<[f64; 3]> {
+    if points.iter().any(|p| p[2].abs() >= 360.0) {
+        return points
+            .iter()
+            .map(|p| [p[0], p[1], if p[2].abs() >= 360.0 { -p[2] } else { p[2] }])
+            .collect();
+    }
+    let n = points.len();
+    (0..n)
+        .map(|k| {
+            let p = points[n - 1 - k];
+            let angle = if k == 0 { 0.0 } else { -points[n - k][2] };
+            [p[0], p[1], angle]
+        })
+        .collect()
+}
+
+pub(crate) fn number(value: &IdfValue) -> f64 {
+    value.as_f64().unwrap_or(0.0)
+}
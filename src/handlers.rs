@@ -0,0 +1,50 @@
+//! Typed access to vendor specific sections through user provided handlers. Sections without a
+//! handler are kept as plain `IdfSection`s and written back unchanged.
+
+use crate::idf30::{Error, Idf30, IdfSection};
+
+/// Parses and writes the records of one proprietary section, e.g. `.PADSTACKS`.
+pub trait SectionHandler {
+    type Value;
+
+    /// Name of the handled section, without the leading dot.
+    fn section_name(&self) -> &str;
+
+    fn parse(&self, section: &IdfSection) -> Result<Self::Value, Error>;
+
+    fn write(&self, value: &Self::Value) -> IdfSection<'static>;
+}
+
+impl<'a> Idf30<'a> {
+    /// Values of all sections handled by `handler`, in file order.
+    pub fn handled_sections<H: SectionHandler>(&self, handler: &H) -> Result<Vec<H::Value>, Error> {
+        self.other_sections
+            .iter()
+            .filter(|s| s.name() == handler.section_name())
+            .map(|s| handler.parse(s))
+            .collect()
+    }
+
+    /// Replaces the sections handled by `handler` with `values`. Existing sections are
+    /// overwritten in place, extra values are appended and extra sections removed.
+    pub fn set_handled_sections<H: SectionHandler>(&mut self, handler: &H, values: &[H::Value]) {
+        let mut values = values.iter();
+        let mut i = 0;
+        while i < self.other_sections.len() {
+            if self.other_sections[i].name() != handler.section_name() {
+                i += 1;
+                continue;
+            }
+            match values.next() {
+                Some(value) => {
+                    self.other_sections[i] = handler.write(value);
+                    i += 1;
+                }
+                None => {
+                    self.remove_section(i);
+                }
+            }
+        }
+        self.other_sections.extend(values.map(|v| handler.write(v)));
+    }
+}
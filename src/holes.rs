@@ -0,0 +1,238 @@
+use crate::assembly::IdfAssembly;
+use crate::idf30::{
+    ComponentPlacement, Error, Idf30, IdfSection, IdfValue, Owner, ReferenceDesignator,
+};
+use either::Either;
+use std::fmt::{Display, Formatter};
+
+/// Record of the `.DRILLED_HOLES` section.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DrilledHole<'a> {
+    pub diameter: f32,
+    pub x: f32,
+    pub y: f32,
+    pub plating: Plating,
+    /// Component the hole belongs to, BOARD, NOREFDES or PANEL
+    pub association: ReferenceDesignator<'a>,
+    pub hole_type: HoleType<'a>,
+    pub owner: Owner,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Plating {
+    Plated,
+    NonPlated,
+}
+
+impl Display for Plating {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Plating::Plated => write!(f, "PTH"),
+            Plating::NonPlated => write!(f, "NPTH"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum HoleType<'a> {
+    Pin,
+    Via,
+    Mounting,
+    Tooling,
+    Other(Either<&'a str, String>),
+}
+
+impl<'a> Display for HoleType<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HoleType::Pin => write!(f, "PIN"),
+            HoleType::Via => write!(f, "VIA"),
+            HoleType::Mounting => write!(f, "MTG"),
+            HoleType::Tooling => write!(f, "TOOL"),
+            HoleType::Other(t) => write!(f, "{t}"),
+        }
+    }
+}
+
+impl<'a> DrilledHole<'a> {
+    pub fn from_record(record: &[IdfValue<'a>]) -> Result<DrilledHole<'a>, Error> {
+        if record.len() < 7 {
+            return Err(Error::Malformed("Expected 7 fields per drilled hole"));
+        }
+        let num = |i: usize| {
+            record[i]
+                .as_f64()
+                .map(|x| x as f32)
+                .ok_or(Error::Malformed("Expected drilled hole dimension"))
+        };
+        let text = |i: usize| match &record[i] {
+            IdfValue::String(s) => s.clone(),
+            v => Either::Right(v.to_string()),
+        };
+        let plating = match &*text(3) {
+            "PTH" => Plating::Plated,
+            "NPTH" => Plating::NonPlated,
+            _ => return Err(Error::Malformed("Expected PTH or NPTH")),
+        };
+        let association = match &*text(4) {
+            "BOARD" => ReferenceDesignator::Board,
+            "NOREFDES" => ReferenceDesignator::NoRefDes,
+            _ => ReferenceDesignator::Any(text(4)),
+        };
+        let hole_type = match &*text(5) {
+            "PIN" => HoleType::Pin,
+            "VIA" => HoleType::Via,
+            "MTG" => HoleType::Mounting,
+            "TOOL" => HoleType::Tooling,
+            _ => HoleType::Other(text(5)),
+        };
+        Ok(DrilledHole {
+            diameter: num(0)?,
+            x: num(1)?,
+            y: num(2)?,
+            plating,
+            association,
+            hole_type,
+            owner: text(6).parse()?,
+        })
+    }
+
+    pub fn to_record(&self) -> Vec<IdfValue<'a>> {
+        let association = match &self.association {
+            ReferenceDesignator::Any(d) => d.clone(),
+            d => Either::Right(d.to_string()),
+        };
+        let hole_type = match &self.hole_type {
+            HoleType::Other(t) => t.clone(),
+            t => Either::Right(t.to_string()),
+        };
+        vec![
+            IdfValue::Float(self.diameter as f64),
+            IdfValue::Float(self.x as f64),
+            IdfValue::Float(self.y as f64),
+            IdfValue::String(Either::Right(self.plating.to_string())),
+            IdfValue::String(association),
+            IdfValue::String(hole_type),
+            IdfValue::String(Either::Right(self.owner.to_string())),
+        ]
+    }
+}
+
+impl<'a> Idf30<'a> {
+    /// Typed records of the `.DRILLED_HOLES` section, empty if there is none.
+    pub fn drilled_holes(&self) -> Result<Vec<DrilledHole<'a>>, Error> {
+        match self.section("DRILLED_HOLES") {
+            Some(section) => section
+                .records()
+                .iter()
+                .map(|r| DrilledHole::from_record(r))
+                .collect(),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Like `drilled_holes`, but skips records that cannot be read and returns their errors
+    /// alongside, located by record index, for summaries that should not fail on one bad hole.
+    pub fn drilled_holes_lossy(&self) -> (Vec<DrilledHole<'a>>, Vec<Error>) {
+        let mut holes = vec![];
+        let mut errors = vec![];
+        let records = self.section("DRILLED_HOLES").map(|s| s.records());
+        for (i, r) in records.unwrap_or_default().iter().enumerate() {
+            match DrilledHole::from_record(r) {
+                Ok(hole) => holes.push(hole),
+                Err(e) => errors.push(e.at("DRILLED_HOLES", Some(i), (0, 0))),
+            }
+        }
+        (holes, errors)
+    }
+
+    /// Holes an enclosure can fasten the board with, for placing bosses: non-plated holes of
+    /// at least `min_diameter` and all holes belonging to the board itself.
+    pub fn mounting_holes(&self, min_diameter: f32) -> Result<Vec<DrilledHole<'a>>, Error> {
+        let mut holes = self.drilled_holes()?;
+        holes.retain(|h| {
+            (h.plating == Plating::NonPlated && h.diameter >= min_diameter)
+                || h.association == ReferenceDesignator::Board
+        });
+        Ok(holes)
+    }
+
+    /// Removes the drilled holes matching `predicate` and returns them in their original order.
+    /// The section is only rewritten if a hole was removed.
+    pub fn extract_holes(
+        &mut self,
+        mut predicate: impl FnMut(&DrilledHole<'a>) -> bool,
+    ) -> Result<Vec<DrilledHole<'a>>, Error> {
+        let (removed, kept): (Vec<_>, Vec<_>) = self
+            .drilled_holes()?
+            .into_iter()
+            .partition(|h| predicate(h));
+        if !removed.is_empty() {
+            self.set_drilled_holes(&kept);
+        }
+        Ok(removed)
+    }
+
+    /// Replaces the records of the `.DRILLED_HOLES` section, adding the section if needed.
+    pub fn set_drilled_holes(&mut self, holes: &[DrilledHole<'a>]) {
+        let records = holes.iter().map(|h| h.to_record()).collect();
+        match self
+            .other_sections
+            .iter_mut()
+            .find(|s| s.name() == "DRILLED_HOLES")
+        {
+            Some(section) => *section.records_mut() = records,
+            None => self.other_sections.push(IdfSection::new(
+                Either::Left("DRILLED_HOLES"),
+                vec![],
+                records,
+            )),
+        }
+    }
+}
+
+/// Holes belonging to one component.
+#[derive(Clone, Debug)]
+pub struct ComponentHoles<'s, 'a> {
+    pub placement: &'s ComponentPlacement<'a>,
+    pub holes: Vec<DrilledHole<'a>>,
+}
+
+impl<'a> IdfAssembly<'a> {
+    /// Groups the drilled holes by component, in placement order. Holes name their component,
+    /// NOREFDES holes are given to the nearest component if its outline (or reference point) is
+    /// within `max_distance`. BOARD and PANEL holes are left out.
+    pub fn component_holes(&self, max_distance: f64) -> Result<Vec<ComponentHoles<'_, 'a>>, Error> {
+        let mut grouped: Vec<ComponentHoles> = self
+            .board
+            .placement
+            .iter()
+            .map(|placement| ComponentHoles {
+                placement,
+                holes: vec![],
+            })
+            .collect();
+        let index = self.component_index();
+        for hole in self.board.drilled_holes()? {
+            let owner = match &hole.association {
+                ReferenceDesignator::Any(_) => grouped
+                    .iter()
+                    .position(|g| g.placement.designator == hole.association),
+                ReferenceDesignator::NoRefDes => index
+                    .nearest_component(hole.x as f64, hole.y as f64)
+                    .filter(|(_, d)| *d <= max_distance)
+                    .and_then(|(c, _)| {
+                        grouped
+                            .iter()
+                            .position(|g| std::ptr::eq(g.placement, c.placement))
+                    }),
+                _ => None,
+            };
+            if let Some(i) = owner {
+                grouped[i].holes.push(hole);
+            }
+        }
+        grouped.retain(|g| !g.holes.is_empty());
+        Ok(grouped)
+    }
+}
@@ -1,14 +1,29 @@
+use crate::classify::DEFAULT;
+use crate::date::HeaderDate;
+use crate::reader::{IdfEvent, IdfReader};
+use crate::transform::LOOP_SECTIONS;
+use crate::warnings::ParseWarning;
+use crate::write::WriteOptions;
 use either::Either;
-use pest::iterators::Pairs;
+use grammar::{Idf30Parser, Rule};
+use pest::error::LineColLocation;
+use pest::iterators::{Pair, Pairs};
 use pest::Parser;
-use pest_derive::Parser;
+use std::borrow::Cow;
 use std::fmt::{Display, Formatter};
 use std::num::{ParseFloatError, ParseIntError};
+use std::path::PathBuf;
+use std::str::FromStr;
 use thiserror::Error;
 
-#[derive(Parser)]
-#[grammar = "idf30.pest"]
-struct Idf30Parser;
+mod grammar {
+    use pest_derive::Parser;
+
+    /// The pest rules stay private, grammar changes do not touch the public API.
+    #[derive(Parser)]
+    #[grammar = "idf30.pest"]
+    pub(super) struct Idf30Parser;
+}
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -20,27 +35,201 @@ pub enum Error {
     WrongFileType,
     #[error("MM or THOU expected")]
     WrongUnit,
+    #[error("Expected .{} section", .0)]
+    MissingSection(&'static str),
+    #[error("Reference designator {} is used more than once", .0)]
+    DuplicateDesignator(String),
+    #[error("No component with reference designator {}", .0)]
+    UnknownDesignator(String),
+    #[error("Merged .{} overlaps an existing one", .0)]
+    OverlappingSections(String),
+    #[error("Invalid selector term {}", .0)]
+    InvalidSelector(String),
+    #[error("Malformed CSV at line {}: {}", .0, .1)]
+    MalformedCsv(usize, &'static str),
+    #[error("Malformed drill file at line {}: {}", .0, .1)]
+    MalformedDrill(usize, &'static str),
+    #[error("Malformed outline: {}", .0)]
+    MalformedOutline(&'static str),
+    #[error("No matching file found for {}", .0.display())]
+    IncompletePair(PathBuf),
+    #[error("Parse limit exceeded: {}", .0)]
+    LimitExceeded(&'static str),
     #[error("Expected 2 records per component, got 1")]
     MalformedPlacementSection,
     #[error("{}", .0)]
     Malformed(&'static str),
     #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
     ParseInt(#[from] ParseIntError),
     #[error(transparent)]
     ParseFloat(#[from] ParseFloatError),
     #[error(transparent)]
-    Pest(#[from] pest::error::Error<Rule>),
+    Syntax(SyntaxError),
     #[error("Internal grammar error")]
     GrammarExpectedPair,
-    #[error("Expected different rule, got: {:?}", .0)]
-    GrammarExpectedRule(Rule),
+    #[error("Internal grammar error, unexpected {}", .0)]
+    GrammarExpectedRule(String),
+    #[error("Field {field}: {reason}")]
+    SchemaMismatch { field: String, reason: &'static str },
+    #[error("{location}: {source}")]
+    At {
+        location: Location,
+        source: Box<Error>,
+    },
 }
 
-#[derive(Clone, Debug)]
+impl Error {
+    /// Wraps a parse error with the place it occurred at. Errors that already have a location
+    /// keep the more precise one.
+    pub(crate) fn at(
+        self,
+        section: &str,
+        record: Option<usize>,
+        (line, column): (usize, usize),
+    ) -> Error {
+        if matches!(self, Error::At { .. }) {
+            return self;
+        }
+        Error::At {
+            location: Location {
+                section: section.to_string(),
+                record,
+                line,
+                column,
+            },
+            source: Box::new(self),
+        }
+    }
+
+    /// Moves the reported line by `lines`, for errors in text that was parsed on its own.
+    pub(crate) fn offset_lines(self, lines: usize) -> Error {
+        match self {
+            Error::At {
+                mut location,
+                source,
+            } => {
+                location.line += lines;
+                Error::At { location, source }
+            }
+            Error::Syntax(mut e) => {
+                e.start.0 += lines;
+                e.end.0 += lines;
+                Error::Syntax(e)
+            }
+            e => e,
+        }
+    }
+}
+
+/// Text that does not follow the IDF grammar.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SyntaxError {
+    message: String,
+    /// 1-based line and column
+    start: (usize, usize),
+    end: (usize, usize),
+}
+
+impl SyntaxError {
+    /// What was expected at the error, without its location.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Line and column, both 1-based, of the start of the offending text.
+    pub fn start(&self) -> (usize, usize) {
+        self.start
+    }
+
+    /// Line and column after the offending text, equal to `start` for errors at a position.
+    pub fn end(&self) -> (usize, usize) {
+        self.end
+    }
+}
+
+impl Display for SyntaxError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} at line {}, column {}",
+            self.message, self.start.0, self.start.1
+        )
+    }
+}
+
+impl std::error::Error for SyntaxError {}
+
+fn syntax_error(e: pest::error::Error<Rule>) -> Error {
+    let (start, end) = match e.line_col {
+        LineColLocation::Pos(p) => (p, p),
+        LineColLocation::Span(start, end) => (start, end),
+    };
+    Error::Syntax(SyntaxError {
+        message: e.variant.message().into_owned(),
+        start,
+        end,
+    })
+}
+
+/// Where in the parsed text an error was found.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Location {
+    pub section: String,
+    /// Index of the record in the section, of the component in PLACEMENT
+    pub record: Option<usize>,
+    /// 1-based line and column, 0 for errors found in a model rather than in text
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, ".{}", self.section)?;
+        if let Some(record) = self.record {
+            write!(f, " record {record}")?;
+        }
+        if self.line > 0 {
+            write!(f, " at line {}, column {}", self.line, self.column)?;
+        }
+        Ok(())
+    }
+}
+
+/// Equality is exact for floats, strings compare by text whether borrowed or owned, warnings
+/// are not compared. See `approx_eq` for comparing files after a round trip.
+#[derive(Clone, Debug, Default)]
 pub struct Idf30<'a> {
     pub header: Header<'a>,
     pub placement: Vec<ComponentPlacement<'a>>,
     pub other_sections: Vec<IdfSection<'a>>,
+    /// Order of the sections in the parsed file, used when writing. Sections missing here are
+    /// written after the listed ones: other sections first, then the placement or the library
+    /// definitions.
+    pub section_order: Vec<SectionRef>,
+    /// Issues found in the text the model was parsed from, empty for models built in code
+    pub warnings: Vec<ParseWarning>,
+}
+
+impl<'a> PartialEq for Idf30<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.header == other.header
+            && self.placement == other.placement
+            && self.other_sections == other.other_sections
+            && self.section_order == other.section_order
+    }
+}
+
+/// Section of an `Idf30`, see `Idf30::section_order`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum SectionRef {
+    /// Index into `other_sections`
+    Other(usize),
+    /// All placements, written as a single section
+    Placement,
+    /// Index into the library definitions
+    Definition(usize),
 }
 
 #[derive(Clone, Debug)]
@@ -51,20 +240,63 @@ pub struct Header<'a> {
     pub board_file_version: u32,
 }
 
+impl<'a> Header<'a> {
+    /// Header of a new board file written by this crate, dated now.
+    pub fn new_board(name: impl Into<String>, units: Unit) -> Header<'a> {
+        Header {
+            ty: FileType::BoardFile {
+                board_name: Either::Right(name.into()),
+                units,
+            },
+            source: Either::Right(crate_source()),
+            date: Either::Right(HeaderDate::now().to_string()),
+            board_file_version: 1,
+        }
+    }
+
+    /// Header of a new library file written by this crate, dated now.
+    pub fn new_library() -> Header<'a> {
+        Header {
+            ty: FileType::LibraryFile { components: vec![] },
+            source: Either::Right(crate_source()),
+            date: Either::Right(HeaderDate::now().to_string()),
+            board_file_version: 1,
+        }
+    }
+
+    /// Marks the file as a new revision written by this crate: increments the board file
+    /// version and updates source and date.
+    pub fn bump_version(&mut self) {
+        self.board_file_version += 1;
+        self.source = Either::Right(crate_source());
+        self.touch();
+    }
+}
+
+/// Empty board file in millimetres, see `Header::new_board`.
+impl<'a> Default for Header<'a> {
+    fn default() -> Self {
+        Header::new_board(String::new(), Unit::default())
+    }
+}
+
+impl<'a> PartialEq for Header<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ty == other.ty
+            && same_text(&self.source, &other.source)
+            && same_text(&self.date, &other.date)
+            && self.board_file_version == other.board_file_version
+    }
+}
+
+/// Source tool of files written by this crate, a single token as the header requires.
+fn crate_source() -> String {
+    format!("{}_{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
+}
+
 impl<'a> Display for Header<'a> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let record1 = match &self.ty {
-            FileType::BoardFile { board_name, units }
-            | FileType::PanelFile { board_name, units } => {
-                format!("{} {}\n", board_name, units)
-            }
-            FileType::LibraryFile { .. } => String::new(),
-        };
-        write!(
-            f,
-            ".HEADER\n{} 3.0 {} {} {}\n{}.END_HEADER\n",
-            self.ty, self.source, self.date, self.board_file_version, record1
-        )
+        f.write_str(&WriteOptions::SPEC.header(self))
     }
 }
 
@@ -83,6 +315,34 @@ pub enum FileType<'a> {
     },
 }
 
+impl<'a> PartialEq for FileType<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                FileType::BoardFile { board_name, units },
+                FileType::BoardFile {
+                    board_name: other_name,
+                    units: other_units,
+                },
+            )
+            | (
+                FileType::PanelFile { board_name, units },
+                FileType::PanelFile {
+                    board_name: other_name,
+                    units: other_units,
+                },
+            ) => same_text(board_name, other_name) && units == other_units,
+            (
+                FileType::LibraryFile { components },
+                FileType::LibraryFile {
+                    components: other_components,
+                },
+            ) => components == other_components,
+            _ => false,
+        }
+    }
+}
+
 impl<'a> Display for FileType<'a> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -93,12 +353,37 @@ impl<'a> Display for FileType<'a> {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
 pub enum Unit {
+    #[default]
     SImm,
     Mils,
 }
 
+impl Unit {
+    /// Converts `value` given in these units into `to` units.
+    pub fn convert(&self, value: f64, to: &Unit) -> f64 {
+        match (self, to) {
+            (Unit::SImm, Unit::Mils) => value / 0.0254,
+            (Unit::Mils, Unit::SImm) => value * 0.0254,
+            _ => value,
+        }
+    }
+}
+
+impl FromStr for Unit {
+    type Err = Error;
+
+    /// Accepts MM and THOU in any case, and the MIL and MILS aliases some exporters write.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().as_str() {
+            "MM" => Ok(Unit::SImm),
+            "THOU" | "MIL" | "MILS" => Ok(Unit::Mils),
+            _ => Err(Error::WrongUnit),
+        }
+    }
+}
+
 impl Display for Unit {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -112,23 +397,89 @@ impl Display for Unit {
 pub struct IdfSection<'a> {
     /// e.g. BOARD_OUTLINE
     name: Either<&'a str, String>,
-    /// e.g. ECAD in 'BOARD_OUTLINE ECAD'
+    /// e.g. ECAD in 'BOARD_OUTLINE ECAD', only for outline, keepout and region sections
+    owner: Option<Owner>,
+    /// Arguments after the owner
     args: Vec<Either<&'a str, String>>,
     records: Vec<Vec<IdfValue<'a>>>,
 }
 
-impl<'a> Display for IdfSection<'a> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let args: String = self.args.iter().map(|arg| format!(" {arg}")).collect();
-        let mut records = String::new();
-        for record in self.records.iter() {
-            records.push_str(" ");
-            for v in record {
-                records.push_str(format!(" {v}").as_str());
+impl<'a> IdfSection<'a> {
+    /// Section with the arguments of its first line. The first argument of an outline, keepout
+    /// or region section is taken as its owner if it is one.
+    pub fn new(
+        name: Either<&'a str, String>,
+        mut args: Vec<Either<&'a str, String>>,
+        records: Vec<Vec<IdfValue<'a>>>,
+    ) -> Self {
+        let mut owner = None;
+        if LOOP_SECTIONS.contains(&&*name) {
+            owner = args.first().and_then(|a| a.parse().ok());
+            if owner.is_some() {
+                args.remove(0);
             }
-            records.push_str("\n");
         }
-        write!(f, ".{}{}\n{}.END_{}\n", self.name, args, records, self.name)
+        IdfSection {
+            name,
+            owner,
+            args,
+            records,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Arguments of the first line after the owner, e.g. an owner that is not recognized.
+    pub fn args(&self) -> &[Either<&'a str, String>] {
+        &self.args
+    }
+
+    pub fn records(&self) -> &[Vec<IdfValue<'a>>] {
+        &self.records
+    }
+
+    pub fn records_mut(&mut self) -> &mut Vec<Vec<IdfValue<'a>>> {
+        &mut self.records
+    }
+
+    /// Owner of an outline, keepout or region section, `None` for other sections or if the
+    /// owner is missing or not recognized.
+    pub fn owner(&self) -> Option<Owner> {
+        self.owner
+    }
+
+    /// Sets the owner of an outline, keepout or region section, replacing an owner that was
+    /// not recognized. Other sections are left as is.
+    pub fn set_owner(&mut self, owner: Owner) {
+        if !LOOP_SECTIONS.contains(&&*self.name) {
+            return;
+        }
+        if self.owner.is_none() && !self.args.is_empty() {
+            self.args.remove(0);
+        }
+        self.owner = Some(owner);
+    }
+}
+
+impl<'a> PartialEq for IdfSection<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        same_text(&self.name, &other.name)
+            && self.owner == other.owner
+            && self.args.len() == other.args.len()
+            && self
+                .args
+                .iter()
+                .zip(&other.args)
+                .all(|(a, b)| same_text(a, b))
+            && self.records == other.records
+    }
+}
+
+impl<'a> Display for IdfSection<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&WriteOptions::SPEC.section(self))
     }
 }
 
@@ -145,21 +496,61 @@ pub struct ComponentPlacement<'a> {
     pub placement_status: PlacementStatus,
 }
 
+impl<'a> ComponentPlacement<'a> {
+    /// Unplaced component at the origin on the top side.
+    pub fn new(
+        package: impl Into<Cow<'a, str>>,
+        part: impl Into<Cow<'a, str>>,
+        designator: impl Into<Cow<'a, str>>,
+    ) -> Self {
+        ComponentPlacement {
+            package_name: cow_to_either(package.into()),
+            part_number: cow_to_either(part.into()),
+            designator: ReferenceDesignator::new(designator),
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+            rotation: 0.0,
+            board_side: BoardSide::Top,
+            placement_status: PlacementStatus::Unplaced,
+        }
+    }
+
+    /// Moves the component to `x`, `y` with `rotation` on `side`, marking it placed.
+    pub fn placed_at(mut self, x: f32, y: f32, rotation: f32, side: BoardSide) -> Self {
+        self.x = x;
+        self.y = y;
+        self.rotation = rotation;
+        self.board_side = side;
+        self.placement_status = PlacementStatus::Placed;
+        self
+    }
+}
+
+/// Unplaced NOREFDES component with empty package and part names.
+impl<'a> Default for ComponentPlacement<'a> {
+    fn default() -> Self {
+        ComponentPlacement::new("", "", "NOREFDES")
+    }
+}
+
+impl<'a> PartialEq for ComponentPlacement<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        same_text(&self.package_name, &other.package_name)
+            && same_text(&self.part_number, &other.part_number)
+            && self.designator == other.designator
+            && self.x == other.x
+            && self.y == other.y
+            && self.z == other.z
+            && self.rotation == other.rotation
+            && self.board_side == other.board_side
+            && self.placement_status == other.placement_status
+    }
+}
+
 impl<'a> Display for ComponentPlacement<'a> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{} {} {}\n  {:.4} {:.4} {:.4} {:.3} {} {}\n",
-            escape_string(&self.package_name),
-            escape_string(&self.part_number),
-            self.designator,
-            self.x,
-            self.y,
-            self.z,
-            self.rotation,
-            self.board_side,
-            self.placement_status
-        )
+        f.write_str(&WriteOptions::SPEC.placement(self))
     }
 }
 
@@ -169,45 +560,88 @@ pub struct ComponentDefinition<'a> {
     pub part_number: Either<&'a str, String>,
     pub units: Unit,
     pub height: f32,
-    pub points: Vec<Point>,
+    pub loops: Vec<Loop>,
+}
+
+impl<'a> PartialEq for ComponentDefinition<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        same_text(&self.geometry_name, &other.geometry_name)
+            && same_text(&self.part_number, &other.part_number)
+            && self.units == other.units
+            && self.height == other.height
+            && self.loops == other.loops
+    }
 }
 
 impl<'a> ComponentDefinition<'a> {
-    pub fn to_string(&self) -> String {
-        let mut s = format!(
-            ".ELECTRICAL\n{} {} {} {:.4}\n",
-            self.geometry_name, self.part_number, self.units, self.height
-        );
-        for p in &self.points {
-            let label = if p.label == LoopLabel::CounterClockwise {
-                0
-            } else {
-                1
-            };
-            s.push_str(format!("{} {:.4} {:.4} {:.4}\n", label, p.x, p.y, p.angle).as_str());
+    /// Definition without outline loops.
+    pub fn new(
+        geometry_name: impl Into<Cow<'a, str>>,
+        part_number: impl Into<Cow<'a, str>>,
+        units: Unit,
+        height: f32,
+    ) -> Self {
+        ComponentDefinition {
+            geometry_name: cow_to_either(geometry_name.into()),
+            part_number: cow_to_either(part_number.into()),
+            units,
+            height,
+            loops: vec![],
         }
-        s.push_str(".END_ELECTRICAL\n");
-        s
     }
 }
 
-#[derive(Clone, Debug)]
+impl<'a> Default for ComponentDefinition<'a> {
+    fn default() -> Self {
+        ComponentDefinition::new("", "", Unit::SImm, 0.0)
+    }
+}
+
+/// ELECTRICAL section with the loop points as parsed, each with the index of its loop.
+impl<'a> Display for ComponentDefinition<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&WriteOptions::SPEC.definition(self))
+    }
+}
+
+/// Closed loop of an outline. Index 0 is the outer loop, running counterclockwise, 1 and up are
+/// cutouts running clockwise.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Loop {
+    pub index: u32,
+    pub points: Vec<Point>,
+}
+
+impl Loop {
+    pub fn is_cutout(&self) -> bool {
+        self.index != 0
+    }
+}
+
+/// Loop point. A non-zero angle makes the segment from the previous point an arc sweeping that
+/// many degrees, counterclockwise if positive; 360 is a circle around the previous point.
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct Point {
-    pub label: LoopLabel,
     pub x: f32,
     pub y: f32,
     pub angle: f32,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub enum LoopLabel {
-    Clockwise,
-    CounterClockwise,
+/// Appends a point with loop index `index`, starting a new loop when the index changes.
+pub(crate) fn push_loop_point(loops: &mut Vec<Loop>, index: u32, point: Point) {
+    match loops.last_mut() {
+        Some(l) if l.index == index => l.points.push(point),
+        _ => loops.push(Loop {
+            index,
+            points: vec![point],
+        }),
+    }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub enum ReferenceDesignator<'a> {
     Any(Either<&'a str, String>),
+    #[default]
     NoRefDes,
     Board,
 }
@@ -223,24 +657,42 @@ impl<'a> Display for ReferenceDesignator<'a> {
 }
 
 impl<'a> ReferenceDesignator<'a> {
-    pub fn is_test_point(&self) -> bool {
-        match self {
-            ReferenceDesignator::Any(d) => match d {
-                Either::Left(d) => d.starts_with("TP"),
-                Either::Right(d) => d.starts_with("TP"),
-            },
-            ReferenceDesignator::NoRefDes => false,
-            ReferenceDesignator::Board => false,
+    /// Designator as written in a file, NOREFDES and BOARD are the special designators.
+    pub fn new(designator: impl Into<Cow<'a, str>>) -> Self {
+        match designator.into() {
+            d if d == "NOREFDES" => ReferenceDesignator::NoRefDes,
+            d if d == "BOARD" => ReferenceDesignator::Board,
+            d => ReferenceDesignator::Any(cow_to_either(d)),
         }
     }
+
+    /// True for designators matching the test point rule of the default `Classifier`, i.e.
+    /// starting with TP in any case. It used to be case sensitive, so `tp1` is a test point now
+    /// as well. See `Classifier` for configurable rules that also look at package names.
+    pub fn is_test_point(&self) -> bool {
+        DEFAULT.test_point.matches_designator(self)
+    }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub enum BoardSide {
+    #[default]
     Top,
     Bottom,
 }
 
+impl FromStr for BoardSide {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "TOP" => Ok(BoardSide::Top),
+            "BOTTOM" => Ok(BoardSide::Bottom),
+            _ => Err(Error::Malformed("Expected TOP or BOTTOM for side of board")),
+        }
+    }
+}
+
 impl Display for BoardSide {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -250,14 +702,29 @@ impl Display for BoardSide {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub enum PlacementStatus {
     Placed,
+    #[default]
     Unplaced,
     MCad,
     ECad,
 }
 
+impl FromStr for PlacementStatus {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "PLACED" => Ok(PlacementStatus::Placed),
+            "UNPLACED" => Ok(PlacementStatus::Unplaced),
+            "MCAD" => Ok(PlacementStatus::MCad),
+            "ECAD" => Ok(PlacementStatus::ECad),
+            _ => Err(Error::Malformed("Wrong placement status")),
+        }
+    }
+}
+
 impl Display for PlacementStatus {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -269,39 +736,259 @@ impl Display for PlacementStatus {
     }
 }
 
-#[derive(Clone, PartialEq, Debug)]
+impl PlacementStatus {
+    /// False for UNPLACED components, whose location has no meaning.
+    pub fn is_placed(&self) -> bool {
+        *self != PlacementStatus::Unplaced
+    }
+
+    /// System that fixed the placement, `None` for PLACED and UNPLACED components.
+    pub fn owner(&self) -> Option<Owner> {
+        match self {
+            PlacementStatus::ECad => Some(Owner::ECad),
+            PlacementStatus::MCad => Some(Owner::MCad),
+            _ => None,
+        }
+    }
+
+    /// Whether `owner` may move the component or change its status. PLACED and UNPLACED
+    /// components are free to move, ECAD and MCAD components only by that system.
+    pub fn can_be_moved_by(&self, owner: Owner) -> bool {
+        match self.owner() {
+            Some(o) => o == owner,
+            None => true,
+        }
+    }
+
+    /// Status of a component fixed by `owner`, PLACED for `Owner::Unowned`.
+    pub fn locked_by(owner: Owner) -> PlacementStatus {
+        match owner {
+            Owner::ECad => PlacementStatus::ECad,
+            Owner::MCad => PlacementStatus::MCad,
+            Owner::Unowned => PlacementStatus::Placed,
+        }
+    }
+}
+
+/// Which system may modify an outline, keepout or hole.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Owner {
+    ECad,
+    MCad,
+    Unowned,
+}
+
+impl FromStr for Owner {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim_matches('"') {
+            "ECAD" => Ok(Owner::ECad),
+            "MCAD" => Ok(Owner::MCad),
+            "UNOWNED" => Ok(Owner::Unowned),
+            _ => Err(Error::Malformed("Expected ECAD, MCAD or UNOWNED")),
+        }
+    }
+}
+
+impl Display for Owner {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Owner::ECad => write!(f, "ECAD"),
+            Owner::MCad => write!(f, "MCAD"),
+            Owner::Unowned => write!(f, "UNOWNED"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
 pub enum IdfValue<'a> {
     Integer(i64),
     Float(f64),
     String(Either<&'a str, String>),
 }
 
-impl<'a> Display for IdfValue<'a> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+impl<'a> PartialEq for IdfValue<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (IdfValue::Integer(a), IdfValue::Integer(b)) => a == b,
+            (IdfValue::Float(a), IdfValue::Float(b)) => a == b,
+            (IdfValue::String(a), IdfValue::String(b)) => same_text(a, b),
+            _ => false,
+        }
+    }
+}
+
+/// Whether two strings have the same text, borrowed or owned.
+fn same_text(a: &Either<&str, String>, b: &Either<&str, String>) -> bool {
+    **a == **b
+}
+
+impl<'a> IdfValue<'a> {
+    pub fn string(s: impl Into<Cow<'a, str>>) -> Self {
+        IdfValue::String(cow_to_either(s.into()))
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
         match self {
-            IdfValue::Integer(x) => write!(f, "{x}"),
-            IdfValue::Float(x) => write!(f, "{x:.4}"),
-            IdfValue::String(s) => write!(f, "{}", escape_string(s)),
+            IdfValue::Integer(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    /// Floats and integers as a float.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            IdfValue::Integer(x) => Some(*x as f64),
+            IdfValue::Float(x) => Some(*x),
+            IdfValue::String(_) => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            IdfValue::String(s) => Some(s),
+            _ => None,
         }
     }
 }
 
-fn escape_string<'a: 'b, 'b>(s: &'b Either<&'a str, String>) -> &'b str {
-    match s {
-        Either::Left(s) => {
-            if s.is_empty() {
-                "\"\""
-            } else {
-                s
-            }
+impl<'a> TryFrom<&IdfValue<'a>> for i64 {
+    type Error = Error;
+
+    fn try_from(value: &IdfValue<'a>) -> Result<Self, Self::Error> {
+        value.as_i64().ok_or(Error::Malformed("Expected integer"))
+    }
+}
+
+impl<'a> TryFrom<IdfValue<'a>> for i64 {
+    type Error = Error;
+
+    fn try_from(value: IdfValue<'a>) -> Result<Self, Self::Error> {
+        i64::try_from(&value)
+    }
+}
+
+impl<'a> TryFrom<&IdfValue<'a>> for f64 {
+    type Error = Error;
+
+    fn try_from(value: &IdfValue<'a>) -> Result<Self, Self::Error> {
+        value.as_f64().ok_or(Error::Malformed("Expected number"))
+    }
+}
+
+impl<'a> TryFrom<IdfValue<'a>> for f64 {
+    type Error = Error;
+
+    fn try_from(value: IdfValue<'a>) -> Result<Self, Self::Error> {
+        f64::try_from(&value)
+    }
+}
+
+impl<'a> TryFrom<&IdfValue<'a>> for String {
+    type Error = Error;
+
+    fn try_from(value: &IdfValue<'a>) -> Result<Self, Self::Error> {
+        value
+            .as_str()
+            .map(str::to_string)
+            .ok_or(Error::Malformed("Expected string"))
+    }
+}
+
+impl<'a> TryFrom<IdfValue<'a>> for String {
+    type Error = Error;
+
+    fn try_from(value: IdfValue<'a>) -> Result<Self, Self::Error> {
+        match value {
+            IdfValue::String(s) => Ok(s.either_into()),
+            _ => Err(Error::Malformed("Expected string")),
         }
-        Either::Right(s) => {
-            if s.is_empty() {
-                "\"\""
-            } else {
-                s.as_str()
+    }
+}
+
+impl<'a> Display for IdfValue<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&WriteOptions::SPEC.value(self))
+    }
+}
+
+/// Borrowed strings stay borrowed, owned ones owned.
+pub(crate) fn cow_to_either(s: Cow<'_, str>) -> Either<&str, String> {
+    match s {
+        Cow::Borrowed(s) => Either::Left(s),
+        Cow::Owned(s) => Either::Right(s),
+    }
+}
+
+/// Writes a string field, quoting it unless it reads back as the same string: a letter
+/// followed by letters, digits and `_./:?-`. Empty strings, strings with whitespace or quotes
+/// and strings that look like numbers are quoted, quotes inside are doubled. Names that read
+/// back as keywords are quoted by `WriteOptions`, see `KEYWORDS`.
+pub(crate) fn escape_string(s: &str) -> Cow<'_, str> {
+    quote_unless(s, s.starts_with(|c: char| c.is_ascii_alphabetic()))
+}
+
+/// Like `escape_string` for header fields, which may also start with a digit, as dates do.
+pub(crate) fn escape_header_string(s: &str) -> Cow<'_, str> {
+    quote_unless(s, s.starts_with(|c: char| c.is_ascii_alphanumeric()))
+}
+
+/// `s` as is if `bare_start` and it only has bare characters, quoted otherwise.
+pub(crate) fn quote_unless(s: &str, bare_start: bool) -> Cow<'_, str> {
+    let bare_char = |c: char| c.is_ascii_alphanumeric() || "_./:?-".contains(c);
+    if bare_start && s.chars().all(bare_char) {
+        return Cow::Borrowed(s);
+    }
+    Cow::Owned(format!("\"{}\"", s.replace('"', "\"\"")))
+}
+
+/// Keywords a placement field can hold, strings with the same text are written quoted.
+pub(crate) const KEYWORDS: [&str; 8] = [
+    "NOREFDES", "BOARD", "TOP", "BOTTOM", "PLACED", "UNPLACED", "MCAD", "ECAD",
+];
+
+/// Designator field as written: NOREFDES and BOARD are the special designators unless quoted.
+pub(crate) fn designator_field(token: &str) -> ReferenceDesignator<'_> {
+    match token {
+        "NOREFDES" => ReferenceDesignator::NoRefDes,
+        "BOARD" => ReferenceDesignator::Board,
+        _ => ReferenceDesignator::Any(unquote(token)),
+    }
+}
+
+/// Length in bytes of the field at the start of `s`: a quoted string up to its closing quote,
+/// or anything else up to the next whitespace.
+pub(crate) fn field_len(s: &str) -> usize {
+    let bytes = s.as_bytes();
+    match bytes.first() {
+        Some(&q @ (b'"' | b'\'')) => {
+            let mut end = 1;
+            loop {
+                match bytes[end..].iter().position(|b| *b == q) {
+                    // Doubled quote inside the string
+                    Some(e) if bytes.get(end + e + 1) == Some(&q) => end += e + 2,
+                    Some(e) => return end + e + 1,
+                    None => return bytes.len(),
+                }
             }
         }
+        _ => s.find(char::is_whitespace).unwrap_or(s.len()),
+    }
+}
+
+/// Contents of a string field, without the quotes of a quoted string and with doubled quotes
+/// collapsed. Only strings with doubled quotes are copied.
+pub(crate) fn unquote(s: &str) -> Either<&str, String> {
+    let Some(q) = s.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+        return Either::Left(s);
+    };
+    let inner = s[1..].strip_suffix(q).unwrap_or(&s[1..]);
+    let doubled = if q == '"' { "\"\"" } else { "''" };
+    if inner.contains(doubled) {
+        Either::Right(inner.replace(doubled, &q.to_string()))
+    } else {
+        Either::Left(inner)
     }
 }
 
@@ -314,15 +1001,28 @@ macro_rules! next_inner {
     };
 }
 
+/// Next string field, see `unquote`.
+macro_rules! next_string {
+    ($pairs:expr) => {{
+        let pair = $pairs.next().ok_or(Error::GrammarExpectedPair)?;
+        match pair.as_rule() {
+            Rule::string | Rule::string_num_allowed => Either::Left(pair.as_str()),
+            Rule::quoted_string => unquote(pair.as_str()),
+            rule => return Err(Error::GrammarExpectedRule(format!("{rule:?}"))),
+        }
+    }};
+}
+
 macro_rules! next_str {
     ($pairs:expr) => {{
         let pair = $pairs.next().ok_or(Error::GrammarExpectedPair)?;
         if pair.as_rule() == Rule::string || pair.as_rule() == Rule::string_num_allowed {
             pair.as_str()
         } else if pair.as_rule() == Rule::quoted_string {
-            pair.into_inner().as_str()
+            let quoted = pair.as_str();
+            &quoted[1..quoted.len() - 1]
         } else {
-            return Err(Error::GrammarExpectedRule(pair.as_rule()));
+            return Err(Error::GrammarExpectedRule(format!("{:?}", pair.as_rule())));
         }
     }};
 }
@@ -333,7 +1033,7 @@ macro_rules! next_int {
         if pair.as_rule() == Rule::integer {
             pair.as_str().parse()?
         } else {
-            return Err(Error::GrammarExpectedRule(pair.as_rule()));
+            return Err(Error::GrammarExpectedRule(format!("{:?}", pair.as_rule())));
         }
     }};
 }
@@ -344,104 +1044,120 @@ macro_rules! next_float {
         if pair.as_rule() == Rule::float {
             pair.as_str().parse()?
         } else {
-            return Err(Error::GrammarExpectedRule(pair.as_rule()));
+            return Err(Error::GrammarExpectedRule(format!("{:?}", pair.as_rule())));
         }
     }};
 }
 
 impl<'a> Idf30<'a> {
-    pub fn parse(file: &str) -> Result<Idf30, Error> {
-        let mut idf30 = Idf30Parser::parse(Rule::idf30, file)?;
-        // println!("{idf30:#?}");
-        let mut header = parse_header(&mut idf30)?;
-        let mut placement = vec![];
-        let mut other_sections = vec![];
-        let mut components_definitions = vec![];
-        while let Some(section) = idf30.next() {
-            if section.as_rule() == Rule::EOI {
-                break;
-            }
-            let mut section = section.into_inner();
-            let mut section_header = next_inner!(section);
-            let section_name = next_str!(next_inner!(section_header));
-            if section_name == "PLACEMENT" {
-                while let Some(record) = section.next() {
-                    if record.as_rule() == Rule::section_name {
-                        break;
-                    }
-                    let record = record.into_inner();
-                    let component = parse_component_placement(&mut section, record)?;
-                    placement.push(component);
-                }
-            } else if section_name == "ELECTRICAL" {
-                let component = parse_component_definition(&mut section)?;
-                components_definitions.push(component);
-            } else {
-                let args = section_header
-                    .into_iter()
-                    .map(|arg| Either::Left(arg.as_str()))
-                    .collect();
-                let mut records = vec![];
-                while let Some(record) = section.next() {
-                    if record.as_rule() == Rule::section_name {
-                        break;
-                    }
-                    let record = record.into_inner();
-                    let values: Result<Vec<IdfValue>, Error> = record
-                        .into_iter()
-                        .map(|p| match p.as_rule() {
-                            Rule::integer => Ok(IdfValue::Integer(p.as_str().parse()?)),
-                            Rule::float => Ok(IdfValue::Float(p.as_str().parse()?)),
-                            Rule::string => Ok(IdfValue::String(Either::Left(p.as_str()))),
-                            Rule::quoted_string => Ok(IdfValue::String(Either::Left(p.as_str()))),
-                            _ => return Err(Error::GrammarExpectedRule(Rule::value)),
-                        })
-                        .collect();
-                    records.push(values?);
-                }
-                let section = IdfSection {
-                    name: Either::Left(section_name),
-                    args,
-                    records,
-                };
-                other_sections.push(section);
+    pub fn parse(file: &str) -> Result<Idf30<'_>, Error> {
+        Idf30::read_with(file, &PestReader)
+    }
+
+    /// Name of a board or panel file.
+    pub fn board_name(&self) -> Option<&str> {
+        match &self.header.ty {
+            FileType::BoardFile { board_name, .. } | FileType::PanelFile { board_name, .. } => {
+                Some(board_name)
             }
+            FileType::LibraryFile { .. } => None,
         }
+    }
 
-        if matches!(header.ty, FileType::LibraryFile { .. }) {
-            header.ty = FileType::LibraryFile {
-                components: components_definitions,
-            };
+    /// Units of a board or panel file, library definitions carry their own units.
+    pub fn units(&self) -> Option<&Unit> {
+        match &self.header.ty {
+            FileType::BoardFile { units, .. } | FileType::PanelFile { units, .. } => Some(units),
+            FileType::LibraryFile { .. } => None,
         }
+    }
 
-        Ok(Idf30 {
-            header,
-            placement,
-            other_sections,
-        })
+    /// Library definition for a placement, matched by geometry name and part number, or by
+    /// geometry name alone if no definition has the same part number.
+    pub fn definition(
+        &self,
+        package_name: &str,
+        part_number: &str,
+    ) -> Option<&ComponentDefinition<'a>> {
+        let FileType::LibraryFile { components } = &self.header.ty else {
+            return None;
+        };
+        components
+            .iter()
+            .find(|c| *c.geometry_name == *package_name && *c.part_number == *part_number)
+            .or_else(|| {
+                components
+                    .iter()
+                    .find(|c| *c.geometry_name == *package_name)
+            })
     }
 
-    pub fn to_string(&self) -> String {
-        let mut s = format!("{}", self.header);
-        for o in &self.other_sections {
-            s.push_str(format!("{o}").as_str())
-        }
-        match &self.header.ty {
-            FileType::BoardFile { .. } => {
-                s.push_str(".PLACEMENT\n");
-                for c in &self.placement {
-                    s.push_str(format!("{c}").as_str())
+    /// First section with the given name, e.g. "BOARD_OUTLINE".
+    pub fn section(&self, name: &str) -> Option<&IdfSection<'a>> {
+        self.other_sections.iter().find(|s| s.name() == name)
+    }
+
+    /// Removes `other_sections[index]`, keeping `section_order` pointing at the same sections.
+    pub fn remove_section(&mut self, index: usize) -> IdfSection<'a> {
+        let removed = self.other_sections.remove(index);
+        self.section_order
+            .retain(|r| *r != SectionRef::Other(index));
+        for r in &mut self.section_order {
+            if let SectionRef::Other(i) = r {
+                if *i > index {
+                    *i -= 1;
                 }
-                s.push_str(".END_PLACEMENT\n");
             }
-            FileType::PanelFile { .. } => {}
-            FileType::LibraryFile { components } => {
-                for def in components {
-                    s.push_str(def.to_string().as_str());
+        }
+        removed
+    }
+
+    /// Removes the sections matching `predicate` and returns them in file order.
+    pub fn extract_sections(
+        &mut self,
+        mut predicate: impl FnMut(&IdfSection<'a>) -> bool,
+    ) -> Vec<IdfSection<'a>> {
+        let selected: Vec<usize> = (0..self.other_sections.len())
+            .filter(|&i| predicate(&self.other_sections[i]))
+            .collect();
+        let mut removed: Vec<IdfSection<'a>> = selected
+            .into_iter()
+            .rev()
+            .map(|i| self.remove_section(i))
+            .collect();
+        removed.reverse();
+        removed
+    }
+
+    /// Hands the sections selected by `filter` over to `owner`: outlines, keepouts and regions
+    /// get the new owner, as does every hole of a selected `.DRILLED_HOLES` section. Returns the
+    /// number of sections and holes changed.
+    pub fn set_owner(
+        &mut self,
+        mut filter: impl FnMut(&IdfSection<'a>) -> bool,
+        owner: Owner,
+    ) -> usize {
+        let mut changed = 0;
+        for section in self.other_sections.iter_mut().filter(|s| filter(s)) {
+            if section.name() == "DRILLED_HOLES" {
+                for record in section.records_mut().iter_mut().filter(|r| r.len() >= 7) {
+                    if record[6].to_string().parse::<Owner>().ok() != Some(owner) {
+                        record[6] = IdfValue::String(Either::Right(owner.to_string()));
+                        changed += 1;
+                    }
                 }
+            } else if section.owner().is_some_and(|o| o != owner) {
+                section.set_owner(owner);
+                changed += 1;
             }
         }
-        s
+        changed
+    }
+
+    /// Writes the file with the sections in their original order, see `to_string_with`.
+    #[allow(clippy::inherent_to_string)]
+    pub fn to_string(&self) -> String {
+        self.to_string_with(&WriteOptions::default())
     }
 }
 
@@ -449,14 +1165,9 @@ fn parse_component_placement<'a>(
     section: &mut Pairs<Rule>,
     mut record: Pairs<'a, Rule>,
 ) -> Result<ComponentPlacement<'a>, Error> {
-    let package_name = Either::Left(next_str!(record));
-    let part_number = Either::Left(next_str!(record));
-    let designator = next_str!(record);
-    let designator = match designator {
-        "NOREFDES" => ReferenceDesignator::NoRefDes,
-        "BOARD" => ReferenceDesignator::Board,
-        d => ReferenceDesignator::Any(Either::Left(d)),
-    };
+    let package_name = next_string!(record);
+    let part_number = next_string!(record);
+    let designator = designator_field(record.next().ok_or(Error::GrammarExpectedPair)?.as_str());
     let mut record = section
         .next()
         .ok_or(Error::MalformedPlacementSection)?
@@ -465,24 +1176,8 @@ fn parse_component_placement<'a>(
     let y = next_float!(record);
     let z = next_float!(record);
     let rotation = next_float!(record);
-    let side = next_str!(record);
-    let board_side = match side {
-        "TOP" => BoardSide::Top,
-        "BOTTOM" => BoardSide::Bottom,
-        _ => {
-            return Err(Error::Malformed("Expected TOP or BOTTOM for side of board"));
-        }
-    };
-    let placement_status = next_str!(record);
-    let placement_status = match placement_status {
-        "PLACED" => PlacementStatus::Placed,
-        "UNPLACED" => PlacementStatus::Unplaced,
-        "MCAD" => PlacementStatus::MCad,
-        "ECAD" => PlacementStatus::ECad,
-        _ => {
-            return Err(Error::Malformed("Wrong placement status"));
-        }
-    };
+    let board_side = next_str!(record).parse()?;
+    let placement_status = next_str!(record).parse()?;
     Ok(ComponentPlacement {
         package_name,
         part_number,
@@ -496,7 +1191,203 @@ fn parse_component_placement<'a>(
     })
 }
 
+/// Contents of a section following the header.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParsedSection<'a> {
+    Placement(Vec<ComponentPlacement<'a>>),
+    /// ELECTRICAL section of a library file
+    Definition(ComponentDefinition<'a>),
+    Other(IdfSection<'a>),
+}
+
+/// Reader backed by the pest grammar, used by `Idf30::parse`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PestReader;
+
+impl IdfReader for PestReader {
+    fn read<'a>(
+        &self,
+        text: &'a str,
+        emit: &mut dyn FnMut(IdfEvent<'a>) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let mut idf30 = Idf30Parser::parse(Rule::idf30, text).map_err(syntax_error)?;
+        emit(IdfEvent::Header(parse_header(&mut idf30)?))?;
+        for section in idf30 {
+            if section.as_rule() == Rule::EOI {
+                break;
+            }
+            emit(IdfEvent::Section(parse_section(section)?))?;
+        }
+        Ok(())
+    }
+}
+
+/// Collects parsed sections into an `Idf30`, keeping their order.
+#[derive(Default)]
+pub(crate) struct SectionCollector<'a> {
+    placement: Vec<ComponentPlacement<'a>>,
+    definitions: Vec<ComponentDefinition<'a>>,
+    other_sections: Vec<IdfSection<'a>>,
+    order: Vec<SectionRef>,
+}
+
+impl<'a> SectionCollector<'a> {
+    pub(crate) fn push(&mut self, section: ParsedSection<'a>) {
+        match section {
+            ParsedSection::Placement(p) => {
+                if !self.order.contains(&SectionRef::Placement) {
+                    self.order.push(SectionRef::Placement);
+                }
+                if self.placement.is_empty() {
+                    self.placement = p;
+                } else {
+                    self.placement.extend(p);
+                }
+            }
+            ParsedSection::Definition(d) => {
+                self.order
+                    .push(SectionRef::Definition(self.definitions.len()));
+                self.definitions.push(d);
+            }
+            ParsedSection::Other(s) => {
+                self.order
+                    .push(SectionRef::Other(self.other_sections.len()));
+                self.other_sections.push(s);
+            }
+        }
+    }
+
+    /// Model with these sections, library definitions only end up in library files.
+    pub(crate) fn finish(self, mut header: Header<'a>) -> Idf30<'a> {
+        if matches!(header.ty, FileType::LibraryFile { .. }) {
+            header.ty = FileType::LibraryFile {
+                components: self.definitions,
+            };
+        }
+        Idf30 {
+            header,
+            placement: self.placement,
+            other_sections: self.other_sections,
+            section_order: self.order,
+            warnings: vec![],
+        }
+    }
+}
+
+/// Parses the text of one section, from its name to its end line.
+pub(crate) fn parse_section_text(text: &str) -> Result<ParsedSection<'_>, Error> {
+    let mut pairs = Idf30Parser::parse(Rule::section, text).map_err(syntax_error)?;
+    parse_section(pairs.next().ok_or(Error::GrammarExpectedPair)?)
+}
+
+/// Parses the text of the header section, including its end line.
+pub(crate) fn parse_header_text(text: &str) -> Result<Header<'_>, Error> {
+    let mut pairs = Idf30Parser::parse(Rule::header_section, text).map_err(syntax_error)?;
+    parse_header(&mut pairs)
+}
+
+fn parse_section(section: Pair<Rule>) -> Result<ParsedSection, Error> {
+    let start = section.as_span().start_pos();
+    let mut section = section.into_inner();
+    let mut section_header = next_inner!(section);
+    let section_name = next_str!(next_inner!(section_header));
+    if section_name == "ELECTRICAL" {
+        return Ok(ParsedSection::Definition(
+            parse_component_definition(&mut section)
+                .map_err(|e| e.at(section_name, None, start.line_col()))?,
+        ));
+    }
+    let args = section_header
+        .map(|arg| Either::Left(arg.as_str()))
+        .collect();
+    parse_records(section_name, args, &mut section, 0)
+}
+
+/// Parses the header line of a section, including its line break, into a section without
+/// records.
+pub(crate) fn parse_section_header_text(text: &str) -> Result<IdfSection<'_>, Error> {
+    let mut pairs = Idf30Parser::parse(Rule::section_header, text).map_err(syntax_error)?;
+    let mut section_header = next_inner!(pairs);
+    let section_name = next_str!(next_inner!(section_header));
+    let args = section_header
+        .map(|arg| Either::Left(arg.as_str()))
+        .collect();
+    Ok(IdfSection::new(Either::Left(section_name), args, vec![]))
+}
+
+/// Parses whole records of a section other than ELECTRICAL, e.g. a part of a large section.
+/// Records are numbered from `first` in errors.
+pub(crate) fn parse_records_text<'a>(
+    section_name: &'a str,
+    args: Vec<Either<&'a str, String>>,
+    text: &'a str,
+    first: usize,
+) -> Result<ParsedSection<'a>, Error> {
+    let mut pairs = Idf30Parser::parse(Rule::records, text).map_err(syntax_error)?;
+    parse_records(section_name, args, &mut pairs, first)
+}
+
+/// Records up to the end of a section or of the input, numbered from `first` in errors.
+fn parse_records<'a>(
+    section_name: &'a str,
+    args: Vec<Either<&'a str, String>>,
+    section: &mut Pairs<'a, Rule>,
+    first: usize,
+) -> Result<ParsedSection<'a>, Error> {
+    let is_end = |record: &Pair<Rule>| matches!(record.as_rule(), Rule::section_name | Rule::EOI);
+    if section_name == "PLACEMENT" {
+        let mut placement = vec![];
+        while let Some(record) = section.next() {
+            if is_end(&record) {
+                break;
+            }
+            let position = record.as_span().start_pos();
+            let index = first + placement.len();
+            let record = record.into_inner();
+            let component = parse_component_placement(section, record)
+                .map_err(|e| e.at(section_name, Some(index), position.line_col()))?;
+            placement.push(component);
+        }
+        return Ok(ParsedSection::Placement(placement));
+    }
+    let mut records = vec![];
+    for record in section {
+        if is_end(&record) {
+            break;
+        }
+        let position = record.as_span().start_pos();
+        let index = first + records.len();
+        let record = record.into_inner();
+        let values: Result<Vec<IdfValue>, Error> = record
+            .into_iter()
+            .map(|p| match p.as_rule() {
+                Rule::integer => Ok(IdfValue::Integer(p.as_str().parse()?)),
+                Rule::float => Ok(IdfValue::Float(p.as_str().parse()?)),
+                Rule::string => Ok(IdfValue::String(Either::Left(p.as_str()))),
+                Rule::quoted_string => Ok(IdfValue::String(unquote(p.as_str()))),
+                _ => Err(Error::GrammarExpectedRule(format!("{:?}", Rule::value))),
+            })
+            .collect();
+        records.push(values.map_err(|e| e.at(section_name, Some(index), position.line_col()))?);
+    }
+    Ok(ParsedSection::Other(IdfSection::new(
+        Either::Left(section_name),
+        args,
+        records,
+    )))
+}
+
 fn parse_header<'a>(pairs: &mut Pairs<'a, Rule>) -> Result<Header<'a>, Error> {
+    let start = pairs
+        .peek()
+        .map_or((1, 1), |p| p.as_span().start_pos().line_col());
+    parse_header_records(pairs).map_err(|e| match e {
+        Error::MissingHeader => e,
+        e => e.at("HEADER", None, start),
+    })
+}
+
+fn parse_header_records<'a>(pairs: &mut Pairs<'a, Rule>) -> Result<Header<'a>, Error> {
     let mut header_section = next_inner!(pairs);
     if next_str!(next_inner!(next_inner!(header_section))) != "HEADER" {
         return Err(Error::MissingHeader);
@@ -505,14 +1396,8 @@ fn parse_header<'a>(pairs: &mut Pairs<'a, Rule>) -> Result<Header<'a>, Error> {
     let ty = match next_str!(header_record0) {
         t @ "BOARD_FILE" | t @ "PANEL_FILE" => {
             let mut header_record1 = next_inner!(header_section);
-            let board_name = Either::Left(next_str!(header_record1));
-            let units = match next_str!(header_record1) {
-                "MM" => Unit::SImm,
-                "THOU" => Unit::Mils,
-                _ => {
-                    return Err(Error::WrongUnit);
-                }
-            };
+            let board_name = next_string!(header_record1);
+            let units = next_str!(header_record1).parse()?;
             if t == "BOARD_FILE" {
                 FileType::BoardFile { board_name, units }
             } else {
@@ -525,8 +1410,8 @@ fn parse_header<'a>(pairs: &mut Pairs<'a, Rule>) -> Result<Header<'a>, Error> {
     if next_str!(header_record0) != "3.0" {
         return Err(Error::UnsupportedVersion);
     }
-    let source = Either::Left(next_str!(header_record0));
-    let date = Either::Left(next_str!(header_record0));
+    let source = next_string!(header_record0);
+    let date = next_string!(header_record0);
     let board_file_version = next_str!(header_record0).parse()?;
     let header = Header {
         ty,
@@ -537,49 +1422,45 @@ fn parse_header<'a>(pairs: &mut Pairs<'a, Rule>) -> Result<Header<'a>, Error> {
     Ok(header)
 }
 
+/// Loop index and point of an outline record.
+fn parse_point(coords: &mut Pairs<Rule>) -> Result<(u32, Point), Error> {
+    let index = next_int!(coords);
+    let x = next_float!(coords);
+    let y = next_float!(coords);
+    let angle = next_float!(coords);
+    Ok((index, Point { x, y, angle }))
+}
+
 fn parse_component_definition<'a>(
     section: &mut Pairs<'a, Rule>,
 ) -> Result<ComponentDefinition<'a>, Error> {
-    // println!("cmp def: {section:?}");
     let mut record2 = next_inner!(section);
-    let geometry_name = Either::Left(next_str!(record2));
-    let part_number = Either::Left(next_str!(record2));
-    let units = match next_str!(record2) {
-        "MM" => Unit::SImm,
-        "THOU" => Unit::Mils,
-        _ => {
-            return Err(Error::WrongUnit);
-        }
-    };
+    let geometry_name = next_string!(record2);
+    let part_number = next_string!(record2);
+    let units = next_str!(record2).parse()?;
     let height = next_float!(record2);
-    let mut points = vec![];
-    while let Some(coords) = section.next() {
-        // println!("{coords:?}");
+    let mut loops = vec![];
+    for (i, coords) in section.by_ref().enumerate() {
         if coords.as_rule() == Rule::section_name {
             break;
         }
+        let position = coords.as_span().start_pos();
         let mut coords = coords.into_inner();
         if let Some(p) = coords.peek() {
             if p.as_str() == "PROP" {
                 continue;
             }
         }
-        let label: u32 = next_int!(coords);
-        let label = if label == 0 {
-            LoopLabel::CounterClockwise
-        } else {
-            LoopLabel::Clockwise
-        };
-        let x = next_float!(coords);
-        let y = next_float!(coords);
-        let angle = next_float!(coords);
-        points.push(Point { label, x, y, angle });
+        // The first record of the section is the one above
+        let (index, point) = parse_point(&mut coords)
+            .map_err(|e| e.at("ELECTRICAL", Some(i + 1), position.line_col()))?;
+        push_loop_point(&mut loops, index, point);
     }
     Ok(ComponentDefinition {
         geometry_name,
         part_number,
         units,
         height,
-        points,
+        loops,
     })
 }
@@ -0,0 +1,156 @@
+//! Re-parsing only the sections touched by a text edit, for editors keeping a model of the
+//! file open.
+
+use crate::idf30::{Error, FileType, Idf30, ParsedSection, SectionRef};
+use crate::lazy::{parse_section_at, section_spans, SectionSpan};
+use std::ops::Range;
+
+/// Replacement of the bytes in `range` of the previous text by `new_len` bytes.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub new_len: usize,
+}
+
+/// What `Idf30::apply_edit` parsed again.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Reparse {
+    /// Only these sections
+    Sections(Vec<SectionRef>),
+    /// The whole file, as the edit changed the header or the section structure
+    Full,
+}
+
+impl Idf30<'static> {
+    /// Updates the model parsed from `previous` after `edit` turned it into `text`. Sections
+    /// overlapping the edit are parsed again and spliced in, the others are kept. Edits to the
+    /// header, between sections or adding and removing sections parse the whole file.
+    ///
+    /// On error the model is left unchanged. Edits whose range is not inside `previous` are an
+    /// error.
+    pub fn apply_edit(
+        &mut self,
+        previous: &str,
+        text: &str,
+        edit: &TextEdit,
+    ) -> Result<Reparse, Error> {
+        if edit.range.start > edit.range.end || edit.range.end > previous.len() {
+            return Err(Error::Malformed("Edit outside the previous text"));
+        }
+        match self.splice_edit(previous, text, edit) {
+            Some(reparse) => reparse,
+            None => {
+                *self = Idf30::parse(text)?.into_owned();
+                Ok(Reparse::Full)
+            }
+        }
+    }
+
+    /// Incremental part of `apply_edit`, `None` if the whole file needs to be parsed.
+    fn splice_edit(
+        &mut self,
+        previous: &str,
+        text: &str,
+        edit: &TextEdit,
+    ) -> Option<Result<Reparse, Error>> {
+        if previous.len() - edit.range.len() + edit.new_len != text.len() {
+            return None;
+        }
+        let old = section_spans(previous).ok()?;
+        let new = section_spans(text).ok()?;
+        if old.len() != new.len() || old.iter().zip(&new).any(|(a, b)| a.0 != b.0) {
+            return None;
+        }
+        let affected: Vec<usize> = (0..old.len())
+            .filter(|&i| old[i].1.start <= edit.range.end && edit.range.start < old[i].1.end)
+            .collect();
+        if affected.is_empty() || affected[0] == 0 {
+            return None;
+        }
+        // Edited text has to be inside the affected sections
+        let covered = old[affected[0]].1.start..old[*affected.last()?].1.end;
+        if edit.range.start < covered.start || edit.range.end > covered.end {
+            return None;
+        }
+        let refs = self.section_refs(&old[1..])?;
+        let placement_sections = old.iter().filter(|s| s.0 == "PLACEMENT").count();
+
+        let mut parsed = vec![];
+        for &i in &affected {
+            let r = refs[i - 1];
+            if r == SectionRef::Placement && placement_sections > 1 {
+                return None;
+            }
+            let (name, range, line) = &new[i];
+            match parse_section_at(&text[range.clone()], name, *line) {
+                Ok(section) => parsed.push((r, section)),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        // Check all kinds before changing anything
+        if !parsed.iter().all(|(r, section)| {
+            matches!(
+                (r, section),
+                (SectionRef::Other(_), ParsedSection::Other(_))
+                    | (SectionRef::Placement, ParsedSection::Placement(_))
+                    | (SectionRef::Definition(_), ParsedSection::Definition(_))
+            )
+        }) {
+            return None;
+        }
+        let mut reparsed = vec![];
+        for (r, section) in parsed {
+            match (r, section) {
+                (SectionRef::Other(k), ParsedSection::Other(s)) => {
+                    self.other_sections[k] = s.to_owned_section();
+                }
+                (SectionRef::Placement, ParsedSection::Placement(p)) => {
+                    self.placement = p.iter().map(|c| c.to_owned_placement()).collect();
+                }
+                (SectionRef::Definition(k), ParsedSection::Definition(d)) => {
+                    if let FileType::LibraryFile { components } = &mut self.header.ty {
+                        components[k] = d.to_owned_definition();
+                    }
+                }
+                _ => unreachable!("section kinds checked above"),
+            }
+            reparsed.push(r);
+        }
+        Some(Ok(Reparse::Sections(reparsed)))
+    }
+
+    /// Section of the model for every span after the header, `None` if the model does not
+    /// match the spans, e.g. because it was changed after parsing.
+    fn section_refs(&self, spans: &[SectionSpan]) -> Option<Vec<SectionRef>> {
+        let definitions = match &self.header.ty {
+            FileType::LibraryFile { components } => components.len(),
+            _ => 0,
+        };
+        let (mut other, mut definition) = (0, 0);
+        let mut refs = vec![];
+        for (name, _, _) in spans {
+            refs.push(match *name {
+                "PLACEMENT" => SectionRef::Placement,
+                "ELECTRICAL" => {
+                    definition += 1;
+                    SectionRef::Definition(definition - 1)
+                }
+                _ => {
+                    other += 1;
+                    SectionRef::Other(other - 1)
+                }
+            });
+        }
+        // Placement sections are merged, recorded once
+        let mut order: Vec<SectionRef> = vec![];
+        for r in &refs {
+            if !order.contains(r) {
+                order.push(*r);
+            }
+        }
+        let matches = order == self.section_order
+            && other == self.other_sections.len()
+            && definition == definitions;
+        matches.then_some(refs)
+    }
+}
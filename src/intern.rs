@@ -0,0 +1,54 @@
+//! Shared storage for the strings of owned models. Boards repeat the same package names, part
+//! numbers and section keywords thousands of times, a `StringPool` keeps one copy of each.
+
+use crate::idf30::Idf30;
+use either::Either;
+use std::collections::HashSet;
+
+/// Set of unique strings that models can borrow from.
+///
+/// Filling and borrowing are separate steps: add every model that should share strings with
+/// `insert`, then create the sharing copies with `share`. The copies borrow the pool instead
+/// of the parsed text, which can be dropped.
+#[derive(Clone, Debug, Default)]
+pub struct StringPool {
+    strings: HashSet<Box<str>>,
+}
+
+impl StringPool {
+    pub fn new() -> Self {
+        StringPool::default()
+    }
+
+    /// Adds all strings of `idf` to the pool.
+    pub fn insert(&mut self, idf: &Idf30) {
+        idf.for_each_string(&mut |s: &str| {
+            if !self.strings.contains(s) {
+                self.strings.insert(s.into());
+            }
+        });
+    }
+
+    /// Copy of `idf` that borrows its strings from the pool. Strings that were never inserted
+    /// are copied.
+    pub fn share<'p>(&'p self, idf: &Idf30) -> Idf30<'p> {
+        idf.map_strings(&mut |s: &str| match self.strings.get(s) {
+            Some(shared) => Either::Left(&**shared),
+            None => Either::Right(s.to_string()),
+        })
+    }
+
+    /// Number of unique strings.
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    /// Bytes of string data held by the pool.
+    pub fn size(&self) -> usize {
+        self.strings.iter().map(|s| s.len()).sum()
+    }
+}
@@ -0,0 +1,288 @@
+//! Parsing that only locates sections up front and parses their contents on first access, for
+//! tools that need a few sections of a large file.
+
+use crate::idf30::{
+    field_len, parse_header_text, parse_records_text, parse_section_header_text,
+    parse_section_text, ComponentPlacement, Error, Header, Idf30, IdfSection, ParsedSection,
+    SectionCollector,
+};
+use crate::limits::{check_limits, ParseLimits};
+use std::cell::OnceCell;
+use std::ops::Range;
+
+/// Board, panel or library file with sections parsed lazily, see `Idf30::parse_lazy`.
+pub struct LazyIdf30<'a> {
+    pub header: Header<'a>,
+    sections: Vec<LazySection<'a>>,
+}
+
+struct LazySection<'a> {
+    name: &'a str,
+    /// From the section name to the end of its end line
+    text: &'a str,
+    /// Line the section starts at
+    line: usize,
+    parsed: OnceCell<ParsedSection<'a>>,
+}
+
+impl<'a> LazySection<'a> {
+    fn get(&self) -> Result<&ParsedSection<'a>, Error> {
+        if let Some(parsed) = self.parsed.get() {
+            return Ok(parsed);
+        }
+        let parsed = self.parse()?;
+        Ok(self.parsed.get_or_init(|| parsed))
+    }
+
+    fn parse(&self) -> Result<ParsedSection<'a>, Error> {
+        parse_section_at(self.text, self.name, self.line)
+    }
+
+    /// Same as `parse`, parsing the records about `chunk` bytes at a time and calling
+    /// `on_chunk` with the text parsed so far after each part. Placement records are kept in
+    /// pairs.
+    fn parse_chunked(
+        &self,
+        chunk: usize,
+        mut on_chunk: impl FnMut(&'a str),
+    ) -> Result<ParsedSection<'a>, Error> {
+        let text = self.text;
+        let header_end = text.find('\n').map_or(text.len(), |i| i + 1);
+        let body_end = text.trim_end().rfind('\n').map_or(0, |i| i + 1);
+        if body_end < header_end {
+            return self.parse();
+        }
+        let locate = |e: Error, line: usize| match e {
+            Error::At { .. } | Error::Syntax(_) => e.offset_lines(line - 1),
+            e => e.at(self.name, None, (line, 1)),
+        };
+        let name = self.name;
+        let mut section =
+            parse_section_header_text(&text[..header_end]).map_err(|e| locate(e, self.line))?;
+        // An end line the grammar rejects is reported as when parsing at once
+        let end = text[body_end..]
+            .trim()
+            .strip_prefix('.')
+            .unwrap_or_default();
+        let bare = |c: char| c.is_ascii_alphanumeric() || "_./:?-".contains(c);
+        if !end.starts_with(|c: char| c.is_ascii_alphabetic()) || !end.chars().all(bare) {
+            return self.parse();
+        }
+
+        let mut placement = vec![];
+        let mut records = vec![];
+        let (mut start, mut offset) = (header_end, header_end);
+        let (mut line, mut start_line) = (self.line + 1, self.line + 1);
+        let mut record_lines = 0;
+        let mut parse = |start: usize, end: usize, start_line: usize| -> Result<(), Error> {
+            let first = placement.len() + records.len();
+            match parse_records_text(name, vec![], &text[start..end], first)
+                .map_err(|e| locate(e, start_line))?
+            {
+                ParsedSection::Placement(p) => placement.extend(p),
+                ParsedSection::Other(s) => records.extend(s.records().iter().cloned()),
+                ParsedSection::Definition(_) => unreachable!("ELECTRICAL is parsed at once"),
+            }
+            on_chunk(&text[..end]);
+            Ok(())
+        };
+        for l in text[header_end..body_end].split_inclusive('\n') {
+            offset += l.len();
+            line += 1;
+            let trimmed = l.trim();
+            if !trimmed.is_empty() && !trimmed.starts_with('#') {
+                record_lines += 1;
+            }
+            let paired = name != "PLACEMENT" || record_lines % 2 == 0;
+            if offset - start >= chunk && paired {
+                parse(start, offset, start_line)?;
+                (start, start_line) = (offset, line);
+            }
+        }
+        if start < body_end {
+            parse(start, body_end, start_line)?;
+        }
+        if name == "PLACEMENT" {
+            return Ok(ParsedSection::Placement(placement));
+        }
+        *section.records_mut() = records;
+        Ok(ParsedSection::Other(section))
+    }
+}
+
+/// Parses the text of a section starting at `line`, with error locations relative to the whole
+/// file.
+pub(crate) fn parse_section_at<'a>(
+    text: &'a str,
+    name: &str,
+    line: usize,
+) -> Result<ParsedSection<'a>, Error> {
+    parse_section_text(text).map_err(|e| match e {
+        Error::At { .. } | Error::Syntax(_) => e.offset_lines(line - 1),
+        e => e.at(name, None, (line, 1)),
+    })
+}
+
+/// Name of a section, the byte range from its name to the end of its end line and the line it
+/// starts at.
+pub(crate) type SectionSpan<'a> = (&'a str, Range<usize>, usize);
+
+/// Sections of `file` including the header, found by their start and end lines alone.
+pub(crate) fn section_spans(file: &str) -> Result<Vec<SectionSpan<'_>>, Error> {
+    let mut spans = vec![];
+    let mut offset = 0;
+    // Start offset, line and name of the section being scanned
+    let mut open: Option<(usize, usize, &str)> = None;
+    for (n, line) in file.split_inclusive('\n').enumerate() {
+        let trimmed = line.trim_start();
+        if let Some(keyword) = trimmed.strip_prefix('.') {
+            let at = offset + line.len() - trimmed.len();
+            match open.take() {
+                None => {
+                    let name = keyword.split_whitespace().next().unwrap_or_default();
+                    open = Some((at, n + 1, name));
+                }
+                Some((start, line_number, name)) => {
+                    spans.push((name, start..offset + line.len(), line_number))
+                }
+            }
+        }
+        offset += line.len();
+    }
+    if open.is_some() {
+        return Err(Error::Malformed("Section without end"));
+    }
+    if spans.first().map(|(name, _, _)| *name) != Some("HEADER") {
+        return Err(Error::MissingHeader);
+    }
+    Ok(spans)
+}
+
+/// Fields of a record line, see `LazyIdf30::raw_records`. Quoted strings are yielded with
+/// their quotes, as one field even if they contain whitespace.
+#[derive(Clone, Debug)]
+pub struct RawFields<'a>(pub(crate) &'a str);
+
+impl<'a> Iterator for RawFields<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let rest = self.0.trim_start();
+        if rest.is_empty() {
+            return None;
+        }
+        let (field, rest) = rest.split_at(field_len(rest));
+        self.0 = rest;
+        Some(field)
+    }
+}
+
+impl<'a> Idf30<'a> {
+    /// Parses the header and finds the other sections without parsing their records. Errors in
+    /// a section are reported when it is first accessed.
+    pub fn parse_lazy(file: &str) -> Result<LazyIdf30<'_>, Error> {
+        let mut sections: Vec<LazySection> = section_spans(file)?
+            .into_iter()
+            .map(|(name, range, line)| LazySection {
+                name,
+                text: &file[range],
+                line,
+                parsed: OnceCell::new(),
+            })
+            .collect();
+        let header = sections.remove(0);
+        let header = parse_header_text(header.text).map_err(|e| e.offset_lines(header.line - 1))?;
+        Ok(LazyIdf30 { header, sections })
+    }
+}
+
+impl<'a> Idf30<'a> {
+    /// Same as `parse_lazy`, failing with `Error::LimitExceeded` if the file exceeds `limits`.
+    /// Records are counted and measured by line, as they are not parsed yet.
+    pub fn parse_lazy_with_limits(file: &str, limits: ParseLimits) -> Result<LazyIdf30<'_>, Error> {
+        check_limits(file, limits)?;
+        Idf30::parse_lazy(file)
+    }
+}
+
+impl<'a> LazyIdf30<'a> {
+    /// Names of the sections after the header, in file order, without parsing them.
+    pub fn section_names(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.sections.iter().map(|s| s.name)
+    }
+
+    /// Whether the first section with this name has been parsed already.
+    pub fn is_parsed(&self, name: &str) -> bool {
+        self.sections
+            .iter()
+            .find(|s| s.name == name)
+            .is_some_and(|s| s.parsed.get().is_some())
+    }
+
+    /// First section with the given name, parsing it if needed. PLACEMENT and ELECTRICAL are
+    /// not plain sections, see `placement` and `into_idf30`.
+    pub fn section(&self, name: &str) -> Result<Option<&IdfSection<'a>>, Error> {
+        for s in self.sections.iter().filter(|s| s.name == name) {
+            if let ParsedSection::Other(section) = s.get()? {
+                return Ok(Some(section));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Records of the first section with the given name as written, without parsing them.
+    /// Blank lines and `#` comments are skipped. For streaming conversions that only need the
+    /// field text, e.g. holes to a drill file; nothing is allocated.
+    ///
+    /// Only lazily parsed files have this, an `IdfSection` keeps the parsed values but not the
+    /// text they were parsed from.
+    pub fn raw_records(&self, name: &str) -> Option<impl Iterator<Item = RawFields<'a>> + 'a> {
+        let text = self.sections.iter().find(|s| s.name == name)?.text;
+        Some(
+            text.lines()
+                .skip(1)
+                .map(str::trim_start)
+                .take_while(|line| !line.starts_with('.'))
+                .filter(|line| !line.trim_end().is_empty() && !line.starts_with('#'))
+                .map(RawFields),
+        )
+    }
+
+    /// Placements of the PLACEMENT section, empty if there is none.
+    pub fn placement(&self) -> Result<&[ComponentPlacement<'a>], Error> {
+        for s in self.sections.iter().filter(|s| s.name == "PLACEMENT") {
+            if let ParsedSection::Placement(placement) = s.get()? {
+                return Ok(placement);
+            }
+        }
+        Ok(&[])
+    }
+
+    /// Parses the remaining sections into a complete model.
+    pub fn into_idf30(self) -> Result<Idf30<'a>, Error> {
+        self.materialize(usize::MAX, |_, _| {})
+    }
+
+    /// Like `into_idf30`, calling `on_section` with the name of every section and its text
+    /// parsed so far. Sections other than ELECTRICAL larger than `chunk` bytes are parsed a
+    /// part at a time, with a call after every part.
+    pub(crate) fn materialize(
+        self,
+        chunk: usize,
+        mut on_section: impl FnMut(&'a str, &'a str),
+    ) -> Result<Idf30<'a>, Error> {
+        let mut sections = SectionCollector::default();
+        for mut s in self.sections {
+            let parsed = match s.parsed.take() {
+                Some(parsed) => parsed,
+                None if s.name != "ELECTRICAL" && s.text.len() > chunk => {
+                    s.parse_chunked(chunk, |text| on_section(s.name, text))?
+                }
+                None => s.parse()?,
+            };
+            on_section(s.name, s.text);
+            sections.push(parsed);
+        }
+        Ok(sections.finish(self.header))
+    }
+}
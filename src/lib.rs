@@ -1 +1,52 @@
+pub mod approx;
+pub mod assembly;
+pub mod bom;
+pub mod cache;
+pub mod canonical;
+pub mod checks;
+pub mod classify;
+pub mod date;
+pub mod designators;
+pub mod dfm;
+pub mod dialect;
+pub mod diff;
+pub mod eco;
+pub mod editor;
+pub mod encoding;
+#[cfg(feature = "fast-parser")]
+pub mod fast;
+pub mod footprints;
+pub mod formats;
+pub mod geometry;
+pub mod handlers;
+pub mod holes;
 pub mod idf30;
+pub mod incremental;
+pub mod intern;
+pub mod lazy;
+pub mod library;
+pub mod limits;
+pub mod load;
+pub mod merge;
+pub mod outline;
+pub mod owned;
+pub mod panel;
+pub mod placement;
+pub mod progress;
+pub mod project;
+pub mod reader;
+pub mod rename;
+pub mod save;
+pub mod schema;
+pub mod select;
+pub mod spatial;
+pub mod stats;
+pub mod testpoints;
+pub mod transform;
+pub mod validate;
+pub mod verify;
+pub mod warnings;
+pub mod write;
+
+pub use load::load_pair;
+pub use verify::verify_round_trip;
@@ -0,0 +1,86 @@
+//! Parsed library files kept across many board files, for batch conversions where every board
+//! refers to the same few libraries.
+
+use crate::canonical::fnv1a;
+use crate::idf30::{ComponentDefinition, Error, FileType, Idf30};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Owned library definitions keyed by the hash of the library text and by geometry name.
+/// Loading a library whose text was loaded before does not parse it again.
+#[derive(Clone, Debug, Default)]
+pub struct LibraryCache {
+    /// Libraries with the text they were parsed from, which tells texts with the same hash
+    /// apart
+    libraries: HashMap<u64, (Box<str>, Idf30<'static>)>,
+    /// Indices into the definitions of each library, by geometry name
+    geometries: HashMap<(u64, String), Vec<usize>>,
+}
+
+impl LibraryCache {
+    pub fn new() -> Self {
+        LibraryCache::default()
+    }
+
+    /// Parses a library file unless one with the same contents was loaded before. Returns the
+    /// key to look its definitions up with, the hash of the text or, if another text has the
+    /// same hash, the next free key after it.
+    pub fn load(&mut self, source: &str) -> Result<u64, Error> {
+        let mut hash = fnv1a(source.as_bytes());
+        while let Some((loaded, _)) = self.libraries.get(&hash) {
+            if **loaded == *source {
+                return Ok(hash);
+            }
+            hash = hash.wrapping_add(1);
+        }
+        let library = Idf30::parse(source)?.into_owned();
+        let FileType::LibraryFile { components } = &library.header.ty else {
+            return Err(Error::WrongFileType);
+        };
+        for (i, d) in components.iter().enumerate() {
+            self.geometries
+                .entry((hash, d.geometry_name.to_string()))
+                .or_default()
+                .push(i);
+        }
+        self.libraries.insert(hash, (source.into(), library));
+        Ok(hash)
+    }
+
+    pub fn load_file(&mut self, path: impl AsRef<Path>) -> Result<u64, Error> {
+        self.load(&std::fs::read_to_string(path)?)
+    }
+
+    /// Library loaded under `key`, e.g. to build an `IdfAssembly` from a clone of it.
+    pub fn library(&self, key: u64) -> Option<&Idf30<'static>> {
+        self.libraries.get(&key).map(|(_, library)| library)
+    }
+
+    /// Definition for a placement in the library loaded under `key`, matched like
+    /// `Idf30::definition`: by geometry name and part number, or by geometry name alone.
+    pub fn definition(
+        &self,
+        key: u64,
+        package_name: &str,
+        part_number: &str,
+    ) -> Option<&ComponentDefinition<'static>> {
+        let FileType::LibraryFile { components } = &self.libraries.get(&key)?.1.header.ty else {
+            return None;
+        };
+        let indices = self.geometries.get(&(key, package_name.to_string()))?;
+        indices
+            .iter()
+            .map(|&i| &components[i])
+            .find(|d| *d.part_number == *part_number)
+            .or_else(|| indices.first().map(|&i| &components[i]))
+    }
+
+    /// Number of distinct libraries loaded.
+    pub fn len(&self) -> usize {
+        self.libraries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.libraries.is_empty()
+    }
+}
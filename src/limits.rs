@@ -0,0 +1,80 @@
+//! Limits for parsing untrusted input, checked on the text before it is parsed so that every
+//! parser enforces them the same way and none of them builds more than the limits allow.
+
+use crate::idf30::{Error, Idf30, PestReader};
+use crate::lazy::RawFields;
+
+/// Upper bounds for `Idf30::parse_with_limits`, `Idf30::read_with_limits` and
+/// `Idf30::parse_lazy_with_limits`, the default imposes none.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ParseLimits {
+    /// Sections including the header
+    pub max_sections: usize,
+    /// Records of all sections together, a placement counts as two records. Comments and
+    /// blank lines are not records.
+    pub max_records: usize,
+    /// Length of a single string value in bytes
+    pub max_string_len: usize,
+    pub max_file_size: usize,
+}
+
+impl Default for ParseLimits {
+    fn default() -> Self {
+        ParseLimits {
+            max_sections: usize::MAX,
+            max_records: usize::MAX,
+            max_string_len: usize::MAX,
+            max_file_size: usize::MAX,
+        }
+    }
+}
+
+impl<'a> Idf30<'a> {
+    /// Same as `parse`, failing with `Error::LimitExceeded` before parsing if the input exceeds
+    /// `limits`. See `Idf30::read_with_limits` for other parsers.
+    pub fn parse_with_limits(file: &str, limits: ParseLimits) -> Result<Idf30<'_>, Error> {
+        Idf30::read_with_limits(file, &PestReader, limits)
+    }
+}
+
+/// Checks `file` against `limits` line by line, before any parser builds records from it. A
+/// line starting with a dot opens or ends a section, the other lines of a section are records
+/// unless blank or a `#` comment. Strings are measured by field, without their quotes.
+pub(crate) fn check_limits(file: &str, limits: ParseLimits) -> Result<(), Error> {
+    // Nothing to exceed, skip the scan
+    if limits == ParseLimits::default() {
+        return Ok(());
+    }
+    if file.len() > limits.max_file_size {
+        return Err(Error::LimitExceeded("file size"));
+    }
+    let (mut sections, mut records) = (0, 0);
+    let mut in_section = false;
+    for line in file.lines().map(str::trim) {
+        if line.starts_with('.') {
+            in_section = !in_section;
+            if in_section {
+                sections += 1;
+                if sections > limits.max_sections {
+                    return Err(Error::LimitExceeded("number of sections"));
+                }
+            }
+            continue;
+        }
+        if !in_section || line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        records += 1;
+        if records > limits.max_records {
+            return Err(Error::LimitExceeded("number of records"));
+        }
+        for field in RawFields(line) {
+            let quoted = field.len() > 1 && field.starts_with('"') && field.ends_with('"');
+            let len = if quoted { field.len() - 2 } else { field.len() };
+            if len > limits.max_string_len {
+                return Err(Error::LimitExceeded("string length"));
+            }
+        }
+    }
+    Ok(())
+}
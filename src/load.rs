@@ -0,0 +1,42 @@
+use crate::assembly::IdfAssembly;
+use crate::idf30::{Error, Idf30};
+use std::path::{Path, PathBuf};
+
+/// Board and library extensions used by ECAD tools.
+const EXTENSION_PAIRS: [(&str, &str); 3] = [("emn", "emp"), ("bdf", "ldf"), ("idb", "idl")];
+
+/// Path of the library file for a board file, by extension convention. Both lower and upper
+/// case extensions are tried.
+pub fn library_path(board_path: &Path) -> Result<PathBuf, Error> {
+    let extension = board_path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default();
+    let (_, library) = EXTENSION_PAIRS
+        .iter()
+        .find(|(board, _)| board.eq_ignore_ascii_case(extension))
+        .ok_or_else(|| Error::IncompletePair(board_path.to_path_buf()))?;
+    let candidates = if extension.chars().all(|c| c.is_ascii_uppercase()) {
+        [library.to_ascii_uppercase(), library.to_string()]
+    } else {
+        [library.to_string(), library.to_ascii_uppercase()]
+    };
+    candidates
+        .iter()
+        .map(|e| board_path.with_extension(e))
+        .find(|p| p.is_file())
+        .ok_or_else(|| Error::IncompletePair(board_path.with_extension(library)))
+}
+
+/// Reads a board or panel file together with its library file, e.g. `board.emn` and
+/// `board.emp`.
+pub fn load_pair(board_path: impl AsRef<Path>) -> Result<IdfAssembly<'static>, Error> {
+    let board_path = board_path.as_ref();
+    let library_path = library_path(board_path)?;
+    let board = std::fs::read_to_string(board_path)?;
+    let library = std::fs::read_to_string(library_path)?;
+    IdfAssembly::new(
+        Idf30::parse(&board)?.into_owned(),
+        Idf30::parse(&library)?.into_owned(),
+    )
+}
@@ -0,0 +1,204 @@
+//! Combining two boards into one, e.g. the halves of a rigid-flex board exported separately.
+
+use crate::designators::DuplicatePolicy;
+use crate::geometry::{polylines_overlap, section_loops, tessellate};
+use crate::idf30::{Error, FileType, Idf30, IdfSection, IdfValue, ReferenceDesignator};
+use crate::transform::LOOP_SECTIONS;
+use either::Either;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MergePolicy {
+    /// What to do with placements of the other board whose designator is already used
+    pub designators: DuplicatePolicy,
+    /// Fail with `Error::OverlappingSections` instead of reporting overlaps
+    pub reject_overlaps: bool,
+}
+
+impl Default for MergePolicy {
+    fn default() -> Self {
+        MergePolicy {
+            designators: DuplicatePolicy::AutoSuffix,
+            reject_overlaps: false,
+        }
+    }
+}
+
+/// Designator of the other board that was already used.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DesignatorCollision {
+    pub designator: String,
+    /// New name with `DuplicatePolicy::AutoSuffix`, `None` if the placement was left out
+    pub renamed: Option<String>,
+}
+
+/// Outline, keepout or region of the other board overlapping one of the same kind. Outlines
+/// that only touch do not overlap.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SectionOverlap {
+    pub section: String,
+    /// Indices into `other_sections` of the merged board
+    pub existing: usize,
+    pub added: usize,
+}
+
+/// What `Idf30::merge_with` found.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BoardMerge {
+    pub collisions: Vec<DesignatorCollision>,
+    pub overlaps: Vec<SectionOverlap>,
+    /// Designators used more than once within the other board itself, which are merged as
+    /// they are. Resolve them beforehand so that holes are associated unambiguously.
+    pub duplicates: Vec<String>,
+    /// Sections of the other board that were left out, as the board already has one
+    pub dropped: Vec<String>,
+}
+
+impl<'a> Idf30<'a> {
+    /// Adds the placements, drilled holes, notes, keepouts and other sections of `other` to this
+    /// board. Holes and notes are added to the existing sections, a board outline only if this
+    /// board has none. Holes of placements renamed or left out under `policy` follow them.
+    ///
+    /// Both boards have to be board files in the same units. On error the board is unchanged.
+    pub fn merge_with(
+        &mut self,
+        other: &Idf30<'a>,
+        policy: MergePolicy,
+    ) -> Result<BoardMerge, Error> {
+        match (&self.header.ty, &other.header.ty) {
+            (
+                FileType::BoardFile { units, .. },
+                FileType::BoardFile {
+                    units: other_units, ..
+                },
+            ) if units != other_units => return Err(Error::WrongUnit),
+            (FileType::BoardFile { .. }, FileType::BoardFile { .. }) => {}
+            _ => return Err(Error::WrongFileType),
+        }
+        let mut report = BoardMerge::default();
+
+        // New designator of every placement of `other` colliding with this board, `None` to
+        // leave it out. Holes follow the first placement of their designator.
+        let existing: HashSet<String> = self
+            .placement
+            .iter()
+            .filter(|c| matches!(c.designator, ReferenceDesignator::Any(_)))
+            .map(|c| c.designator.to_string())
+            .collect();
+        let mut taken: HashSet<String> = existing.clone();
+        taken.extend(other.placement.iter().map(|c| c.designator.to_string()));
+        let mut seen: HashSet<&str> = HashSet::new();
+        let mut renames: Vec<Option<Option<String>>> = vec![None; other.placement.len()];
+        let mut hole_renames: HashMap<String, Option<String>> = HashMap::new();
+        for (i, c) in other.placement.iter().enumerate() {
+            let ReferenceDesignator::Any(d) = &c.designator else {
+                continue;
+            };
+            if !seen.insert(d) && !report.duplicates.iter().any(|r| *r == **d) {
+                report.duplicates.push(d.to_string());
+            }
+            if !existing.contains(&**d) {
+                continue;
+            }
+            let renamed = match policy.designators {
+                DuplicatePolicy::Error => return Err(Error::DuplicateDesignator(d.to_string())),
+                DuplicatePolicy::KeepFirst => None,
+                DuplicatePolicy::AutoSuffix => {
+                    let name = (2..)
+                        .map(|n| format!("{d}_{n}"))
+                        .find(|name| !taken.contains(name))
+                        .expect("unbounded range");
+                    taken.insert(name.clone());
+                    Some(name)
+                }
+            };
+            report.collisions.push(DesignatorCollision {
+                designator: d.to_string(),
+                renamed: renamed.clone(),
+            });
+            hole_renames
+                .entry(d.to_string())
+                .or_insert_with(|| renamed.clone());
+            renames[i] = Some(renamed);
+        }
+
+        let mut sections = self.other_sections.clone();
+        for section in &other.other_sections {
+            let name = section.name();
+            match name {
+                "DRILLED_HOLES" | "NOTES" => {
+                    let mut records = section.records().to_vec();
+                    if name == "DRILLED_HOLES" {
+                        records.retain_mut(|r| rename_hole(r, &hole_renames));
+                    }
+                    match sections.iter_mut().find(|s| s.name() == name) {
+                        Some(existing) => existing.records_mut().extend(records),
+                        None => sections.push(IdfSection::new(
+                            Either::Right(name.to_string()),
+                            section.args().to_vec(),
+                            records,
+                        )),
+                    }
+                }
+                "BOARD_OUTLINE" if sections.iter().any(|s| s.name() == name) => {
+                    report.dropped.push(name.to_string());
+                }
+                _ => {
+                    if LOOP_SECTIONS.contains(&name) {
+                        let added: Vec<Vec<(f64, f64)>> = section_loops(section)
+                            .iter()
+                            .map(|l| tessellate(l))
+                            .collect();
+                        for (existing, s) in sections.iter().enumerate() {
+                            let loops: Vec<Vec<(f64, f64)>> =
+                                section_loops(s).iter().map(|l| tessellate(l)).collect();
+                            if s.name() == name
+                                && !added.is_empty()
+                                && !loops.is_empty()
+                                && polylines_overlap(&loops, &added)
+                            {
+                                if policy.reject_overlaps {
+                                    return Err(Error::OverlappingSections(name.to_string()));
+                                }
+                                report.overlaps.push(SectionOverlap {
+                                    section: name.to_string(),
+                                    existing,
+                                    added: sections.len(),
+                                });
+                            }
+                        }
+                    }
+                    sections.push(section.clone());
+                }
+            }
+        }
+
+        for (c, renamed) in other.placement.iter().zip(renames) {
+            let mut c = c.clone();
+            if let Some(renamed) = renamed {
+                match renamed {
+                    Some(name) => c.designator = ReferenceDesignator::Any(Either::Right(name)),
+                    None => continue,
+                }
+            }
+            self.placement.push(c);
+        }
+        self.other_sections = sections;
+        Ok(report)
+    }
+}
+
+/// Renames the component a hole record belongs to, `false` if the hole is to be left out.
+fn rename_hole(record: &mut [IdfValue], renames: &HashMap<String, Option<String>>) -> bool {
+    let Some(IdfValue::String(association)) = record.get(4) else {
+        return true;
+    };
+    match renames.get(&**association) {
+        None => true,
+        Some(None) => false,
+        Some(Some(name)) => {
+            record[4] = IdfValue::String(Either::Right(name.clone()));
+            true
+        }
+    }
+}
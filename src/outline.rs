@@ -0,0 +1,58 @@
+//! Typed board and panel outlines, with the thickness and point containment checks that
+//! placement and panelization tools need.
+
+use crate::geometry::number;
+use crate::idf30::{Error, Idf30, IdfSection, Loop, Owner};
+
+/// Typed view of a `.BOARD_OUTLINE` or `.PANEL_OUTLINE` section.
+#[derive(Clone, Debug)]
+pub struct BoardOutline {
+    pub owner: Option<Owner>,
+    pub thickness: f32,
+    /// Outer loop first, then the cutouts
+    pub loops: Vec<Loop>,
+}
+
+impl BoardOutline {
+    pub fn from_section(section: &IdfSection) -> Result<BoardOutline, Error> {
+        if !matches!(section.name(), "BOARD_OUTLINE" | "PANEL_OUTLINE") {
+            return Err(Error::Malformed("Expected BOARD_OUTLINE or PANEL_OUTLINE"));
+        }
+        let thickness = section
+            .records()
+            .first()
+            .filter(|r| r.len() == 1)
+            .map(|r| number(&r[0]) as f32)
+            .ok_or(Error::Malformed("Outline does not contain board thickness"))?;
+        Ok(BoardOutline {
+            owner: section.owner(),
+            thickness,
+            loops: section.loops(),
+        })
+    }
+
+    /// Whether the point lies on the board: inside the outer loop and outside of every cutout.
+    /// Arcs and circles are followed exactly.
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        let Some((outer, cutouts)) = self.loops.split_first() else {
+            return false;
+        };
+        outer.contains(x, y) && !cutouts.iter().any(|l| l.contains(x, y))
+    }
+}
+
+impl<'a> Idf30<'a> {
+    /// Outline of the board, or of the panel in panel files, `None` if there is none.
+    pub fn board_outline(&self) -> Result<Option<BoardOutline>, Error> {
+        self.section("BOARD_OUTLINE")
+            .or_else(|| self.section("PANEL_OUTLINE"))
+            .map(BoardOutline::from_section)
+            .transpose()
+    }
+
+    /// Board thickness from the first record of the outline, in board units, `None` if there
+    /// is no outline.
+    pub fn board_thickness(&self) -> Result<Option<f32>, Error> {
+        Ok(self.board_outline()?.map(|o| o.thickness))
+    }
+}
@@ -0,0 +1,194 @@
+//! Conversion of parsed data into values that no longer borrow the parsed text.
+
+use crate::idf30::{
+    ComponentDefinition, ComponentPlacement, FileType, Header, Idf30, IdfSection, IdfValue,
+    ReferenceDesignator,
+};
+use either::Either;
+
+fn owned(s: &str) -> Either<&'static str, String> {
+    Either::Right(s.to_string())
+}
+
+impl<'a> Idf30<'a> {
+    /// Copies all strings, so that the text the file was parsed from can be dropped.
+    pub fn into_owned(self) -> Idf30<'static> {
+        self.map_strings(&mut owned)
+    }
+
+    /// Copy of the model with every string replaced by `f`, e.g. by an owned or shared copy.
+    pub(crate) fn map_strings<'b>(
+        &self,
+        f: &mut impl FnMut(&str) -> Either<&'b str, String>,
+    ) -> Idf30<'b> {
+        Idf30 {
+            header: self.header.map_strings(f),
+            placement: self.placement.iter().map(|p| p.map_strings(f)).collect(),
+            other_sections: self
+                .other_sections
+                .iter()
+                .map(|s| s.map_strings(f))
+                .collect(),
+            section_order: self.section_order.clone(),
+            warnings: self.warnings.clone(),
+        }
+    }
+
+    /// Calls `f` with every string of the model, in the order `map_strings` replaces them.
+    pub(crate) fn for_each_string(&self, f: &mut impl FnMut(&str)) {
+        match &self.header.ty {
+            FileType::BoardFile { board_name, .. } | FileType::PanelFile { board_name, .. } => {
+                f(board_name)
+            }
+            FileType::LibraryFile { components } => {
+                for c in components {
+                    f(&c.geometry_name);
+                    f(&c.part_number);
+                }
+            }
+        }
+        f(&self.header.source);
+        f(&self.header.date);
+        for p in &self.placement {
+            f(&p.package_name);
+            f(&p.part_number);
+            if let ReferenceDesignator::Any(d) = &p.designator {
+                f(d);
+            }
+        }
+        for s in &self.other_sections {
+            f(s.name());
+            s.args().iter().for_each(|a| f(a));
+            for value in s.records().iter().flatten() {
+                if let IdfValue::String(v) = value {
+                    f(v);
+                }
+            }
+        }
+    }
+}
+
+impl<'a> Header<'a> {
+    pub fn into_owned(self) -> Header<'static> {
+        self.map_strings(&mut owned)
+    }
+
+    fn map_strings<'b>(&self, f: &mut impl FnMut(&str) -> Either<&'b str, String>) -> Header<'b> {
+        let ty = match &self.ty {
+            FileType::BoardFile { board_name, units } => FileType::BoardFile {
+                board_name: f(board_name),
+                units: units.clone(),
+            },
+            FileType::PanelFile { board_name, units } => FileType::PanelFile {
+                board_name: f(board_name),
+                units: units.clone(),
+            },
+            FileType::LibraryFile { components } => FileType::LibraryFile {
+                components: components.iter().map(|c| c.map_strings(f)).collect(),
+            },
+        };
+        Header {
+            ty,
+            source: f(&self.source),
+            date: f(&self.date),
+            board_file_version: self.board_file_version,
+        }
+    }
+}
+
+impl<'a> ComponentPlacement<'a> {
+    pub fn to_owned_placement(&self) -> ComponentPlacement<'static> {
+        self.map_strings(&mut owned)
+    }
+
+    fn map_strings<'b>(
+        &self,
+        f: &mut impl FnMut(&str) -> Either<&'b str, String>,
+    ) -> ComponentPlacement<'b> {
+        ComponentPlacement {
+            package_name: f(&self.package_name),
+            part_number: f(&self.part_number),
+            designator: self.designator.map_strings(f),
+            x: self.x,
+            y: self.y,
+            z: self.z,
+            rotation: self.rotation,
+            board_side: self.board_side.clone(),
+            placement_status: self.placement_status.clone(),
+        }
+    }
+}
+
+impl<'a> ReferenceDesignator<'a> {
+    pub fn to_owned_designator(&self) -> ReferenceDesignator<'static> {
+        self.map_strings(&mut owned)
+    }
+
+    fn map_strings<'b>(
+        &self,
+        f: &mut impl FnMut(&str) -> Either<&'b str, String>,
+    ) -> ReferenceDesignator<'b> {
+        match self {
+            ReferenceDesignator::Any(d) => ReferenceDesignator::Any(f(d)),
+            ReferenceDesignator::NoRefDes => ReferenceDesignator::NoRefDes,
+            ReferenceDesignator::Board => ReferenceDesignator::Board,
+        }
+    }
+}
+
+impl<'a> ComponentDefinition<'a> {
+    pub fn to_owned_definition(&self) -> ComponentDefinition<'static> {
+        self.map_strings(&mut owned)
+    }
+
+    fn map_strings<'b>(
+        &self,
+        f: &mut impl FnMut(&str) -> Either<&'b str, String>,
+    ) -> ComponentDefinition<'b> {
+        ComponentDefinition {
+            geometry_name: f(&self.geometry_name),
+            part_number: f(&self.part_number),
+            units: self.units.clone(),
+            height: self.height,
+            loops: self.loops.clone(),
+        }
+    }
+}
+
+impl<'a> IdfSection<'a> {
+    pub fn to_owned_section(&self) -> IdfSection<'static> {
+        self.map_strings(&mut owned)
+    }
+
+    fn map_strings<'b>(
+        &self,
+        f: &mut impl FnMut(&str) -> Either<&'b str, String>,
+    ) -> IdfSection<'b> {
+        let mut section = IdfSection::new(
+            f(self.name()),
+            self.args().iter().map(|a| f(a)).collect(),
+            self.records()
+                .iter()
+                .map(|r| r.iter().map(|v| v.map_strings(f)).collect())
+                .collect(),
+        );
+        if let Some(owner) = self.owner() {
+            section.set_owner(owner);
+        }
+        section
+    }
+}
+
+impl<'a> IdfValue<'a> {
+    pub fn to_owned_value(&self) -> IdfValue<'static> {
+        self.map_strings(&mut owned)
+    }
+
+    fn map_strings<'b>(&self, f: &mut impl FnMut(&str) -> Either<&'b str, String>) -> IdfValue<'b> {
+        match self {
+            IdfValue::Integer(x) => IdfValue::Integer(*x),
+            IdfValue::Float(x) => IdfValue::Float(*x),
+            IdfValue::String(s) => IdfValue::String(f(s)),
+        }
+    }
+}
@@ -0,0 +1,510 @@
+use crate::geometry::{edges_distance, polylines_contain, section_loops, tessellate, BoundingBox};
+use crate::holes::{DrilledHole, HoleType, Plating};
+use crate::idf30::{
+    BoardSide, ComponentPlacement, Error, FileType, Header, Idf30, IdfSection, IdfValue, Owner,
+    PlacementStatus, ReferenceDesignator,
+};
+use crate::transform::{normalize_angle, Transform};
+use either::Either;
+
+/// Array of identical boards to be laid out on a panel.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PanelSpec {
+    pub rows: u32,
+    pub cols: u32,
+    /// Gap between adjacent boards along X
+    pub spacing_x: f32,
+    /// Gap between adjacent boards along Y
+    pub spacing_y: f32,
+    /// Rotation of every board instance, counterclockwise in degrees
+    pub rotation: f32,
+    pub rails: Rails,
+    /// Copy the drilled holes of every board instance into the panel
+    pub merge_holes: bool,
+}
+
+/// Width of the panel border on each side of the board array.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Rails {
+    pub left: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub top: f32,
+}
+
+impl<'a> Idf30<'a> {
+    /// Generates a `PANEL_FILE` with a rectangular panel outline around `spec.rows` by
+    /// `spec.cols` instances of this board.
+    ///
+    /// Instances are placed row by row, starting from the bottom left corner, so that the
+    /// bounding boxes of the rotated board outlines are `spacing_x`/`spacing_y` apart.
+    pub fn panelize(&self, spec: &PanelSpec) -> Result<Idf30<'a>, Error> {
+        let (board_name, units) = match &self.header.ty {
+            FileType::BoardFile { board_name, units } => (board_name.clone(), units.clone()),
+            _ => return Err(Error::WrongFileType),
+        };
+        let outline = self
+            .section("BOARD_OUTLINE")
+            .ok_or(Error::MissingSection("BOARD_OUTLINE"))?;
+        let rotation = Transform::rotation(spec.rotation as f64);
+        let bbox = outline
+            .bounding_box_transformed(&rotation)
+            .ok_or(Error::Malformed("BOARD_OUTLINE does not contain any loop"))?;
+        let thickness = self.board_thickness()?.ok_or(Error::Malformed(
+            "BOARD_OUTLINE does not contain board thickness",
+        ))? as f64;
+
+        let rails = spec.rails;
+        let pitch_x = bbox.width() + spec.spacing_x as f64;
+        let pitch_y = bbox.height() + spec.spacing_y as f64;
+        let mut placement = vec![];
+        let mut holes = vec![];
+        for row in 0..spec.rows {
+            for col in 0..spec.cols {
+                let x = rails.left as f64 + col as f64 * pitch_x - bbox.min_x;
+                let y = rails.bottom as f64 + row as f64 * pitch_y - bbox.min_y;
+                placement.push(ComponentPlacement {
+                    package_name: board_name.clone(),
+                    part_number: Either::Left(""),
+                    designator: ReferenceDesignator::Board,
+                    x: x as f32,
+                    y: y as f32,
+                    z: 0.0,
+                    rotation: normalize_angle(spec.rotation as f64) as f32,
+                    board_side: BoardSide::Top,
+                    placement_status: PlacementStatus::Placed,
+                });
+                if spec.merge_holes {
+                    if let Some(board_holes) = self.section("DRILLED_HOLES") {
+                        let mut board_holes = board_holes.clone();
+                        board_holes.transform(&rotation.then(&Transform::translation(x, y)));
+                        holes.extend(board_holes.records().iter().cloned());
+                    }
+                }
+            }
+        }
+
+        let width =
+            (rails.left + rails.right) as f64 + spec.cols as f64 * pitch_x - spec.spacing_x as f64;
+        let height =
+            (rails.bottom + rails.top) as f64 + spec.rows as f64 * pitch_y - spec.spacing_y as f64;
+        let corner = |x: f64, y: f64| {
+            vec![
+                IdfValue::Integer(0),
+                IdfValue::Float(x),
+                IdfValue::Float(y),
+                IdfValue::Float(0.0),
+            ]
+        };
+        let mut other_sections = vec![IdfSection::new(
+            Either::Left("PANEL_OUTLINE"),
+            vec![Either::Left("UNOWNED")],
+            vec![
+                vec![IdfValue::Float(thickness)],
+                corner(0.0, 0.0),
+                corner(width, 0.0),
+                corner(width, height),
+                corner(0.0, height),
+                corner(0.0, 0.0),
+            ],
+        )];
+        if !holes.is_empty() {
+            other_sections.push(IdfSection::new(
+                Either::Left("DRILLED_HOLES"),
+                vec![],
+                holes,
+            ));
+        }
+
+        Ok(Idf30 {
+            header: Header {
+                ty: FileType::PanelFile {
+                    board_name: Either::Right(format!("{board_name}_panel")),
+                    units,
+                },
+                source: self.header.source.clone(),
+                date: self.header.date.clone(),
+                board_file_version: 1,
+            },
+            placement,
+            other_sections,
+            section_order: vec![],
+            warnings: vec![],
+        })
+    }
+}
+
+/// A board placed on a panel.
+#[derive(Clone, Debug)]
+pub struct BoardInstance<'a, 'b> {
+    pub placement: &'b ComponentPlacement<'a>,
+    /// Maps board coordinates into panel coordinates
+    pub transform: Transform,
+}
+
+impl<'a> Idf30<'a> {
+    /// Board instances of a `PANEL_FILE` whose package name matches the board name of `board`.
+    pub fn board_instances<'b>(
+        &'b self,
+        board: &Idf30,
+    ) -> Result<Vec<BoardInstance<'a, 'b>>, Error> {
+        if !matches!(self.header.ty, FileType::PanelFile { .. }) {
+            return Err(Error::WrongFileType);
+        }
+        let board_name = match &board.header.ty {
+            FileType::BoardFile { board_name, .. } => board_name,
+            _ => return Err(Error::WrongFileType),
+        };
+        Ok(self
+            .placement
+            .iter()
+            .filter(|p| *p.package_name == **board_name)
+            .map(|placement| BoardInstance {
+                placement,
+                transform: placement.placement_transform(),
+            })
+            .collect())
+    }
+
+    /// Standalone copy of `board` moved to where instance `index` sits on this panel.
+    ///
+    /// Only the sections of `board` are carried over, panel level sections such as the panel
+    /// outline or tooling holes are not.
+    pub fn flatten_instance<'b>(
+        &self,
+        board: &Idf30<'b>,
+        index: usize,
+    ) -> Result<Idf30<'b>, Error> {
+        let instance = self
+            .board_instances(board)?
+            .into_iter()
+            .nth(index)
+            .ok_or(Error::Malformed("No such board instance on the panel"))?;
+        let mut flat = board.clone();
+        flat.transform(&instance.transform);
+        Ok(flat)
+    }
+
+    /// Collapses a one-up panel back into its board: `board` moved to where its single
+    /// instance sits, see `flatten_instance`.
+    pub fn flatten<'b>(&self, board: &Idf30<'b>) -> Result<Idf30<'b>, Error> {
+        if self.board_instances(board)?.len() != 1 {
+            return Err(Error::Malformed("Panel does not hold exactly one board"));
+        }
+        self.flatten_instance(board, 0)
+    }
+
+    /// Wraps the board into a trivial `PANEL_FILE`: one instance at the origin, with the board
+    /// outline as panel outline. `flatten` turns it back into the board.
+    pub fn to_panel(&self) -> Result<Idf30<'a>, Error> {
+        let (board_name, units) = match &self.header.ty {
+            FileType::BoardFile { board_name, units } => (board_name.clone(), units.clone()),
+            _ => return Err(Error::WrongFileType),
+        };
+        let outline = self
+            .section("BOARD_OUTLINE")
+            .ok_or(Error::MissingSection("BOARD_OUTLINE"))?;
+        let mut panel_outline = IdfSection::new(
+            Either::Left("PANEL_OUTLINE"),
+            outline.args().to_vec(),
+            outline.records().to_vec(),
+        );
+        if let Some(owner) = outline.owner() {
+            panel_outline.set_owner(owner);
+        }
+        Ok(Idf30 {
+            header: Header {
+                ty: FileType::PanelFile {
+                    board_name: Either::Right(format!("{board_name}_panel")),
+                    units,
+                },
+                source: self.header.source.clone(),
+                date: self.header.date.clone(),
+                board_file_version: 1,
+            },
+            placement: vec![ComponentPlacement {
+                package_name: board_name,
+                part_number: Either::Left(""),
+                designator: ReferenceDesignator::Board,
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+                rotation: 0.0,
+                board_side: BoardSide::Top,
+                placement_status: PlacementStatus::Placed,
+            }],
+            other_sections: vec![panel_outline],
+            section_order: vec![],
+            warnings: vec![],
+        })
+    }
+}
+
+/// Distance below which outlines are considered touching rather than overlapping.
+const EPSILON: f64 = 1e-6;
+
+/// Problem found by `Idf30::panel_interference`. Instances are indices into the panel's
+/// placements, holes indices into its drilled holes.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PanelInterference {
+    /// Outlines of two board instances overlap, touching edges are allowed
+    BoardsOverlap { first: usize, second: usize },
+    /// Board instance reaches into the rails
+    OnRails { instance: usize },
+    /// Board instance is not inside the panel outline
+    OutsidePanel { instance: usize },
+    /// Tooling hole inside or crossing the outline of a board instance
+    ToolingHoleOnBoard { hole: usize, instance: usize },
+}
+
+impl<'a> Idf30<'a> {
+    /// Checks the board instances of a `PANEL_FILE` against each other, the panel outline and
+    /// `rails`, measured inward from the bounding box of the panel outline, and its tooling
+    /// holes against the board outlines. Instances of boards not in `boards` are skipped.
+    pub fn panel_interference(
+        &self,
+        boards: &[&Idf30],
+        rails: &Rails,
+    ) -> Result<Vec<PanelInterference>, Error> {
+        if !matches!(self.header.ty, FileType::PanelFile { .. }) {
+            return Err(Error::WrongFileType);
+        }
+        let panel = self
+            .section("PANEL_OUTLINE")
+            .ok_or(Error::MissingSection("PANEL_OUTLINE"))?;
+        let panel: Vec<Vec<(f64, f64)>> =
+            section_loops(panel).iter().map(|l| tessellate(l)).collect();
+        let bbox = BoundingBox::from_points(panel.iter().flatten().copied())
+            .ok_or(Error::Malformed("PANEL_OUTLINE does not contain any loop"))?;
+        let inner = BoundingBox {
+            min_x: bbox.min_x + rails.left as f64,
+            min_y: bbox.min_y + rails.bottom as f64,
+            max_x: bbox.max_x - rails.right as f64,
+            max_y: bbox.max_y - rails.top as f64,
+        };
+
+        // Outline of every known instance in panel coordinates
+        let mut instances = vec![];
+        for (index, placement) in self.placement.iter().enumerate() {
+            let board = boards.iter().find(|b| match &b.header.ty {
+                FileType::BoardFile { board_name, .. } => **board_name == *placement.package_name,
+                _ => false,
+            });
+            let Some(outline) = board.and_then(|b| b.section("BOARD_OUTLINE")) else {
+                continue;
+            };
+            let mut outline = outline.clone();
+            outline.transform(&placement.placement_transform());
+            let loops: Vec<Vec<(f64, f64)>> = section_loops(&outline)
+                .iter()
+                .map(|l| tessellate(l))
+                .collect();
+            if !loops.is_empty() {
+                instances.push((index, loops));
+            }
+        }
+
+        let mut found = vec![];
+        for (i, (first, a)) in instances.iter().enumerate() {
+            for (second, b) in &instances[i + 1..] {
+                if interiors_overlap(a, b) {
+                    found.push(PanelInterference::BoardsOverlap {
+                        first: *first,
+                        second: *second,
+                    });
+                }
+            }
+        }
+        for (instance, loops) in &instances {
+            let points = || loops.iter().flatten();
+            let on_panel = |p: &&(f64, f64)| {
+                polylines_contain(&panel, p.0, p.1) || edge_distance(&panel, **p) < EPSILON
+            };
+            if !points().all(|p| on_panel(&p)) || crosses(loops, &panel) {
+                found.push(PanelInterference::OutsidePanel {
+                    instance: *instance,
+                });
+            } else if points().any(|p| {
+                p.0 < inner.min_x - EPSILON
+                    || p.0 > inner.max_x + EPSILON
+                    || p.1 < inner.min_y - EPSILON
+                    || p.1 > inner.max_y + EPSILON
+            }) {
+                found.push(PanelInterference::OnRails {
+                    instance: *instance,
+                });
+            }
+        }
+        for (hole, h) in self.drilled_holes()?.iter().enumerate() {
+            if h.hole_type != HoleType::Tooling {
+                continue;
+            }
+            let (x, y, radius) = (h.x as f64, h.y as f64, h.diameter as f64 / 2.0);
+            for (instance, loops) in &instances {
+                if polylines_contain(loops, x, y) || edge_distance(loops, (x, y)) < radius {
+                    found.push(PanelInterference::ToolingHoleOnBoard {
+                        hole,
+                        instance: *instance,
+                    });
+                }
+            }
+        }
+        Ok(found)
+    }
+}
+
+fn edge_distance(polylines: &[Vec<(f64, f64)>], p: (f64, f64)) -> f64 {
+    edges_distance(polylines, &[vec![p]])
+}
+
+/// Whether the areas enclosed by `a` and `b` share more than their edges.
+fn interiors_overlap(a: &[Vec<(f64, f64)>], b: &[Vec<(f64, f64)>]) -> bool {
+    let strictly_inside = |outer: &[Vec<(f64, f64)>], p: (f64, f64)| {
+        polylines_contain(outer, p.0, p.1) && edge_distance(outer, p) > EPSILON
+    };
+    let centroid = |polylines: &[Vec<(f64, f64)>]| {
+        let points = &polylines[0];
+        let n = points.len() as f64;
+        let (x, y) = points
+            .iter()
+            .fold((0.0, 0.0), |s, p| (s.0 + p.0, s.1 + p.1));
+        (x / n, y / n)
+    };
+    a.iter().flatten().any(|p| strictly_inside(b, *p))
+        || b.iter().flatten().any(|p| strictly_inside(a, *p))
+        || strictly_inside(b, centroid(a))
+        || crosses(a, b)
+}
+
+/// Whether an edge of `a` properly crosses an edge of `b`, not just touching it.
+fn crosses(a: &[Vec<(f64, f64)>], b: &[Vec<(f64, f64)>]) -> bool {
+    let edges = |polylines: &[Vec<(f64, f64)>]| -> Vec<((f64, f64), (f64, f64))> {
+        polylines
+            .iter()
+            .flat_map(|l| l.iter().copied().zip(l.iter().copied().cycle().skip(1)))
+            .collect()
+    };
+    let cross = |o: (f64, f64), a: (f64, f64), b: (f64, f64)| {
+        (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+    };
+    let opposite =
+        |d1: f64, d2: f64| (d1 > EPSILON && d2 < -EPSILON) || (d1 < -EPSILON && d2 > EPSILON);
+    let b = edges(b);
+    edges(a).iter().any(|&(p, q)| {
+        b.iter().any(|&(r, s)| {
+            opposite(cross(r, s, p), cross(r, s, q)) && opposite(cross(p, q, r), cross(p, q, s))
+        })
+    })
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Corner {
+    BottomLeft,
+    BottomRight,
+    TopRight,
+    TopLeft,
+}
+
+/// Fiducials and tooling holes added by `Idf30::add_panel_features`, in panel units. Offsets
+/// are measured inward from the corners of the panel outline's bounding box.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PanelFeatures {
+    pub tooling_diameter: f32,
+    pub tooling_offset: (f32, f32),
+    pub tooling_corners: Vec<Corner>,
+    pub fiducial_package: String,
+    pub fiducial_part: String,
+    pub fiducial_offset: (f32, f32),
+    /// Corners with a top side fiducial, three by default so the panel cannot be loaded
+    /// rotated
+    pub fiducial_corners: Vec<Corner>,
+}
+
+/// 3 mm tooling holes 5 mm from every corner and 1 mm fiducials 10 mm from three corners.
+impl Default for PanelFeatures {
+    fn default() -> Self {
+        PanelFeatures {
+            tooling_diameter: 3.0,
+            tooling_offset: (5.0, 5.0),
+            tooling_corners: vec![
+                Corner::BottomLeft,
+                Corner::BottomRight,
+                Corner::TopRight,
+                Corner::TopLeft,
+            ],
+            fiducial_package: "FIDUCIAL_1MM".to_string(),
+            fiducial_part: String::new(),
+            fiducial_offset: (10.0, 5.0),
+            fiducial_corners: vec![Corner::BottomLeft, Corner::BottomRight, Corner::TopLeft],
+        }
+    }
+}
+
+impl<'a> Idf30<'a> {
+    /// Adds the tooling holes of `features` to `.DRILLED_HOLES` as non-plated PANEL tooling
+    /// holes, and its fiducials to the placement as NOREFDES components.
+    pub fn add_panel_features(&mut self, features: &PanelFeatures) -> Result<(), Error> {
+        if !matches!(self.header.ty, FileType::PanelFile { .. }) {
+            return Err(Error::WrongFileType);
+        }
+        let bbox = self
+            .section("PANEL_OUTLINE")
+            .ok_or(Error::MissingSection("PANEL_OUTLINE"))?
+            .bounding_box()
+            .ok_or(Error::Malformed("PANEL_OUTLINE does not contain any loop"))?;
+        let at = |corner: &Corner, (dx, dy): (f32, f32)| {
+            let (dx, dy) = (dx as f64, dy as f64);
+            let (x, y) = match corner {
+                Corner::BottomLeft => (bbox.min_x + dx, bbox.min_y + dy),
+                Corner::BottomRight => (bbox.max_x - dx, bbox.min_y + dy),
+                Corner::TopRight => (bbox.max_x - dx, bbox.max_y - dy),
+                Corner::TopLeft => (bbox.min_x + dx, bbox.max_y - dy),
+            };
+            (x as f32, y as f32)
+        };
+
+        let holes: Vec<Vec<IdfValue<'a>>> = features
+            .tooling_corners
+            .iter()
+            .map(|corner| {
+                let (x, y) = at(corner, features.tooling_offset);
+                DrilledHole {
+                    diameter: features.tooling_diameter,
+                    x,
+                    y,
+                    plating: Plating::NonPlated,
+                    association: ReferenceDesignator::Any(Either::Left("PANEL")),
+                    hole_type: HoleType::Tooling,
+                    owner: Owner::Unowned,
+                }
+                .to_record()
+            })
+            .collect();
+        match self
+            .other_sections
+            .iter_mut()
+            .find(|s| s.name() == "DRILLED_HOLES")
+        {
+            Some(section) => section.records_mut().extend(holes),
+            None if holes.is_empty() => {}
+            None => self.other_sections.push(IdfSection::new(
+                Either::Left("DRILLED_HOLES"),
+                vec![],
+                holes,
+            )),
+        }
+
+        for corner in &features.fiducial_corners {
+            let (x, y) = at(corner, features.fiducial_offset);
+            self.placement.push(
+                ComponentPlacement::new(
+                    features.fiducial_package.clone(),
+                    features.fiducial_part.clone(),
+                    "NOREFDES",
+                )
+                .placed_at(x, y, 0.0, BoardSide::Top),
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+use crate::idf30::{
+    BoardSide, ComponentPlacement, Idf30, Owner, PlacementStatus, ReferenceDesignator,
+};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// Result of `Idf30::set_status`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct StatusUpdate {
+    /// Number of selected placements that now have the new status
+    pub changed: usize,
+    /// Designators of selected placements locked by the other system, left unchanged
+    pub locked: Vec<String>,
+}
+
+/// Placement field to sort by, see `Idf30::sort_placements_by`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SortKey {
+    /// Natural reference designator order, R2 before R10
+    Designator,
+    /// Top side first
+    Side,
+    Package,
+    PartNumber,
+}
+
+impl SortKey {
+    fn compare(&self, a: &ComponentPlacement, b: &ComponentPlacement) -> Ordering {
+        match self {
+            SortKey::Designator => a.designator.cmp(&b.designator),
+            SortKey::Side => a.board_side.cmp(&b.board_side),
+            SortKey::Package => (*a.package_name).cmp(&*b.package_name),
+            SortKey::PartNumber => (*a.part_number).cmp(&*b.part_number),
+        }
+    }
+}
+
+impl<'a> Idf30<'a> {
+    /// Maps reference designators to their index in `placement`, for repeated lookups. The first
+    /// placement wins for duplicate designators, NOREFDES and BOARD are left out.
+    ///
+    /// The index is a snapshot: it is invalidated by adding, removing, sorting or renaming
+    /// placements, moving them is fine.
+    pub fn placement_index(&self) -> HashMap<String, usize> {
+        let mut index = HashMap::with_capacity(self.placement.len());
+        for (i, p) in self.placement.iter().enumerate() {
+            if let ReferenceDesignator::Any(d) = &p.designator {
+                index.entry(d.to_string()).or_insert(i);
+            }
+        }
+        index
+    }
+
+    /// Placement with the given reference designator. This is a linear scan, build a
+    /// `placement_index` for many lookups.
+    pub fn get_placement(&self, designator: &str) -> Option<&ComponentPlacement<'a>> {
+        self.placement
+            .iter()
+            .find(|p| matches!(&p.designator, ReferenceDesignator::Any(d) if **d == *designator))
+    }
+
+    pub fn get_placement_mut(&mut self, designator: &str) -> Option<&mut ComponentPlacement<'a>> {
+        self.placement
+            .iter_mut()
+            .find(|p| matches!(&p.designator, ReferenceDesignator::Any(d) if **d == *designator))
+    }
+
+    /// Sorts placements by reference designator in natural order.
+    pub fn sort_placements(&mut self) {
+        self.sort_placements_by(&[SortKey::Designator]);
+    }
+
+    /// Stable sort of placements by `keys`, later keys break ties of earlier ones.
+    pub fn sort_placements_by(&mut self, keys: &[SortKey]) {
+        self.placement.sort_by(|a, b| {
+            keys.iter()
+                .fold(Ordering::Equal, |o, key| o.then_with(|| key.compare(a, b)))
+        });
+    }
+
+    /// Sets the status of the placements matching `selector` on behalf of `by`. Placements
+    /// that `by` may not move according to `PlacementStatus::can_be_moved_by` keep their
+    /// status and are reported as locked.
+    pub fn set_status(
+        &mut self,
+        by: Owner,
+        selector: impl Fn(&ComponentPlacement) -> bool,
+        status: PlacementStatus,
+    ) -> StatusUpdate {
+        let mut update = StatusUpdate::default();
+        for p in self.placement.iter_mut().filter(|p| selector(p)) {
+            if p.placement_status.can_be_moved_by(by) {
+                p.placement_status = status.clone();
+                update.changed += 1;
+            } else {
+                update.locked.push(p.designator.to_string());
+            }
+        }
+        update
+    }
+
+    /// Two copies of the board, one with the top side placements only and one with the bottom
+    /// side ones, for tools that take a file per side. Both keep the outline, holes and all
+    /// other sections.
+    pub fn split_by_side(&self) -> (Idf30<'a>, Idf30<'a>) {
+        let side = |side: BoardSide| {
+            let mut board = self.clone();
+            board.placement.retain(|p| p.board_side == side);
+            board
+        };
+        (side(BoardSide::Top), side(BoardSide::Bottom))
+    }
+
+    /// Removes the placements matching `predicate` and returns them in their original order,
+    /// e.g. to move all test points into a file of their own.
+    pub fn extract_placements(
+        &mut self,
+        mut predicate: impl FnMut(&ComponentPlacement<'a>) -> bool,
+    ) -> Vec<ComponentPlacement<'a>> {
+        let (removed, kept) = std::mem::take(&mut self.placement)
+            .into_iter()
+            .partition(|p| predicate(p));
+        self.placement = kept;
+        removed
+    }
+}
@@ -0,0 +1,126 @@
+//! Progress reporting for parsing and writing large files.
+
+use crate::idf30::{Error, FileType, Idf30, SectionRef};
+use crate::warnings::file_warnings;
+use crate::write::{section_sequence, WriteOptions};
+use std::io::Write;
+
+/// Placements written between two progress events inside the PLACEMENT section.
+const PLACEMENT_STEP: usize = 1024;
+/// Bytes of a section parsed between two progress events inside it.
+const PARSE_STEP: usize = 64 * 1024;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ProgressEvent<'s> {
+    /// Bytes of the input parsed or of the output written so far
+    pub bytes: usize,
+    /// Size of the input when parsing, unknown when writing
+    pub total: Option<usize>,
+    /// Section that was just processed, e.g. "BOARD_OUTLINE"
+    pub section: &'s str,
+}
+
+impl<'a> Idf30<'a> {
+    /// Same as `parse`, reporting progress after the header, after every section and every
+    /// 64 KiB of a larger section other than ELECTRICAL.
+    pub fn parse_with_progress(
+        file: &str,
+        mut progress: impl FnMut(ProgressEvent),
+    ) -> Result<Idf30<'_>, Error> {
+        let total = Some(file.len());
+        let lazy = Idf30::parse_lazy(file)?;
+        progress(ProgressEvent {
+            bytes: 0,
+            total,
+            section: "HEADER",
+        });
+        let mut idf = lazy.materialize(PARSE_STEP, |section, text| {
+            // Sections are slices of `file`, their end is the offset reached
+            let end = text.as_ptr() as usize - file.as_ptr() as usize + text.len();
+            progress(ProgressEvent {
+                bytes: end,
+                total,
+                section,
+            })
+        })?;
+        idf.warnings = file_warnings(file, &idf);
+        Ok(idf)
+    }
+
+    /// Writes the same text as `to_string`, reporting progress after every section and every
+    /// 1024 placements.
+    pub fn write_with_progress(
+        &self,
+        w: &mut impl Write,
+        mut progress: impl FnMut(ProgressEvent),
+    ) -> Result<(), Error> {
+        let mut w = Counting { inner: w, bytes: 0 };
+        w.write_all(self.header.to_string().as_bytes())?;
+        progress(ProgressEvent {
+            bytes: w.bytes,
+            total: None,
+            section: "HEADER",
+        });
+        for r in section_sequence(self, &WriteOptions::default()) {
+            match r {
+                SectionRef::Other(i) => {
+                    let o = &self.other_sections[i];
+                    w.write_all(o.to_string().as_bytes())?;
+                    progress(ProgressEvent {
+                        bytes: w.bytes,
+                        total: None,
+                        section: o.name(),
+                    });
+                }
+                SectionRef::Placement => {
+                    w.write_all(b".PLACEMENT\n")?;
+                    for (i, c) in self.placement.iter().enumerate() {
+                        w.write_all(c.to_string().as_bytes())?;
+                        if (i + 1) % PLACEMENT_STEP == 0 {
+                            progress(ProgressEvent {
+                                bytes: w.bytes,
+                                total: None,
+                                section: "PLACEMENT",
+                            });
+                        }
+                    }
+                    w.write_all(b".END_PLACEMENT\n")?;
+                    progress(ProgressEvent {
+                        bytes: w.bytes,
+                        total: None,
+                        section: "PLACEMENT",
+                    });
+                }
+                SectionRef::Definition(i) => {
+                    if let FileType::LibraryFile { components } = &self.header.ty {
+                        w.write_all(components[i].to_string().as_bytes())?;
+                        progress(ProgressEvent {
+                            bytes: w.bytes,
+                            total: None,
+                            section: "ELECTRICAL",
+                        });
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writer counting the bytes passed through.
+struct Counting<'w, W: Write> {
+    inner: &'w mut W,
+    bytes: usize,
+}
+
+impl<'w, W: Write> Write for Counting<'w, W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.bytes += n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
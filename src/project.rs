@@ -0,0 +1,147 @@
+use crate::assembly::IdfAssembly;
+use crate::idf30::{Error, Idf30};
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FileKind {
+    Board,
+    Panel,
+    Library,
+}
+
+#[derive(Clone, Debug)]
+pub struct ProjectFile {
+    pub path: PathBuf,
+    pub kind: FileKind,
+    pub idf: Idf30<'static>,
+    /// For boards, index of the library file in `Project::files`
+    pub library: Option<usize>,
+    /// For panels, indices of the boards the panel places
+    pub boards: Vec<usize>,
+}
+
+/// IDF files found in a directory.
+#[derive(Debug, Default)]
+pub struct Project {
+    pub files: Vec<ProjectFile>,
+    /// IDF files that could not be read or parsed
+    pub errors: Vec<(PathBuf, Error)>,
+}
+
+impl Project {
+    /// Reads every IDF file in `dir`, recognized by its header rather than by extension.
+    ///
+    /// Boards are linked to the library with the same file stem, or to the only library if
+    /// there is just one. Panels are linked to the boards whose board name matches one of
+    /// the panel's placements.
+    pub fn load(dir: impl AsRef<Path>) -> Result<Project, Error> {
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.is_file())
+            .collect();
+        paths.sort();
+        let mut project = Project::default();
+        for path in paths {
+            let Ok(bytes) = std::fs::read(&path) else {
+                continue;
+            };
+            let Ok(text) = String::from_utf8(bytes) else {
+                continue;
+            };
+            let Some(kind) = sniff(&text) else {
+                continue;
+            };
+            match Idf30::parse(&text) {
+                Ok(idf) => project.files.push(ProjectFile {
+                    path,
+                    kind,
+                    idf: idf.into_owned(),
+                    library: None,
+                    boards: vec![],
+                }),
+                Err(e) => project.errors.push((path, e)),
+            }
+        }
+        project.link();
+        Ok(project)
+    }
+
+    pub fn boards(&self) -> impl Iterator<Item = &ProjectFile> {
+        self.of_kind(FileKind::Board)
+    }
+
+    pub fn panels(&self) -> impl Iterator<Item = &ProjectFile> {
+        self.of_kind(FileKind::Panel)
+    }
+
+    pub fn libraries(&self) -> impl Iterator<Item = &ProjectFile> {
+        self.of_kind(FileKind::Library)
+    }
+
+    /// Board at `index` in `files` together with its library.
+    pub fn assembly(&self, index: usize) -> Option<IdfAssembly<'static>> {
+        let board = self.files.get(index)?;
+        let library = &self.files[board.library?];
+        IdfAssembly::new(board.idf.clone(), library.idf.clone()).ok()
+    }
+
+    fn of_kind(&self, kind: FileKind) -> impl Iterator<Item = &ProjectFile> {
+        self.files.iter().filter(move |f| f.kind == kind)
+    }
+
+    fn link(&mut self) {
+        let libraries: Vec<usize> = (0..self.files.len())
+            .filter(|&i| self.files[i].kind == FileKind::Library)
+            .collect();
+        let boards: Vec<(usize, String)> = self
+            .files
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| f.kind == FileKind::Board)
+            .map(|(i, f)| (i, f.idf.board_name().unwrap_or_default().to_string()))
+            .collect();
+        for i in 0..self.files.len() {
+            match self.files[i].kind {
+                FileKind::Board => {
+                    let stem = self.files[i].path.file_stem();
+                    self.files[i].library = libraries
+                        .iter()
+                        .copied()
+                        .find(|&l| self.files[l].path.file_stem() == stem)
+                        .or(match libraries.as_slice() {
+                            [only] => Some(*only),
+                            _ => None,
+                        });
+                }
+                FileKind::Panel => {
+                    let panel = &self.files[i].idf;
+                    self.files[i].boards = boards
+                        .iter()
+                        .filter(|(_, name)| {
+                            panel.placement.iter().any(|p| *p.package_name == **name)
+                        })
+                        .map(|(b, _)| *b)
+                        .collect();
+                }
+                FileKind::Library => {}
+            }
+        }
+    }
+}
+
+/// Kind of IDF file from the first record of its header.
+fn sniff(text: &str) -> Option<FileKind> {
+    let mut lines = text
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'));
+    if !lines.next()?.eq_ignore_ascii_case(".HEADER") {
+        return None;
+    }
+    match lines.next()?.split_whitespace().next()? {
+        "BOARD_FILE" => Some(FileKind::Board),
+        "PANEL_FILE" => Some(FileKind::Panel),
+        "LIBRARY_FILE" => Some(FileKind::Library),
+        _ => None,
+    }
+}
@@ -0,0 +1,56 @@
+//! Parser backends. A reader turns text into events, `Idf30::read_with` assembles them, so
+//! backends can be swapped without changing the code using the model.
+
+use crate::idf30::{Error, Header, Idf30, ParsedSection, SectionCollector};
+use crate::limits::{check_limits, ParseLimits};
+use crate::warnings::file_warnings;
+
+/// Part of a file in the order it appears.
+#[derive(Clone, Debug, PartialEq)]
+pub enum IdfEvent<'a> {
+    /// Always the first event
+    Header(Header<'a>),
+    Section(ParsedSection<'a>),
+}
+
+/// Parser backend, see `PestReader` and, with the `fast-parser` feature, `FastReader`.
+pub trait IdfReader {
+    /// Parses `text`, passing the header and then every section to `emit`. Errors returned by
+    /// `emit` stop reading and are returned as is.
+    fn read<'a>(
+        &self,
+        text: &'a str,
+        emit: &mut dyn FnMut(IdfEvent<'a>) -> Result<(), Error>,
+    ) -> Result<(), Error>;
+}
+
+impl<'a> Idf30<'a> {
+    /// Parses `text` with `reader`, collecting the `warnings` of the model.
+    pub fn read_with(text: &'a str, reader: &impl IdfReader) -> Result<Idf30<'a>, Error> {
+        Idf30::read_with_limits(text, reader, ParseLimits::default())
+    }
+
+    /// Same as `read_with`, failing with `Error::LimitExceeded` before `reader` is called if
+    /// the text exceeds `limits`.
+    pub fn read_with_limits(
+        text: &'a str,
+        reader: &impl IdfReader,
+        limits: ParseLimits,
+    ) -> Result<Idf30<'a>, Error> {
+        check_limits(text, limits)?;
+        let mut header = None;
+        let mut sections = SectionCollector::default();
+        reader.read(text, &mut |event| {
+            match event {
+                IdfEvent::Header(h) if header.is_none() => header = Some(h),
+                IdfEvent::Header(_) => return Err(Error::Malformed("Second header")),
+                IdfEvent::Section(_) if header.is_none() => return Err(Error::MissingHeader),
+                IdfEvent::Section(s) => sections.push(s),
+            }
+            Ok(())
+        })?;
+        let mut idf = sections.finish(header.ok_or(Error::MissingHeader)?);
+        idf.warnings = file_warnings(text, &idf);
+        Ok(idf)
+    }
+}
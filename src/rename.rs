@@ -0,0 +1,167 @@
+use crate::assembly::IdfAssembly;
+use crate::idf30::{FileType, Idf30};
+use either::Either;
+use std::collections::{BTreeSet, HashMap};
+
+/// Name field that a bulk rename applies to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RenameField {
+    /// Package name of placements and geometry name of library definitions
+    PackageName,
+    PartNumber,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RenameReport {
+    /// Number of placements and library definitions that were changed
+    pub renamed: usize,
+    /// Distinct names present in the file that had no replacement
+    pub unmatched: Vec<String>,
+    /// Mapping entries that did not match anything, only filled by `Idf30::rename` and
+    /// `IdfAssembly::rename`
+    pub unused: Vec<String>,
+}
+
+impl<'a> Idf30<'a> {
+    /// Replaces names found in `mapping` across placements and library definitions.
+    pub fn rename(
+        &mut self,
+        field: RenameField,
+        mapping: &HashMap<String, String>,
+    ) -> RenameReport {
+        rename_mapped(mapping, |rename| self.rename_with(field, rename))
+    }
+
+    /// Replaces every name for which `rename` returns `Some`.
+    ///
+    /// All replacements are computed before any of them is applied, so `rename` always sees the
+    /// original names, even when one mapping's output is another mapping's input.
+    pub fn rename_with(
+        &mut self,
+        field: RenameField,
+        rename: impl FnMut(&str) -> Option<String>,
+    ) -> RenameReport {
+        rename_files(&mut [self], field, rename)
+    }
+}
+
+impl<'a> IdfAssembly<'a> {
+    /// Same as `Idf30::rename`, for the placements of the board and the definitions of the
+    /// library together.
+    pub fn rename(
+        &mut self,
+        field: RenameField,
+        mapping: &HashMap<String, String>,
+    ) -> RenameReport {
+        rename_mapped(mapping, |rename| self.rename_with(field, rename))
+    }
+
+    /// Same as `Idf30::rename_with` for both files. Names of both are computed before either
+    /// is changed and a name gets the same replacement in both, so placements keep resolving
+    /// to their definitions.
+    pub fn rename_with(
+        &mut self,
+        field: RenameField,
+        rename: impl FnMut(&str) -> Option<String>,
+    ) -> RenameReport {
+        rename_files(&mut [&mut self.board, &mut self.library], field, rename)
+    }
+}
+
+/// Renames with the names in `mapping` through `rename_with` and fills in `unused`.
+fn rename_mapped(
+    mapping: &HashMap<String, String>,
+    rename_with: impl FnOnce(&mut dyn FnMut(&str) -> Option<String>) -> RenameReport,
+) -> RenameReport {
+    let mut used = BTreeSet::new();
+    let mut report = rename_with(&mut |name| {
+        let new = mapping.get(name)?;
+        used.insert(name.to_string());
+        Some(new.clone())
+    });
+    report.unused = mapping
+        .keys()
+        .filter(|k| !used.contains(*k))
+        .cloned()
+        .collect();
+    report.unused.sort();
+    report
+}
+
+/// Renames placements and library definitions of all `files`, calling `rename` once per
+/// distinct name before anything is changed.
+fn rename_files(
+    files: &mut [&mut Idf30],
+    field: RenameField,
+    mut rename: impl FnMut(&str) -> Option<String>,
+) -> RenameReport {
+    let mut cache: HashMap<String, Option<String>> = HashMap::new();
+    let mut lookup = |name: &str| -> Option<String> {
+        if let Some(new) = cache.get(name) {
+            return new.clone();
+        }
+        let new = rename(name);
+        cache.insert(name.to_string(), new.clone());
+        new
+    };
+
+    let mut placement_names = vec![];
+    let mut definition_names = vec![];
+    for file in files.iter() {
+        let mut names = vec![];
+        for c in &file.placement {
+            let name = match field {
+                RenameField::PackageName => &c.package_name,
+                RenameField::PartNumber => &c.part_number,
+            };
+            names.push(lookup(name));
+        }
+        placement_names.push(names);
+        let mut names = vec![];
+        if let FileType::LibraryFile { components } = &file.header.ty {
+            for c in components {
+                let name = match field {
+                    RenameField::PackageName => &c.geometry_name,
+                    RenameField::PartNumber => &c.part_number,
+                };
+                names.push(lookup(name));
+            }
+        }
+        definition_names.push(names);
+    }
+
+    let mut report = RenameReport::default();
+    for ((file, placement_names), definition_names) in
+        files.iter_mut().zip(placement_names).zip(definition_names)
+    {
+        for (c, new) in file.placement.iter_mut().zip(placement_names) {
+            let name = match field {
+                RenameField::PackageName => &mut c.package_name,
+                RenameField::PartNumber => &mut c.part_number,
+            };
+            if let Some(new) = new {
+                *name = Either::Right(new);
+                report.renamed += 1;
+            }
+        }
+        if let FileType::LibraryFile { components } = &mut file.header.ty {
+            for (c, new) in components.iter_mut().zip(definition_names) {
+                let name = match field {
+                    RenameField::PackageName => &mut c.geometry_name,
+                    RenameField::PartNumber => &mut c.part_number,
+                };
+                if let Some(new) = new {
+                    *name = Either::Right(new);
+                    report.renamed += 1;
+                }
+            }
+        }
+    }
+    report.unmatched = cache
+        .into_iter()
+        .filter(|(_, new)| new.is_none())
+        .map(|(name, _)| name)
+        .collect();
+    report.unmatched.sort();
+    report
+}
@@ -0,0 +1,53 @@
+//! Saving files the way the specification intends for exchange iterations: the board file
+//! version goes up with every revision that changes the contents.
+
+use crate::idf30::{Error, Idf30};
+use std::path::{Path, PathBuf};
+
+impl<'a> Idf30<'a> {
+    /// Writes the file to `path`. If its fingerprint differs from `loaded`, the one it had when
+    /// it was read, the board file version is incremented and the date set to now first.
+    /// Returns whether it did so.
+    pub fn save(&mut self, path: impl AsRef<Path>, loaded: u64) -> Result<bool, Error> {
+        let changed = self.fingerprint() != loaded;
+        if changed {
+            self.header.board_file_version += 1;
+            self.header.touch();
+        }
+        std::fs::write(path, self.to_string())?;
+        Ok(changed)
+    }
+}
+
+/// File read from disk together with its fingerprint, see `Idf30::save`.
+#[derive(Clone, Debug)]
+pub struct TrackedFile {
+    pub path: PathBuf,
+    pub idf: Idf30<'static>,
+    /// Fingerprint when read or last saved
+    saved: u64,
+}
+
+impl TrackedFile {
+    pub fn open(path: impl Into<PathBuf>) -> Result<TrackedFile, Error> {
+        let path = path.into();
+        let idf = Idf30::parse(&std::fs::read_to_string(&path)?)?.into_owned();
+        Ok(TrackedFile {
+            saved: idf.fingerprint(),
+            path,
+            idf,
+        })
+    }
+
+    /// Whether the contents changed since the file was read or last saved.
+    pub fn is_modified(&self) -> bool {
+        self.idf.fingerprint() != self.saved
+    }
+
+    /// Writes the file back, bumping its version if it was modified. Returns whether it was.
+    pub fn save(&mut self) -> Result<bool, Error> {
+        let changed = self.idf.save(&self.path, self.saved)?;
+        self.saved = self.idf.fingerprint();
+        Ok(changed)
+    }
+}
@@ -0,0 +1,144 @@
+//! Field names and types for the records of custom sections, so that their values can be
+//! looked up by name instead of position.
+
+use crate::idf30::{Error, Idf30, IdfSection, IdfValue};
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FieldType {
+    Integer,
+    /// Float, integers are converted
+    Float,
+    String,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Field {
+    pub name: String,
+    pub ty: FieldType,
+    /// Optional fields may be left out at the end of a record
+    pub optional: bool,
+}
+
+/// Fields of every record of a section, in order.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RecordSchema {
+    pub fields: Vec<Field>,
+}
+
+/// Values of a record by field name, optional fields left out are missing.
+pub type Row<'s, 'a> = HashMap<&'s str, IdfValue<'a>>;
+
+impl RecordSchema {
+    pub fn new() -> Self {
+        RecordSchema::default()
+    }
+
+    pub fn field(mut self, name: impl Into<String>, ty: FieldType) -> Self {
+        self.fields.push(Field {
+            name: name.into(),
+            ty,
+            optional: false,
+        });
+        self
+    }
+
+    pub fn optional(mut self, name: impl Into<String>, ty: FieldType) -> Self {
+        self.fields.push(Field {
+            name: name.into(),
+            ty,
+            optional: true,
+        });
+        self
+    }
+
+    /// Checks a record against the schema, naming the first field that does not match.
+    pub fn row<'s, 'a>(&'s self, record: &[IdfValue<'a>]) -> Result<Row<'s, 'a>, Error> {
+        if record.len() > self.fields.len() {
+            return Err(Error::Malformed("More fields than the schema defines"));
+        }
+        let mut row = HashMap::new();
+        for (i, field) in self.fields.iter().enumerate() {
+            let wrong = |reason| Error::SchemaMismatch {
+                field: field.name.clone(),
+                reason,
+            };
+            let Some(value) = record.get(i) else {
+                if field.optional {
+                    continue;
+                }
+                return Err(wrong("missing"));
+            };
+            let value = match (field.ty, value) {
+                (FieldType::Integer, IdfValue::Integer(_))
+                | (FieldType::Float, IdfValue::Float(_))
+                | (FieldType::String, IdfValue::String(_)) => value.clone(),
+                (FieldType::Float, IdfValue::Integer(x)) => IdfValue::Float(*x as f64),
+                (FieldType::Integer, _) => return Err(wrong("expected integer")),
+                (FieldType::Float, _) => return Err(wrong("expected number")),
+                (FieldType::String, _) => return Err(wrong("expected string")),
+            };
+            row.insert(field.name.as_str(), value);
+        }
+        Ok(row)
+    }
+}
+
+/// Schemas of custom sections by section name.
+#[derive(Clone, Debug, Default)]
+pub struct SchemaRegistry {
+    schemas: HashMap<String, RecordSchema>,
+}
+
+impl SchemaRegistry {
+    pub fn new() -> Self {
+        SchemaRegistry::default()
+    }
+
+    /// Sets the schema of the sections named `section`, replacing an earlier one.
+    pub fn register(&mut self, section: impl Into<String>, schema: RecordSchema) {
+        self.schemas.insert(section.into(), schema);
+    }
+
+    pub fn get(&self, section: &str) -> Option<&RecordSchema> {
+        self.schemas.get(section)
+    }
+
+    /// Rows of `section`, `None` if no schema is registered for it. Errors name the record and
+    /// the field that does not match.
+    pub fn rows<'s, 'a>(
+        &'s self,
+        section: &IdfSection<'a>,
+    ) -> Option<Result<Vec<Row<'s, 'a>>, Error>> {
+        let schema = self.get(section.name())?;
+        Some(
+            section
+                .records()
+                .iter()
+                .enumerate()
+                .map(|(i, record)| {
+                    schema
+                        .row(record)
+                        .map_err(|e| e.at(section.name(), Some(i), (0, 0)))
+                })
+                .collect(),
+        )
+    }
+
+    /// Checks every registered section of `idf`, returning an error for each record that does
+    /// not match.
+    pub fn validate(&self, idf: &Idf30) -> Vec<Error> {
+        let mut errors = vec![];
+        for section in &idf.other_sections {
+            let Some(schema) = self.get(section.name()) else {
+                continue;
+            };
+            for (i, record) in section.records().iter().enumerate() {
+                if let Err(e) = schema.row(record) {
+                    errors.push(e.at(section.name(), Some(i), (0, 0)));
+                }
+            }
+        }
+        errors
+    }
+}
@@ -0,0 +1,94 @@
+//! Placement selectors written as text, e.g. `refdes:R* side:bottom`, for filters given on a
+//! command line or in a config file.
+
+use crate::classify::glob;
+use crate::idf30::{BoardSide, ComponentPlacement, Error, Idf30, PlacementStatus};
+use std::str::FromStr;
+
+/// Placement field a selector term looks at.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Term {
+    /// Reference designator pattern, NOREFDES and BOARD match as written
+    Designator(String),
+    Package(String),
+    PartNumber(String),
+    Side(BoardSide),
+    Status(PlacementStatus),
+}
+
+impl Term {
+    pub fn matches(&self, placement: &ComponentPlacement) -> bool {
+        match self {
+            Term::Designator(p) => glob(p, &placement.designator.to_string()),
+            Term::Package(p) => glob(p, &placement.package_name),
+            Term::PartNumber(p) => glob(p, &placement.part_number),
+            Term::Side(side) => placement.board_side == *side,
+            Term::Status(status) => placement.placement_status == *status,
+        }
+    }
+}
+
+/// Placements matching every whitespace separated term of a query, each term being
+/// `key:value` with alternatives separated by commas:
+/// * `refdes:R*,C*`, `package:*QFN*` and `part:PN-10#` take patterns as in `classify::Rule`
+/// * `side:top` or `side:bottom`
+/// * `status:placed`, `unplaced`, `mcad` or `ecad`
+///
+/// Keys and values are case insensitive. An empty query selects everything.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Selector {
+    /// Alternatives of every term
+    pub terms: Vec<Vec<Term>>,
+}
+
+impl Selector {
+    pub fn matches(&self, placement: &ComponentPlacement) -> bool {
+        self.terms
+            .iter()
+            .all(|alternatives| alternatives.iter().any(|t| t.matches(placement)))
+    }
+}
+
+impl FromStr for Selector {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let terms = s
+            .split_whitespace()
+            .map(|term| {
+                let (key, values) = term.split_once(':').ok_or_else(|| invalid(term))?;
+                values
+                    .split(',')
+                    .map(|value| {
+                        let upper = value.to_ascii_uppercase();
+                        let term = match key.to_ascii_lowercase().as_str() {
+                            "refdes" if !value.is_empty() => Term::Designator(upper),
+                            "package" if !value.is_empty() => Term::Package(upper),
+                            "part" if !value.is_empty() => Term::PartNumber(upper),
+                            "side" => Term::Side(upper.parse().map_err(|_| invalid(term))?),
+                            "status" => Term::Status(upper.parse().map_err(|_| invalid(term))?),
+                            _ => return Err(invalid(term)),
+                        };
+                        Ok(term)
+                    })
+                    .collect()
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(Selector { terms })
+    }
+}
+
+fn invalid(term: &str) -> Error {
+    Error::InvalidSelector(term.to_string())
+}
+
+impl<'a> Idf30<'a> {
+    /// Placements matching `selector`, see `Selector` for the syntax.
+    pub fn select(
+        &self,
+        selector: &str,
+    ) -> Result<impl Iterator<Item = &ComponentPlacement<'a>>, Error> {
+        let selector: Selector = selector.parse()?;
+        Ok(self.placement.iter().filter(move |p| selector.matches(p)))
+    }
+}
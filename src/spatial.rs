@@ -0,0 +1,183 @@
+//! Grid index over component extents for proximity queries.
+
+use crate::assembly::{IdfAssembly, Outline, ResolvedComponent};
+use crate::geometry::{polylines_distance, BoundingBox};
+
+/// Uniform grid of bounding boxes, each box is listed in every cell it touches.
+#[derive(Clone, Debug)]
+pub struct SpatialIndex {
+    boxes: Vec<BoundingBox>,
+    extents: Option<BoundingBox>,
+    cell_size: f64,
+    cols: usize,
+    rows: usize,
+    cells: Vec<Vec<usize>>,
+}
+
+impl SpatialIndex {
+    /// Indexes the boxes, cells are sized to the average box.
+    pub fn new(boxes: Vec<BoundingBox>) -> SpatialIndex {
+        let extents = boxes.iter().copied().reduce(|a, b| a.union(&b));
+        let mut index = SpatialIndex {
+            extents,
+            cell_size: 1.0,
+            cols: 1,
+            rows: 1,
+            cells: vec![vec![]],
+            boxes: vec![],
+        };
+        if let Some(extents) = extents {
+            let average =
+                boxes.iter().map(|b| b.width().max(b.height())).sum::<f64>() / boxes.len() as f64;
+            let size = extents.width().max(extents.height());
+            // Keep the grid reasonably sized for degenerate and tiny boxes
+            index.cell_size = average.max(size / 256.0).max(f64::EPSILON);
+            index.cols = (extents.width() / index.cell_size).floor() as usize + 1;
+            index.rows = (extents.height() / index.cell_size).floor() as usize + 1;
+            index.cells = vec![vec![]; index.cols * index.rows];
+            for (i, b) in boxes.iter().enumerate() {
+                let (c0, r0, c1, r1) = index.cell_range(b);
+                for row in r0..=r1 {
+                    for col in c0..=c1 {
+                        index.cells[row * index.cols + col].push(i);
+                    }
+                }
+            }
+        }
+        index.boxes = boxes;
+        index
+    }
+
+    pub fn len(&self) -> usize {
+        self.boxes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.boxes.is_empty()
+    }
+
+    /// Indices of the boxes intersecting `area`, in ascending order.
+    pub fn query(&self, area: &BoundingBox) -> Vec<usize> {
+        let Some(extents) = self.extents else {
+            return vec![];
+        };
+        if area.max_x < extents.min_x
+            || area.min_x > extents.max_x
+            || area.max_y < extents.min_y
+            || area.min_y > extents.max_y
+        {
+            return vec![];
+        }
+        let (c0, r0, c1, r1) = self.cell_range(area);
+        let mut found: Vec<usize> = (r0..=r1)
+            .flat_map(|row| (c0..=c1).map(move |col| (row, col)))
+            .flat_map(|(row, col)| self.cells[row * self.cols + col].iter().copied())
+            .filter(|&i| {
+                let b = &self.boxes[i];
+                b.min_x <= area.max_x
+                    && b.max_x >= area.min_x
+                    && b.min_y <= area.max_y
+                    && b.max_y >= area.min_y
+            })
+            .collect();
+        found.sort_unstable();
+        found.dedup();
+        found
+    }
+
+    /// Item closest to `(x, y)` by `distance`, which must not be less than the distance to the
+    /// item's bounding box.
+    pub fn nearest(&self, x: f64, y: f64, distance: impl Fn(usize) -> f64) -> Option<(usize, f64)> {
+        let extents = self.extents?;
+        let far = extents.width() + extents.height() + box_distance(&extents, x, y);
+        let mut radius = self.cell_size;
+        loop {
+            let area = BoundingBox {
+                min_x: x - radius,
+                min_y: y - radius,
+                max_x: x + radius,
+                max_y: y + radius,
+            };
+            let best = self
+                .query(&area)
+                .into_iter()
+                .filter(|&i| box_distance(&self.boxes[i], x, y) <= radius)
+                .map(|i| (i, distance(i)))
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+            match best {
+                Some(best) if best.1 <= radius => return Some(best),
+                _ if radius > far => return best,
+                _ => radius *= 2.0,
+            }
+        }
+    }
+
+    fn cell_range(&self, b: &BoundingBox) -> (usize, usize, usize, usize) {
+        let extents = self.extents.unwrap_or(*b);
+        let cell = |v: f64, origin: f64, n: usize| {
+            (((v - origin) / self.cell_size).floor().max(0.0) as usize).min(n - 1)
+        };
+        (
+            cell(b.min_x, extents.min_x, self.cols),
+            cell(b.min_y, extents.min_y, self.rows),
+            cell(b.max_x, extents.min_x, self.cols),
+            cell(b.max_y, extents.min_y, self.rows),
+        )
+    }
+}
+
+fn box_distance(b: &BoundingBox, x: f64, y: f64) -> f64 {
+    let dx = (b.min_x - x).max(x - b.max_x).max(0.0);
+    let dy = (b.min_y - y).max(y - b.max_y).max(0.0);
+    dx.hypot(dy)
+}
+
+/// Spatial index over the components of an assembly, by library outline or reference point.
+pub struct ComponentIndex<'s, 'a> {
+    components: Vec<(ResolvedComponent<'s, 'a>, Outline)>,
+    index: SpatialIndex,
+}
+
+impl<'s, 'a> ComponentIndex<'s, 'a> {
+    /// Component closest to `(x, y)` on either side, with the distance to its outline, zero if
+    /// the point is inside.
+    pub fn nearest_component(&self, x: f64, y: f64) -> Option<(ResolvedComponent<'s, 'a>, f64)> {
+        let point = [vec![(x, y)]];
+        self.index
+            .nearest(x, y, |i| polylines_distance(&self.components[i].1, &point))
+            .map(|(i, d)| (self.components[i].0, d))
+    }
+
+    /// Components whose extents intersect `area`.
+    pub fn query(&self, area: &BoundingBox) -> Vec<ResolvedComponent<'s, 'a>> {
+        self.index
+            .query(area)
+            .into_iter()
+            .map(|i| self.components[i].0)
+            .collect()
+    }
+}
+
+impl<'a> IdfAssembly<'a> {
+    pub fn component_index(&self) -> ComponentIndex<'_, 'a> {
+        let components: Vec<_> = self.components().map(|c| (c, c.footprint())).collect();
+        let index = SpatialIndex::new(
+            components
+                .iter()
+                .filter_map(|(_, o)| BoundingBox::from_points(o.iter().flatten().copied()))
+                .collect(),
+        );
+        ComponentIndex { components, index }
+    }
+
+    /// Smallest distance between the outlines of two components, zero if they overlap. The
+    /// sides are not taken into account.
+    pub fn clearance(&self, first: &str, second: &str) -> Option<f64> {
+        let find = |designator: &str| {
+            self.components()
+                .find(|c| c.placement.designator.to_string() == designator)
+                .map(|c| c.footprint())
+        };
+        Some(polylines_distance(&find(first)?, &find(second)?))
+    }
+}
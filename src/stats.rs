@@ -0,0 +1,75 @@
+use crate::geometry::{section_loops, signed_area, BoundingBox};
+use crate::idf30::{BoardSide, Error, Idf30, PlacementStatus};
+use std::collections::BTreeMap;
+
+/// Holes whose diameters differ by less than this (in board units) are counted together.
+const DIAMETER_TOLERANCE: f32 = 1e-4;
+
+/// Summary of a board or panel, lengths in board units.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BoardStats {
+    pub components: usize,
+    pub per_side: BTreeMap<BoardSide, usize>,
+    pub per_package: BTreeMap<String, usize>,
+    pub per_status: BTreeMap<PlacementStatus, usize>,
+    /// `(diameter, count)` in ascending order of diameter
+    pub holes_by_diameter: Vec<(f32, usize)>,
+    /// Drilled hole records that could not be read and are not counted
+    pub malformed_holes: usize,
+    /// Area inside the outline minus cutouts
+    pub outline_area: Option<f64>,
+    /// Extents of the outline
+    pub dimensions: Option<BoundingBox>,
+}
+
+impl BoardStats {
+    pub fn hole_count(&self) -> usize {
+        self.holes_by_diameter.iter().map(|(_, n)| n).sum()
+    }
+}
+
+impl<'a> Idf30<'a> {
+    pub fn stats(&self) -> Result<BoardStats, Error> {
+        let mut stats = BoardStats {
+            components: self.placement.len(),
+            ..BoardStats::default()
+        };
+        for c in &self.placement {
+            *stats.per_side.entry(c.board_side.clone()).or_default() += 1;
+            *stats
+                .per_package
+                .entry(c.package_name.to_string())
+                .or_default() += 1;
+            *stats
+                .per_status
+                .entry(c.placement_status.clone())
+                .or_default() += 1;
+        }
+
+        let (holes, errors) = self.drilled_holes_lossy();
+        stats.malformed_holes = errors.len();
+        for hole in holes {
+            match stats
+                .holes_by_diameter
+                .iter_mut()
+                .find(|(d, _)| (d - hole.diameter).abs() < DIAMETER_TOLERANCE)
+            {
+                Some((_, n)) => *n += 1,
+                None => stats.holes_by_diameter.push((hole.diameter, 1)),
+            }
+        }
+        stats.holes_by_diameter.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        if let Some(outline) = self
+            .section("BOARD_OUTLINE")
+            .or_else(|| self.section("PANEL_OUTLINE"))
+        {
+            let loops = section_loops(outline);
+            stats.outline_area = loops.split_first().map(|(outer, cutouts)| {
+                signed_area(outer).abs() - cutouts.iter().map(|l| signed_area(l).abs()).sum::<f64>()
+            });
+            stats.dimensions = outline.bounding_box();
+        }
+        Ok(stats)
+    }
+}
@@ -0,0 +1,88 @@
+//! Test point report for test fixture design.
+
+use crate::classify::Classifier;
+use crate::formats::centroid::csv_field;
+use crate::holes::Plating;
+use crate::idf30::{BoardSide, Error, Idf30, ReferenceDesignator};
+use std::fmt::{Display, Formatter};
+
+/// Side a fixture probe can reach a test point from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Access {
+    Top,
+    Bottom,
+    /// Through-hole test points, with a plated hole of their own
+    Both,
+}
+
+impl Display for Access {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Access::Top => write!(f, "TOP"),
+            Access::Bottom => write!(f, "BOTTOM"),
+            Access::Both => write!(f, "BOTH"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TestPoint {
+    pub designator: String,
+    pub x: f32,
+    pub y: f32,
+    pub side: BoardSide,
+    pub accessible_from: Access,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TestPointReport {
+    pub test_points: Vec<TestPoint>,
+}
+
+impl TestPointReport {
+    /// One test point per row with RefDes, X, Y, Side and AccessibleFrom columns, in board units.
+    pub fn to_csv(&self) -> String {
+        let mut s = String::from("RefDes,X,Y,Side,AccessibleFrom\n");
+        for t in &self.test_points {
+            s.push_str(&format!(
+                "{},{:.4},{:.4},{},{}\n",
+                csv_field(&t.designator),
+                t.x,
+                t.y,
+                t.side,
+                t.accessible_from
+            ));
+        }
+        s
+    }
+}
+
+impl<'a> Idf30<'a> {
+    /// Placements `classifier` takes for test points, in placement order. Test points with a
+    /// plated hole naming them can be probed from both sides, others from their own side.
+    pub fn test_point_report(&self, classifier: &Classifier) -> Result<TestPointReport, Error> {
+        let holes = self.drilled_holes()?;
+        let test_points = classifier
+            .test_points(self)
+            .map(|c| {
+                let through_hole = matches!(c.designator, ReferenceDesignator::Any(_))
+                    && holes
+                        .iter()
+                        .any(|h| h.plating == Plating::Plated && h.association == c.designator);
+                let accessible_from = match (through_hole, &c.board_side) {
+                    (true, _) => Access::Both,
+                    (false, BoardSide::Top) => Access::Top,
+                    (false, BoardSide::Bottom) => Access::Bottom,
+                };
+                TestPoint {
+                    designator: c.designator.to_string(),
+                    x: c.x,
+                    y: c.y,
+                    side: c.board_side.clone(),
+                    accessible_from,
+                }
+            })
+            .collect();
+        Ok(TestPointReport { test_points })
+    }
+}
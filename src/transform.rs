@@ -0,0 +1,332 @@
+use crate::geometry::number;
+use crate::idf30::{BoardSide, ComponentPlacement, Error, Idf30, IdfSection, IdfValue};
+
+/// Sections whose records contain `label x y angle` loop points.
+pub(crate) const LOOP_SECTIONS: &[&str] = &[
+    "BOARD_OUTLINE",
+    "PANEL_OUTLINE",
+    "OTHER_OUTLINE",
+    "ROUTE_OUTLINE",
+    "PLACE_OUTLINE",
+    "ROUTE_KEEPOUT",
+    "VIA_KEEPOUT",
+    "PLACE_KEEPOUT",
+    "PLACE_REGION",
+];
+
+/// 2D affine transform, `x' = xx * x + xy * y + dx` and `y' = yx * x + yy * y + dy`.
+///
+/// Only compositions of translations, rotations, uniform scaling and mirroring are meaningful
+/// for IDF data, since placement rotations and arc angles have to be carried along.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub xx: f64,
+    pub xy: f64,
+    pub yx: f64,
+    pub yy: f64,
+    pub dx: f64,
+    pub dy: f64,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::identity()
+    }
+}
+
+impl Transform {
+    pub fn identity() -> Self {
+        Transform {
+            xx: 1.0,
+            xy: 0.0,
+            yx: 0.0,
+            yy: 1.0,
+            dx: 0.0,
+            dy: 0.0,
+        }
+    }
+
+    pub fn translation(dx: f64, dy: f64) -> Self {
+        Transform {
+            dx,
+            dy,
+            ..Transform::identity()
+        }
+    }
+
+    /// Counterclockwise rotation around the origin, in degrees.
+    pub fn rotation(angle: f64) -> Self {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        Transform {
+            xx: cos,
+            xy: -sin,
+            yx: sin,
+            yy: cos,
+            dx: 0.0,
+            dy: 0.0,
+        }
+    }
+
+    /// Negates X coordinates, i.e. mirrors about the Y axis, as when the board is flipped over.
+    pub fn mirror_x() -> Self {
+        Transform {
+            xx: -1.0,
+            ..Transform::identity()
+        }
+    }
+
+    pub fn scaling(factor: f64) -> Self {
+        Transform {
+            xx: factor,
+            yy: factor,
+            ..Transform::identity()
+        }
+    }
+
+    /// Transform that applies `self` first and `next` after it.
+    pub fn then(&self, next: &Transform) -> Transform {
+        Transform {
+            xx: next.xx * self.xx + next.xy * self.yx,
+            xy: next.xx * self.xy + next.xy * self.yy,
+            yx: next.yx * self.xx + next.yy * self.yx,
+            yy: next.yx * self.xy + next.yy * self.yy,
+            dx: next.xx * self.dx + next.xy * self.dy + next.dx,
+            dy: next.yx * self.dx + next.yy * self.dy + next.dy,
+        }
+    }
+
+    pub fn inverse(&self) -> Transform {
+        let det = self.determinant();
+        let xx = self.yy / det;
+        let xy = -self.xy / det;
+        let yx = -self.yx / det;
+        let yy = self.xx / det;
+        Transform {
+            xx,
+            xy,
+            yx,
+            yy,
+            dx: -(xx * self.dx + xy * self.dy),
+            dy: -(yx * self.dx + yy * self.dy),
+        }
+    }
+
+    pub fn apply(&self, x: f64, y: f64) -> (f64, f64) {
+        (
+            self.xx * x + self.xy * y + self.dx,
+            self.yx * x + self.yy * y + self.dy,
+        )
+    }
+
+    pub fn is_mirrored(&self) -> bool {
+        self.determinant() < 0.0
+    }
+
+    pub fn scale_factor(&self) -> f64 {
+        self.determinant().abs().sqrt()
+    }
+
+    /// Rotation part of the transform in degrees, after the mirroring (if any) has been applied.
+    pub fn rotation_angle(&self) -> f64 {
+        if self.is_mirrored() {
+            (-self.yx).atan2(-self.xx).to_degrees()
+        } else {
+            self.yx.atan2(self.xx).to_degrees()
+        }
+    }
+
+    /// Maps a component rotation, normalized to `[0, 360)`.
+    ///
+    /// Bottom side components are mirrored before they are rotated, so a mirroring transform
+    /// reverses the rotation direction as well as moving the component to the other side.
+    pub fn apply_rotation(&self, rotation: f64) -> f64 {
+        let rotation = if self.is_mirrored() {
+            self.rotation_angle() - rotation
+        } else {
+            rotation + self.rotation_angle()
+        };
+        normalize_angle(rotation)
+    }
+
+    fn determinant(&self) -> f64 {
+        self.xx * self.yy - self.xy * self.yx
+    }
+}
+
+impl<'a> Idf30<'a> {
+    /// Applies `transform` to outlines, keepouts, drilled holes, notes and placements.
+    ///
+    /// Scaling affects planar dimensions only (coordinates, hole diameters and text sizes);
+    /// board thickness and component heights and offsets are left untouched.
+    pub fn transform(&mut self, transform: &Transform) {
+        for section in &mut self.other_sections {
+            section.transform(transform);
+        }
+        for component in &mut self.placement {
+            component.transform(transform);
+        }
+    }
+
+    pub fn translate(&mut self, dx: f32, dy: f32) {
+        self.transform(&Transform::translation(dx as f64, dy as f64));
+    }
+
+    /// Counterclockwise rotation around the origin, in degrees.
+    pub fn rotate(&mut self, angle: f32) {
+        self.transform(&Transform::rotation(angle as f64));
+    }
+
+    /// Flips the board over the Y axis, moving all components to the opposite side.
+    pub fn mirror_x(&mut self) {
+        self.transform(&Transform::mirror_x());
+    }
+
+    pub fn scale(&mut self, factor: f32) {
+        self.transform(&Transform::scaling(factor as f64));
+    }
+}
+
+impl<'a> ComponentPlacement<'a> {
+    /// Maps the component's library outline into board coordinates. Bottom side outlines are
+    /// mirrored about their Y axis before being rotated.
+    pub fn placement_transform(&self) -> Transform {
+        let mirror = if self.board_side == BoardSide::Bottom {
+            Transform::mirror_x()
+        } else {
+            Transform::identity()
+        };
+        mirror
+            .then(&Transform::rotation(self.rotation as f64))
+            .then(&Transform::translation(self.x as f64, self.y as f64))
+    }
+
+    /// Moves the component to the other side of `board`, mirrored about the vertical axis
+    /// through the center of the board outline. The rotation is mirrored as well, as bottom
+    /// side outlines are mirrored before they are rotated.
+    pub fn flip_side(&mut self, board: &Idf30) -> Result<(), Error> {
+        let bbox = board
+            .section("BOARD_OUTLINE")
+            .ok_or(Error::MissingSection("BOARD_OUTLINE"))?
+            .bounding_box()
+            .ok_or(Error::Malformed("BOARD_OUTLINE does not contain any loop"))?;
+        self.flip_about((bbox.min_x + bbox.max_x) / 2.0);
+        Ok(())
+    }
+
+    /// Like `flip_side`, mirroring about the vertical line at `axis_x`.
+    pub fn flip_about(&mut self, axis_x: f64) {
+        self.transform(
+            &Transform::translation(-axis_x, 0.0)
+                .then(&Transform::mirror_x())
+                .then(&Transform::translation(axis_x, 0.0)),
+        );
+    }
+
+    pub fn transform(&mut self, transform: &Transform) {
+        let (x, y) = transform.apply(self.x as f64, self.y as f64);
+        self.x = x as f32;
+        self.y = y as f32;
+        self.rotation = transform.apply_rotation(self.rotation as f64) as f32;
+        if transform.is_mirrored() {
+            self.board_side = match self.board_side {
+                BoardSide::Top => BoardSide::Bottom,
+                BoardSide::Bottom => BoardSide::Top,
+            };
+        }
+    }
+}
+
+impl<'a> IdfSection<'a> {
+    /// Transforms the coordinates of the sections whose layout is known, others are left as is.
+    pub fn transform(&mut self, transform: &Transform) {
+        let name = self.name().to_string();
+        let scale = transform.scale_factor();
+        let records = self.records_mut();
+        if LOOP_SECTIONS.contains(&name.as_str()) {
+            for record in records.iter_mut().filter(|r| is_loop_point(r)) {
+                transform_xy(record, 1, transform);
+            }
+            if transform.is_mirrored() {
+                reverse_loops(records);
+            }
+        } else if name == "DRILLED_HOLES" {
+            for record in records.iter_mut().filter(|r| r.len() >= 3) {
+                scale_value(&mut record[0], scale);
+                transform_xy(record, 1, transform);
+            }
+        } else if name == "NOTES" {
+            for record in records.iter_mut().filter(|r| r.len() >= 4) {
+                transform_xy(record, 0, transform);
+                scale_value(&mut record[2], scale);
+                scale_value(&mut record[3], scale);
+            }
+        }
+    }
+}
+
+pub(crate) fn is_loop_point(record: &[IdfValue]) -> bool {
+    record.len() == 4
+        && matches!(record[0], IdfValue::Integer(_))
+        && record[1..].iter().all(is_number)
+}
+
+pub(crate) fn normalize_angle(angle: f64) -> f64 {
+    let angle = angle.rem_euclid(360.0);
+    if angle >= 360.0 {
+        0.0
+    } else {
+        angle
+    }
+}
+
+/// Restores the winding of every loop after mirroring: points are visited in reverse order and
+/// each arc angle moves to the point that now ends the arc. Mirroring and reversing both flip
+/// the sweep direction, so the angle keeps its sign. Full circles are left alone.
+fn reverse_loops(records: &mut [Vec<IdfValue>]) {
+    let mut start = 0;
+    while start < records.len() {
+        if !is_loop_point(&records[start]) {
+            start += 1;
+            continue;
+        }
+        let label = records[start][0].clone();
+        let mut end = start + 1;
+        while end < records.len() && is_loop_point(&records[end]) && records[end][0] == label {
+            end += 1;
+        }
+        let run = &mut records[start..end];
+        let is_circle = run.iter().any(|r| number(&r[3]).abs() == 360.0);
+        if !is_circle {
+            let angles: Vec<IdfValue> = run.iter().map(|r| r[3].clone()).collect();
+            run.reverse();
+            let n = run.len();
+            for (k, record) in run.iter_mut().enumerate() {
+                record[3] = if k == 0 {
+                    IdfValue::Float(0.0)
+                } else {
+                    angles[n - k].clone()
+                };
+            }
+        }
+        start = end;
+    }
+}
+
+fn transform_xy(record: &mut [IdfValue], at: usize, transform: &Transform) {
+    if !is_number(&record[at]) || !is_number(&record[at + 1]) {
+        return;
+    }
+    let (x, y) = transform.apply(number(&record[at]), number(&record[at + 1]));
+    record[at] = IdfValue::Float(x);
+    record[at + 1] = IdfValue::Float(y);
+}
+
+fn scale_value(value: &mut IdfValue, factor: f64) {
+    if is_number(value) {
+        *value = IdfValue::Float(number(value) * factor);
+    }
+}
+
+fn is_number(value: &IdfValue) -> bool {
+    matches!(value, IdfValue::Integer(_) | IdfValue::Float(_))
+}
@@ -0,0 +1,191 @@
+//! Checks of outline geometry that MCAD importers rely on.
+
+use crate::geometry::{section_loops, segment_distance, tessellate};
+use crate::idf30::{FileType, Idf30, IdfSection, Loop};
+use crate::transform::LOOP_SECTIONS;
+use std::fmt::{Display, Formatter};
+
+/// Outline a loop belongs to.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum OutlineSource {
+    /// Outline, keepout or region section, by name
+    Section(String),
+    /// Library definition, by geometry name
+    Definition(String),
+}
+
+/// Problem with one loop of an outline.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoopIssue {
+    pub source: OutlineSource,
+    /// Loop index from the file, 0 for the outer loop
+    pub loop_index: u32,
+    pub kind: LoopIssueKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LoopIssueKind {
+    /// Outer loop running clockwise or cutout running counterclockwise
+    WrongWinding,
+    /// Last point differs from the first one
+    NotClosed,
+    /// Segments `first` and `second` cross or touch, segment `i` ends at point `i + 1`
+    SelfIntersection { first: usize, second: usize },
+}
+
+impl Display for OutlineSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutlineSource::Section(name) => write!(f, ".{name}"),
+            OutlineSource::Definition(name) => write!(f, "definition {name}"),
+        }
+    }
+}
+
+impl Display for LoopIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Loop {} of {}: ", self.loop_index, self.source)?;
+        match self.kind {
+            LoopIssueKind::WrongWinding => write!(f, "wrong winding"),
+            LoopIssueKind::NotClosed => write!(f, "not closed"),
+            LoopIssueKind::SelfIntersection { first, second } => {
+                write!(f, "segments {first} and {second} intersect")
+            }
+        }
+    }
+}
+
+impl<'a> Idf30<'a> {
+    /// Outer loops that run clockwise and cutouts that run counterclockwise, in sections and
+    /// library definitions. Importers that trust the direction invert such outlines.
+    pub fn winding_issues(&self) -> Vec<LoopIssue> {
+        let mut issues = vec![];
+        self.for_each_loop(|source, l| {
+            if has_wrong_winding(l) {
+                issues.push(LoopIssue {
+                    source: source.clone(),
+                    loop_index: l.index,
+                    kind: LoopIssueKind::WrongWinding,
+                });
+            }
+        });
+        issues
+    }
+
+    /// Loops that cross themselves, besides the `winding_issues`, which breaks extrusion in
+    /// MCAD tools, usually without an error. Loops whose last point is not the first one are
+    /// closed implicitly like everywhere else in this crate, and only reported as `NotClosed`
+    /// with `require_closed`, for importers that insist on the closing point.
+    pub fn loop_issues(&self, require_closed: bool) -> Vec<LoopIssue> {
+        let mut issues = self.winding_issues();
+        self.for_each_loop(|source, l| {
+            let issue = |kind| LoopIssue {
+                source: source.clone(),
+                loop_index: l.index,
+                kind,
+            };
+            if l.as_circle().is_some() {
+                return;
+            }
+            if let (true, Some(first), Some(last)) =
+                (require_closed, l.points.first(), l.points.last())
+            {
+                if (first.x - last.x).abs() > CLOSE_TOLERANCE
+                    || (first.y - last.y).abs() > CLOSE_TOLERANCE
+                {
+                    issues.push(issue(LoopIssueKind::NotClosed));
+                }
+            }
+            for (first, second) in self_intersections(l) {
+                issues.push(issue(LoopIssueKind::SelfIntersection { first, second }));
+            }
+        });
+        issues
+    }
+
+    /// Reverses the loops reported by `winding_issues`, returning how many were fixed. Loops
+    /// of a fixed section are renumbered in file order, see `IdfSection::set_loops`.
+    pub fn fix_winding(&mut self) -> usize {
+        let mut fixed = 0;
+        for s in &mut self.other_sections {
+            if !LOOP_SECTIONS.contains(&s.name()) {
+                continue;
+            }
+            let wrong = s.loops().iter().filter(|l| has_wrong_winding(l)).count();
+            if wrong > 0 {
+                s.set_loops(&section_loops(s));
+                fixed += wrong;
+            }
+        }
+        if let FileType::LibraryFile { components } = &mut self.header.ty {
+            for l in components.iter_mut().flat_map(|c| &mut c.loops) {
+                if has_wrong_winding(l) {
+                    l.reverse();
+                    fixed += 1;
+                }
+            }
+        }
+        fixed
+    }
+
+    fn for_each_loop(&self, mut f: impl FnMut(&OutlineSource, &Loop)) {
+        for s in self.loop_sections() {
+            let source = OutlineSource::Section(s.name().to_string());
+            s.loops().iter().for_each(|l| f(&source, l));
+        }
+        if let FileType::LibraryFile { components } = &self.header.ty {
+            for c in components {
+                let source = OutlineSource::Definition(c.geometry_name.to_string());
+                c.loops.iter().for_each(|l| f(&source, l));
+            }
+        }
+    }
+
+    fn loop_sections(&self) -> impl Iterator<Item = &IdfSection<'a>> {
+        self.other_sections
+            .iter()
+            .filter(|s| LOOP_SECTIONS.contains(&s.name()))
+    }
+}
+
+/// Largest distance between the first and last point of a closed loop, in file units.
+const CLOSE_TOLERANCE: f32 = 1e-4;
+
+/// Pairs of non-adjacent segments of a loop that cross or touch, arcs included.
+fn self_intersections(l: &Loop) -> Vec<(usize, usize)> {
+    let points: Vec<[f64; 3]> = l
+        .points
+        .iter()
+        .map(|p| [p.x as f64, p.y as f64, p.angle as f64])
+        .collect();
+    let mut segments: Vec<Vec<(f64, f64)>> = points.windows(2).map(tessellate).collect();
+    // Loops are closed implicitly with a straight line
+    if let (Some(first), Some(last)) = (points.first(), points.last()) {
+        if (first[0], first[1]) != (last[0], last[1]) {
+            segments.push(tessellate(&[*last, [first[0], first[1], 0.0]]));
+        }
+    }
+    let n = segments.len();
+    let mut found = vec![];
+    for i in 0..n {
+        // Neighbours share an end point, the last segment is next to the first one
+        for j in i + 2..n {
+            if i == 0 && j == n - 1 {
+                continue;
+            }
+            let crosses = segments[i].windows(2).any(|a| {
+                segments[j]
+                    .windows(2)
+                    .any(|b| segment_distance(a[0], a[1], b[0], b[1]) < 1e-9)
+            });
+            if crosses {
+                found.push((i, j));
+            }
+        }
+    }
+    found
+}
+
+fn has_wrong_winding(l: &Loop) -> bool {
+    l.area() > 0.0 && l.is_counterclockwise() == l.is_cutout()
+}
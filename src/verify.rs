@@ -0,0 +1,225 @@
+//! Checking that a file survives parsing and writing, for CI of converters built on this crate.
+
+use crate::idf30::{ComponentDefinition, Error, FileType, Idf30, SectionRef};
+use crate::lazy::section_spans;
+use crate::write::{section_sequence, WriteOptions};
+use std::ops::Range;
+
+/// Coordinates may differ by this much after writing with four decimals, three for rotations.
+const TOLERANCE: f32 = 1e-3;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoundTripReport {
+    /// Text written from the parsed input
+    pub written: String,
+    /// First difference between the input and the written text parsed again, `None` if they
+    /// match
+    pub divergence: Option<Divergence>,
+}
+
+impl RoundTripReport {
+    pub fn is_faithful(&self) -> bool {
+        self.divergence.is_none()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Divergence {
+    /// e.g. PLACEMENT, `None` if the written text does not parse
+    pub section: Option<String>,
+    /// Reference designator, geometry name or 1-based record number, `None` for the section as
+    /// a whole
+    pub item: Option<String>,
+    pub reason: String,
+    /// Byte range of the section in the input, `None` if it only exists in the written text
+    pub input_span: Option<Range<usize>>,
+    /// Byte range of the section in the written text, `None` if it was not written
+    pub written_span: Option<Range<usize>>,
+}
+
+/// Parses `input`, writes it, parses the written text and compares both models. Strings have to
+/// match, coordinates within the precision of the written text. Errors parsing `input` are
+/// returned as is, the written text failing to parse is a divergence.
+pub fn verify_round_trip(input: &str) -> Result<RoundTripReport, Error> {
+    verify_round_trip_with(input, &WriteOptions::default())
+}
+
+/// Like `verify_round_trip`, writing with `options`. Sections are matched by kind and index, so
+/// a changed section order is not a divergence.
+pub fn verify_round_trip_with(
+    input: &str,
+    options: &WriteOptions,
+) -> Result<RoundTripReport, Error> {
+    let first = Idf30::parse(input)?;
+    let written = first.to_string_with(options);
+    let divergence = match Idf30::parse(&written) {
+        Ok(second) => first_difference(&first, &second).map(|(at, item, reason)| Divergence {
+            section: Some(section_name(&first, &second, at).to_string()),
+            item,
+            reason: reason.to_string(),
+            input_span: span(input, at),
+            written_span: span(&written, at),
+        }),
+        Err(e) => Some(Divergence {
+            section: None,
+            item: None,
+            reason: format!("written text does not parse: {e}"),
+            input_span: None,
+            written_span: None,
+        }),
+    };
+    Ok(RoundTripReport {
+        written,
+        divergence,
+    })
+}
+
+/// Section a difference was found in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum At {
+    Header,
+    Section(SectionRef),
+}
+
+type Difference = (At, Option<String>, &'static str);
+
+fn first_difference(a: &Idf30, b: &Idf30) -> Option<Difference> {
+    let header = |reason| Some((At::Header, None, reason));
+    let (ha, hb) = (&a.header, &b.header);
+    match (&ha.ty, &hb.ty) {
+        (
+            FileType::BoardFile { board_name, units },
+            FileType::BoardFile {
+                board_name: other_name,
+                units: other_units,
+            },
+        )
+        | (
+            FileType::PanelFile { board_name, units },
+            FileType::PanelFile {
+                board_name: other_name,
+                units: other_units,
+            },
+        ) => {
+            if **board_name != **other_name {
+                return header("board name");
+            }
+            if units != other_units {
+                return header("units");
+            }
+        }
+        (FileType::LibraryFile { .. }, FileType::LibraryFile { .. }) => {}
+        _ => return header("file type"),
+    }
+    if *ha.source != *hb.source {
+        return header("source");
+    }
+    if *ha.date != *hb.date {
+        return header("date");
+    }
+    if ha.board_file_version != hb.board_file_version {
+        return header("board file version");
+    }
+
+    let (da, db) = (definitions(a), definitions(b));
+    for r in section_sequence(a, &WriteOptions::default()) {
+        let at = At::Section(r);
+        match r {
+            SectionRef::Other(i) => {
+                let (sa, Some(sb)) = (&a.other_sections[i], b.other_sections.get(i)) else {
+                    return Some((at, None, "section missing"));
+                };
+                if sa.name() != sb.name() {
+                    return Some((at, None, "section name"));
+                }
+                if sa.owner() != sb.owner() || sa.args() != sb.args() {
+                    return Some((at, None, "section arguments"));
+                }
+                if sa.records().len() != sb.records().len() {
+                    return Some((at, None, "record count"));
+                }
+                let records = sa.records().iter().zip(sb.records());
+                for (k, (ra, rb)) in records.enumerate() {
+                    let same = ra.len() == rb.len()
+                        && ra.iter().zip(rb).all(|(x, y)| x.approx_eq(y, TOLERANCE));
+                    if !same {
+                        return Some((at, Some(format!("record {}", k + 1)), "record values"));
+                    }
+                }
+            }
+            SectionRef::Placement => {
+                if a.placement.len() != b.placement.len() {
+                    return Some((at, None, "component count"));
+                }
+                for (ca, cb) in a.placement.iter().zip(&b.placement) {
+                    if !ca.approx_eq(cb, TOLERANCE) {
+                        return Some((at, Some(ca.designator.to_string()), "component"));
+                    }
+                }
+            }
+            SectionRef::Definition(i) => match db.get(i) {
+                None => return Some((at, None, "section missing")),
+                Some(d) if !da[i].approx_eq(d, TOLERANCE) => {
+                    return Some((at, Some(da[i].geometry_name.to_string()), "definition"));
+                }
+                Some(_) => {}
+            },
+        }
+    }
+    if b.other_sections.len() > a.other_sections.len() {
+        let at = At::Section(SectionRef::Other(a.other_sections.len()));
+        return Some((at, None, "extra section"));
+    }
+    if db.len() > da.len() {
+        return Some((
+            At::Section(SectionRef::Definition(da.len())),
+            None,
+            "extra section",
+        ));
+    }
+    None
+}
+
+fn definitions<'i, 'a>(idf: &'i Idf30<'a>) -> &'i [ComponentDefinition<'a>] {
+    match &idf.header.ty {
+        FileType::LibraryFile { components } => components,
+        _ => &[],
+    }
+}
+
+fn section_name<'s>(a: &'s Idf30, b: &'s Idf30, at: At) -> &'s str {
+    match at {
+        At::Header => "HEADER",
+        At::Section(SectionRef::Other(i)) => a
+            .other_sections
+            .get(i)
+            .or(b.other_sections.get(i))
+            .map_or("", |s| s.name()),
+        At::Section(SectionRef::Placement) => "PLACEMENT",
+        At::Section(SectionRef::Definition(_)) => "ELECTRICAL",
+    }
+}
+
+/// Byte range of the section of `text` at `at`, `None` if the text has no such section.
+fn span(text: &str, at: At) -> Option<Range<usize>> {
+    let spans = section_spans(text).ok()?;
+    let (mut other, mut definition) = (0, 0);
+    for (name, range, _) in spans {
+        let here = match name {
+            "HEADER" => At::Header,
+            "PLACEMENT" => At::Section(SectionRef::Placement),
+            "ELECTRICAL" => {
+                definition += 1;
+                At::Section(SectionRef::Definition(definition - 1))
+            }
+            _ => {
+                other += 1;
+                At::Section(SectionRef::Other(other - 1))
+            }
+        };
+        if here == at {
+            return Some(range);
+        }
+    }
+    None
+}
@@ -0,0 +1,147 @@
+//! Data quality issues that do not prevent a file from being parsed.
+
+use crate::idf30::{unquote, Error, Idf30};
+use crate::lazy::RawFields;
+use crate::transform::LOOP_SECTIONS;
+use std::fmt::{Display, Formatter};
+
+/// Sections of an IDF 3.0 file besides the loop sections.
+const OTHER_SECTIONS: &[&str] = &[
+    "HEADER",
+    "DRILLED_HOLES",
+    "NOTES",
+    "PLACEMENT",
+    "ELECTRICAL",
+    "MECHANICAL",
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseWarning {
+    /// Section that IDF 3.0 does not define, kept as is
+    UnknownSection { name: String, line: usize },
+    /// Section closed by something other than `.END_<name>`
+    MismatchedEnd {
+        name: String,
+        end: String,
+        line: usize,
+    },
+    /// Outline or keepout owner other than ECAD, MCAD or UNOWNED
+    UnknownOwner { section: String, owner: String },
+    /// `PROP` record of an ELECTRICAL section, which the model does not keep
+    DroppedProperty { name: String, line: usize },
+    /// Placement rotation outside of [0, 360) that was wrapped into this range
+    RotationNormalized {
+        designator: String,
+        from: f32,
+        to: f32,
+    },
+}
+
+impl Display for ParseWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseWarning::UnknownSection { name, line } => {
+                write!(f, "Unknown section .{name} at line {line}")
+            }
+            ParseWarning::MismatchedEnd { name, end, line } => {
+                write!(f, "Section .{name} ended by .{end} at line {line}")
+            }
+            ParseWarning::UnknownOwner { section, owner } => {
+                write!(f, "Unknown owner {owner} of .{section}")
+            }
+            ParseWarning::DroppedProperty { name, line } => {
+                write!(f, "Property {name} at line {line} was dropped")
+            }
+            ParseWarning::RotationNormalized {
+                designator,
+                from,
+                to,
+            } => write!(f, "Rotation of {designator} changed from {from} to {to}"),
+        }
+    }
+}
+
+impl<'a> Idf30<'a> {
+    /// Same as `parse`, also wrapping rotations outside of [0, 360) into that range. Returns
+    /// the `warnings` of the model, including the rotations changed.
+    pub fn parse_with_warnings(file: &str) -> Result<(Idf30<'_>, Vec<ParseWarning>), Error> {
+        let mut idf = Idf30::parse(file)?;
+        for c in &mut idf.placement {
+            if (0.0..360.0).contains(&c.rotation) {
+                continue;
+            }
+            let mut to = c.rotation.rem_euclid(360.0);
+            // Rounds up to 360 for small negative rotations
+            if to >= 360.0 {
+                to = 0.0;
+            }
+            idf.warnings.push(ParseWarning::RotationNormalized {
+                designator: c.designator.to_string(),
+                from: c.rotation,
+                to,
+            });
+            c.rotation = to;
+        }
+        let warnings = idf.warnings.clone();
+        Ok((idf, warnings))
+    }
+}
+
+/// Issues in `file` that `idf` was parsed from: unknown sections, mismatched section ends,
+/// dropped properties and unknown owners.
+pub(crate) fn file_warnings(file: &str, idf: &Idf30) -> Vec<ParseWarning> {
+    let mut warnings = vec![];
+    let mut open: Option<&str> = None;
+    for (n, line) in file.lines().enumerate() {
+        let Some(keyword) = line.trim().strip_prefix('.') else {
+            let mut fields = RawFields(line);
+            if open == Some("ELECTRICAL") && fields.next() == Some("PROP") {
+                warnings.push(ParseWarning::DroppedProperty {
+                    name: fields
+                        .next()
+                        .map(|f| unquote(f).to_string())
+                        .unwrap_or_default(),
+                    line: n + 1,
+                });
+            }
+            continue;
+        };
+        let keyword = keyword.split_whitespace().next().unwrap_or_default();
+        let line = n + 1;
+        match open.take() {
+            None => {
+                if !LOOP_SECTIONS.contains(&keyword) && !OTHER_SECTIONS.contains(&keyword) {
+                    warnings.push(ParseWarning::UnknownSection {
+                        name: keyword.to_string(),
+                        line,
+                    });
+                }
+                open = Some(keyword);
+            }
+            Some(name) => {
+                if keyword.strip_prefix("END_") != Some(name) {
+                    warnings.push(ParseWarning::MismatchedEnd {
+                        name: name.to_string(),
+                        end: keyword.to_string(),
+                        line,
+                    });
+                }
+            }
+        }
+    }
+
+    for section in &idf.other_sections {
+        if !LOOP_SECTIONS.contains(&section.name()) {
+            continue;
+        }
+        if let Some(owner) = section.args().first() {
+            if section.owner().is_none() {
+                warnings.push(ParseWarning::UnknownOwner {
+                    section: section.name().to_string(),
+                    owner: owner.to_string(),
+                });
+            }
+        }
+    }
+    warnings
+}
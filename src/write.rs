@@ -0,0 +1,343 @@
+//! Options for writing a model back to text.
+
+use crate::idf30::{
+    escape_header_string, escape_string, quote_unless, ComponentDefinition, ComponentPlacement,
+    FileType, Header, Idf30, IdfSection, IdfValue, ReferenceDesignator, SectionRef, KEYWORDS,
+};
+use std::borrow::Cow;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+/// How `Idf30::to_string_with` lays out a file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WriteOptions {
+    /// Section names, such as "PLACEMENT" or "BOARD_OUTLINE", written first in this order.
+    /// Sections not named here follow in their original order.
+    pub order: Vec<String>,
+    /// Decimals of coordinates, heights and angles, `None` for four and three for rotations
+    pub precision: Option<usize>,
+    /// Quote source, board, package, part, geometry and designator names even where they would
+    /// read back without quotes
+    pub quote_names: bool,
+    pub line_ending: LineEnding,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+/// Section order of the specification, placement last.
+pub(crate) const SPEC_ORDER: [&str; 12] = [
+    "BOARD_OUTLINE",
+    "PANEL_OUTLINE",
+    "OTHER_OUTLINE",
+    "ROUTE_OUTLINE",
+    "PLACE_OUTLINE",
+    "ROUTE_KEEPOUT",
+    "VIA_KEEPOUT",
+    "PLACE_KEEPOUT",
+    "PLACE_REGION",
+    "DRILLED_HOLES",
+    "NOTES",
+    "PLACEMENT",
+];
+
+/// Options for importers that reject some files the specification allows. A profile for an
+/// importer is only added together with the behaviour of that importer it works around, until
+/// then set the `WriteOptions` directly.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum WriteProfile {
+    /// Default options, sections in their original order
+    #[default]
+    Spec,
+}
+
+impl WriteProfile {
+    pub fn options(&self) -> WriteOptions {
+        match self {
+            WriteProfile::Spec => WriteOptions::default(),
+        }
+    }
+}
+
+impl From<WriteProfile> for WriteOptions {
+    fn from(profile: WriteProfile) -> Self {
+        profile.options()
+    }
+}
+
+impl WriteOptions {
+    /// Options the `Display` impls write with.
+    pub(crate) const SPEC: WriteOptions = WriteOptions {
+        order: Vec::new(),
+        precision: None,
+        quote_names: false,
+        line_ending: LineEnding::Lf,
+    };
+
+    fn float(&self, x: impl Display, decimals: usize) -> String {
+        format!("{x:.*}", self.precision.unwrap_or(decimals))
+    }
+
+    /// Package, part, geometry or designator name, quoted if it reads back as a keyword.
+    fn name<'s>(&self, s: &'s str) -> Cow<'s, str> {
+        match self.quote_names || KEYWORDS.contains(&s) {
+            true => quote_unless(s, false),
+            false => escape_string(s),
+        }
+    }
+
+    fn header_name<'s>(&self, s: &'s str) -> Cow<'s, str> {
+        match self.quote_names {
+            true => quote_unless(s, false),
+            false => escape_header_string(s),
+        }
+    }
+
+    pub(crate) fn header(&self, header: &Header) -> String {
+        let record1 = match &header.ty {
+            FileType::BoardFile { board_name, units }
+            | FileType::PanelFile { board_name, units } => {
+                format!("{} {}\n", self.header_name(board_name), units)
+            }
+            FileType::LibraryFile { .. } => String::new(),
+        };
+        format!(
+            ".HEADER\n{} 3.0 {} {} {}\n{}.END_HEADER\n",
+            header.ty,
+            self.header_name(&header.source),
+            escape_header_string(&header.date),
+            header.board_file_version,
+            record1
+        )
+    }
+
+    pub(crate) fn section(&self, section: &IdfSection) -> String {
+        let (start, end) = section_lines(section);
+        format!("{start}{}{end}", self.records(section.records()))
+    }
+
+    fn records(&self, records: &[Vec<IdfValue>]) -> String {
+        let mut s = String::new();
+        for record in records {
+            s.push(' ');
+            for v in record {
+                s.push(' ');
+                s.push_str(&self.value(v));
+            }
+            s.push('\n');
+        }
+        s
+    }
+
+    pub(crate) fn value(&self, value: &IdfValue) -> String {
+        match value {
+            IdfValue::Float(x) => self.float(*x, 4),
+            IdfValue::Integer(x) => x.to_string(),
+            IdfValue::String(s) => escape_string(s).into_owned(),
+        }
+    }
+
+    pub(crate) fn placement(&self, c: &ComponentPlacement) -> String {
+        format!(
+            "{} {} {}\n  {} {} {} {} {} {}\n",
+            self.name(&c.package_name),
+            self.name(&c.part_number),
+            match &c.designator {
+                ReferenceDesignator::Any(d) => self.name(d),
+                d => Cow::Owned(d.to_string()),
+            },
+            self.float(c.x, 4),
+            self.float(c.y, 4),
+            self.float(c.z, 4),
+            self.float(c.rotation, 3),
+            c.board_side,
+            c.placement_status
+        )
+    }
+
+    pub(crate) fn definition(&self, d: &ComponentDefinition) -> String {
+        let mut s = format!(
+            ".ELECTRICAL\n{} {} {} {}\n",
+            self.name(&d.geometry_name),
+            self.name(&d.part_number),
+            d.units,
+            self.float(d.height, 4)
+        );
+        for l in &d.loops {
+            for p in &l.points {
+                s.push_str(&format!(
+                    "{} {} {} {}\n",
+                    l.index,
+                    self.float(p.x, 4),
+                    self.float(p.y, 4),
+                    self.float(p.angle, 4)
+                ));
+            }
+        }
+        s.push_str(".END_ELECTRICAL\n");
+        s
+    }
+}
+
+/// Opening and closing line of a section.
+fn section_lines(section: &IdfSection) -> (String, String) {
+    let owner = section.owner().map(|o| format!(" {o}")).unwrap_or_default();
+    let args: String = section.args().iter().map(|arg| format!(" {arg}")).collect();
+    let name = section.name();
+    (format!(".{name}{owner}{args}\n"), format!(".END_{name}\n"))
+}
+
+/// Sections of `idf` in the order they are written with `options`.
+pub(crate) fn section_sequence(idf: &Idf30, options: &WriteOptions) -> Vec<SectionRef> {
+    let definitions = match &idf.header.ty {
+        FileType::LibraryFile { components } => Some(components.len()),
+        _ => None,
+    };
+    let valid = |r: &SectionRef| match *r {
+        SectionRef::Other(i) => i < idf.other_sections.len(),
+        SectionRef::Placement => definitions.is_none(),
+        SectionRef::Definition(i) => definitions.is_some_and(|n| i < n),
+    };
+    let mut sequence: Vec<SectionRef> = vec![];
+    let remaining = (0..idf.other_sections.len())
+        .map(SectionRef::Other)
+        .chain(definitions.is_none().then_some(SectionRef::Placement))
+        .chain((0..definitions.unwrap_or(0)).map(SectionRef::Definition));
+    for r in idf.section_order.iter().copied().chain(remaining) {
+        if valid(&r) && !sequence.contains(&r) {
+            sequence.push(r);
+        }
+    }
+    let rank = |r: &SectionRef| {
+        let name = match *r {
+            SectionRef::Other(i) => idf.other_sections[i].name(),
+            SectionRef::Placement => "PLACEMENT",
+            SectionRef::Definition(_) => "ELECTRICAL",
+        };
+        options
+            .order
+            .iter()
+            .position(|o| o == name)
+            .unwrap_or(options.order.len())
+    };
+    sequence.sort_by_key(rank);
+    sequence
+}
+
+impl<'a> Idf30<'a> {
+    /// Writes the file laid out as given by `options`, e.g.
+    /// `idf.to_string_with(&WriteProfile::Spec.into())`.
+    pub fn to_string_with(&self, options: &WriteOptions) -> String {
+        let mut s = options.header(&self.header);
+        for r in section_sequence(self, options) {
+            match r {
+                SectionRef::Other(i) => s.push_str(&options.section(&self.other_sections[i])),
+                SectionRef::Placement => {
+                    s.push_str(".PLACEMENT\n");
+                    for c in &self.placement {
+                        s.push_str(&options.placement(c));
+                    }
+                    s.push_str(".END_PLACEMENT\n");
+                }
+                SectionRef::Definition(i) => {
+                    if let FileType::LibraryFile { components } = &self.header.ty {
+                        s.push_str(&options.definition(&components[i]));
+                    }
+                }
+            }
+        }
+        match options.line_ending {
+            LineEnding::Lf => s,
+            LineEnding::CrLf => s.replace('\n', "\r\n"),
+        }
+    }
+
+    /// Same text as `to_string_with`, formatted on all available cores. Long sections are
+    /// split into chunks of records, so this pays off for panels with many thousands of holes
+    /// or placements; small files are faster written on one thread.
+    pub fn to_string_parallel(&self, options: &WriteOptions) -> String {
+        let mut pieces = vec![Piece::Text(options.header(&self.header))];
+        for r in section_sequence(self, options) {
+            match r {
+                SectionRef::Other(i) => {
+                    let section = &self.other_sections[i];
+                    let (start, end) = section_lines(section);
+                    pieces.push(Piece::Text(start));
+                    pieces.extend(section.records().chunks(CHUNK).map(Piece::Records));
+                    pieces.push(Piece::Text(end));
+                }
+                SectionRef::Placement => {
+                    pieces.push(Piece::Text(".PLACEMENT\n".to_string()));
+                    pieces.extend(self.placement.chunks(CHUNK).map(Piece::Placements));
+                    pieces.push(Piece::Text(".END_PLACEMENT\n".to_string()));
+                }
+                SectionRef::Definition(i) => {
+                    if let FileType::LibraryFile { components } = &self.header.ty {
+                        pieces.push(Piece::Definition(&components[i]));
+                    }
+                }
+            }
+        }
+
+        // Workers take the next piece until none are left and hand back what they formatted
+        let next = AtomicUsize::new(0);
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let mut formatted = vec![String::new(); pieces.len()];
+        std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads.min(pieces.len()))
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut done = vec![];
+                        loop {
+                            let i = next.fetch_add(1, AtomicOrdering::Relaxed);
+                            let Some(piece) = pieces.get(i) else {
+                                break done;
+                            };
+                            let s = piece.format(options);
+                            done.push((
+                                i,
+                                match options.line_ending {
+                                    LineEnding::Lf => s,
+                                    LineEnding::CrLf => s.replace('\n', "\r\n"),
+                                },
+                            ));
+                        }
+                    })
+                })
+                .collect();
+            for worker in workers {
+                for (i, s) in worker.join().expect("formatting does not panic") {
+                    formatted[i] = s;
+                }
+            }
+        });
+        formatted.concat()
+    }
+}
+
+/// Records or placements formatted as one unit of work by `Idf30::to_string_parallel`.
+const CHUNK: usize = 4096;
+
+enum Piece<'s, 'a> {
+    Text(String),
+    Records(&'s [Vec<IdfValue<'a>>]),
+    Placements(&'s [ComponentPlacement<'a>]),
+    Definition(&'s ComponentDefinition<'a>),
+}
+
+impl<'s, 'a> Piece<'s, 'a> {
+    fn format(&self, options: &WriteOptions) -> String {
+        match self {
+            Piece::Text(s) => s.clone(),
+            Piece::Records(records) => options.records(records),
+            Piece::Placements(placements) => {
+                placements.iter().map(|c| options.placement(c)).collect()
+            }
+            Piece::Definition(d) => options.definition(d),
+        }
+    }
+}
@@ -1,6 +1,44 @@
 #[cfg(test)]
 mod tests {
-    use idf::idf30::{FileType, Idf30};
+    use either::Either;
+    use idf::assembly::IdfAssembly;
+    use idf::checks::OffBoardReason;
+    use idf::classify::{Classifier, Rule};
+    use idf::date::HeaderDate;
+    use idf::designators::{DuplicatePolicy, RenumberScheme};
+    use idf::dfm::{DfmCategory, DfmRules};
+    use idf::dialect::Dialect;
+    use idf::diff::{Change, ConflictKind, MergeConflict};
+    use idf::editor::BoardEditor;
+    use idf::encoding::{decode, detect_encoding, Encoding};
+    use idf::footprints::{ChipSize, PackageOutline, PackageSize};
+    use idf::formats::centroid::CentroidOptions;
+    use idf::formats::idf20::Idf20Warning;
+    use idf::handlers::SectionHandler;
+    use idf::holes::{DrilledHole, HoleType, Plating};
+    use idf::idf30::{
+        BoardSide, ComponentDefinition, ComponentPlacement, Error, FileType, Header, Idf30,
+        IdfSection, IdfValue, Owner, ParsedSection, PestReader, PlacementStatus, Point,
+        ReferenceDesignator, SectionRef, Unit,
+    };
+    use idf::incremental::{Reparse, TextEdit};
+    use idf::intern::StringPool;
+    use idf::library::LibraryCache;
+    use idf::limits::ParseLimits;
+    use idf::merge::{DesignatorCollision, MergePolicy};
+    use idf::panel::{PanelFeatures, PanelInterference, PanelSpec, Rails};
+    use idf::placement::SortKey;
+    use idf::project::{FileKind, Project};
+    use idf::reader::{IdfEvent, IdfReader};
+    use idf::rename::RenameField;
+    use idf::save::TrackedFile;
+    use idf::schema::{FieldType, RecordSchema, SchemaRegistry};
+    use idf::select::{Selector, Term};
+    use idf::testpoints::Access;
+    use idf::validate::{LoopIssueKind, OutlineSource};
+    use idf::warnings::ParseWarning;
+    use idf::write::{LineEnding, WriteOptions, WriteProfile};
+    use std::collections::{HashMap, HashSet};
 
     #[test]
     fn board_file_parse() {
@@ -16,4 +54,3458 @@ mod tests {
         assert!(matches!(lib.header.ty, FileType::LibraryFile { .. }));
         println!("{lib:#?}");
     }
+
+    #[test]
+    fn approx_eq_after_round_trip() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let board = Idf30::parse(&contents).unwrap();
+        let written = board.to_string();
+        let reparsed = Idf30::parse(&written).unwrap();
+        assert!(board.approx_eq(&reparsed, 1e-4));
+
+        let mut moved = reparsed.clone();
+        moved.placement[0].x += 0.01;
+        assert!(!board.approx_eq(&moved, 1e-4));
+        assert!(board.approx_eq(&moved, 0.1));
+
+        let mut turned = reparsed.clone();
+        turned.placement[0].rotation = 359.999;
+        let mut upright = reparsed.clone();
+        upright.placement[0].rotation = 0.0;
+        assert!(turned.approx_eq(&upright, 1e-2));
+        assert!(upright.approx_eq(&turned, 1e-2));
+        assert!(!turned.approx_eq(&upright, 1e-4));
+    }
+
+    #[test]
+    fn transform_round_trip() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let board = Idf30::parse(&contents).unwrap();
+
+        let mut moved = board.clone();
+        moved.translate(10.0, -5.0);
+        moved.rotate(90.0);
+        assert!((moved.placement[0].rotation - 270.0).abs() < 1e-3);
+        moved.rotate(-90.0);
+        moved.translate(-10.0, 5.0);
+        assert!(board.approx_eq(&moved, 1e-3));
+
+        let mut flipped = board.clone();
+        flipped.mirror_x();
+        assert_eq!(flipped.placement[0].board_side, BoardSide::Bottom);
+        assert!((flipped.placement[0].x + board.placement[0].x).abs() < 1e-3);
+        flipped.mirror_x();
+        assert!(board.approx_eq(&flipped, 1e-3));
+    }
+
+    #[test]
+    fn panelize_board() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let board = Idf30::parse(&contents).unwrap();
+        let spec = PanelSpec {
+            rows: 2,
+            cols: 3,
+            spacing_x: 2.0,
+            spacing_y: 2.0,
+            rotation: 90.0,
+            rails: Rails {
+                bottom: 5.0,
+                top: 5.0,
+                ..Rails::default()
+            },
+            merge_holes: true,
+        };
+        let panel = board.panelize(&spec).unwrap();
+        assert_eq!(panel.placement.len(), 6);
+        let holes = panel.section("DRILLED_HOLES").unwrap();
+        assert_eq!(holes.records().len(), 12);
+
+        let written = panel.to_string();
+        let reparsed = Idf30::parse(&written).unwrap();
+        assert!(matches!(reparsed.header.ty, FileType::PanelFile { .. }));
+        assert!(panel.approx_eq(&reparsed, 1e-3));
+        let outline = reparsed
+            .section("PANEL_OUTLINE")
+            .unwrap()
+            .bounding_box()
+            .unwrap();
+        let board_bbox = board
+            .section("BOARD_OUTLINE")
+            .unwrap()
+            .bounding_box()
+            .unwrap();
+        assert!((outline.width() - (3.0 * board_bbox.height() + 4.0)).abs() < 1e-3);
+        assert!((outline.height() - (2.0 * board_bbox.width() + 12.0)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn flatten_panel_instance() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let board = Idf30::parse(&contents).unwrap();
+        let spec = PanelSpec {
+            rows: 1,
+            cols: 2,
+            spacing_x: 1.0,
+            spacing_y: 0.0,
+            rotation: 0.0,
+            rails: Rails::default(),
+            merge_holes: false,
+        };
+        let panel = board.panelize(&spec).unwrap();
+        let instances = panel.board_instances(&board).unwrap();
+        assert_eq!(instances.len(), 2);
+
+        let flat = panel.flatten_instance(&board, 1).unwrap();
+        let (dx, dy) = instances[1].transform.apply(0.0, 0.0);
+        let mut expected = board.clone();
+        expected.translate(dx as f32, dy as f32);
+        assert!(flat.approx_eq(&expected, 1e-3));
+        assert!(panel.flatten_instance(&board, 2).is_err());
+    }
+
+    #[test]
+    fn duplicate_designators() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let mut board = Idf30::parse(&contents).unwrap();
+        assert!(board.duplicate_designators().is_empty());
+        board.placement.push(board.placement[0].clone());
+        board.placement.push(board.placement[0].clone());
+
+        let duplicates = board.duplicate_designators();
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].designator, "J2");
+        assert_eq!(duplicates[0].indices, vec![0, 3, 4]);
+        assert!(board
+            .clone()
+            .resolve_duplicate_designators(DuplicatePolicy::Error)
+            .is_err());
+
+        let mut kept = board.clone();
+        kept.resolve_duplicate_designators(DuplicatePolicy::KeepFirst)
+            .unwrap();
+        assert_eq!(kept.placement.len(), 3);
+
+        let resolved = board
+            .resolve_duplicate_designators(DuplicatePolicy::AutoSuffix)
+            .unwrap();
+        assert_eq!(resolved[0].renamed, vec!["J2_2", "J2_3"]);
+        assert_eq!(board.placement[4].designator.to_string(), "J2_3");
+        assert!(board.duplicate_designators().is_empty());
+    }
+
+    #[test]
+    fn designator_prefix_number_and_order() {
+        let r15 = ReferenceDesignator::Any(Either::Left("R15"));
+        assert_eq!(r15.prefix(), Some("R"));
+        assert_eq!(r15.number(), Some(15));
+        assert_eq!(ReferenceDesignator::NoRefDes.number(), None);
+
+        let mut designators: Vec<ReferenceDesignator> = ["R10", "C1", "R2", "R2A", "TP1"]
+            .into_iter()
+            .map(|d| ReferenceDesignator::Any(Either::Left(d)))
+            .collect();
+        designators.push(ReferenceDesignator::Board);
+        designators.push(ReferenceDesignator::NoRefDes);
+        designators.sort();
+        let sorted: Vec<String> = designators.iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            sorted,
+            vec!["C1", "R2", "R2A", "R10", "TP1", "NOREFDES", "BOARD"]
+        );
+        assert_eq!(
+            ReferenceDesignator::Any(Either::Left("U1")),
+            ReferenceDesignator::Any(Either::Right("U1".to_string()))
+        );
+    }
+
+    #[test]
+    fn sort_placements() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let mut board = Idf30::parse(&contents).unwrap();
+        let mut r10 = board.placement[0].clone();
+        r10.designator = ReferenceDesignator::Any(Either::Left("J10"));
+        r10.board_side = BoardSide::Bottom;
+        board.placement.insert(0, r10);
+
+        board.sort_placements();
+        let order: Vec<String> = board
+            .placement
+            .iter()
+            .map(|c| c.designator.to_string())
+            .collect();
+        assert_eq!(order, vec!["J2", "J3", "J10", "NOREFDES"]);
+
+        board.sort_placements_by(&[SortKey::Side, SortKey::Package]);
+        assert_eq!(board.placement[0].package_name.to_string(), "FID_C40M120");
+        assert_eq!(board.placement[3].designator.to_string(), "J10");
+    }
+
+    #[test]
+    fn renumber_designators() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let mut board = Idf30::parse(&contents).unwrap();
+        let mapping = board
+            .renumber_designators(RenumberScheme::Sequential)
+            .unwrap();
+        assert_eq!(mapping["J2"], "J1");
+        assert_eq!(mapping["J3"], "J2");
+        assert_eq!(mapping.len(), 2);
+
+        // J2 is at the bottom right, J1 at the top left
+        let mapping = board
+            .renumber_designators(RenumberScheme::Geographic { row_height: 5.0 })
+            .unwrap();
+        assert_eq!(mapping["J1"], "J2");
+        assert_eq!(mapping["J2"], "J1");
+        assert_eq!(board.placement[2].designator.to_string(), "NOREFDES");
+        assert!(board
+            .renumber_designators(RenumberScheme::Geographic { row_height: 0.0 })
+            .is_err());
+
+        // Suffixes are kept, gates of one part share a number
+        for (d, x) in [
+            ("U15A", 1.0),
+            ("U15B", 2.0),
+            ("U3", 3.0),
+            ("1TP", 4.0),
+            ("J-4", 5.0),
+            ("LOGO", 6.0),
+        ] {
+            board
+                .placement
+                .push(placement("SOIC8", "", d, (x, 0.0, 0.0), BoardSide::Top));
+        }
+        let mapping = board
+            .renumber_designators(RenumberScheme::Sequential)
+            .unwrap();
+        assert_eq!(mapping["U3"], "U1");
+        assert_eq!(mapping["U15A"], "U2A");
+        assert_eq!(mapping["U15B"], "U2B");
+        assert!(!mapping.contains_key("1TP"));
+        assert_eq!(board.placement[6].designator.to_string(), "1TP");
+        // Separators stay, designators without a number are skipped
+        assert_eq!(mapping["J-4"], "J-1");
+        assert!(!mapping.contains_key("LOGO"));
+        assert_eq!(board.placement[8].designator.to_string(), "LOGO");
+    }
+
+    #[test]
+    fn bulk_rename() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let mut board = Idf30::parse(&contents).unwrap();
+        let mapping = HashMap::from([
+            ("TMP-478".to_string(), "TMP-591".to_string()),
+            ("TMP-591".to_string(), "TMP-600".to_string()),
+            ("TMP-999".to_string(), "TMP-000".to_string()),
+        ]);
+        let report = board.rename(RenameField::PartNumber, &mapping);
+        assert_eq!(report.renamed, 2);
+        assert_eq!(report.unmatched, vec![""]);
+        assert_eq!(report.unused, vec!["TMP-999"]);
+        assert_eq!(board.placement[0].part_number.to_string(), "TMP-591");
+        assert_eq!(board.placement[1].part_number.to_string(), "TMP-600");
+
+        let contents = std::fs::read_to_string("./tests/library.idf").unwrap();
+        let mut lib = Idf30::parse(&contents).unwrap();
+        let report = lib.rename_with(RenameField::PackageName, |name| Some(name.to_uppercase()));
+        assert!(report.renamed > 0);
+        if let FileType::LibraryFile { components } = &lib.header.ty {
+            assert_eq!(components[0].geometry_name.to_string(), "CS13_A");
+        }
+
+        // Placements and definitions of an assembly are renamed together
+        let board = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let mut assembly = assembly(&board, &contents);
+        let mapping = HashMap::from([("dip_14w".to_string(), "DIP14".to_string())]);
+        let report = assembly.rename(RenameField::PackageName, &mapping);
+        assert_eq!(report.renamed, 2);
+        assert!(report.unused.is_empty());
+        assert_eq!(
+            assembly.board.placement[0].package_name.to_string(),
+            "DIP14"
+        );
+        assert!(assembly
+            .library
+            .definition("DIP14", "pn-hs346-dip")
+            .is_some());
+        assert_eq!(assembly.unresolved().len(), 1);
+    }
+
+    #[test]
+    fn centroid_csv_export() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let board = Idf30::parse(&contents).unwrap();
+        let csv = board.to_centroid_csv(&CentroidOptions {
+            units: Some(Unit::Mils),
+            origin: (2.7, 43.1),
+        });
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "RefDes,X,Y,Rotation,Side,Package,PartNumber");
+        assert_eq!(lines[3], "NOREFDES,0.0000,0.0000,180.000,TOP,FID_C40M120,");
+        let x: f64 = lines[1].split(',').nth(1).unwrap().parse().unwrap();
+        assert!((x - (84.1509 - 2.7) / 0.0254).abs() < 0.01);
+    }
+
+    #[test]
+    fn centroid_csv_import() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let mut board = Idf30::parse(&contents).unwrap();
+        let csv = "Designator,Mid X,Mid Y,Layer,Rotation,Footprint\n\
+                   J2,10mm,20mm,Bottom,90,HIROSE\n\
+                   R1,1.5,2.5,Top,0,\"R 0603, thin\"\n";
+        let options = CentroidOptions::default();
+        let report = board
+            .clone()
+            .update_from_centroid_csv(csv, &options, false)
+            .unwrap();
+        assert_eq!(report.updated, 1);
+        assert_eq!(report.unmatched, vec!["R1"]);
+
+        let report = board.update_from_centroid_csv(csv, &options, true).unwrap();
+        assert_eq!(report.added, 1);
+        assert_eq!(board.placement[0].x, 10.0);
+        assert_eq!(board.placement[0].board_side, BoardSide::Bottom);
+        assert_eq!(board.placement[3].package_name.to_string(), "R 0603, thin");
+
+        let exported = board.to_centroid_csv(&options);
+        let mut reimported = board.clone();
+        reimported
+            .update_from_centroid_csv(&exported, &options, false)
+            .unwrap();
+        assert!(board.approx_eq(&reimported, 1e-3));
+        assert!(board
+            .update_from_centroid_csv("Designator,X\nJ2,1", &options, false)
+            .is_err());
+
+        // Units on values are converted to the board units
+        let csv = "RefDes,X,Y\nJ2,100mil,0.5in\n";
+        board
+            .update_from_centroid_csv(csv, &options, false)
+            .unwrap();
+        assert!((board.placement[0].x - 2.54).abs() < 1e-4);
+        assert!((board.placement[0].y - 12.7).abs() < 1e-4);
+        assert!(board
+            .update_from_centroid_csv("RefDes,X,Y\nJ2,1cm,0\n", &options, false)
+            .is_err());
+    }
+
+    #[test]
+    fn gerber_x3_component_layers() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let mut board = Idf30::parse(&contents).unwrap();
+        board.placement[0].package_name = Either::Left("dip_14w");
+        board.placement[0].part_number = Either::Left("pn-hs346-dip");
+        let contents = std::fs::read_to_string("./tests/library.idf").unwrap();
+        let lib = Idf30::parse(&contents).unwrap();
+
+        let layers = board.to_gerber_x3(Some(&lib), 4).unwrap();
+        assert!(layers.top.contains("%TF.FileFunction,Component,L1,Top*%"));
+        assert!(layers
+            .bottom
+            .contains("%TF.FileFunction,Component,L4,Bot*%"));
+        assert!(!layers.bottom.contains("%TO.C,"));
+        assert!(layers.top.contains("%TO.C,J2*%\n%TO.CRot,180.000*%"));
+        assert!(layers.top.contains("%TO.CHgt,5.0800*%"));
+        assert_eq!(layers.top.matches("D03*").count(), 3);
+        assert_eq!(layers.top.matches("D02*").count(), 1);
+        assert!(layers.top.ends_with("M02*\n"));
+    }
+
+    #[test]
+    fn ipc2581_export() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let board = Idf30::parse(&contents).unwrap();
+        let xml = board.to_ipc2581().unwrap();
+        assert!(xml.starts_with("<?xml"));
+        assert!(xml.contains("<Step name=\"board.brd\">"));
+        assert!(xml.contains("<PolyBegin x=\"5.0000\" y=\"62.0000\"/>"));
+        assert_eq!(xml.matches("<Component ").count(), 3);
+        assert!(xml.contains("refDes=\"NOREFDES\" packageRef=\"FID_C40M120\" part=\"\""));
+        assert!(xml.contains("diameter=\"1.6002\" platingStatus=\"NONPLATED\""));
+        assert!(xml.trim_end().ends_with("</IPC-2581>"));
+    }
+
+    #[test]
+    fn odb_job_export() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let mut board = Idf30::parse(&contents).unwrap();
+        board.placement[1].package_name = Either::Left("dip_14w");
+        let contents = std::fs::read_to_string("./tests/library.idf").unwrap();
+        let lib = Idf30::parse(&contents).unwrap();
+
+        let job = board.to_odb(Some(&lib)).unwrap();
+        let profile = &job.files["steps/pcb/profile"];
+        assert!(profile.starts_with("UNITS=MM\n#\nS P 0\nOB 5.0000 62.0000 I\n"));
+        let top = &job.files["steps/pcb/layers/comp_+_top/components"];
+        assert!(top.contains("CMP 0 84.1509 -3.4969 180.000 N J2 TMP-478;"));
+        assert!(top.contains("CMP 2 2.7000 43.1000 180.000 N NOREFDES -;"));
+        let eda = &job.files["steps/pcb/eda/data"];
+        assert!(
+            eda.contains("PKG dip_14w 0 -1.2700 -16.5100 8.8900 1.2700;\nCT\nOB 8.8900 1.2700 I")
+        );
+        assert!(job.files.contains_key("matrix/matrix"));
+    }
+
+    #[test]
+    fn typed_drilled_holes() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let mut board = Idf30::parse(&contents).unwrap();
+        let mut holes = board.drilled_holes().unwrap();
+        assert_eq!(holes.len(), 2);
+        assert_eq!(holes[1].plating, Plating::NonPlated);
+        assert_eq!(holes[1].association, ReferenceDesignator::Board);
+        assert_eq!(holes[0].hole_type, HoleType::Pin);
+
+        holes[0].diameter = 1.0;
+        board.set_drilled_holes(&holes);
+        let written = board.to_string();
+        let reparsed = Idf30::parse(&written).unwrap();
+        assert_eq!(reparsed.drilled_holes().unwrap(), holes);
+    }
+
+    #[test]
+    fn excellon_export() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let board = Idf30::parse(&contents).unwrap();
+        let files = board.to_excellon().unwrap();
+        assert_eq!(
+            files.plated,
+            format!(
+                "M48\n; generated by idf {}\n; TYPE=PLATED\nMETRIC,LZ\nT1C1.3970\n%\nG90\nG05\n\
+                 T1\nX65.0000Y60.5000\nM30\n",
+                env!("CARGO_PKG_VERSION")
+            )
+        );
+        assert!(files.non_plated.contains("T1C1.6002\n"));
+        assert!(files.non_plated.contains("T1\nX3.5000Y18.5000\n"));
+    }
+
+    #[test]
+    fn excellon_import() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let mut board = Idf30::parse(&contents).unwrap();
+        let files = board.to_excellon().unwrap();
+        let report = board
+            .merge_excellon(&files.plated, Plating::NonPlated, 1e-3)
+            .unwrap();
+        assert_eq!(report.duplicates, 1);
+        assert_eq!(report.added, 0);
+
+        let drill = "M48\nINCH,TZ\nT1C0.0400\n%\nT1\nX001Y0005\nY002\nM30\n";
+        let report = board.merge_excellon(drill, Plating::Plated, 1e-3).unwrap();
+        assert_eq!(report.added, 2);
+        let holes = board.drilled_holes().unwrap();
+        assert_eq!(holes.len(), 4);
+        assert!((holes[2].diameter - 1.016).abs() < 1e-4);
+        assert!((holes[2].x - 0.00254).abs() < 1e-6);
+        assert!((holes[3].y - 0.00508).abs() < 1e-6);
+        assert!(board
+            .merge_excellon("X1.0Y1.0\n", Plating::Plated, 0.0)
+            .is_err());
+    }
+
+    fn dxf(entities: &[&str]) -> String {
+        let mut s = String::from("0\nSECTION\n2\nENTITIES\n");
+        for e in entities {
+            s.push_str(e);
+        }
+        s.push_str("0\nENDSEC\n0\nEOF\n");
+        s
+    }
+
+    #[test]
+    fn dxf_outline_import() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let mut board = Idf30::parse(&contents).unwrap();
+        // Rectangle with the top edge drawn right to left as a half circle, and a hole
+        let drawing = dxf(&[
+            "0\nLINE\n10\n0\n20\n0\n11\n100\n21\n0\n",
+            "0\nLINE\n10\n0\n20\n50\n11\n0\n21\n0\n",
+            "0\nLINE\n10\n100\n20\n0\n11\n100\n21\n50\n",
+            "0\nARC\n10\n50\n20\n50\n40\n50\n50\n0\n51\n180\n",
+            "0\nCIRCLE\n10\n20\n20\n20\n40\n2\n",
+        ]);
+        board
+            .import_dxf_outline(&drawing, "BOARD_OUTLINE", Unit::SImm)
+            .unwrap();
+        let outline = board
+            .other_sections
+            .iter()
+            .find(|s| s.name() == "BOARD_OUTLINE")
+            .unwrap();
+        assert_eq!(outline.records()[0], vec![IdfValue::Float(0.7013)]);
+        let bbox = outline.bounding_box().unwrap();
+        assert!((bbox.max_y - 100.0).abs() < 1e-6);
+        assert!((bbox.max_x - 100.0).abs() < 1e-6);
+        let labels: Vec<&IdfValue> = outline.records()[1..].iter().map(|r| &r[0]).collect();
+        assert_eq!(
+            labels
+                .iter()
+                .filter(|l| ***l == IdfValue::Integer(0))
+                .count(),
+            5
+        );
+        assert_eq!(
+            labels
+                .iter()
+                .filter(|l| ***l == IdfValue::Integer(1))
+                .count(),
+            2
+        );
+        // The cutout circle runs clockwise
+        assert_eq!(
+            outline.records().last().unwrap()[3],
+            IdfValue::Float(-360.0)
+        );
+
+        let open = dxf(&["0\nLINE\n10\n0\n20\n0\n11\n100\n21\n0\n"]);
+        assert!(board
+            .import_dxf_outline(&open, "BOARD_OUTLINE", Unit::SImm)
+            .is_err());
+
+        let square = dxf(&["0\nLWPOLYLINE\n90\n2\n70\n1\n10\n0\n20\n0\n42\n1\n10\n10\n20\n0\n"]);
+        board
+            .import_dxf_outline(&square, "VIA_KEEPOUT", Unit::Mils)
+            .unwrap();
+        let keepout = board
+            .other_sections
+            .iter()
+            .find(|s| s.name() == "VIA_KEEPOUT")
+            .unwrap();
+        assert_eq!(keepout.records().len(), 3);
+        assert_eq!(keepout.records()[1][3], IdfValue::Float(180.0));
+        assert!((keepout.bounding_box().unwrap().max_x - 0.254).abs() < 1e-6);
+    }
+
+    #[test]
+    fn svg_path_import() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let mut board = Idf30::parse(&contents).unwrap();
+        // 20 x 10 keepout with rounded right end, drawn clockwise on screen
+        board
+            .import_svg_path(
+                "M0,0 H20 a5,5 0 0,1 0,10 h-20 z",
+                "ROUTE_KEEPOUT",
+                Unit::SImm,
+            )
+            .unwrap();
+        let keepout = board
+            .other_sections
+            .iter()
+            .find(|s| s.name() == "ROUTE_KEEPOUT")
+            .unwrap();
+        let bbox = keepout.bounding_box().unwrap();
+        assert!((bbox.min_y + 10.0).abs() < 1e-6);
+        assert!((bbox.max_x - 25.0).abs() < 1e-3);
+        // Loop is stored counterclockwise, so the arc turns left
+        let arc = keepout
+            .records()
+            .iter()
+            .find(|r| r.len() == 4 && r[3] != IdfValue::Float(0.0))
+            .unwrap();
+        assert_eq!(arc[3], IdfValue::Float(180.0));
+
+        assert!(board
+            .import_svg_path("M0 0 C1 1 2 2 3 3 z", "ROUTE_KEEPOUT", Unit::SImm)
+            .is_err());
+    }
+
+    #[test]
+    fn board_stats() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let mut board = Idf30::parse(&contents).unwrap();
+        let stats = board.stats().unwrap();
+        assert_eq!(stats.components, 3);
+        assert_eq!(stats.per_side[&BoardSide::Top], 3);
+        assert_eq!(stats.per_package["FID_C40M120"], 1);
+        assert_eq!(stats.per_status[&PlacementStatus::ECad], 2);
+        assert_eq!(stats.per_status[&PlacementStatus::Placed], 1);
+        assert_eq!(stats.hole_count(), 2);
+        assert_eq!(stats.holes_by_diameter[0], (1.397, 1));
+
+        let square = "0\nSECTION\n2\nENTITIES\n0\nLWPOLYLINE\n70\n1\n10\n0\n20\n0\n10\n10\n20\n0\n\
+                      10\n10\n20\n10\n10\n0\n20\n10\n0\nCIRCLE\n10\n5\n20\n5\n40\n1\n0\nENDSEC\n";
+        board
+            .import_dxf_outline(square, "BOARD_OUTLINE", Unit::SImm)
+            .unwrap();
+        let stats = board.stats().unwrap();
+        let area = stats.outline_area.unwrap();
+        assert!((area - (100.0 - std::f64::consts::PI)).abs() < 0.01);
+        assert!((stats.dimensions.unwrap().width() - 10.0).abs() < 1e-9);
+
+        // A bad hole record is skipped and counted
+        let holes = board
+            .other_sections
+            .iter_mut()
+            .find(|s| s.name() == "DRILLED_HOLES")
+            .unwrap();
+        holes.records_mut()[0].truncate(3);
+        let stats = board.stats().unwrap();
+        assert_eq!((stats.hole_count(), stats.malformed_holes), (1, 1));
+        let (holes, errors) = board.drilled_holes_lossy();
+        assert_eq!(holes.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            ".DRILLED_HOLES record 0: Expected 7 fields per drilled hole"
+        );
+    }
+
+    fn placement<'a>(
+        package: &'a str,
+        part: &'a str,
+        designator: &'a str,
+        (x, y, rotation): (f32, f32, f32),
+        board_side: BoardSide,
+    ) -> ComponentPlacement<'a> {
+        ComponentPlacement::new(package, part, designator).placed_at(x, y, rotation, board_side)
+    }
+
+    fn assembly<'a>(board: &'a str, library: &'a str) -> IdfAssembly<'a> {
+        let mut board = Idf30::parse(board).unwrap();
+        board.placement = vec![
+            placement(
+                "dip_14w",
+                "pn-hs346-dip",
+                "U1",
+                (20.0, 20.0, 0.0),
+                BoardSide::Top,
+            ),
+            placement(
+                "cc1210",
+                "pn-cc1210",
+                "C1",
+                (10.0, 40.0, 90.0),
+                BoardSide::Bottom,
+            ),
+            placement("unknown", "", "X1", (0.0, 0.0, 0.0), BoardSide::Top),
+        ];
+        IdfAssembly::new(board, Idf30::parse(library).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn component_heights() {
+        let board = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let library = std::fs::read_to_string("./tests/library.idf").unwrap();
+        let mut assembly = assembly(&board, &library);
+        assert_eq!(assembly.unresolved().len(), 1);
+        let (top, height) = assembly.max_height(BoardSide::Top).unwrap();
+        assert_eq!(top.designator.to_string(), "U1");
+        assert!((height - 5.08).abs() < 1e-4);
+        let (_, height) = assembly.max_height(BoardSide::Bottom).unwrap();
+        assert!((height - 1.7018).abs() < 1e-4);
+
+        assembly
+            .board
+            .other_sections
+            .retain(|s| s.name() != "BOARD_OUTLINE");
+        let map = assembly.height_map(BoardSide::Top, 1.0).unwrap();
+        assert_eq!((map.cols, map.rows), (11, 18));
+        assert_eq!(map.get(0, 0), Some(5.08));
+        assert_eq!(map.get(9, 16), Some(5.08));
+        assert_eq!(map.get(11, 0), None);
+    }
+
+    #[test]
+    fn component_collisions() {
+        let board = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let library = std::fs::read_to_string("./tests/library.idf").unwrap();
+        let mut assembly = assembly(&board, &library);
+        assert!(assembly.collisions(0.0).is_empty());
+        // Second DIP 1 mm to the right of the first one's body, and one overlapping it
+        assembly.board.placement.extend([
+            placement(
+                "dip_14w",
+                "pn-hs346-dip",
+                "U2",
+                (31.16, 20.0, 0.0),
+                BoardSide::Top,
+            ),
+            placement(
+                "dip_14w",
+                "pn-hs346-dip",
+                "U3",
+                (20.0, 5.0, 0.0),
+                BoardSide::Top,
+            ),
+            placement(
+                "dip_14w",
+                "pn-hs346-dip",
+                "U4",
+                (20.0, 5.0, 0.0),
+                BoardSide::Bottom,
+            ),
+        ]);
+        let collisions = assembly.collisions(0.0);
+        assert_eq!(collisions.len(), 1);
+        assert_eq!(
+            (collisions[0].first.as_str(), collisions[0].second.as_str()),
+            ("U1", "U3")
+        );
+        assert_eq!(collisions[0].distance, 0.0);
+        let collisions = assembly.collisions(1.5);
+        assert_eq!(collisions.len(), 3);
+        assert!((collisions[0].distance - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn clearance_report() {
+        let board = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let library = std::fs::read_to_string("./tests/library.idf").unwrap();
+        let mut assembly = assembly(&board, &library);
+        assembly.board.placement.extend([
+            placement(
+                "dip_14w",
+                "pn-hs346-dip",
+                "U2",
+                (31.16, 20.0, 0.0),
+                BoardSide::Top,
+            ),
+            placement(
+                "dip_14w",
+                "pn-hs346-dip",
+                "U3",
+                (20.0, 5.0, 0.0),
+                BoardSide::Top,
+            ),
+        ]);
+        let all = assembly.clearance_report(&[], &[], 1.5);
+        assert_eq!(all, assembly.collisions(1.5));
+        assert_eq!(assembly.clearance_report(&["U2"], &[], 1.5).len(), 2);
+        let report = assembly.clearance_report(&["U2"], &["U1", "C1"], 1.5);
+        assert_eq!(report.len(), 1);
+        assert_eq!(
+            (report[0].first.as_str(), report[0].second.as_str()),
+            ("U1", "U2")
+        );
+        assert!(assembly.clearance_report(&["U2"], &["U1"], 0.5).is_empty());
+    }
+
+    #[test]
+    fn keepout_violations() {
+        let board = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let library = std::fs::read_to_string("./tests/library.idf").unwrap();
+        let mut assembly = assembly(&board, &library);
+        // 2 mm high keepout on the top side from y = -1 to 5, over U1's lower edge and X1
+        assembly
+            .board
+            .import_svg_path("M-1,1 H25 V-5 H-1 Z", "PLACE_KEEPOUT", Unit::SImm)
+            .unwrap();
+        let keepout = assembly.board.other_sections.last_mut().unwrap();
+        keepout.records_mut()[0] =
+            vec![IdfValue::String(Either::Left("TOP")), IdfValue::Float(2.0)];
+        let violations = assembly.keepout_violations(0.0, true);
+        let designators: Vec<&str> = violations.iter().map(|v| v.designator.as_str()).collect();
+        assert_eq!(designators, ["U1", "X1"]);
+        assert_eq!(violations[0].height, Some(5.08));
+        assert_eq!(violations[0].max_height, 2.0);
+
+        // C1 is on the bottom side, the keepout only applies to the top
+        assert_eq!(assembly.keepout_violations(10.0, true).len(), 2);
+        assembly.board.placement[0].y = 27.0;
+        let violations = assembly.keepout_violations(10.0, true);
+        assert!((violations[0].distance - 5.49).abs() < 1e-4);
+    }
+
+    #[test]
+    fn off_board_components() {
+        let board = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let library = std::fs::read_to_string("./tests/library.idf").unwrap();
+        let mut assembly = assembly(&board, &library);
+        assembly
+            .board
+            .import_svg_path(
+                "M0,0 H50 V-50 H0 Z M5,-35 h4 v-4 h-4 z M30,-5 h2 v-2 h-2 z",
+                "BOARD_OUTLINE",
+                Unit::SImm,
+            )
+            .unwrap();
+        // C1 overlaps the first cutout, X1 sits just outside the corner
+        assembly.board.placement[2].x = -1.0;
+        assembly.board.placement.extend([
+            placement("unknown", "", "X2", (60.0, 10.0, 0.0), BoardSide::Top),
+            placement("unknown", "", "X3", (7.0, 37.0, 0.0), BoardSide::Top),
+            placement(
+                "dip_14w",
+                "pn-hs346-dip",
+                "U2",
+                (45.0, 20.0, 0.0),
+                BoardSide::Top,
+            ),
+        ]);
+        let reasons: Vec<(String, OffBoardReason)> = assembly
+            .off_board_components()
+            .into_iter()
+            .map(|o| (o.designator, o.reason))
+            .collect();
+        assert_eq!(
+            reasons,
+            [
+                ("C1".to_string(), OffBoardReason::CrossesEdge),
+                ("X1".to_string(), OffBoardReason::Outside),
+                ("X2".to_string(), OffBoardReason::Outside),
+                ("X3".to_string(), OffBoardReason::InCutout),
+                ("U2".to_string(), OffBoardReason::CrossesEdge),
+            ]
+        );
+        assembly.board.placement[0].x = 26.0;
+        assembly.board.placement[0].y = 21.0;
+        assert!(assembly
+            .off_board_components()
+            .iter()
+            .any(|o| o.designator == "U1" && o.reason == OffBoardReason::CoversCutout));
+    }
+
+    #[test]
+    fn nearest_component_and_clearance() {
+        let board = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let library = std::fs::read_to_string("./tests/library.idf").unwrap();
+        let assembly = assembly(&board, &library);
+        let index = assembly.component_index();
+        let (nearest, distance) = index.nearest_component(20.0, 10.0).unwrap();
+        assert_eq!(nearest.placement.designator.to_string(), "U1");
+        assert_eq!(distance, 0.0);
+        let (nearest, distance) = index.nearest_component(31.89, 10.0).unwrap();
+        assert_eq!(nearest.placement.designator.to_string(), "U1");
+        assert!((distance - 3.0).abs() < 1e-6);
+        let (nearest, distance) = index.nearest_component(-3.0, -4.0).unwrap();
+        assert_eq!(nearest.placement.designator.to_string(), "X1");
+        assert!((distance - 5.0).abs() < 1e-6);
+
+        // X1 reference point to the corner of U1's body
+        let clearance = assembly.clearance("U1", "X1").unwrap();
+        assert!((clearance - 18.73f64.hypot(3.49)).abs() < 1e-4);
+        assert_eq!(assembly.clearance("U1", "U9"), None);
+    }
+
+    #[test]
+    fn holes_per_component() {
+        let board = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let library = std::fs::read_to_string("./tests/library.idf").unwrap();
+        let mut assembly = assembly(&board, &library);
+        let hole = |x: f32, y: f32, association| DrilledHole {
+            diameter: 0.8,
+            x,
+            y,
+            plating: Plating::Plated,
+            association,
+            hole_type: HoleType::Pin,
+            owner: Owner::ECad,
+        };
+        let c1 = ReferenceDesignator::Any(Either::Left("C1"));
+        assembly.board.set_drilled_holes(&[
+            hole(0.0, 0.0, c1),
+            hole(20.0, 10.0, ReferenceDesignator::NoRefDes),
+            hole(29.0, 10.0, ReferenceDesignator::NoRefDes),
+            hole(35.0, 10.0, ReferenceDesignator::NoRefDes),
+            hole(20.0, 10.0, ReferenceDesignator::Board),
+        ]);
+        let holes = assembly.component_holes(0.5).unwrap();
+        assert_eq!(holes.len(), 2);
+        assert_eq!(holes[0].placement.designator.to_string(), "U1");
+        assert_eq!(holes[0].holes.len(), 2);
+        assert_eq!(holes[1].placement.designator.to_string(), "C1");
+        assert_eq!(holes[1].holes[0].x, 0.0);
+    }
+
+    #[test]
+    fn section_and_hole_owners() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let mut board = Idf30::parse(&contents).unwrap();
+        assert_eq!(
+            board.section("BOARD_OUTLINE").unwrap().owner(),
+            Some(Owner::ECad)
+        );
+        assert_eq!(board.section("DRILLED_HOLES").unwrap().owner(), None);
+        assert_eq!(board.drilled_holes().unwrap()[0].owner, Owner::Unowned);
+        assert!("OTHER".parse::<Owner>().is_err());
+
+        board.other_sections[1].set_owner(Owner::MCad);
+        let written = board.to_string();
+        assert!(written.contains(".ROUTE_KEEPOUT MCAD"));
+        let board = Idf30::parse(&written).unwrap();
+        assert_eq!(
+            board.section("ROUTE_KEEPOUT").unwrap().owner(),
+            Some(Owner::MCad)
+        );
+    }
+
+    #[test]
+    fn hand_over_ownership() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let mut board = Idf30::parse(&contents).unwrap();
+        let changed = board.set_owner(|s| s.name() != "BOARD_OUTLINE", Owner::MCad);
+        assert_eq!(changed, 3);
+        assert_eq!(
+            board.section("BOARD_OUTLINE").unwrap().owner(),
+            Some(Owner::ECad)
+        );
+        assert_eq!(
+            board.section("ROUTE_KEEPOUT").unwrap().owner(),
+            Some(Owner::MCad)
+        );
+        assert!(board
+            .drilled_holes()
+            .unwrap()
+            .iter()
+            .all(|h| h.owner == Owner::MCad));
+        assert_eq!(
+            board.set_owner(|s| s.name() == "ROUTE_KEEPOUT", Owner::MCad),
+            0
+        );
+    }
+
+    #[test]
+    fn three_way_merge() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let base = Idf30::parse(&contents).unwrap();
+        let mut ecad = base.clone();
+        ecad.placement[1].x += 1.0;
+        ecad.placement[2].x = 10.0;
+        ecad.placement.push(placement(
+            "cc1210",
+            "pn-cc1210",
+            "C5",
+            (1.0, 2.0, 0.0),
+            BoardSide::Top,
+        ));
+        let mut mcad = base.clone();
+        mcad.placement[0].x += 1.0;
+        mcad.placement[2].x = 11.0;
+        mcad.other_sections[1].records_mut().pop();
+
+        let diff = base.diff(&ecad, 1e-4);
+        assert_eq!(diff.placements.len(), 3);
+        assert!(matches!(diff.placements[2].change, Change::Added(_)));
+        assert_eq!(diff.placements[2].key, "C5");
+        assert!(diff.sections.is_empty());
+
+        // Repeated designators are matched by their order
+        let mut repeated = base.clone();
+        repeated.placement.push(base.placement[2].clone());
+        let mut moved = repeated.clone();
+        moved.placement[3].x += 1.0;
+        let keys: Vec<String> = repeated
+            .diff(&moved, 1e-4)
+            .placements
+            .into_iter()
+            .map(|c| c.key)
+            .collect();
+        assert_eq!(keys, ["NOREFDES[1]"]);
+
+        let merge = Idf30::merge(&base, &ecad, &mcad, 1e-4).unwrap();
+        let conflicts: Vec<(&str, &ConflictKind)> = merge
+            .conflicts
+            .iter()
+            .map(|c| (c.item.as_str(), &c.kind))
+            .collect();
+        assert_eq!(
+            conflicts,
+            [
+                ("J2", &ConflictKind::NotOwner(Owner::ECad)),
+                ("NOREFDES", &ConflictKind::BothChanged),
+                ("ROUTE_KEEPOUT", &ConflictKind::NotOwner(Owner::ECad)),
+            ]
+        );
+        let merged = &merge.merged;
+        assert_eq!(merged.placement.len(), 4);
+        assert_eq!(merged.placement[0].x, base.placement[0].x);
+        assert_eq!(merged.placement[1].x, ecad.placement[1].x);
+        assert_eq!(merged.placement[2].x, 2.7);
+        assert!(merged
+            .section("ROUTE_KEEPOUT")
+            .unwrap()
+            .approx_eq(base.section("ROUTE_KEEPOUT").unwrap(), 0.0));
+
+        // Holes are merged one by one, with the owner of each record
+        let unchanged = Idf30::merge(&base, &base, &base, 1e-4).unwrap();
+        assert_eq!(unchanged.merged.to_string(), base.to_string());
+        let hole = |x: f64, owner: &'static str| {
+            vec![
+                IdfValue::Float(1.0),
+                IdfValue::Float(x),
+                IdfValue::Float(0.0),
+                IdfValue::String(Either::Left("PTH")),
+                IdfValue::String(Either::Left("BOARD")),
+                IdfValue::String(Either::Left("VIA")),
+                IdfValue::String(Either::Left(owner)),
+            ]
+        };
+        let mut base = base;
+        base.other_sections[2]
+            .records_mut()
+            .push(hole(30.0, "ECAD"));
+        let (mut ecad, mut mcad) = (base.clone(), base.clone());
+        ecad.other_sections[2]
+            .records_mut()
+            .push(hole(10.0, "ECAD"));
+        mcad.other_sections[2]
+            .records_mut()
+            .push(hole(20.0, "MCAD"));
+        // A hole of another diameter is another hole, neither may be changed by MCAD
+        mcad.other_sections[2].records_mut()[2][0] = IdfValue::Float(2.0);
+        let merge = Idf30::merge(&base, &ecad, &mcad, 1e-4).unwrap();
+        assert_eq!(
+            merge.conflicts,
+            ["DRILLED_HOLES(30, 0, 1)", "DRILLED_HOLES(30, 0, 2)"].map(|item| MergeConflict {
+                item: item.to_string(),
+                kind: ConflictKind::NotOwner(Owner::ECad),
+            })
+        );
+        let merged = merge.merged.section("DRILLED_HOLES").unwrap().records();
+        assert_eq!(merged.len(), 5);
+        assert_eq!(merged[2], hole(30.0, "ECAD"));
+        assert_eq!(merged[3], hole(10.0, "ECAD"));
+        assert_eq!(merged[4], hole(20.0, "MCAD"));
+    }
+
+    #[test]
+    fn idx_baseline_and_changes() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let base = Idf30::parse(&contents).unwrap();
+        let baseline = base.to_idx_baseline(None).unwrap();
+        assert!(baseline
+            .contains("<foundation:Item id=\"SEC_BOARD_OUTLINE\" geometryType=\"BOARD_OUTLINE\">"));
+        assert!(baseline.contains("id=\"SEC_DRILLED_HOLES_NPTH\" geometryType=\"HOLE_NON_PLATED\""));
+        assert!(baseline.contains("<foundation:Item id=\"CMP_J2\" geometryType=\"COMPONENT\">"));
+        assert!(baseline
+            .contains("<d2:UpperBound><property:Value>0.7013</property:Value></d2:UpperBound>"));
+        assert!(baseline.contains("EDMDProcessInstructionSendInformation"));
+
+        let mut revision = base.clone();
+        revision.placement[1].x += 1.0;
+        revision.other_sections.remove(1);
+        let changes = base
+            .diff(&revision, 1e-4)
+            .to_idx_changes("board.brd", &Unit::SImm, None)
+            .unwrap();
+        assert!(changes
+            .contains("<computational:DeletedItem>SEC_ROUTE_KEEPOUT</computational:DeletedItem>"));
+        assert!(changes.contains("<computational:Item>CMP_J3</computational:Item>"));
+        assert!(changes.contains("<pdm:tx><property:Value>72.5020</property:Value></pdm:tx>"));
+        assert!(!changes.contains("CMP_J2"));
+    }
+
+    #[test]
+    fn idf20_writer() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let mut board = Idf30::parse(&contents).unwrap();
+        board.placement[0].z = 0.5;
+        board
+            .import_svg_path("M0,0 h1 v1 h-1 z", "VIA_KEEPOUT", Unit::SImm)
+            .unwrap();
+        let (written, warnings) = board.to_idf20();
+        assert!(written.starts_with(".HEADER\nBOARD_FILE 2.0 "));
+        assert!(written.contains(".BOARD_OUTLINE\n  0.7013\n"));
+        assert!(written.contains(".ROUTE_KEEPOUT\n  0 3.5000 18.5000 0.0000\n"));
+        assert!(written.contains("  1.3970 65.0000 60.5000 PTH U? PIN\n"));
+        assert!(written.contains("\n  84.1509 -3.4969 180.000 TOP PLACED\n"));
+        assert!(!written.contains("VIA_KEEPOUT"));
+        assert_eq!(
+            warnings,
+            [
+                Idf20Warning::DroppedOwner("BOARD_OUTLINE".to_string()),
+                Idf20Warning::DroppedKeepoutAttributes("ROUTE_KEEPOUT".to_string()),
+                Idf20Warning::DroppedOwner("ROUTE_KEEPOUT".to_string()),
+                Idf20Warning::DroppedSection("VIA_KEEPOUT".to_string()),
+                Idf20Warning::DroppedOffset("J2".to_string()),
+                Idf20Warning::ChangedStatus("J2".to_string()),
+                Idf20Warning::ChangedStatus("J3".to_string()),
+            ]
+        );
+
+        board.header.source = Either::Left("Altium Designer");
+        board.header.ty = FileType::BoardFile {
+            board_name: Either::Left("main board"),
+            units: Unit::SImm,
+        };
+        let (written, _) = board.to_idf20();
+        assert!(written.starts_with(
+            ".HEADER\nBOARD_FILE 2.0 \"Altium Designer\" 2023/10/18.14:05:44 1\n\"main board\" MM\n"
+        ));
+    }
+
+    #[test]
+    fn load_board_library_pair() {
+        let dir = std::env::temp_dir().join(format!("idf_pair_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::copy("./tests/board.idf", dir.join("board.emn")).unwrap();
+        std::fs::copy("./tests/library.idf", dir.join("board.emp")).unwrap();
+        std::fs::copy("./tests/board.idf", dir.join("lonely.BDF")).unwrap();
+
+        let assembly = idf::load_pair(dir.join("board.emn")).unwrap();
+        assert_eq!(assembly.board.board_name(), Some("board.brd"));
+        assert!(assembly.library.definition("cc1210", "pn-cc1210").is_some());
+
+        match idf::load_pair(dir.join("lonely.BDF")) {
+            Err(idf::idf30::Error::IncompletePair(path)) => {
+                assert_eq!(path, dir.join("lonely.ldf"))
+            }
+            other => panic!("expected missing library, got {other:?}"),
+        }
+        assert!(idf::load_pair(dir.join("board.txt")).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn load_project_directory() {
+        let dir = std::env::temp_dir().join(format!("idf_project_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let board = Idf30::parse(&contents).unwrap();
+        let panel = board
+            .panelize(&PanelSpec {
+                rows: 1,
+                cols: 2,
+                spacing_x: 100.0,
+                spacing_y: 0.0,
+                rotation: 0.0,
+                rails: Rails::default(),
+                merge_holes: false,
+            })
+            .unwrap();
+        std::fs::write(dir.join("main.emn"), &contents).unwrap();
+        std::fs::copy("./tests/library.idf", dir.join("main.emp")).unwrap();
+        std::fs::write(dir.join("array.idf"), panel.to_string()).unwrap();
+        std::fs::write(dir.join("notes.txt"), "not an IDF file").unwrap();
+        std::fs::write(dir.join("broken.emn"), ".HEADER\nBOARD_FILE 3.0\n").unwrap();
+
+        let project = Project::load(&dir).unwrap();
+        assert_eq!(project.files.len(), 3);
+        assert_eq!(project.errors.len(), 1);
+        assert!(project.errors[0].0.ends_with("broken.emn"));
+        let kinds: Vec<FileKind> = project.files.iter().map(|f| f.kind).collect();
+        assert_eq!(kinds, [FileKind::Panel, FileKind::Board, FileKind::Library]);
+        assert_eq!(project.files[0].boards, [1]);
+        assert_eq!(project.files[1].library, Some(2));
+        assert_eq!(project.boards().count(), 1);
+        assert!(project.assembly(1).is_some());
+        assert!(project.assembly(0).is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn header_dates() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let mut board = Idf30::parse(&contents).unwrap();
+        let date = board.header.parsed_date().unwrap();
+        assert_eq!((date.year, date.month, date.day), (2023, 10, 18));
+        assert_eq!((date.hour, date.minute, date.second), (14, 5, 44));
+        assert_eq!(date.to_string(), "2023/10/18.14:05:44");
+        for variant in [
+            "2023-10-18.14:05:44",
+            "2023/10/18T14:05:44",
+            "2023/10/18.14:05:00",
+            "2023/10/18.14:05",
+            "2023/10/8.14:05:44",
+            "2023-1-18-14:5",
+        ] {
+            let parsed: HeaderDate = variant.parse().unwrap();
+            assert_eq!((parsed.year, parsed.minute), (2023, 5));
+        }
+        assert!("18/10/2023".parse::<HeaderDate>().is_err());
+        assert!("2023/13/18".parse::<HeaderDate>().is_err());
+        assert!("2023/10/18 14:05".parse::<HeaderDate>().is_err());
+        assert!("2023/10.14:05".parse::<HeaderDate>().is_err());
+
+        assert_eq!(
+            HeaderDate::from_unix(951825600).to_string(),
+            "2000/02/29.12:00:00"
+        );
+        board.header.touch();
+        assert!(board.header.parsed_date().unwrap() > date);
+        let reparsed = Idf30::parse(&board.to_string())
+            .unwrap()
+            .header
+            .parsed_date();
+        assert_eq!(reparsed, board.header.parsed_date());
+    }
+
+    #[test]
+    fn new_board_header() {
+        let mut header = Header::new_board("generated", Unit::Mils);
+        assert_eq!(header.board_file_version, 1);
+        assert!(header.source.starts_with("idf_"));
+        assert!(header.parsed_date().is_some());
+        header.bump_version();
+        assert_eq!(header.board_file_version, 2);
+
+        let board = Idf30 {
+            header,
+            placement: vec![],
+            other_sections: vec![],
+            section_order: vec![],
+            warnings: vec![],
+        };
+        let written = board.to_string();
+        let parsed = Idf30::parse(&written).unwrap();
+        assert_eq!(parsed.board_name(), Some("generated"));
+        assert_eq!(parsed.units(), Some(&Unit::Mils));
+        assert_eq!(parsed.header.board_file_version, 2);
+    }
+
+    #[test]
+    fn placement_lookup() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let mut board = Idf30::parse(&contents).unwrap();
+        let index = board.placement_index();
+        assert_eq!(index.len(), 2);
+        assert_eq!(board.placement[index["J3"]].x, 71.502);
+        assert!(!index.contains_key("NOREFDES"));
+        assert_eq!(board.get_placement("J2").unwrap().rotation, 180.0);
+        assert!(board.get_placement("NOREFDES").is_none());
+        board.get_placement_mut("J2").unwrap().x = 1.0;
+        assert_eq!(board.placement[index["J2"]].x, 1.0);
+    }
+
+    #[test]
+    fn classify_components() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let mut board = Idf30::parse(&contents).unwrap();
+        board.placement.push(placement(
+            "TP_10",
+            "",
+            "tp3",
+            (1.0, 1.0, 0.0),
+            BoardSide::Bottom,
+        ));
+        board.placement.push(placement(
+            "MTG_M3",
+            "",
+            "H2",
+            (5.0, 5.0, 0.0),
+            BoardSide::Top,
+        ));
+
+        let fiducials: Vec<_> = board.fiducials().map(|p| &*p.package_name).collect();
+        assert_eq!(fiducials, vec!["FID_C40M120"]);
+        let test_points: Vec<_> = board
+            .test_points()
+            .map(|p| p.designator.to_string())
+            .collect();
+        assert_eq!(test_points, vec!["tp3"]);
+        // The designator check follows the default classifier, in any case
+        assert!(board.placement[3].designator.is_test_point());
+        assert!(ReferenceDesignator::Any(Either::Left("TP3")).is_test_point());
+        assert!(!ReferenceDesignator::NoRefDes.is_test_point());
+
+        let mut classifier = Classifier::default();
+        assert_eq!(classifier.mounting_holes(&board).count(), 1);
+        classifier.mounting_hole = Rule::new(&["J#"], &[]);
+        let holes: Vec<_> = classifier
+            .mounting_holes(&board)
+            .map(|p| p.designator.to_string())
+            .collect();
+        assert_eq!(holes, vec!["J2", "J3"]);
+    }
+
+    #[test]
+    fn bom_groups_by_part_number() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let mut board = Idf30::parse(&contents).unwrap();
+        board.placement.clear();
+        for d in ["C3", "C1", "C2", "C4", "C7", "C10", "C11", "C05"] {
+            board.placement.push(placement(
+                "0603",
+                "CAP-100N",
+                d,
+                (0.0, 0.0, 0.0),
+                BoardSide::Top,
+            ));
+        }
+        board.placement.push(placement(
+            "0603",
+            "CAP-100N",
+            "C8",
+            (0.0, 0.0, 0.0),
+            BoardSide::Top,
+        ));
+        board.placement.last_mut().unwrap().placement_status = PlacementStatus::Unplaced;
+        board.placement.push(placement(
+            "0805",
+            "CAP-100N",
+            "C9",
+            (0.0, 0.0, 0.0),
+            BoardSide::Top,
+        ));
+        board.placement.push(placement(
+            "0402",
+            "RES,1K",
+            "R1",
+            (0.0, 0.0, 0.0),
+            BoardSide::Top,
+        ));
+        board.placement.last_mut().unwrap().designator = ReferenceDesignator::NoRefDes;
+
+        let bom = board.bom();
+        assert_eq!(bom.len(), 3);
+        assert_eq!(bom[0].package, "0603");
+        assert_eq!(bom[0].quantity, 8);
+        assert_eq!(bom[0].designator_ranges(), "C1-C4, C05, C7, C10, C11");
+        assert_eq!(bom[1].designators, vec!["C9"]);
+        assert_eq!(bom[2].quantity, 1);
+        assert!(bom[2].designators.is_empty());
+        assert_eq!(
+            board.to_bom_csv(),
+            "PartNumber,Package,Quantity,RefDes\n\
+             CAP-100N,0603,8,\"C1-C4, C05, C7, C10, C11\"\n\
+             CAP-100N,0805,1,C9\n\
+             \"RES,1K\",0402,1,\n"
+        );
+    }
+
+    #[test]
+    fn generated_footprints() {
+        assert_eq!(
+            PackageOutline::from_name("R0603"),
+            Some(PackageOutline::Chip(ChipSize::C0603))
+        );
+        assert_eq!(PackageOutline::from_name("R06030"), None);
+        assert_eq!(
+            PackageOutline::from_name("SOIC-8_3.9x4.9mm").map(|p| p.extent()),
+            Some((6.0, 4.9, 1.75))
+        );
+        assert_eq!(
+            PackageOutline::from_name("QFN-32_5x5mm"),
+            Some(PackageOutline::Qfn {
+                body: (5.0, 5.0),
+                height: 0.9
+            })
+        );
+        assert_eq!(
+            PackageOutline::from_name("LQFP-64_10x10mm").map(|p| p.extent()),
+            Some((12.0, 12.0, 1.6))
+        );
+        assert_eq!(PackageOutline::from_name("QFN-32"), None);
+
+        let definition =
+            PackageOutline::Chip(ChipSize::C0402).definition("0402", "CAP", Unit::Mils);
+        assert_eq!(definition.loops[0].points.len(), 5);
+        assert!((definition.loops[0].points[2].x - 19.685).abs() < 1e-3);
+        assert!((definition.height - 13.780).abs() < 1e-3);
+
+        let contents = std::fs::read_to_string("./tests/library.idf").unwrap();
+        let mut library = Idf30::parse(&contents).unwrap();
+        let board_contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let mut board = Idf30::parse(&board_contents).unwrap();
+        board.placement.push(placement(
+            "C0805",
+            "",
+            "C5",
+            (0.0, 0.0, 0.0),
+            BoardSide::Top,
+        ));
+        board.placement.push(placement(
+            "C0805",
+            "",
+            "C6",
+            (0.0, 0.0, 0.0),
+            BoardSide::Top,
+        ));
+        let added = library.fill_missing_definitions(&board, Unit::SImm, PackageOutline::from_name);
+        assert_eq!(added, 1);
+        let c = library.definition("C0805", "").unwrap();
+        assert!((c.height - 0.6).abs() < 1e-6);
+        assert!(library.to_string().contains("C0805"));
+    }
+
+    #[test]
+    fn synthesize_library_from_sizes() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let board = Idf30::parse(&contents).unwrap();
+        let sizes = HashMap::from([(
+            "HIROSE_BM50U-4DP".to_string(),
+            PackageSize {
+                x: 10.0,
+                y: 4.0,
+                height: 1.5,
+            },
+        )]);
+        let synthesized = board.synthesize_library(&sizes).unwrap();
+        assert_eq!(
+            synthesized.missing,
+            vec!["HIROSE_BM28B0610DS2035V51", "FID_C40M120"]
+        );
+        let written = synthesized.library.to_string();
+        let library = Idf30::parse(&written).unwrap();
+        let definition = library.definition("HIROSE_BM50U-4DP", "TMP-591").unwrap();
+        assert_eq!(definition.height, 1.5);
+        assert_eq!(definition.loops[0].points[2].x, 5.0);
+        assert_eq!(definition.loops[0].points[2].y, 2.0);
+        assert!(
+            matches!(&library.header.ty, FileType::LibraryFile { components } if components.len() == 1)
+        );
+    }
+
+    #[test]
+    fn shared_strings() {
+        let mut pool = StringPool::new();
+        let shared = {
+            let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+            let mut board = Idf30::parse(&contents).unwrap();
+            let copy = board.placement[1].clone();
+            board.placement.push(copy);
+            pool.insert(&board);
+            let shared = pool.share(&board);
+            assert!(board.approx_eq(&shared, 0.0));
+            shared
+        };
+        let (a, b) = (
+            &shared.placement[1].package_name,
+            &shared.placement[3].package_name,
+        );
+        assert!(std::ptr::eq(a.as_ptr(), b.as_ptr()));
+        assert!(matches!(a, Either::Left(_)));
+        assert!(pool.len() < 100);
+    }
+
+    #[cfg(feature = "fast-parser")]
+    #[test]
+    fn fast_parser_matches_pest() {
+        for path in ["./tests/board.idf", "./tests/library.idf"] {
+            let contents = std::fs::read_to_string(path).unwrap();
+            let pest = Idf30::parse(&contents).unwrap();
+            let fast = Idf30::parse_fast(&contents).unwrap();
+            assert_eq!(format!("{pest:?}"), format!("{fast:?}"));
+        }
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let truncated = contents.replace("TOP  PLACED", "");
+        assert!(Idf30::parse_fast(&truncated).is_err());
+        // A section must be closed by its own end, not by the next section header
+        let unclosed = contents.replace(".END_BOARD_OUTLINE\n", "");
+        assert_eq!(
+            Idf30::parse_fast(&unclosed).err().unwrap().to_string(),
+            ".BOARD_OUTLINE at line 9, column 1: Expected end of section",
+        );
+        let unclosed = contents.replace(".END_PLACEMENT", "");
+        assert!(Idf30::parse_fast(&unclosed).is_err());
+        let unclosed = contents.replace(".END_HEADER", ".END_PLACEMENT");
+        assert!(Idf30::parse_fast(&unclosed).is_err());
+    }
+
+    #[test]
+    fn lazy_sections() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let lazy = Idf30::parse_lazy(&contents).unwrap();
+        assert_eq!(lazy.header.board_file_version, 1);
+        assert!(lazy.section_names().any(|n| n == "DRILLED_HOLES"));
+        let outline = lazy.section("BOARD_OUTLINE").unwrap().unwrap();
+        assert_eq!(outline.owner(), Some(Owner::ECad));
+        assert!(outline.args().is_empty());
+        assert!(lazy.is_parsed("BOARD_OUTLINE"));
+        assert!(!lazy.is_parsed("DRILLED_HOLES"));
+        assert_eq!(lazy.placement().unwrap().len(), 3);
+        assert!(lazy.section("MISSING").unwrap().is_none());
+
+        let full = lazy.into_idf30().unwrap();
+        let eager = Idf30::parse(&contents).unwrap();
+        assert_eq!(format!("{full:?}"), format!("{eager:?}"));
+
+        let broken = contents.replace("PTH  U?", "PTH  U? %");
+        let lazy = Idf30::parse_lazy(&broken).unwrap();
+        assert!(lazy.section("BOARD_OUTLINE").unwrap().is_some());
+        assert!(lazy.section("DRILLED_HOLES").is_err());
+    }
+
+    #[test]
+    fn progress_events() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let mut events = vec![];
+        let board = Idf30::parse_with_progress(&contents, |e| {
+            events.push((e.bytes, e.total, e.section.to_string()))
+        })
+        .unwrap();
+        assert_eq!(events.first().unwrap().2, "HEADER");
+        assert_eq!(events.last().unwrap().0, contents.len());
+        assert!(events.windows(2).all(|w| w[0].0 < w[1].0));
+        assert!(events.iter().any(|e| e.2 == "DRILLED_HOLES"));
+
+        // Large sections report progress while they are parsed, the model is the same
+        let mut large = contents.replace(".END_PLACEMENT\n", "");
+        for i in 0..5000 {
+            large.push_str(&format!(
+                "cc1210 pn-cc1210 C{i}\n{i}.0 1.0 0.0 90.0 TOP PLACED\n"
+            ));
+        }
+        large.push_str(".END_PLACEMENT\n.CUSTOM\n.END_CUSTOM\n");
+        let mut events = vec![];
+        let large_board =
+            Idf30::parse_with_progress(&large, |e| events.push((e.bytes, e.section.to_string())))
+                .unwrap();
+        let parsed = Idf30::parse(&large).unwrap();
+        assert_eq!(large_board, parsed);
+        assert_eq!(large_board.warnings, parsed.warnings);
+        assert_eq!(large_board.warnings.len(), 1);
+        assert!(events.iter().filter(|e| e.1 == "PLACEMENT").count() > 2);
+        assert!(events.windows(2).all(|w| w[0].0 < w[1].0));
+        assert_eq!(
+            Idf30::parse_with_progress(&large.replace("C4000\n4000.0", "C4000\nx"), |_| {})
+                .unwrap_err()
+                .to_string(),
+            Idf30::parse(&large.replace("C4000\n4000.0", "C4000\nx"))
+                .unwrap_err()
+                .to_string()
+        );
+
+        let mut written = vec![];
+        let mut last = 0;
+        board
+            .write_with_progress(&mut written, |e| last = e.bytes)
+            .unwrap();
+        assert_eq!(String::from_utf8(written).unwrap(), board.to_string());
+        assert_eq!(last, board.to_string().len());
+    }
+
+    #[test]
+    fn parse_limits() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        assert!(Idf30::parse_with_limits(&contents, ParseLimits::default()).is_ok());
+        let limited = |limits: ParseLimits| {
+            Idf30::parse_with_limits(&contents, limits)
+                .err()
+                .map(|e| e.to_string())
+        };
+        assert_eq!(
+            limited(ParseLimits {
+                max_sections: 4,
+                ..Default::default()
+            }),
+            Some("Parse limit exceeded: number of sections".to_string())
+        );
+        assert!(limited(ParseLimits {
+            max_sections: 5,
+            ..Default::default()
+        })
+        .is_none());
+        assert!(limited(ParseLimits {
+            max_records: 10,
+            ..Default::default()
+        })
+        .is_some());
+        assert!(limited(ParseLimits {
+            max_string_len: 20,
+            ..Default::default()
+        })
+        .is_some());
+        assert!(limited(ParseLimits {
+            max_file_size: 100,
+            ..Default::default()
+        })
+        .is_some());
+
+        // Comments are not records, the lazy and fast parsers enforce limits too
+        let records = |max_records| ParseLimits {
+            max_records,
+            ..Default::default()
+        };
+        assert!(Idf30::parse_with_limits(&contents, records(16)).is_ok());
+        assert!(Idf30::parse_with_limits(&contents, records(15)).is_err());
+        let commented = contents.replace(".DRILLED_HOLES\n", ".DRILLED_HOLES\n# holes\n");
+        assert!(Idf30::parse_lazy_with_limits(&commented, records(16)).is_ok());
+        assert!(Idf30::parse_lazy_with_limits(&commented, records(15)).is_err());
+        assert!(Idf30::parse_lazy_with_limits(
+            &contents,
+            ParseLimits {
+                max_string_len: 20,
+                ..Default::default()
+            }
+        )
+        .is_err());
+        #[cfg(feature = "fast-parser")]
+        {
+            use idf::fast::FastReader;
+            assert!(Idf30::read_with_limits(&commented, &FastReader, records(16)).is_ok());
+            assert!(Idf30::read_with_limits(&contents, &FastReader, records(15)).is_err());
+        }
+
+        // Limits are checked before the reader sees the text
+        struct Unreachable;
+        impl IdfReader for Unreachable {
+            fn read<'a>(
+                &self,
+                _: &'a str,
+                _: &mut dyn FnMut(IdfEvent<'a>) -> Result<(), Error>,
+            ) -> Result<(), Error> {
+                panic!("Over limit text was parsed");
+            }
+        }
+        assert!(matches!(
+            Idf30::read_with_limits(&contents, &Unreachable, records(15)),
+            Err(Error::LimitExceeded("number of records"))
+        ));
+        let truncated = &contents[..contents.find(".END_PLACEMENT").unwrap()];
+        assert!(matches!(
+            Idf30::parse_with_limits(truncated, records(10)),
+            Err(Error::LimitExceeded("number of records"))
+        ));
+    }
+
+    #[test]
+    fn parse_warnings() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let (_, warnings) = Idf30::parse_with_warnings(&contents).unwrap();
+        assert!(warnings.is_empty());
+
+        let contents = contents
+            .replace(
+                ".END_DRILLED_HOLES",
+                ".END_HOLES\n.MY_DATA\n1\n.END_MY_DATA",
+            )
+            .replace(".ROUTE_KEEPOUT  ECAD", ".ROUTE_KEEPOUT  NOBODY")
+            .replace(
+                "84.1509     -3.4969      0.0000     180.000",
+                "84.1509 -3.4969 0.0000 540.000",
+            );
+        let (board, warnings) = Idf30::parse_with_warnings(&contents).unwrap();
+        assert_eq!(board.placement[0].rotation, 180.0);
+        let messages: Vec<String> = warnings.iter().map(|w| w.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Section .DRILLED_HOLES ended by .END_HOLES at line 18",
+                "Unknown section .MY_DATA at line 19",
+                "Unknown owner NOBODY of .ROUTE_KEEPOUT",
+                "Rotation of J2 changed from 540 to 180",
+            ]
+        );
+        // `parse` reports the same issues without changing values
+        let board = Idf30::parse(&contents).unwrap();
+        assert_eq!(board.warnings.len(), 3);
+        assert_eq!(board.placement[0].rotation, 540.0);
+
+        let contents = contents.replace("540.000", "-0.00001");
+        let (board, _) = Idf30::parse_with_warnings(&contents).unwrap();
+        assert_eq!(board.placement[0].rotation, 0.0);
+    }
+
+    #[test]
+    fn error_locations() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let broken = contents.replace("TOP  PLACED", "TOP  GLUED");
+        let message = Idf30::parse(&broken).err().unwrap().to_string();
+        assert_eq!(
+            message,
+            ".PLACEMENT record 2 at line 24, column 1: Wrong placement status"
+        );
+        let lazy = Idf30::parse_lazy(&broken).unwrap();
+        assert_eq!(lazy.placement().err().unwrap().to_string(), message);
+        #[cfg(feature = "fast-parser")]
+        assert_eq!(
+            Idf30::parse_fast(&broken).err().unwrap().to_string(),
+            message
+        );
+
+        let broken = contents.replace("board.brd  MM", "board.brd  INCH");
+        let message = Idf30::parse(&broken).err().unwrap().to_string();
+        assert_eq!(message, ".HEADER at line 1, column 1: MM or THOU expected");
+    }
+
+    #[test]
+    fn unit_aliases() {
+        for (alias, unit) in [
+            ("mm", Unit::SImm),
+            ("MILS", Unit::Mils),
+            ("mil", Unit::Mils),
+        ] {
+            assert_eq!(alias.parse::<Unit>().unwrap(), unit);
+        }
+        assert!("INCH".parse::<Unit>().is_err());
+        let contents = std::fs::read_to_string("./tests/board.idf")
+            .unwrap()
+            .replace("board.brd  MM", "board.brd  mm");
+        let board = Idf30::parse(&contents).unwrap();
+        assert_eq!(board.units(), Some(&Unit::SImm));
+        assert!(board.to_string().contains("board.brd MM\n"));
+    }
+
+    #[test]
+    fn quoted_string_escaping() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let mut board = Idf30::parse(&contents).unwrap();
+        board.placement = vec![placement(
+            "CAP_0603",
+            "CAP 0.1\"",
+            "C1",
+            (1.0, 2.0, 0.0),
+            BoardSide::Top,
+        )];
+        let written = board.to_string();
+        assert!(written.contains("CAP_0603 \"CAP 0.1\"\"\" C1\n"));
+        let parsed = Idf30::parse(&written).unwrap();
+        assert_eq!(&*parsed.placement[0].part_number, "CAP 0.1\"");
+        #[cfg(feature = "fast-parser")]
+        assert_eq!(
+            &*Idf30::parse_fast(&written).unwrap().placement[0].part_number,
+            "CAP 0.1\""
+        );
+
+        let contents = contents.replace("FID_C40M120  \"\"", "FID_C40M120  'O''RING \"A\"'");
+        let board = Idf30::parse(&contents).unwrap();
+        assert_eq!(&*board.placement[2].part_number, "O'RING \"A\"");
+    }
+
+    #[test]
+    fn quote_strings_on_output() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let mut board = Idf30::parse(&contents).unwrap();
+        board.placement = vec![
+            placement("0603", "RES 10K", "R1", (1.0, 2.0, 0.0), BoardSide::Top),
+            placement("R0603", ".END", "R+2", (3.0, 4.0, 0.0), BoardSide::Top),
+        ];
+        let written = board.to_string();
+        assert!(written.contains("\"0603\" \"RES 10K\" R1\n"));
+        assert!(written.contains("R0603 \".END\" \"R+2\"\n"));
+        let parsed = Idf30::parse(&written).unwrap();
+        assert_eq!(&*parsed.placement[0].package_name, "0603");
+        assert_eq!(&*parsed.placement[0].part_number, "RES 10K");
+        assert_eq!(parsed.placement[1].designator.to_string(), "R+2");
+
+        let contents = std::fs::read_to_string("./tests/library.idf").unwrap();
+        let library = Idf30::parse(&contents).unwrap();
+        let written = library.to_string();
+        assert!(written.starts_with(".HEADER\nLIBRARY_FILE 3.0 \"Sample File Generator\" 10/22/96"));
+        assert_eq!(
+            &*Idf30::parse(&written).unwrap().header.source,
+            "Sample File Generator"
+        );
+    }
+
+    #[test]
+    fn placement_status_rules() {
+        assert!(PlacementStatus::Placed.can_be_moved_by(Owner::MCad));
+        assert!(PlacementStatus::ECad.can_be_moved_by(Owner::ECad));
+        assert!(!PlacementStatus::ECad.can_be_moved_by(Owner::MCad));
+        assert!(!PlacementStatus::Unplaced.is_placed());
+        assert_eq!(
+            PlacementStatus::locked_by(Owner::MCad),
+            PlacementStatus::MCad
+        );
+
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let mut board = Idf30::parse(&contents).unwrap();
+        let update = board.set_status(
+            Owner::MCad,
+            |p| p.board_side == BoardSide::Top,
+            PlacementStatus::MCad,
+        );
+        assert_eq!(update.locked, ["J2", "J3"]);
+        assert_eq!(update.changed, 1);
+        assert_eq!(board.placement[1].placement_status, PlacementStatus::ECad);
+        assert_eq!(board.placement[2].placement_status, PlacementStatus::MCad);
+    }
+
+    #[test]
+    fn loops_by_index() {
+        let file = ".HEADER
+LIBRARY_FILE 3.0 gen 2024/01/01.00:00:00 1
+.END_HEADER
+.ELECTRICAL
+frame pn-frame MM 1.0
+0 0.0 0.0 0.0
+0 10.0 0.0 0.0
+0 10.0 10.0 0.0
+0 0.0 0.0 0.0
+1 4.0 4.0 0.0
+1 6.0 6.0 0.0
+1 4.0 4.0 0.0
+.END_ELECTRICAL
+";
+        let library = Idf30::parse(file).unwrap();
+        let definition = library.definition("frame", "pn-frame").unwrap();
+        assert_eq!(definition.loops.len(), 2);
+        assert!(!definition.loops[0].is_cutout());
+        assert!(definition.loops[1].is_cutout());
+        assert_eq!(definition.loops[1].points[1].x, 6.0);
+        let written = library.to_string();
+        assert!(written.contains("1 6.0000 6.0000 0.0000\n"));
+        let reparsed = Idf30::parse(&written).unwrap();
+        assert!(reparsed.approx_eq(&library, 1e-4));
+
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let board = Idf30::parse(&contents).unwrap();
+        let keepout = &board.other_sections[1].loops();
+        assert_eq!(keepout.len(), 1);
+        assert_eq!(keepout[0].index, 0);
+        assert_eq!(keepout[0].points[1].angle, 360.0);
+    }
+
+    #[test]
+    fn circle_loops() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let board = Idf30::parse(&contents).unwrap();
+        let keepout = &board.other_sections[1].loops()[0];
+        let ((x, y), r) = keepout.as_circle().unwrap();
+        assert_eq!((x, y), (3.5, 18.5));
+        assert!((r - 1.1811).abs() < 1e-4);
+        assert!((keepout.area() - std::f64::consts::PI * 1.1811f64.powi(2)).abs() < 1e-3);
+        let bbox = keepout.bounding_box().unwrap();
+        assert!((bbox.min_x - 2.3189).abs() < 1e-4);
+        assert!((bbox.height() - 2.3622).abs() < 1e-4);
+        assert!(keepout.polyline().len() > 8);
+        assert!(board.other_sections[0].loops()[0].as_circle().is_none());
+    }
+
+    #[test]
+    fn winding_fix() {
+        let file = ".HEADER
+BOARD_FILE 3.0 gen 2024/01/01.00:00:00 1
+b MM
+.END_HEADER
+.BOARD_OUTLINE ECAD
+1.6
+0 0.0 0.0 0.0
+0 0.0 10.0 0.0
+0 10.0 10.0 0.0
+0 10.0 0.0 0.0
+0 0.0 0.0 0.0
+1 4.0 4.0 0.0
+1 6.0 4.0 0.0
+1 6.0 6.0 0.0
+1 4.0 4.0 0.0
+2 7.0 7.0 0.0
+2 8.0 7.0 -360.0
+.END_BOARD_OUTLINE
+";
+        let mut board = Idf30::parse(file).unwrap();
+        let issues = board.winding_issues();
+        assert_eq!(issues.len(), 2);
+        assert_eq!(
+            issues[0].source,
+            OutlineSource::Section("BOARD_OUTLINE".to_string())
+        );
+        assert_eq!(issues[1].loop_index, 1);
+        assert_eq!(issues[1].kind, LoopIssueKind::WrongWinding);
+        assert_eq!(board.fix_winding(), 2);
+        assert!(board.winding_issues().is_empty());
+        let loops = board.other_sections[0].loops();
+        assert!(loops[0].is_counterclockwise());
+        assert_eq!(loops[1].points[1].x, 6.0);
+        assert_eq!(loops.len(), 3);
+    }
+
+    #[test]
+    fn loop_validation() {
+        let file = ".HEADER
+LIBRARY_FILE 3.0 gen 2024/01/01.00:00:00 1
+.END_HEADER
+.ELECTRICAL
+bowtie pn-bowtie MM 1.0
+0 0.0 0.0 0.0
+0 10.0 10.0 0.0
+0 10.0 0.0 0.0
+0 0.0 10.0 0.0
+0 0.0 0.0 0.0
+.END_ELECTRICAL
+.ELECTRICAL
+implicit-bowtie pn-implicit-bowtie MM 1.0
+0 0.0 0.0 0.0
+0 10.0 10.0 0.0
+0 10.0 0.0 0.0
+0 0.0 10.0 0.0
+.END_ELECTRICAL
+.ELECTRICAL
+open pn-open MM 1.0
+0 0.0 0.0 0.0
+0 10.0 0.0 0.0
+0 10.0 10.0 0.0
+.END_ELECTRICAL
+.ELECTRICAL
+round pn-round MM 1.0
+0 0.0 0.0 0.0
+0 2.0 0.0 360.0
+.END_ELECTRICAL
+";
+        let library = Idf30::parse(file).unwrap();
+        let issues = library.loop_issues(false);
+        assert_eq!(issues.len(), 2, "{issues:?}");
+        // The closing line of an implicitly closed loop is checked too
+        assert_eq!(
+            issues[1].source,
+            OutlineSource::Definition("implicit-bowtie".to_string())
+        );
+        assert_eq!(
+            issues[1].kind,
+            LoopIssueKind::SelfIntersection {
+                first: 0,
+                second: 2
+            }
+        );
+        let issues: Vec<_> = library
+            .loop_issues(true)
+            .into_iter()
+            .filter(|i| i.source != OutlineSource::Definition("implicit-bowtie".to_string()))
+            .collect();
+        assert_eq!(issues.len(), 2, "{issues:?}");
+        assert_eq!(
+            issues[0].source,
+            OutlineSource::Definition("bowtie".to_string())
+        );
+        assert_eq!(
+            issues[0].kind,
+            LoopIssueKind::SelfIntersection {
+                first: 0,
+                second: 2
+            }
+        );
+        assert_eq!(
+            issues[1].source,
+            OutlineSource::Definition("open".to_string())
+        );
+        assert_eq!(issues[1].kind, LoopIssueKind::NotClosed);
+    }
+
+    #[test]
+    fn outline_contains() {
+        let file = ".HEADER
+BOARD_FILE 3.0 gen 2024/01/01.00:00:00 1
+b MM
+.END_HEADER
+.BOARD_OUTLINE MCAD
+1.6
+0 0.0 0.0 0.0
+0 10.0 0.0 0.0
+0 10.0 10.0 180.0
+0 0.0 10.0 0.0
+0 0.0 0.0 0.0
+1 5.0 5.0 0.0
+1 6.0 5.0 -360.0
+.END_BOARD_OUTLINE
+";
+        let board = Idf30::parse(file).unwrap();
+        let outline = board.board_outline().unwrap().unwrap();
+        assert_eq!(outline.owner, Some(Owner::MCad));
+        assert_eq!(outline.thickness, 1.6);
+        assert!(outline.contains(2.0, 2.0));
+        assert!(outline.contains(14.9, 5.0));
+        assert!(outline.contains(10.5, 9.9));
+        assert!(!outline.contains(14.0, 1.0));
+        assert!(!outline.contains(-0.1, 5.0));
+        assert!(!outline.contains(5.0, 5.5));
+        assert!(outline.contains(5.0, 6.5));
+    }
+
+    #[test]
+    fn component_z_span() {
+        let board = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let library = std::fs::read_to_string("./tests/library.idf").unwrap();
+        let mut assembly = assembly(&board, &library);
+        let thickness = assembly.board.board_thickness().unwrap().unwrap();
+        assert_eq!(thickness, 0.7013);
+        let mut malformed = assembly.board.clone();
+        let outline = malformed
+            .other_sections
+            .iter_mut()
+            .find(|s| s.name() == "BOARD_OUTLINE")
+            .unwrap();
+        outline.records_mut().remove(0);
+        assert!(malformed.board_thickness().is_err());
+        assembly.board.placement[0].z = 0.1;
+        assembly.board.placement[1].board_side = BoardSide::Bottom;
+        let components: Vec<_> = assembly.components().collect();
+        let height = components[0].height().unwrap();
+        let (low, high) = components[0].z_span(thickness).unwrap();
+        assert!((low - (thickness + 0.1)).abs() < 1e-6);
+        assert!((high - (thickness + height)).abs() < 1e-6);
+        let (low, high) = components[1].z_span(thickness).unwrap();
+        assert_eq!(high, 0.0);
+        assert_eq!(low, -components[1].height().unwrap());
+    }
+
+    #[test]
+    fn assembly_bounding_box_3d() {
+        let board = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let library = std::fs::read_to_string("./tests/library.idf").unwrap();
+        let mut assembly = assembly(&board, &library);
+        assembly.board.placement[1].board_side = BoardSide::Bottom;
+        let bbox = assembly.bounding_box_3d().unwrap();
+        let thickness = 0.7013f32;
+        let components: Vec<_> = assembly.components().collect();
+        let top = components[0].height().unwrap();
+        let bottom = components[1].height().unwrap();
+        assert!((bbox.max_z - (thickness + top) as f64).abs() < 1e-6);
+        assert!((bbox.min_z + bottom as f64).abs() < 1e-6);
+        let outline = assembly.board.other_sections[0].bounding_box().unwrap();
+        assert!(bbox.min_x <= outline.min_x && bbox.max_y >= outline.max_y);
+        let u1 = components[0].bounding_box().unwrap();
+        assert!(bbox.max_x >= u1.max_x);
+        assert_eq!(bbox.size().2, bbox.max_z - bbox.min_z);
+    }
+
+    #[test]
+    fn dfm_report() {
+        let board = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let library = std::fs::read_to_string("./tests/library.idf").unwrap();
+        let assembly = assembly(&board, &library);
+        let report = assembly.dfm_report(&DfmRules::default()).unwrap();
+        // The sample outline is a single segment, closed implicitly
+        assert!(report.findings.is_empty());
+
+        let rules = DfmRules {
+            max_height_top: Some(0.1),
+            max_height_bottom: Some(0.1),
+            min_hole_diameter: Some(1.5),
+            ..DfmRules::default()
+        };
+        let report = assembly.dfm_report(&rules).unwrap();
+        let count = |category| {
+            report
+                .findings
+                .iter()
+                .filter(|f| f.category == category)
+                .count()
+        };
+        assert_eq!(count(DfmCategory::Height), 2);
+        assert_eq!(count(DfmCategory::HoleDiameter), 1);
+        let hole = report
+            .findings
+            .iter()
+            .find(|f| f.category == DfmCategory::HoleDiameter)
+            .unwrap();
+        assert_eq!(hole.subject, "hole at 65.0000 60.5000");
+        let csv = report.to_csv();
+        assert!(csv.starts_with("Category,Subject,Value,Limit,Message\nheight,U1,"));
+        let json = report.to_json();
+        assert!(json.starts_with("[\n  {\"category\": \"height\", \"subject\": \"U1\""));
+
+        // A bad hole record is reported, the other checks still run
+        let mut assembly = assembly;
+        let holes = assembly
+            .board
+            .other_sections
+            .iter_mut()
+            .find(|s| s.name() == "DRILLED_HOLES")
+            .unwrap();
+        holes.records_mut()[1][3] = IdfValue::String(Either::Left("XTH"));
+        let report = assembly.dfm_report(&rules).unwrap();
+        let malformed = report
+            .findings
+            .iter()
+            .find(|f| f.category == DfmCategory::Malformed)
+            .unwrap();
+        assert_eq!(malformed.subject, ".DRILLED_HOLES record 1");
+        assert_eq!(malformed.message, "Expected PTH or NPTH");
+        assert!(report.to_json().contains("\"value\": null"));
+        assert_eq!(
+            report
+                .findings
+                .iter()
+                .filter(|f| f.category == DfmCategory::Height)
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn board_panel_conversion() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let board = Idf30::parse(&contents).unwrap();
+        let panel = board.to_panel().unwrap();
+        assert!(matches!(panel.header.ty, FileType::PanelFile { .. }));
+        assert_eq!(panel.board_instances(&board).unwrap().len(), 1);
+        let outline = panel.section("PANEL_OUTLINE").unwrap();
+        assert_eq!(outline.owner(), Some(Owner::ECad));
+        assert_eq!(outline.loops().len(), 1);
+        let written = panel.to_string();
+        let panel = Idf30::parse(&written).unwrap();
+        let flat = panel.flatten(&board).unwrap();
+        assert!(flat.approx_eq(&board, 1e-4));
+
+        let mut panel = board.to_panel().unwrap();
+        panel.placement.push(panel.placement[0].clone());
+        assert!(panel.flatten(&board).is_err());
+        assert!(panel.to_panel().is_err());
+    }
+
+    #[test]
+    fn section_order_on_output() {
+        let file = ".HEADER\nBOARD_FILE 3.0 \"gen\" 2024/01/01.12:00:00 1\nb MM\n.END_HEADER\n\
+                    .PLACEMENT\nP N U1\n1.0 2.0 0.0 0.0 TOP PLACED\n.END_PLACEMENT\n\
+                    .BOARD_OUTLINE ECAD\n1.6\n0 0.0 0.0 0.0\n0 1.0 0.0 0.0\n0 0.0 0.0 0.0\n.END_BOARD_OUTLINE\n";
+        let idf = Idf30::parse(file).unwrap();
+        let written = idf.to_string();
+        assert!(written.find(".PLACEMENT").unwrap() < written.find(".BOARD_OUTLINE").unwrap());
+        let reparsed = Idf30::parse(&written).unwrap();
+        assert_eq!(reparsed.section_order, idf.section_order);
+
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let board = Idf30::parse(&contents).unwrap();
+        let written = board.to_string();
+        assert!(written.find(".PLACEMENT").unwrap() > written.find(".BOARD_OUTLINE").unwrap());
+        let options = WriteOptions {
+            order: vec!["PLACEMENT".into(), "DRILLED_HOLES".into()],
+            ..WriteOptions::default()
+        };
+        let written = board.to_string_with(&options);
+        assert!(written.contains(".END_HEADER\n.PLACEMENT\n"));
+        let names: Vec<_> = Idf30::parse(&written)
+            .unwrap()
+            .other_sections
+            .iter()
+            .map(|s| s.name().to_string())
+            .collect();
+        assert_eq!(names, ["DRILLED_HOLES", "BOARD_OUTLINE", "ROUTE_KEEPOUT"]);
+    }
+
+    #[test]
+    fn ast_equality() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let board = Idf30::parse(&contents).unwrap();
+        assert_eq!(board, Idf30::parse(&contents).unwrap());
+        let written = board.to_string();
+        let reparsed = Idf30::parse(&written).unwrap();
+        assert_eq!(reparsed, Idf30::parse(&reparsed.to_string()).unwrap());
+        let mut moved = board.clone();
+        moved.placement[0].x += 1.0;
+        assert_ne!(moved, board);
+        // Borrowed and owned strings with the same text are equal
+        assert_eq!(board.clone().into_owned(), board);
+        let mut renamed = board.clone();
+        renamed.placement[0].package_name = Either::Right("OTHER".to_string());
+        assert_ne!(renamed, board);
+        assert_eq!(
+            IdfValue::String(Either::Left("PTH")),
+            IdfValue::string("PTH".to_string())
+        );
+        let empty = Idf30::default();
+        assert!(empty.placement.is_empty() && empty.other_sections.is_empty());
+        assert_eq!(empty.header.ty, Header::default().ty);
+        assert!(matches!(
+            Header::default().ty,
+            FileType::BoardFile {
+                units: Unit::SImm,
+                ..
+            }
+        ));
+
+        let designators: HashSet<_> = [
+            ReferenceDesignator::Any(Either::Left("U1")),
+            ReferenceDesignator::Any(Either::Right("U1".to_string())),
+            ReferenceDesignator::default(),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(designators.len(), 2);
+        assert_eq!(
+            Point::default(),
+            Point {
+                x: 0.0,
+                y: 0.0,
+                angle: 0.0
+            }
+        );
+        assert_eq!(PlacementStatus::default(), PlacementStatus::Unplaced);
+    }
+
+    #[test]
+    fn definition_display() {
+        let contents = std::fs::read_to_string("./tests/library.idf").unwrap();
+        let library = Idf30::parse(&contents).unwrap();
+        let FileType::LibraryFile { components } = &library.header.ty else {
+            panic!("expected a library file");
+        };
+        for definition in components {
+            let written = format!("{definition}");
+            assert!(written.starts_with(".ELECTRICAL\n"));
+            assert!(written.ends_with(".END_ELECTRICAL\n"));
+            let file = format!("{}{written}", Header::new_library());
+            let reparsed = Idf30::parse(&file).unwrap();
+            let FileType::LibraryFile { components } = &reparsed.header.ty else {
+                panic!("expected a library file");
+            };
+            assert_eq!(components[0].loops.len(), definition.loops.len());
+            for (a, b) in components[0].loops.iter().zip(&definition.loops) {
+                assert_eq!(a.index, b.index);
+                assert!(a.approx_eq(b, 1e-4));
+            }
+        }
+    }
+
+    #[test]
+    fn constructors() {
+        let c = ComponentPlacement::new("R0603", "10k", "R1");
+        assert_eq!(c.package_name, Either::Left("R0603"));
+        assert_eq!(c.placement_status, PlacementStatus::Unplaced);
+        let c = ComponentPlacement::new("TP", String::from("pn"), "NOREFDES".to_string())
+            .placed_at(1.0, 2.0, 90.0, BoardSide::Bottom);
+        assert_eq!(c.part_number, Either::<&str, String>::Right("pn".into()));
+        assert!(matches!(c.designator, ReferenceDesignator::NoRefDes));
+        assert_eq!(c.placement_status, PlacementStatus::Placed);
+        assert_eq!(
+            c.to_string(),
+            "TP pn NOREFDES\n  1.0000 2.0000 0.0000 90.000 BOTTOM PLACED\n"
+        );
+
+        let moved = ComponentPlacement {
+            x: 5.0,
+            ..ComponentPlacement::new("P", "N", "U1")
+        };
+        assert_eq!(moved.x, 5.0);
+        assert!(matches!(
+            ComponentPlacement::default().designator,
+            ReferenceDesignator::NoRefDes
+        ));
+
+        let definition = ComponentDefinition::new("R0603", "10k", Unit::SImm, 0.5);
+        assert!(definition.loops.is_empty());
+        assert_eq!(
+            IdfValue::string("ALL"),
+            IdfValue::String(Either::Left("ALL"))
+        );
+    }
+
+    #[test]
+    fn syntax_errors() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let broken = contents.replace(".END_DRILLED_HOLES", ".END_DRILLED_HOLES\n%");
+        let Error::Syntax(e) = Idf30::parse(&broken).err().unwrap() else {
+            panic!("expected a syntax error");
+        };
+        assert_eq!(e.start(), (19, 1));
+        assert!(!e.message().is_empty());
+        assert!(e.to_string().ends_with("at line 19, column 1"));
+
+        let broken = contents.replace("1.3970 ", "1.3970 % ");
+        let lazy = Idf30::parse_lazy(&broken).unwrap();
+        let Error::Syntax(e) = lazy.section("DRILLED_HOLES").err().unwrap() else {
+            panic!("expected a syntax error");
+        };
+        assert_eq!(e.start(), (16, 12));
+    }
+
+    #[test]
+    fn reader_backends() {
+        /// Drops keepouts, forwarding everything else from the pest backend.
+        struct WithoutKeepouts;
+
+        impl IdfReader for WithoutKeepouts {
+            fn read<'a>(
+                &self,
+                text: &'a str,
+                emit: &mut dyn FnMut(IdfEvent<'a>) -> Result<(), Error>,
+            ) -> Result<(), Error> {
+                PestReader.read(text, &mut |event| match event {
+                    IdfEvent::Section(ParsedSection::Other(s)) if s.name().ends_with("KEEPOUT") => {
+                        Ok(())
+                    }
+                    event => emit(event),
+                })
+            }
+        }
+
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let board = Idf30::read_with(&contents, &PestReader).unwrap();
+        assert_eq!(board, Idf30::parse(&contents).unwrap());
+        let filtered = Idf30::read_with(&contents, &WithoutKeepouts).unwrap();
+        assert_eq!(
+            filtered.other_sections.len(),
+            board.other_sections.len() - 1
+        );
+        assert_eq!(filtered.placement, board.placement);
+        assert_eq!(
+            Idf30::parse(&filtered.to_string()).unwrap().other_sections,
+            filtered.other_sections
+        );
+
+        struct Headerless;
+
+        impl IdfReader for Headerless {
+            fn read<'a>(
+                &self,
+                _text: &'a str,
+                emit: &mut dyn FnMut(IdfEvent<'a>) -> Result<(), Error>,
+            ) -> Result<(), Error> {
+                emit(IdfEvent::Section(ParsedSection::Placement(vec![])))
+            }
+        }
+
+        assert!(matches!(
+            Idf30::read_with("", &Headerless),
+            Err(Error::MissingHeader)
+        ));
+    }
+
+    #[test]
+    fn incremental_reparse() {
+        let previous = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let mut board = Idf30::parse(&previous).unwrap().into_owned();
+        let edit_text = |text: &str, from: &str, to: &str| {
+            let start = text.find(from).unwrap();
+            let edit = TextEdit {
+                range: start..start + from.len(),
+                new_len: to.len(),
+            };
+            (text.replacen(from, to, 1), edit)
+        };
+
+        let (text, edit) = edit_text(&previous, "65.0000", "66.0000");
+        let reparse = board.apply_edit(&previous, &text, &edit).unwrap();
+        assert_eq!(reparse, Reparse::Sections(vec![SectionRef::Other(2)]));
+        assert_eq!(board, Idf30::parse(&text).unwrap());
+
+        let (next, edit) = edit_text(&text, "TOP  PLACED", "BOTTOM  PLACED");
+        let reparse = board.apply_edit(&text, &next, &edit).unwrap();
+        assert_eq!(reparse, Reparse::Sections(vec![SectionRef::Placement]));
+        assert_eq!(board.placement[2].board_side, BoardSide::Bottom);
+
+        let (broken, edit) = edit_text(&next, "BOTTOM  PLACED", "BOTTOM  GLUED");
+        assert!(board.apply_edit(&next, &broken, &edit).is_err());
+        assert_eq!(board.placement[2].placement_status, PlacementStatus::Placed);
+
+        let (text, edit) = edit_text(&next, "board.brd", "other.brd");
+        assert_eq!(
+            board.apply_edit(&next, &text, &edit).unwrap(),
+            Reparse::Full
+        );
+        assert_eq!(board.board_name(), Some("other.brd"));
+    }
+
+    #[test]
+    fn value_accessors() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let board = Idf30::parse(&contents).unwrap();
+        let hole = &board.section("DRILLED_HOLES").unwrap().records()[0];
+        assert_eq!(hole[0].as_f64(), Some(1.397));
+        assert_eq!(hole[3].as_str(), Some("PTH"));
+        assert_eq!(hole[3].as_f64(), None);
+
+        assert_eq!(IdfValue::Integer(3).as_f64(), Some(3.0));
+        assert_eq!(IdfValue::Float(3.0).as_i64(), None);
+        assert_eq!(i64::try_from(IdfValue::Integer(-2)).unwrap(), -2);
+        assert_eq!(f64::try_from(&IdfValue::Integer(7)).unwrap(), 7.0);
+        assert_eq!(String::try_from(&hole[4]).unwrap(), "U?");
+        assert!(f64::try_from(IdfValue::string("PTH")).is_err());
+        assert!(String::try_from(IdfValue::Float(1.0)).is_err());
+    }
+
+    #[test]
+    fn section_schemas() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let file = format!(
+            "{contents}.TOOL_ANNOTATIONS\nU1 2 1.5 \"check height\"\nJ2 3 2\nJ3 x 1.0\n.END_TOOL_ANNOTATIONS\n"
+        );
+        let board = Idf30::parse(&file).unwrap();
+        let mut registry = SchemaRegistry::new();
+        registry.register(
+            "TOOL_ANNOTATIONS",
+            RecordSchema::new()
+                .field("designator", FieldType::String)
+                .field("layer", FieldType::Integer)
+                .field("gap", FieldType::Float)
+                .optional("note", FieldType::String),
+        );
+        let section = board.section("TOOL_ANNOTATIONS").unwrap();
+        let schema = registry.get("TOOL_ANNOTATIONS").unwrap();
+        let row = schema.row(&section.records()[0]).unwrap();
+        assert_eq!(row["note"].as_str(), Some("check height"));
+        let row = schema.row(&section.records()[1]).unwrap();
+        assert_eq!(row["gap"], IdfValue::Float(2.0));
+        assert!(!row.contains_key("note"));
+
+        let error = registry.rows(section).unwrap().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            ".TOOL_ANNOTATIONS record 2: Field layer: expected integer"
+        );
+        assert!(matches!(&error, Error::At { location, .. } if location.record == Some(2)));
+        assert_eq!(registry.validate(&board).len(), 1);
+        assert!(registry
+            .rows(board.section("DRILLED_HOLES").unwrap())
+            .is_none());
+    }
+
+    #[test]
+    fn section_handlers() {
+        #[derive(Debug, PartialEq)]
+        struct Padstack {
+            name: String,
+            diameter: f64,
+        }
+
+        struct Padstacks;
+
+        impl SectionHandler for Padstacks {
+            type Value = Vec<Padstack>;
+
+            fn section_name(&self) -> &str {
+                "PADSTACKS"
+            }
+
+            fn parse(&self, section: &IdfSection) -> Result<Vec<Padstack>, Error> {
+                section
+                    .records()
+                    .iter()
+                    .map(|r| match r.as_slice() {
+                        [name, diameter] => Ok(Padstack {
+                            name: name.try_into()?,
+                            diameter: diameter.try_into()?,
+                        }),
+                        _ => Err(Error::Malformed("Expected name and diameter")),
+                    })
+                    .collect()
+            }
+
+            fn write(&self, value: &Vec<Padstack>) -> IdfSection<'static> {
+                IdfSection::new(
+                    Either::Left("PADSTACKS"),
+                    vec![],
+                    value
+                        .iter()
+                        .map(|p| {
+                            vec![
+                                IdfValue::string(p.name.clone()),
+                                IdfValue::Float(p.diameter),
+                            ]
+                        })
+                        .collect(),
+                )
+            }
+        }
+
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let file = format!(
+            "{contents}.PADSTACKS\nvia 0.3\npad 1\n.END_PADSTACKS\n.VENDOR_EXT\nkeep 1\n.END_VENDOR_EXT\n"
+        );
+        let mut board = Idf30::parse(&file).unwrap();
+        let stacks = board.handled_sections(&Padstacks).unwrap();
+        assert_eq!(stacks.len(), 1);
+        assert_eq!(
+            stacks[0][1],
+            Padstack {
+                name: "pad".into(),
+                diameter: 1.0
+            }
+        );
+
+        board.set_handled_sections(
+            &Padstacks,
+            &[vec![Padstack {
+                name: "via".into(),
+                diameter: 0.25,
+            }]],
+        );
+        let written = board.to_string();
+        assert!(
+            written.contains(".PADSTACKS\n  via 0.2500\n.END_PADSTACKS\n.VENDOR_EXT\n  keep 1\n")
+        );
+
+        board.set_handled_sections(&Padstacks, &[]);
+        assert!(board.section("PADSTACKS").is_none());
+        assert!(board
+            .to_string()
+            .ends_with(".VENDOR_EXT\n  keep 1\n.END_VENDOR_EXT\n"));
+    }
+
+    #[test]
+    fn altium_dialect() {
+        let file = ".HEADER
+BOARD_FILE 3.0 Altium Designer 24 2024/01/01.12:00:00 1
+main board MM
+.END_HEADER
+.PLACEMENT
+R0603 RES 10k 1% R1
+  1.0000 2.0000 0.0000 0.000 TOP Placed
+SOIC8 LM358 U1
+  5.0000 2.0000 0.0000 90.000 BOTTOM Locked
+.END_PLACEMENT
+";
+        assert!(Idf30::parse(file).is_err());
+        let board = Idf30::parse_dialect(file, Dialect::Altium).unwrap();
+        assert_eq!(&*board.header.source, "Altium Designer 24");
+        assert_eq!(board.board_name(), Some("main board"));
+        assert_eq!(&*board.placement[0].part_number, "RES 10k 1%");
+        assert_eq!(board.placement[0].placement_status, PlacementStatus::Placed);
+        assert_eq!(board.placement[1].placement_status, PlacementStatus::ECad);
+
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        assert_eq!(Dialect::Standard.normalize(&contents), contents);
+        assert_eq!(
+            Idf30::parse_dialect(&contents, Dialect::Altium).unwrap(),
+            Idf30::parse(&contents).unwrap()
+        );
+    }
+
+    #[test]
+    fn allegro_dialect() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let file = contents
+            .replace(".ROUTE_KEEPOUT  ECAD", ".ROUTE_KEEPOUT ANY")
+            .replace("PIN  UNOWNED", "PIN  ANY  VIA_12 TOP BOTTOM")
+            .replace("HOLE_C63D63N  UNOWNED", "HOLE_C63D63N  MCAD  NPTH_63");
+        let board = Idf30::parse_dialect(&file, Dialect::Allegro).unwrap();
+        assert_eq!(
+            board.section("ROUTE_KEEPOUT").unwrap().owner(),
+            Some(Owner::Unowned)
+        );
+        let holes = board.drilled_holes().unwrap();
+        assert_eq!(holes.len(), 2);
+        assert_eq!(holes[0].owner, Owner::Unowned);
+        assert_eq!(holes[1].owner, Owner::MCad);
+        assert_eq!(
+            board.section("DRILLED_HOLES").unwrap().records()[0].len(),
+            7
+        );
+    }
+
+    #[test]
+    fn xpedition_dialect() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let file = contents.replace("TMP-478  J2\n   84.1509", "TMP-478  J2   84.1509");
+        assert!(Idf30::parse(&file).is_err());
+        let board = Idf30::parse_dialect(&file, Dialect::Xpedition).unwrap();
+        assert_eq!(board, Idf30::parse(&contents).unwrap());
+
+        let library = ".HEADER
+LIBRARY_FILE 3.0 gen 2024/01/01.00:00:00 1
+.END_HEADER
+.ELECTRICAL
+cap pn-cap MM 1.0
+PROP CAPACITANCE 100.0
+PART_PROP \"Value tolerance\" 5
+0 0.0 0.0 0.0
+0 1.0 0.0 0.0
+0 0.0 0.0 0.0
+.END_ELECTRICAL
+";
+        assert!(Idf30::parse(library).is_err());
+        assert!(Dialect::Xpedition
+            .normalize(library)
+            .contains("\nPROP \"Value tolerance\" 5\n"));
+        let library = Idf30::parse_dialect(library, Dialect::Xpedition).unwrap();
+        assert_eq!(
+            library.definition("cap", "pn-cap").unwrap().loops[0]
+                .points
+                .len(),
+            3
+        );
+        assert_eq!(
+            library.warnings,
+            [
+                ParseWarning::DroppedProperty {
+                    name: "CAPACITANCE".to_string(),
+                    line: 6
+                },
+                ParseWarning::DroppedProperty {
+                    name: "Value tolerance".to_string(),
+                    line: 7
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn eagle_dialect() {
+        let file = ".HEADER
+BOARD_FILE 3.0 EAGLE 2024/01/01.12:00:00
+board MM
+.END_HEADER
+.PLACEMENT
+R0603 RC-10K R1
+  12 -3 0 90 TOP PLACED
+.END_PLACEMENT
+";
+        assert!(Idf30::parse(file).is_err());
+        let board = Idf30::parse_dialect(file, Dialect::Eagle).unwrap();
+        assert_eq!(board.header.board_file_version, 1);
+        assert_eq!((board.placement[0].x, board.placement[0].y), (12.0, -3.0));
+        assert_eq!(board.placement[0].rotation, 90.0);
+
+        let library = ".HEADER
+LIBRARY_FILE 3.0 EAGLE 2024/01/01.12:00:00
+.END_HEADER
+.ELECTRICAL
+r pn MM 1
+0 0 0 0
+0 2 0 0
+0 0 0 0
+.END_ELECTRICAL
+";
+        let library = Idf30::parse_dialect(library, Dialect::Eagle).unwrap();
+        let definition = library.definition("r", "pn").unwrap();
+        assert_eq!(definition.height, 1.0);
+        assert_eq!(definition.loops[0].points[1].x, 2.0);
+    }
+
+    #[test]
+    fn write_profiles() {
+        let board = Idf30::parse(include_str!("board.idf")).unwrap();
+        let spec = board.to_string_with(&WriteProfile::Spec.into());
+        assert_eq!(spec, board.to_string());
+        assert_eq!(
+            spec,
+            "\
+.HEADER
+BOARD_FILE 3.0 allegro_17.4 2023/10/18.14:05:44 1
+board.brd MM
+.END_HEADER
+.BOARD_OUTLINE ECAD
+  0.7013
+  0 5.0000 62.0000 0.0000
+  0 5.0000 60.8500 0.0000
+.END_BOARD_OUTLINE
+.ROUTE_KEEPOUT ECAD
+  ALL
+  0 3.5000 18.5000 0.0000
+  0 4.6811 18.5000 360.0000
+.END_ROUTE_KEEPOUT
+.DRILLED_HOLES
+  1.3970 65.0000 60.5000 PTH U? PIN UNOWNED
+  1.6002 3.5000 18.5000 NPTH BOARD HOLE_C63D63N UNOWNED
+.END_DRILLED_HOLES
+.PLACEMENT
+HIROSE_BM28B0610DS2035V51 TMP-478 J2
+  84.1509 -3.4969 0.0000 180.000 TOP ECAD
+HIROSE_BM50U-4DP TMP-591 J3
+  71.5020 60.4995 0.0000 0.000 TOP ECAD
+FID_C40M120 \"\" NOREFDES
+  2.7000 43.1000 0.0000 180.000 TOP PLACED
+.END_PLACEMENT
+"
+        );
+
+        let options = WriteOptions {
+            order: vec!["PLACEMENT".to_string()],
+            quote_names: true,
+            line_ending: LineEnding::CrLf,
+            ..WriteOptions::default()
+        };
+        let crlf = board.to_string_with(&options);
+        assert!(crlf.ends_with(".END_DRILLED_HOLES\r\n"));
+        assert_eq!(crlf.matches('\n').count(), crlf.matches("\r\n").count());
+        assert!(crlf.contains("\"J2\""));
+
+        let options = WriteOptions {
+            precision: Some(6),
+            ..WriteOptions::default()
+        };
+        assert!(board
+            .to_string_with(&options)
+            .contains("65.000000 60.500000"));
+    }
+
+    #[test]
+    fn round_trip_report() {
+        let input = include_str!("board.idf");
+        let report = idf::verify_round_trip(input).unwrap();
+        assert!(report.is_faithful());
+        assert_eq!(report.written, Idf30::parse(input).unwrap().to_string());
+
+        let options = WriteOptions {
+            precision: Some(1),
+            ..WriteOptions::default()
+        };
+        let report = idf::verify::verify_round_trip_with(input, &options).unwrap();
+        let divergence = report.divergence.unwrap();
+        assert_eq!(divergence.section.as_deref(), Some("BOARD_OUTLINE"));
+        assert_eq!(divergence.item.as_deref(), Some("record 1"));
+        let input_section = &input[divergence.input_span.unwrap()];
+        assert!(input_section.starts_with(".BOARD_OUTLINE  ECAD\n    0.7013"));
+        let written_section = &report.written[divergence.written_span.unwrap()];
+        assert!(written_section.starts_with(".BOARD_OUTLINE ECAD\n  0.7\n"));
+        assert!(written_section.ends_with(".END_BOARD_OUTLINE\n"));
+
+        assert!(idf::verify_round_trip(".HEADER\n").is_err());
+    }
+
+    #[test]
+    fn canonical_form() {
+        let input = include_str!("board.idf");
+        let mut board = Idf30::parse(input).unwrap();
+        let mut shuffled = board.clone();
+        shuffled.placement.reverse();
+        shuffled.placement[0].rotation = -180.0;
+        shuffled.other_sections.reverse();
+        fn holes<'i>(idf: &'i mut Idf30<'static>) -> &'i mut Vec<Vec<IdfValue<'static>>> {
+            let mut sections = idf.other_sections.iter_mut();
+            sections
+                .find(|s| s.name() == "DRILLED_HOLES")
+                .unwrap()
+                .records_mut()
+        }
+        holes(&mut shuffled).reverse();
+        holes(&mut shuffled)[0][0] = IdfValue::Integer(2);
+        holes(&mut board)[1][0] = IdfValue::Float(2.0);
+
+        board.canonicalize();
+        shuffled.canonicalize();
+        assert_eq!(board.to_string(), shuffled.to_string());
+        let designators: Vec<_> = board
+            .placement
+            .iter()
+            .map(|c| c.designator.to_string())
+            .collect();
+        assert_eq!(designators, ["J2", "J3", "NOREFDES"]);
+        assert_eq!(board.placement[2].rotation, 180.0);
+        let holes = board.drilled_holes().unwrap();
+        assert_eq!((holes[0].x, holes[1].x), (3.5, 65.0));
+        assert_eq!(holes[0].diameter, 2.0);
+    }
+
+    #[test]
+    fn content_fingerprint() {
+        let board = Idf30::parse(include_str!("board.idf")).unwrap();
+        let options = WriteOptions {
+            precision: Some(6),
+            ..WriteOptions::default()
+        };
+        let mut export = Idf30::parse(&board.to_string_with(&options))
+            .unwrap()
+            .into_owned();
+        export.header.date = Either::Right("2024/01/02.03:04:05".to_string());
+        export.header.board_file_version = 7;
+        export.placement.swap(0, 2);
+        assert_eq!(board.fingerprint(), export.fingerprint());
+
+        export.placement[0].x += 0.01;
+        assert_ne!(board.fingerprint(), export.fingerprint());
+    }
+
+    #[test]
+    fn save_bumps_version() {
+        let dir = std::env::temp_dir().join(format!("idf_save_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("board.emn");
+        std::fs::copy("./tests/board.idf", &path).unwrap();
+
+        let mut file = TrackedFile::open(&path).unwrap();
+        assert!(!file.save().unwrap());
+        assert_eq!(file.idf.header.board_file_version, 1);
+        assert_eq!(&*file.idf.header.date, "2023/10/18.14:05:44");
+
+        file.idf.placement[0].rotation = 90.0;
+        assert!(file.is_modified());
+        assert!(file.save().unwrap());
+        assert!(!file.is_modified());
+        let saved = Idf30::parse(&std::fs::read_to_string(&path).unwrap())
+            .unwrap()
+            .into_owned();
+        assert_eq!(saved.header.board_file_version, 2);
+        assert_ne!(&*saved.header.date, "2023/10/18.14:05:44");
+        assert_eq!(saved.placement[0].rotation, 90.0);
+        assert!(!TrackedFile::open(&path).unwrap().save().unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn board_editor_undo() {
+        let board = Idf30::parse(include_str!("board.idf")).unwrap();
+        let mut editor = BoardEditor::new(board.clone());
+        editor.move_by("J2", 2.54, 0.0).unwrap();
+        editor.rotate("J3", 270.0).unwrap();
+        editor.flip("J3").unwrap();
+        editor.remove("NOREFDES").unwrap();
+        editor
+            .add(placement(
+                "R0603",
+                "RC-10K",
+                "R1",
+                (10.0, 20.0, 0.0),
+                BoardSide::Top,
+            ))
+            .unwrap();
+        assert!(matches!(
+            editor.add(placement(
+                "R0603",
+                "RC-10K",
+                "R1",
+                (0.0, 0.0, 0.0),
+                BoardSide::Top
+            )),
+            Err(Error::DuplicateDesignator(_))
+        ));
+        assert!(matches!(
+            editor.move_by("U9", 1.0, 1.0),
+            Err(Error::UnknownDesignator(_))
+        ));
+        assert_eq!(editor.history().len(), 5);
+
+        let j3 = &editor.board().placement[1];
+        assert_eq!(
+            (j3.x, j3.rotation, j3.board_side.clone()),
+            (71.502, 90.0, BoardSide::Bottom)
+        );
+        let keys: Vec<_> = editor
+            .changes(1e-4)
+            .placements
+            .iter()
+            .map(|c| c.key.clone())
+            .collect();
+        assert_eq!(keys, ["J2", "J3", "NOREFDES", "R1"]);
+
+        assert!(editor.undo());
+        assert!(editor.undo());
+        assert_eq!(editor.board().placement.len(), 3);
+        assert!(editor.redo());
+        assert_eq!(editor.board().placement.len(), 2);
+        while editor.undo() {}
+        assert!(editor.changes(1e-4).is_empty());
+        assert_eq!(editor.into_board(), board);
+    }
+
+    #[test]
+    fn eco_report() {
+        let board = Idf30::parse(include_str!("board.idf")).unwrap();
+        let mut editor = BoardEditor::new(board);
+        editor.move_by("J2", 2.54, -1.0).unwrap();
+        editor.rotate("J2", 90.0).unwrap();
+        editor.flip("J3").unwrap();
+        editor.remove("NOREFDES").unwrap();
+        editor
+            .add(placement(
+                "R0603",
+                "RC-10K",
+                "R1",
+                (10.0, 20.0, 0.0),
+                BoardSide::Top,
+            ))
+            .unwrap();
+        let diff = editor.changes(1e-4);
+
+        assert_eq!(
+            diff.to_eco_text(&Unit::SImm),
+            "J2 moved 2.54 mm +X, 1.00 mm -Y, rotated 90°\n\
+             J3 flipped to BOTTOM\n\
+             NOREFDES removed\n\
+             R1 added at (10.00, 20.00) mm, 0° on TOP, package R0603\n"
+        );
+        let markdown = diff.to_eco_markdown(&Unit::SImm);
+        assert!(markdown.starts_with("# Engineering change order\n\n## Components\n"));
+        assert!(markdown.contains("| J3 | flipped to BOTTOM |\n"));
+        assert!(!markdown.contains("## Sections"));
+    }
+
+    #[test]
+    fn panel_interference() {
+        let mut board = Idf30::parse(include_str!("board.idf")).unwrap();
+        let outline = board
+            .other_sections
+            .iter_mut()
+            .find(|s| s.name() == "BOARD_OUTLINE")
+            .unwrap();
+        let corners = [
+            [0.0, 0.0],
+            [50.0, 0.0],
+            [50.0, 40.0],
+            [0.0, 40.0],
+            [0.0, 0.0],
+        ];
+        outline.set_loops(&[corners.map(|[x, y]| [x, y, 0.0]).to_vec()]);
+        let rails = Rails {
+            left: 10.0,
+            right: 10.0,
+            ..Rails::default()
+        };
+        let mut panel = board
+            .panelize(&PanelSpec {
+                rows: 1,
+                cols: 2,
+                spacing_x: 0.0,
+                spacing_y: 0.0,
+                rotation: 0.0,
+                rails,
+                merge_holes: false,
+            })
+            .unwrap();
+        let tooling = |x: f32, y: f32| DrilledHole {
+            diameter: 3.0,
+            x,
+            y,
+            plating: Plating::NonPlated,
+            association: ReferenceDesignator::Board,
+            hole_type: HoleType::Tooling,
+            owner: Owner::Unowned,
+        };
+        panel.set_drilled_holes(&[tooling(5.0, 20.0), tooling(58.0, 20.0)]);
+        let found = panel.panel_interference(&[&board], &rails).unwrap();
+        assert_eq!(
+            found,
+            [PanelInterference::ToolingHoleOnBoard {
+                hole: 1,
+                instance: 0
+            }]
+        );
+
+        panel.set_drilled_holes(&[]);
+        let strict_rails = Rails {
+            bottom: 5.0,
+            ..rails
+        };
+        assert_eq!(
+            panel.panel_interference(&[&board], &strict_rails).unwrap(),
+            [
+                PanelInterference::OnRails { instance: 0 },
+                PanelInterference::OnRails { instance: 1 }
+            ]
+        );
+
+        panel.placement[1].x -= 10.0;
+        panel.placement[0].y -= 1.0;
+        assert_eq!(
+            panel.panel_interference(&[&board], &rails).unwrap(),
+            [
+                PanelInterference::BoardsOverlap {
+                    first: 0,
+                    second: 1
+                },
+                PanelInterference::OutsidePanel { instance: 0 }
+            ]
+        );
+        assert!(board.panel_interference(&[&board], &rails).is_err());
+        assert!(panel.panel_interference(&[], &rails).unwrap().is_empty());
+    }
+
+    #[test]
+    fn panel_features() {
+        let board = Idf30::parse(include_str!("board.idf")).unwrap();
+        let mut panel = board.to_panel().unwrap();
+        let bbox = panel
+            .section("PANEL_OUTLINE")
+            .unwrap()
+            .bounding_box()
+            .unwrap();
+        panel.add_panel_features(&PanelFeatures::default()).unwrap();
+
+        let holes = panel.drilled_holes().unwrap();
+        assert_eq!(holes.len(), 4);
+        assert!(holes.iter().all(|h| h.hole_type == HoleType::Tooling
+            && h.plating == Plating::NonPlated
+            && h.association.to_string() == "PANEL"));
+        assert!((holes[2].x as f64 - (bbox.max_x - 5.0)).abs() < 1e-3);
+        assert!((holes[2].y as f64 - (bbox.max_y - 5.0)).abs() < 1e-3);
+
+        let fiducials: Vec<_> = panel.placement[1..].iter().collect();
+        assert_eq!(fiducials.len(), 3);
+        assert!(fiducials.iter().all(|f| *f.package_name == *"FIDUCIAL_1MM"
+            && f.designator == ReferenceDesignator::NoRefDes
+            && f.placement_status == PlacementStatus::Placed));
+        assert!((fiducials[1].x as f64 - (bbox.max_x - 10.0)).abs() < 1e-3);
+
+        let written = panel.to_string();
+        assert_eq!(Idf30::parse(&written).unwrap().placement.len(), 4);
+        assert!(board
+            .clone()
+            .add_panel_features(&PanelFeatures::default())
+            .is_err());
+    }
+
+    #[test]
+    fn mounting_holes() {
+        let mut board = Idf30::parse(include_str!("board.idf")).unwrap();
+        let holes = board.mounting_holes(1.0).unwrap();
+        assert_eq!(holes.len(), 1);
+        assert_eq!((holes[0].x, holes[0].diameter), (3.5, 1.6002));
+        assert!(board.mounting_holes(2.0).unwrap().len() == 1);
+
+        let mut all = board.drilled_holes().unwrap();
+        all[0].association = ReferenceDesignator::Board;
+        all.push(DrilledHole {
+            diameter: 3.2,
+            x: 40.0,
+            y: 30.0,
+            plating: Plating::NonPlated,
+            association: ReferenceDesignator::NoRefDes,
+            hole_type: HoleType::Mounting,
+            owner: Owner::MCad,
+        });
+        board.set_drilled_holes(&all);
+        let positions: Vec<_> = board
+            .mounting_holes(3.0)
+            .unwrap()
+            .iter()
+            .map(|h| (h.x, h.y))
+            .collect();
+        assert_eq!(positions, [(65.0, 60.5), (3.5, 18.5), (40.0, 30.0)]);
+    }
+
+    #[test]
+    fn test_point_report() {
+        let mut board = Idf30::parse(include_str!("board.idf")).unwrap();
+        board.placement.extend([
+            placement("TP_SMD", "", "TP1", (10.0, 12.5, 0.0), BoardSide::Top),
+            placement("TP_SMD", "", "TP2", (20.0, 5.0, 0.0), BoardSide::Bottom),
+            placement("TP_TH", "", "TP3", (30.0, 7.25, 0.0), BoardSide::Top),
+        ]);
+        let mut holes = board.drilled_holes().unwrap();
+        holes.push(DrilledHole {
+            diameter: 1.0,
+            x: 30.0,
+            y: 7.25,
+            plating: Plating::Plated,
+            association: ReferenceDesignator::new("TP3"),
+            hole_type: HoleType::Pin,
+            owner: Owner::ECad,
+        });
+        board.set_drilled_holes(&holes);
+
+        let report = board.test_point_report(&Classifier::default()).unwrap();
+        let access: Vec<_> = report
+            .test_points
+            .iter()
+            .map(|t| (t.designator.as_str(), t.accessible_from))
+            .collect();
+        assert_eq!(
+            access,
+            [
+                ("TP1", Access::Top),
+                ("TP2", Access::Bottom),
+                ("TP3", Access::Both)
+            ]
+        );
+        assert_eq!(
+            report.to_csv(),
+            "RefDes,X,Y,Side,AccessibleFrom\n\
+             TP1,10.0000,12.5000,TOP,TOP\n\
+             TP2,20.0000,5.0000,BOTTOM,BOTTOM\n\
+             TP3,30.0000,7.2500,TOP,BOTH\n"
+        );
+    }
+
+    #[test]
+    fn flip_component_side() {
+        let mut board = Idf30::parse(include_str!("board.idf")).unwrap();
+        let outline = board
+            .other_sections
+            .iter_mut()
+            .find(|s| s.name() == "BOARD_OUTLINE")
+            .unwrap();
+        let corners = [
+            [0.0, 0.0],
+            [50.0, 0.0],
+            [50.0, 40.0],
+            [0.0, 40.0],
+            [0.0, 0.0],
+        ];
+        outline.set_loops(&[corners.map(|[x, y]| [x, y, 0.0]).to_vec()]);
+
+        let mut c = placement("SOIC8", "PN", "U1", (10.0, 5.0, 30.0), BoardSide::Top);
+        c.flip_side(&board).unwrap();
+        assert_eq!(
+            (c.x, c.y, c.board_side.clone()),
+            (40.0, 5.0, BoardSide::Bottom)
+        );
+        assert!((c.rotation - 330.0).abs() < 1e-3);
+        // The outline lands where the mirrored board puts it
+        let (x, y) = c.placement_transform().apply(1.0, 0.0);
+        assert!((x - (40.0 - 30f64.to_radians().cos())).abs() < 1e-4);
+        assert!((y - (5.0 + 30f64.to_radians().sin())).abs() < 1e-4);
+
+        c.flip_side(&board).unwrap();
+        assert_eq!((c.x, c.board_side.clone()), (10.0, BoardSide::Top));
+        assert!((c.rotation - 30.0).abs() < 1e-3);
+        board.remove_section(0);
+        assert!(c.flip_side(&board).is_err());
+    }
+
+    #[test]
+    fn merge_boards() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let board = Idf30::parse(&contents).unwrap();
+        let mut other = board.clone();
+        let holes = other
+            .other_sections
+            .iter_mut()
+            .find(|s| s.name() == "DRILLED_HOLES")
+            .unwrap();
+        holes.records_mut()[0][4] = IdfValue::String(Either::Left("J2"));
+
+        let mut merged = board.clone();
+        let report = merged.merge_with(&other, MergePolicy::default()).unwrap();
+        assert_eq!(
+            report.collisions[0],
+            DesignatorCollision {
+                designator: "J2".to_string(),
+                renamed: Some("J2_2".to_string()),
+            }
+        );
+        assert_eq!(report.collisions.len(), 2);
+        assert_eq!(report.dropped, vec!["BOARD_OUTLINE".to_string()]);
+        assert_eq!(report.overlaps.len(), 1);
+        assert_eq!(report.overlaps[0].section, "ROUTE_KEEPOUT");
+        assert_eq!(merged.placement.len(), 6);
+        assert!(merged.get_placement("J3_2").is_some());
+        let holes = merged.drilled_holes().unwrap();
+        assert_eq!(holes.len(), 4);
+        assert_eq!(holes[2].association.to_string(), "J2_2");
+        assert_eq!(
+            merged
+                .other_sections
+                .iter()
+                .filter(|s| s.name() == "ROUTE_KEEPOUT")
+                .count(),
+            2
+        );
+
+        let policy = MergePolicy {
+            designators: DuplicatePolicy::KeepFirst,
+            ..MergePolicy::default()
+        };
+        let mut merged = board.clone();
+        merged.merge_with(&other, policy).unwrap();
+        assert_eq!(merged.placement.len(), 4);
+        assert_eq!(merged.drilled_holes().unwrap().len(), 3);
+
+        let mut merged = board.clone();
+        let policy = MergePolicy {
+            designators: DuplicatePolicy::Error,
+            ..MergePolicy::default()
+        };
+        assert!(matches!(
+            merged.merge_with(&other, policy),
+            Err(Error::DuplicateDesignator(d)) if d == "J2"
+        ));
+        let policy = MergePolicy {
+            reject_overlaps: true,
+            ..MergePolicy::default()
+        };
+        assert!(matches!(
+            merged.merge_with(&other, policy),
+            Err(Error::OverlappingSections(_))
+        ));
+        assert_eq!(merged, board);
+
+        // Duplicates within the other board are reported, holes follow the first one
+        let mut other = board.clone();
+        let mut copy = other.placement[0].clone();
+        copy.x += 5.0;
+        other.placement.push(copy);
+        let mut merged = board.clone();
+        let report = merged.merge_with(&other, MergePolicy::default()).unwrap();
+        assert_eq!(report.duplicates, vec!["J2".to_string()]);
+        let renamed: Vec<_> = report
+            .collisions
+            .iter()
+            .filter_map(|c| c.renamed.as_deref())
+            .collect();
+        assert_eq!(renamed, ["J2_2", "J3_2", "J2_3"]);
+
+        // Keepouts that only share an edge do not overlap
+        let mut keepout = board.clone();
+        keepout
+            .import_svg_path("M0,0 h1 v1 h-1 z", "VIA_KEEPOUT", Unit::SImm)
+            .unwrap();
+        let mut touching = board.clone();
+        touching
+            .import_svg_path("M1,0 h1 v1 h-1 z", "VIA_KEEPOUT", Unit::SImm)
+            .unwrap();
+        touching
+            .import_svg_path("M0.5,0.5 h1 v1 h-1 z", "VIA_KEEPOUT", Unit::SImm)
+            .unwrap();
+        let report = keepout
+            .merge_with(&touching, MergePolicy::default())
+            .unwrap();
+        let overlaps: Vec<_> = report
+            .overlaps
+            .iter()
+            .filter(|o| o.section == "VIA_KEEPOUT")
+            .collect();
+        assert_eq!(overlaps.len(), 1);
+    }
+
+    #[test]
+    fn split_by_side() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let mut board = Idf30::parse(&contents).unwrap();
+        board.placement[1].board_side = BoardSide::Bottom;
+        let (top, bottom) = board.split_by_side();
+        assert_eq!(top.placement.len(), 2);
+        assert!(top.placement.iter().all(|p| p.board_side == BoardSide::Top));
+        assert_eq!(bottom.placement, vec![board.placement[1].clone()]);
+        assert_eq!(top.other_sections, board.other_sections);
+        assert_eq!(bottom.other_sections, board.other_sections);
+    }
+
+    #[test]
+    fn extract_items() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let mut board = Idf30::parse(&contents).unwrap();
+        let fiducials = board.extract_placements(|p| p.package_name.starts_with("FID"));
+        assert_eq!(fiducials.len(), 1);
+        assert_eq!(fiducials[0].designator, ReferenceDesignator::NoRefDes);
+        assert_eq!(board.placement.len(), 2);
+
+        let holes = board
+            .extract_holes(|h| h.plating == Plating::NonPlated)
+            .unwrap();
+        assert_eq!(holes.len(), 1);
+        assert_eq!(holes[0].diameter, 1.6002);
+        assert_eq!(board.drilled_holes().unwrap().len(), 1);
+        assert!(board.extract_holes(|_| false).unwrap().is_empty());
+
+        let order: Vec<String> = board
+            .other_sections
+            .iter()
+            .map(|s| s.name().to_string())
+            .collect();
+        let keepouts = board.extract_sections(|s| s.name().ends_with("KEEPOUT"));
+        assert_eq!(keepouts.len(), 1);
+        assert_eq!(board.other_sections.len(), order.len() - 1);
+        assert!(board.section("ROUTE_KEEPOUT").is_none());
+        assert!(board.to_string().contains(".BOARD_OUTLINE"));
+    }
+
+    #[test]
+    fn placement_selectors() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let mut board = Idf30::parse(&contents).unwrap();
+        board.placement[1].board_side = BoardSide::Bottom;
+        let selected = |query: &str| -> Vec<String> {
+            board
+                .select(query)
+                .unwrap()
+                .map(|p| p.designator.to_string())
+                .collect()
+        };
+        assert_eq!(selected("refdes:j*"), vec!["J2", "J3"]);
+        assert_eq!(selected("refdes:J* side:bottom"), vec!["J3"]);
+        assert_eq!(
+            selected("package:*C40*,HIROSE*"),
+            vec!["J2", "J3", "NOREFDES"]
+        );
+        assert_eq!(selected("status:unplaced").len(), 0);
+        assert_eq!(selected("").len(), 3);
+
+        let selector: Selector = "Side:TOP status:placed,ecad".parse().unwrap();
+        assert_eq!(selector.terms[0], vec![Term::Side(BoardSide::Top)]);
+        assert_eq!(selector.terms[1].len(), 2);
+        let top = board.extract_placements(|p| selector.matches(p));
+        assert_eq!(top.len(), 2);
+        for query in ["refdes", "side:left", "color:red", "refdes:"] {
+            assert!(matches!(
+                query.parse::<Selector>(),
+                Err(Error::InvalidSelector(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn binary_cache() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let board = Idf30::parse(&contents).unwrap();
+        let cache = board.to_cache(&contents);
+        let cached = Idf30::from_cache(&cache, &contents).unwrap().unwrap();
+        assert_eq!(cached, board);
+        assert_eq!(cached.to_string(), board.to_string());
+
+        let edited = contents.replace("J3", "J4");
+        assert!(Idf30::from_cache(&cache, &edited).unwrap().is_none());
+        assert!(Idf30::from_cache(&cache[..cache.len() - 1], &contents).is_err());
+        assert!(Idf30::from_cache(b"IDF", &contents).is_err());
+
+        let contents = std::fs::read_to_string("./tests/library.idf").unwrap();
+        let library = Idf30::parse(&contents).unwrap();
+        let cache = library.to_cache(&contents);
+        assert_eq!(
+            Idf30::from_cache(&cache, &contents).unwrap().unwrap(),
+            library
+        );
+    }
+
+    #[test]
+    fn library_cache() {
+        let contents = std::fs::read_to_string("./tests/library.idf").unwrap();
+        let mut cache = LibraryCache::new();
+        let key = cache.load(&contents).unwrap();
+        assert_eq!(cache.load_file("./tests/library.idf").unwrap(), key);
+        assert_eq!(cache.len(), 1);
+        let d = cache.definition(key, "cc1210", "pn-cc1210").unwrap();
+        assert_eq!(d.height, 67.0);
+        assert_eq!(
+            *cache
+                .definition(key, "cc1210", "other")
+                .unwrap()
+                .part_number,
+            *"pn-cc1210"
+        );
+        assert!(cache.definition(key, "missing", "pn-cc1210").is_none());
+        assert!(cache.definition(key + 1, "cc1210", "pn-cc1210").is_none());
+        assert_eq!(
+            cache.library(key).unwrap().to_string(),
+            Idf30::parse(&contents).unwrap().to_string()
+        );
+
+        let board = std::fs::read_to_string("./tests/board.idf").unwrap();
+        assert!(matches!(cache.load(&board), Err(Error::WrongFileType)));
+        assert_eq!(cache.len(), 1);
+
+        let other = contents.replacen("pn-cc1210", "pn-cc1210-x", 1);
+        let other_key = cache.load(&other).unwrap();
+        assert_ne!(other_key, key);
+        assert_eq!(cache.load(&other).unwrap(), other_key);
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn parallel_write() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let mut board = Idf30::parse(&contents).unwrap();
+        let holes: Vec<DrilledHole> = (0..10_000)
+            .map(|i| DrilledHole {
+                diameter: 0.3,
+                x: (i % 100) as f32,
+                y: (i / 100) as f32,
+                plating: Plating::Plated,
+                association: ReferenceDesignator::Board,
+                hole_type: HoleType::Via,
+                owner: Owner::ECad,
+            })
+            .collect();
+        board.set_drilled_holes(&holes);
+        for options in [
+            WriteOptions::default(),
+            WriteOptions {
+                quote_names: true,
+                line_ending: LineEnding::CrLf,
+                ..WriteOptions::default()
+            },
+            WriteOptions {
+                order: vec!["BOARD_OUTLINE".to_string()],
+                ..WriteOptions::default()
+            },
+        ] {
+            assert_eq!(
+                board.to_string_parallel(&options),
+                board.to_string_with(&options)
+            );
+        }
+
+        let contents = std::fs::read_to_string("./tests/library.idf").unwrap();
+        let library = Idf30::parse(&contents).unwrap();
+        let options = WriteOptions::default();
+        assert_eq!(
+            library.to_string_parallel(&options),
+            library.to_string_with(&options)
+        );
+    }
+
+    #[test]
+    fn raw_section_records() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let lazy = Idf30::parse_lazy(&contents).unwrap();
+        let holes: Vec<Vec<&str>> = lazy
+            .raw_records("DRILLED_HOLES")
+            .unwrap()
+            .map(|r| r.collect())
+            .collect();
+        assert_eq!(holes.len(), 2);
+        assert_eq!(
+            holes[0],
+            ["1.3970", "65.0000", "60.5000", "PTH", "U?", "PIN", "UNOWNED"]
+        );
+        let placement: Vec<Vec<&str>> = lazy
+            .raw_records("PLACEMENT")
+            .unwrap()
+            .map(|r| r.collect())
+            .collect();
+        assert_eq!(placement[4], ["FID_C40M120", "\"\"", "NOREFDES"]);
+        assert!(lazy.raw_records("NOTES").is_none());
+        assert!(!lazy.is_parsed("DRILLED_HOLES"));
+
+        let commented = contents.replace(".DRILLED_HOLES\n", ".DRILLED_HOLES\n  # via\n");
+        let lazy = Idf30::parse_lazy(&commented).unwrap();
+        assert_eq!(lazy.raw_records("DRILLED_HOLES").unwrap().count(), 2);
+    }
+
+    #[test]
+    fn parse_encoded_bytes() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let expected = Idf30::parse(&contents).unwrap().to_string();
+        let bytes = contents.as_bytes();
+        assert_eq!(detect_encoding(bytes), Encoding::Utf8);
+        let board = Idf30::parse_bytes(bytes).unwrap();
+        assert!(matches!(board.header.source, Either::Left(_)));
+        assert_eq!(board.to_string(), expected);
+
+        let mut bom = vec![0xef, 0xbb, 0xbf];
+        bom.extend_from_slice(bytes);
+        assert_eq!(Idf30::parse_bytes(&bom).unwrap().to_string(), expected);
+
+        let le: Vec<u8> = contents
+            .encode_utf16()
+            .flat_map(|u| u.to_le_bytes())
+            .collect();
+        assert_eq!(detect_encoding(&le), Encoding::Utf16Le);
+        assert_eq!(Idf30::parse_bytes(&le).unwrap().to_string(), expected);
+        let be: Vec<u8> = [0xfeff]
+            .into_iter()
+            .chain(contents.encode_utf16())
+            .flat_map(|u| u.to_be_bytes())
+            .collect();
+        assert_eq!(detect_encoding(&be), Encoding::Utf16Be);
+        assert_eq!(Idf30::parse_bytes(&be).unwrap().to_string(), expected);
+        assert!(decode(&le[..le.len() - 1]).is_err());
+
+        let cp1252: Vec<u8> = contents
+            .replace("allegro_17.4", "\"allegro 17.4 \u{e9}\"")
+            .chars()
+            .map(|c| c as u8)
+            .collect();
+        assert_eq!(detect_encoding(&cp1252), Encoding::Windows1252);
+        let board = Idf30::parse_bytes(&cp1252).unwrap();
+        assert_eq!(&*board.header.source, "allegro 17.4 \u{e9}");
+        assert_eq!(
+            decode(&[0x93, b'x', 0x94, 0x80]).unwrap(),
+            "\u{201c}x\u{201d}\u{20ac}"
+        );
+
+        // UTF-16 without byte order mark starting with blank lines
+        let text = format!("\n\n{contents}");
+        let blank: Vec<u8> = text.encode_utf16().flat_map(|u| u.to_be_bytes()).collect();
+        assert_eq!(detect_encoding(&blank), Encoding::Utf16Be);
+        assert_eq!(decode(&blank).unwrap(), text);
+        // UTF-8 with a broken byte is not misread as Windows-1252
+        let mut broken = contents
+            .replace("allegro_17.4", "\"allegro \u{e9}\"")
+            .into_bytes();
+        broken.push(0xff);
+        assert_eq!(detect_encoding(&broken), Encoding::Utf8);
+        assert!(decode(&broken).is_err());
+    }
+
+    #[test]
+    fn keyword_names_round_trip() {
+        let contents = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let mut board = Idf30::parse(&contents).unwrap();
+        board.placement[0].designator = ReferenceDesignator::Any(Either::Left("BOARD"));
+        board.placement[0].package_name = Either::Left("TOP");
+        board.placement[1].designator = ReferenceDesignator::Any(Either::Left("NOREFDES"));
+        board.placement[1].part_number = Either::Left("PLACED");
+        let written = board.to_string();
+        assert!(written.contains("\"TOP\" TMP-478 \"BOARD\"\n"));
+        assert!(written.contains(" NOREFDES\n"));
+        let reparsed = Idf30::parse(&written).unwrap();
+        assert_eq!(reparsed, board);
+        assert_eq!(
+            reparsed.placement[2].designator,
+            ReferenceDesignator::NoRefDes
+        );
+        #[cfg(feature = "fast-parser")]
+        assert_eq!(Idf30::parse_fast(&written).unwrap(), board);
+    }
+
+    #[test]
+    fn definition_error_location() {
+        let contents = std::fs::read_to_string("./tests/library.idf").unwrap();
+        let file = contents.replacen("0 -55.0 -55.0 0.0", "99999999999 -55.0 -55.0 0.0", 1);
+        let Err(Error::At { location, source }) = Idf30::parse(&file) else {
+            panic!("expected an error with location");
+        };
+        assert_eq!(location.section, "ELECTRICAL");
+        assert_eq!(location.record, Some(2));
+        assert_eq!(location.line, 7);
+        assert!(matches!(*source, Error::ParseInt(_)));
+    }
+
+    #[test]
+    fn edit_out_of_range() {
+        let previous = std::fs::read_to_string("./tests/board.idf").unwrap();
+        let mut board = Idf30::parse(&previous).unwrap().into_owned();
+        let unchanged = board.clone();
+        let (start, end) = (20, 10);
+        for range in [previous.len()..previous.len() + 10, start..end] {
+            let edit = TextEdit { range, new_len: 0 };
+            assert!(matches!(
+                board.apply_edit(&previous, "", &edit),
+                Err(Error::Malformed(_))
+            ));
+        }
+        assert_eq!(board, unchanged);
+    }
 }